@@ -333,10 +333,11 @@ const AITool: React.FC = () => {
 
           // 发送到 ComfyUI
           if (jsonContent && config.url) {
-            const comfyuiBaseUrl = new URL(config.url).origin;
+            const comfyuiUrl = new URL(config.url);
             invoke('send_workflow_to_comfyui', {
-              comfyUrl: comfyuiBaseUrl,
+              comfyUrl: comfyuiUrl.origin,
               workflowJson: jsonContent,
+              basePath: comfyuiUrl.pathname,
             }).then((result) => {
               if (result === 'extension') {
                 showToast('success', '工作流已自动加载!', 3000);
@@ -345,6 +346,11 @@ const AITool: React.FC = () => {
               }
               // 其他情况（如剪贴板方案）静默处理，避免控制台噪音
             }).catch((err) => {
+              // 认证失败需要提示用户，其他错误静默处理
+              if (String(err).includes('认证失败')) {
+                showToast('error', String(err), 4000);
+                return;
+              }
               // 静默处理错误，这些错误是正常的（ComfyUI 未运行或扩展未安装时）
               // 只在开发模式下输出日志
               if (process.env.NODE_ENV === 'development') {
@@ -360,15 +366,9 @@ const AITool: React.FC = () => {
             await openUrl(config.url, '_blank');
           }
 
-          // 键盘模拟
+          // 外部浏览器不是 AI 标签页，simulate_paste 无法确认前台窗口，改为提示用户手动粘贴
           if (clipboardSuccess && jsonContent) {
-            invoke('simulate_paste', { delayMs: 4000 })
-              .then(() => {
-                showToast('info', '已发送粘贴命令', 3000);
-              })
-              .catch(() => {
-                showToast('info', '请在ComfyUI中按 Ctrl+V', 5000);
-              });
+            showToast('info', '工作流已在剪贴板中，请在浏览器的 ComfyUI 页面按 Ctrl+V 粘贴', 5000);
           }
         } catch (error) {
           console.error('Tauri error:', error);
@@ -28,6 +28,9 @@ interface AssetFolder {
   name: string;
   space_type: string;
   asset_count: number;
+  favorite_count: number;
+  rated_count: number;
+  read_sidecars: boolean;
 }
 
 interface AssetEntry {
@@ -41,6 +44,7 @@ interface AssetEntry {
   height: number;
   thumb_path: string;
   modified_at: number;
+  thumb_status: 'none' | 'ok' | 'failed' | 'pending';
 }
 
 interface ScanProgress {
@@ -49,6 +53,23 @@ interface ScanProgress {
   total: number;
   file_name: string;
   phase: string;
+  elapsed_secs: number;
+  items_per_sec: number;
+  eta_secs: number | null;
+}
+
+interface ThumbWorkerProgress {
+  asset_id: number;
+  success: boolean;
+  done: number;
+  idle: boolean;
+}
+
+interface BatchProgress {
+  operation: string;
+  current: number;
+  total: number;
+  current_file: string;
 }
 
 interface QueryResult {
@@ -70,6 +91,7 @@ interface TagInfo {
   name: string;
   color: string;
   asset_count: number;
+  space_type?: string | null;
 }
 
 interface AssetDetail {
@@ -142,6 +164,10 @@ interface FfmpegStatusInfo {
   installed: boolean;
   path: string | null;
   version: string | null;
+  version_number: string | null;
+  features: string[];
+  missing_filters: string[];
+  ffprobe_version: string | null;
 }
 
 interface FfmpegDownloadProgress {
@@ -169,6 +195,18 @@ const AUDIO_EXTS = new Set(['mp3', 'wav', 'ogg', 'flac', 'aac', 'wma', 'm4a', 'o
 const MESH_EXTS = new Set(['fbx', 'obj', 'gltf', 'glb', 'blend', '3ds', 'dae', 'stl']);
 const SPINE_EXTS = new Set(['spine', 'skel', 'atlas']);
 
+// 可撤销的批量操作类型（asset_undo_last 返回的 kind）
+const UNDO_LABELS: Record<string, string> = {
+  add_tag: '批量添加标签', set_rating: '批量评分', favorite: '批量收藏', apply_metadata: '批量整理',
+};
+
+// 命令错误的提示文本：asset_* / team_* 命令抛出 { code, message }，其余命令抛出字符串
+function errorMessage(e: any, fallback: string): string {
+  if (typeof e === 'string') return e || fallback;
+  if (e?.code === 'db_locked') return '数据库正忙，请稍后重试';
+  return e?.message || fallback;
+}
+
 function formatFileSize(bytes: number): string {
   if (bytes < 1024) return bytes + 'B';
   if (bytes < 1048576) return (bytes / 1024).toFixed(1) + 'KB';
@@ -176,6 +214,13 @@ function formatFileSize(bytes: number): string {
   return (bytes / 1073741824).toFixed(1) + 'GB';
 }
 
+function formatDuration(secs: number): string {
+  const s = Math.ceil(secs);
+  if (s < 60) return `${s} 秒`;
+  if (s < 3600) return `${Math.floor(s / 60)} 分 ${s % 60} 秒`;
+  return `${Math.floor(s / 3600)} 小时 ${Math.floor((s % 3600) / 60)} 分`;
+}
+
 function formatDate(ts: number): string {
   if (!ts) return '';
   const d = new Date(ts * 1000);
@@ -1114,9 +1159,11 @@ const ContextMenu: React.FC<{
 
 const FfmpegSettingsPanel: React.FC<{
   onClose: () => void;
-}> = ({ onClose }) => {
+  onPreviewsGenerated: () => void;
+}> = ({ onClose, onPreviewsGenerated }) => {
   const [status, setStatus] = useState<FfmpegStatusInfo | null>(null);
   const [downloading, setDownloading] = useState(false);
+  const [backfilling, setBackfilling] = useState(false);
   const [progress, setProgress] = useState<FfmpegDownloadProgress | null>(null);
   const { showToast } = useToast();
 
@@ -1144,14 +1191,47 @@ const FfmpegSettingsPanel: React.FC<{
     return () => { unlisten?.(); };
   }, []);
 
+  // 使用已安装在其他位置的 FFmpeg；path 为 null 时恢复自动查找
+  const applyCustomPath = async (path: string | null) => {
+    try {
+      const s = await invoke<FfmpegStatusInfo>('ffmpeg_set_custom_path', { path });
+      setStatus(s);
+      if (path) showToast('success', '已使用指定的 FFmpeg');
+    } catch (e: any) {
+      showToast('error', errorMessage(e, '设置 FFmpeg 路径失败'));
+    }
+  };
+
+  const handlePickCustomPath = async () => {
+    const selected = await open({ multiple: false, title: '选择 ffmpeg 可执行文件' });
+    if (typeof selected === 'string') await applyCustomPath(selected);
+  };
+
+  // 为扫描时没有 FFmpeg 的视频/音频补生成预览
+  const handleBackfill = async () => {
+    setBackfilling(true);
+    try {
+      const count = await invoke<number>('asset_generate_missing_media_thumbnails');
+      if (count > 0) {
+        showToast('success', `已生成 ${count} 个视频/音频预览`);
+        onPreviewsGenerated();
+      }
+    } catch (e: any) {
+      showToast('error', errorMessage(e, '生成预览失败'));
+    } finally {
+      setBackfilling(false);
+    }
+  };
+
   const handleInstall = async () => {
     setDownloading(true);
     setProgress({ phase: 'downloading', progress: 0, message: '准备下载...' });
     try {
       await invoke('ffmpeg_download');
       showToast('success', 'FFmpeg 安装完成');
+      await handleBackfill();
     } catch (e: any) {
-      showToast('error', e?.toString() || '安装失败');
+      showToast('error', errorMessage(e, '安装失败'));
       setDownloading(false);
     }
   };
@@ -1181,7 +1261,7 @@ const FfmpegSettingsPanel: React.FC<{
               {status?.installed ? '已安装' : '未安装'}
             </div>
             {status?.version && (
-              <div className="text-[10px] text-[#666] truncate">{status.version}</div>
+              <div className="text-[10px] text-[#666] truncate" title={status.features.join(' ')}>{status.version}</div>
             )}
             {status?.path && (
               <div className="text-[10px] text-[#555] truncate">{status.path}</div>
@@ -1189,6 +1269,16 @@ const FfmpegSettingsPanel: React.FC<{
           </div>
         </div>
 
+        {status?.installed && !status.ffprobe_version && (
+          <div className="text-[11px] text-[#f59e0b] mb-2">未找到 ffprobe，无法读取视频尺寸和时长</div>
+        )}
+        {status?.installed && status.missing_filters.length > 0 && (
+          <div className="text-[11px] text-[#f59e0b] mb-2">
+            当前 FFmpeg 缺少滤镜 {status.missing_filters.join('、')}
+            {status.missing_filters.includes('showwavespic') && '，音频波形图不可用'}
+          </div>
+        )}
+
         <p className="text-[11px] text-[#777] mb-4">
           FFmpeg 用于生成视频文件的缩略图预览。安装后将自动支持 MP4、MOV、AVI、MKV 等视频格式的缩略图。
           安装过程在后台进行，不影响其他操作。
@@ -1220,6 +1310,26 @@ const FfmpegSettingsPanel: React.FC<{
             自动下载安装 FFmpeg
           </button>
         )}
+        {!downloading && (
+          <div className="flex items-center justify-between mb-2 text-[11px]">
+            <button onClick={handlePickCustomPath} className="text-[#3b82f6] hover:text-[#60a5fa]">
+              使用已有的 FFmpeg...
+            </button>
+            <button onClick={() => applyCustomPath(null)} className="text-[#555] hover:text-[#aaa]">
+              恢复自动查找
+            </button>
+          </div>
+        )}
+        {status?.installed && (
+          <button
+            onClick={handleBackfill}
+            disabled={backfilling}
+            className="w-full flex items-center justify-center gap-2 px-4 py-2.5 bg-[#222] text-[#ccc] text-xs rounded-lg hover:bg-[#2a2a2a] transition-colors disabled:opacity-50"
+          >
+            {backfilling ? <Loader2 size={14} className="animate-spin" /> : <RefreshCw size={14} />}
+            为已有视频/音频生成预览
+          </button>
+        )}
       </div>
     </div>
   );
@@ -1336,6 +1446,23 @@ const VirtualGrid: React.FC<VirtualGridProps> = ({ assets, containerRef, onClick
     return () => el.removeEventListener('scroll', onScroll);
   }, [containerRef]);
 
+  // 可见区域内还在等待后台生成缩略图的资产：滚动停下后让它们插队
+  const lastPrioritizedRef = useRef('');
+  useEffect(() => {
+    const timer = setTimeout(() => {
+      const ids = layoutItems
+        .filter(li => li.y + li.h >= viewTop && li.y <= viewBottom)
+        .map(li => assets[li.idx])
+        .filter(a => a.thumb_status === 'pending')
+        .map(a => a.id);
+      const key = ids.join(',');
+      if (ids.length === 0 || key === lastPrioritizedRef.current) return;
+      lastPrioritizedRef.current = key;
+      invoke('asset_prioritize_thumbnails', { assetIds: ids }).catch(() => {});
+    }, 300);
+    return () => clearTimeout(timer);
+  }, [layoutItems, assets, viewTop, viewBottom]);
+
   const visibleItems = useMemo(() => {
     const nodes: React.ReactNode[] = [];
     for (const li of layoutItems) {
@@ -1810,6 +1937,7 @@ const FORMAT_GROUPS: { label: string; exts: string[] }[] = [
 const SORT_OPTIONS = [
   { value: 'modified', label: '修改时间' },
   { value: 'name', label: '文件名' },
+  { value: 'name_natural', label: '文件名（自然排序）' },
   { value: 'size', label: '文件大小' },
   { value: 'ext', label: '格式' },
 ];
@@ -1830,6 +1958,10 @@ export default function AssetManager() {
   const [scanning, setScanning] = useState(false);
   const [scanProgress, setScanProgress] = useState<ScanProgress | null>(null);
 
+  // Batch export progress
+  const [batchProgress, setBatchProgress] = useState<BatchProgress | null>(null);
+  const batchOpRef = useRef<number | null>(null);
+
   // Search & filter
   const [searchText, setSearchText] = useState('');
   const [formatFilter, setFormatFilter] = useState<string[]>([]);
@@ -1898,13 +2030,13 @@ export default function AssetManager() {
   const [filterFavorites, setFilterFavorites] = useState(false);
   const [filterMinRating, setFilterMinRating] = useState(0);
 
-  // Load OS username from backend (Phase 3 fix)
+  // Load user/machine identity from backend (team records are written with it server-side)
   useEffect(() => {
     (async () => {
       try {
-        const user = await invoke<string>('asset_get_os_username');
-        setCurrentUser(user);
-        setCurrentMachine(typeof window !== 'undefined' ? window.location.hostname : '');
+        const identity = await invoke<{ username: string; machine: string }>('team_get_identity');
+        setCurrentUser(identity.username);
+        setCurrentMachine(identity.machine);
       } catch {
         const user = localStorage.getItem('arthub_username') || '';
         const machine = localStorage.getItem('arthub_machine') || '';
@@ -1951,6 +2083,7 @@ export default function AssetManager() {
       const result = await invoke<QueryResult>('asset_query', {
         params: {
           folder_id: selectedFolderId,
+          space_type: space,
           search: searchText || null,
           extensions: formatFilter.length > 0 ? formatFilter : null,
           min_width: null,
@@ -1980,7 +2113,7 @@ export default function AssetManager() {
     if (requestId === loadRequestRef.current) {
       setLoading(false);
     }
-  }, [selectedFolderId, searchText, formatFilter, sortBy, sortDesc, currentPage, filterByTag, filterMinRating, filterFavorites]);
+  }, [selectedFolderId, space, searchText, formatFilter, sortBy, sortDesc, currentPage, filterByTag, filterMinRating, filterFavorites]);
 
   // Load when filters change — use a dedicated effect that directly queries
   useEffect(() => {
@@ -1996,6 +2129,7 @@ export default function AssetManager() {
         const result = await invoke<QueryResult>('asset_query', {
           params: {
             folder_id: selectedFolderId,
+            space_type: space,
             search: searchText || null,
             extensions: formatFilter.length > 0 ? formatFilter : null,
             min_width: null,
@@ -2054,8 +2188,9 @@ export default function AssetManager() {
       unlisten = await listen<ScanProgress>('asset-scan-progress', (event) => {
         const p = event.payload;
         setScanProgress(p);
+        // 添加文件夹时后台自动开始的扫描也由事件驱动进度条
+        setScanning(p.phase !== 'complete');
         if (p.phase === 'complete') {
-          setScanning(false);
           setScanProgress(null);
           loadFolders();
           loadAssets(false);
@@ -2065,15 +2200,67 @@ export default function AssetManager() {
     return () => { unlisten?.(); };
   }, [loadFolders]);
 
+  // ---- Background scan errors ----
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    (async () => {
+      unlisten = await listen<{ folder_id: number; message: string }>('asset-scan-error', (event) => {
+        setScanning(false);
+        setScanProgress(null);
+        showToast('error', '扫描失败: ' + event.payload.message);
+      });
+    })();
+    return () => { unlisten?.(); };
+  }, [showToast]);
+
+  // ---- Background thumbnail listener ----
+  // 扫描只写入元数据时缩略图在后台生成：每生成一批刷新一次，队列清空时再刷新
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    (async () => {
+      unlisten = await listen<ThumbWorkerProgress>('asset-thumbnail-worker-progress', (event) => {
+        const p = event.payload;
+        if (p.idle || p.done % 50 === 0) loadAssets(false);
+      });
+    })();
+    return () => { unlisten?.(); };
+  }, [loadAssets]);
+
+  // ---- Startup thumbnail validation ----
+  // 启动维护任务发现损坏的缩略图时提示修复结果
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    (async () => {
+      unlisten = await listen<{ broken: number; repaired: number }>('asset-thumbnails-validated', (event) => {
+        const { broken, repaired } = event.payload;
+        showToast(repaired === broken ? 'success' : 'error', `发现 ${broken} 个损坏的缩略图，已修复 ${repaired} 个`);
+        loadAssets(false);
+      });
+    })();
+    return () => { unlisten?.(); };
+  }, [loadAssets, showToast]);
+
+  // ---- Batch progress listener ----
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    (async () => {
+      unlisten = await listen<BatchProgress>('asset-batch-progress', (event) => {
+        const p = event.payload;
+        setBatchProgress(p.current >= p.total ? null : p);
+      });
+    })();
+    return () => { unlisten?.(); };
+  }, []);
+
   // ---- Phase 2: Load tags ----
   const loadTags = useCallback(async () => {
     try {
-      const tags = await invoke<TagInfo[]>('asset_get_tags');
+      const tags = await invoke<TagInfo[]>('asset_get_tags', { spaceType: space });
       setAllTags(tags);
     } catch (e) {
       console.error('加载标签失败:', e);
     }
-  }, []);
+  }, [space]);
 
   useEffect(() => { loadTags(); }, [loadTags]);
 
@@ -2148,11 +2335,15 @@ export default function AssetManager() {
   // ---- Phase 2: Tag Handlers ----
   const handleCreateTag = async (name: string, color: string) => {
     try {
-      await invoke<TagInfo>('asset_create_tag', { name, color });
+      const result = await invoke<{ tag: TagInfo; created: boolean }>('asset_create_tag', { name, color });
       await loadTags();
-      showToast('success', `标签 "${name}" 已创建`);
+      if (result.created) {
+        showToast('success', `标签 "${name}" 已创建`);
+      } else {
+        showToast('info', `标签 "${result.tag.name}" 已存在`);
+      }
     } catch (e: any) {
-      showToast('error', e?.toString() || '创建标签失败');
+      showToast('error', errorMessage(e, '创建标签失败'));
     }
   };
 
@@ -2162,16 +2353,26 @@ export default function AssetManager() {
       await loadTags();
       showToast('success', '标签已删除');
     } catch (e: any) {
-      showToast('error', e?.toString() || '删除标签失败');
+      showToast('error', errorMessage(e, '删除标签失败'));
     }
   };
 
   const handleUpdateTag = async (tagId: number, name: string, color: string) => {
     try {
-      await invoke('asset_update_tag', { tagId, name, color });
+      await invoke<number>('asset_update_tag', { tagId, name, color });
       await loadTags();
     } catch (e: any) {
-      showToast('error', e?.toString() || '更新标签失败');
+      if (e?.code === 'already_exists' && window.confirm(`标签 "${name}" 已存在，是否合并到该标签？`)) {
+        try {
+          await invoke<number>('asset_update_tag', { tagId, name, color, merge: true });
+          await loadTags();
+          showToast('success', `已合并到标签 "${name}"`);
+        } catch (err: any) {
+          showToast('error', errorMessage(err, '合并标签失败'));
+        }
+        return;
+      }
+      showToast('error', errorMessage(e, '更新标签失败'));
     }
   };
 
@@ -2194,7 +2395,7 @@ export default function AssetManager() {
       }
       await loadTags();
     } catch (e: any) {
-      showToast('error', e?.toString() || '添加标签失败');
+      showToast('error', errorMessage(e, '添加标签失败'));
     }
   };
 
@@ -2210,7 +2411,7 @@ export default function AssetManager() {
       });
       await loadTags();
     } catch (e: any) {
-      showToast('error', e?.toString() || '移除标签失败');
+      showToast('error', errorMessage(e, '移除标签失败'));
     }
   };
 
@@ -2221,7 +2422,7 @@ export default function AssetManager() {
       setAssetRatingsMap(prev => new Map(prev).set(assetId, rating));
       if (detailAssetId === assetId) loadAssetDetail(assetId);
     } catch (e: any) {
-      showToast('error', e?.toString() || '设置评分失败');
+      showToast('error', errorMessage(e, '设置评分失败'));
     }
   };
 
@@ -2231,7 +2432,7 @@ export default function AssetManager() {
       await invoke('asset_set_note', { assetId, note });
       if (detailAssetId === assetId) loadAssetDetail(assetId);
     } catch (e: any) {
-      showToast('error', e?.toString() || '设置备注失败');
+      showToast('error', errorMessage(e, '设置备注失败'));
     }
   };
 
@@ -2245,7 +2446,7 @@ export default function AssetManager() {
         return next;
       });
     } catch (e: any) {
-      showToast('error', e?.toString() || '收藏操作失败');
+      showToast('error', errorMessage(e, '收藏操作失败'));
     }
   };
 
@@ -2259,7 +2460,7 @@ export default function AssetManager() {
       loadAssets(false);
       loadFolders();
     } catch (e: any) {
-      showToast('error', e?.toString() || '批量删除失败');
+      showToast('error', errorMessage(e, '批量删除失败'));
     }
   };
 
@@ -2268,16 +2469,31 @@ export default function AssetManager() {
     try {
       const dir = await open({ directory: true, title: '选择导出目录' });
       if (!dir || typeof dir !== 'string') return;
-      const count = await invoke<number>('asset_batch_export', {
+      const opId = await invoke<number>('asset_begin_batch');
+      batchOpRef.current = opId;
+      const result = await invoke<{ count: number; cancelled: boolean }>('asset_batch_export', {
         assetIds: Array.from(selectedIds),
         targetDir: dir,
+        opId,
       });
-      showToast('success', `已导出 ${count} 个文件到 ${dir}`);
+      if (result.cancelled) {
+        showToast('warning', `导出已取消，已导出 ${result.count} 个文件`);
+      } else {
+        showToast('success', `已导出 ${result.count} 个文件到 ${dir}`);
+      }
     } catch (e: any) {
-      showToast('error', e?.toString() || '批量导出失败');
+      showToast('error', errorMessage(e, '批量导出失败'));
+    } finally {
+      batchOpRef.current = null;
+      setBatchProgress(null);
     }
   };
 
+  const handleCancelBatch = async () => {
+    if (batchOpRef.current === null) return;
+    await invoke('asset_cancel_batch', { opId: batchOpRef.current }).catch(() => {});
+  };
+
   const handleBatchFavorite = async (favorite: boolean) => {
     if (selectedIds.size === 0) return;
     try {
@@ -2285,7 +2501,7 @@ export default function AssetManager() {
       await loadFavorites();
       showToast('success', favorite ? '已批量收藏' : '已批量取消收藏');
     } catch (e: any) {
-      showToast('error', e?.toString() || '批量收藏操作失败');
+      showToast('error', errorMessage(e, '批量收藏操作失败'));
     }
   };
 
@@ -2298,7 +2514,21 @@ export default function AssetManager() {
       }
       showToast('success', `已批量设置 ${rating} 星评分`);
     } catch (e: any) {
-      showToast('error', e?.toString() || '批量设置评分失败');
+      showToast('error', errorMessage(e, '批量设置评分失败'));
+    }
+  };
+
+  const handleUndo = async () => {
+    try {
+      const op = await invoke<{ id: number; kind: string; asset_count: number }>('asset_undo_last');
+      // 标签/评分缓存按需重新加载
+      setAssetTagsMap(new Map());
+      setAssetRatingsMap(new Map());
+      await loadFavorites();
+      if (detailAssetId) loadAssetDetail(detailAssetId);
+      showToast('success', `已撤销${UNDO_LABELS[op.kind] || '操作'}（${op.asset_count} 个资源）`);
+    } catch (e: any) {
+      showToast('info', errorMessage(e, '撤销失败'));
     }
   };
 
@@ -2333,7 +2563,7 @@ export default function AssetManager() {
       setShowSmartFolderEditor(false);
       showToast('success', `智能文件夹 "${name}" 已创建`);
     } catch (e: any) {
-      showToast('error', e?.toString() || '创建智能文件夹失败');
+      showToast('error', errorMessage(e, '创建智能文件夹失败'));
     }
   };
 
@@ -2344,7 +2574,7 @@ export default function AssetManager() {
       await loadSmartFolders();
       showToast('success', '智能文件夹已删除');
     } catch (e: any) {
-      showToast('error', e?.toString() || '删除失败');
+      showToast('error', errorMessage(e, '删除失败'));
     }
   };
 
@@ -2394,14 +2624,14 @@ export default function AssetManager() {
     if (!teamSharedRoot || !currentUser) return;
     try {
       const ok = await invoke<boolean>('team_acquire_lock', {
-        sharedRoot: teamSharedRoot, filePath, username: currentUser, machine: currentMachine,
+        sharedRoot: teamSharedRoot, filePath,
       });
       if (ok) {
         showToast('success', '已锁定文件');
         // Start heartbeat
         const timer = setInterval(async () => {
           await invoke('team_refresh_heartbeat', {
-            sharedRoot: teamSharedRoot, filePath, username: currentUser,
+            sharedRoot: teamSharedRoot, filePath,
           }).catch(() => {});
         }, 60000);
         setHeartbeatTimers(prev => new Map(prev).set(filePath, timer));
@@ -2414,7 +2644,7 @@ export default function AssetManager() {
         showToast('error', '文件已被他人锁定');
       }
     } catch (e: any) {
-      showToast('error', e?.toString() || '锁定失败');
+      showToast('error', errorMessage(e, '锁定失败'));
     }
   };
 
@@ -2422,7 +2652,7 @@ export default function AssetManager() {
     if (!teamSharedRoot || !currentUser) return;
     try {
       await invoke('team_release_lock', {
-        sharedRoot: teamSharedRoot, filePath, username: currentUser,
+        sharedRoot: teamSharedRoot, filePath,
       });
       // Stop heartbeat
       const timer = heartbeatTimers.get(filePath);
@@ -2430,7 +2660,7 @@ export default function AssetManager() {
       showToast('success', '已释放锁定');
       setDetailLockStatus({ is_locked: false, locked_by: null, machine: null, locked_at: null, is_stale: false });
     } catch (e: any) {
-      showToast('error', e?.toString() || '释放失败');
+      showToast('error', errorMessage(e, '释放失败'));
     }
   };
 
@@ -2442,7 +2672,7 @@ export default function AssetManager() {
       });
       showToast('success', `已恢复到版本 v${version}`);
     } catch (e: any) {
-      showToast('error', e?.toString() || '恢复失败');
+      showToast('error', errorMessage(e, '恢复失败'));
     }
   };
 
@@ -2478,6 +2708,13 @@ export default function AssetManager() {
         const searchInput = document.querySelector('[placeholder="搜索文件名..."]') as HTMLInputElement;
         searchInput?.focus();
       }
+      // Ctrl+Z: undo last batch metadata change
+      if (e.key === 'z' && (e.ctrlKey || e.metaKey) && !e.shiftKey) {
+        const target = e.target as HTMLElement;
+        if (target.tagName === 'INPUT' || target.tagName === 'TEXTAREA') return;
+        e.preventDefault();
+        handleUndo();
+      }
       // Delete: batch delete selected assets
       if (e.key === 'Delete' && selectedIds.size > 0) {
         handleBatchDelete();
@@ -2532,11 +2769,18 @@ export default function AssetManager() {
     try {
       const selected = await open({ directory: true, multiple: false, title: '选择资源文件夹' });
       if (!selected || typeof selected !== 'string') return;
-      await invoke('asset_add_folder', { path: selected, spaceType: space });
+      try {
+        await invoke('asset_add_folder', { path: selected, spaceType: space, autoScan: true });
+      } catch (e: any) {
+        // 与已有文件夹嵌套：确认后仍然添加（其中的文件会被重复计数）
+        if (e?.code !== 'nested_folder') throw e;
+        if (!window.confirm(`${e.message}，其中的文件会在两个文件夹中重复出现。仍要添加吗？`)) return;
+        await invoke('asset_add_folder', { path: selected, spaceType: space, allowNested: true, autoScan: true });
+      }
       await loadFolders();
       showToast('success', '文件夹已添加');
     } catch (e: any) {
-      showToast('error', e?.toString() || '添加失败');
+      showToast('error', errorMessage(e, '添加失败'));
     }
   };
 
@@ -2547,17 +2791,35 @@ export default function AssetManager() {
       await loadFolders();
       showToast('success', '文件夹已移除');
     } catch (e: any) {
-      showToast('error', e?.toString() || '移除失败');
+      showToast('error', errorMessage(e, '移除失败'));
     }
   };
 
   const handleScanFolder = async (folderId: number) => {
     setScanning(true);
-    setScanProgress({ folder_id: folderId, current: 0, total: 0, file_name: '准备扫描...', phase: 'scanning' });
+    setScanProgress({
+      folder_id: folderId, current: 0, total: 0, file_name: '准备扫描...', phase: 'scanning',
+      elapsed_secs: 0, items_per_sec: 0, eta_secs: null,
+    });
     try {
-      await invoke('asset_scan_folder', { folderId });
+      const summary = await invoke<{
+        oversized: [string, number, number][];
+        inaccessible: { count: number; samples: [string, string][] };
+        truncated: boolean;
+      }>('asset_scan_folder', { folderId });
+      if (summary.truncated) {
+        showToast('warning', '文件夹内条目过多（可能存在循环链接），扫描已提前结束，结果不完整');
+      }
+      if (summary.oversized.length > 0) {
+        showToast('warning', `${summary.oversized.length} 个文件尺寸过大，已跳过缩略图生成`);
+      }
+      if (summary.inaccessible.count > 0) {
+        const first = summary.inaccessible.samples[0];
+        showToast('warning', `${summary.inaccessible.count} 个文件或目录无法访问（可能没有权限），已跳过` +
+          (first ? `，如: ${first[0]}` : ''));
+      }
     } catch (e: any) {
-      showToast('error', '扫描失败: ' + (e?.toString() || ''));
+      showToast('error', '扫描失败: ' + errorMessage(e, '未知错误'));
       setScanning(false);
       setScanProgress(null);
     }
@@ -2759,6 +3021,12 @@ export default function AssetManager() {
               {scanProgress.phase === 'scanning' ? '扫描文件...' : `处理缩略图 ${scanProgress.current}/${scanProgress.total}`}
             </span>
             <span className="truncate max-w-[300px]">{scanProgress.file_name}</span>
+            {scanProgress.phase === 'thumbnails' && scanProgress.items_per_sec > 0 && (
+              <span className="ml-auto flex-none">
+                {scanProgress.items_per_sec.toFixed(1)} 个/秒
+                {scanProgress.eta_secs != null && ` · 剩余约 ${formatDuration(scanProgress.eta_secs)}`}
+              </span>
+            )}
           </div>
           {scanProgress.total > 0 && (
             <div className="mt-1 h-1 bg-[#1a1a1a] rounded-full overflow-hidden">
@@ -2771,6 +3039,29 @@ export default function AssetManager() {
         </div>
       )}
 
+      {batchProgress && (
+        <div className="flex-none px-4 py-2 bg-[#111] border-b border-[#222]">
+          <div className="flex items-center gap-3 text-xs text-[#888]">
+            <Loader2 size={14} className="animate-spin text-[#3b82f6]" />
+            <span>
+              {batchProgress.operation === 'export' ? '导出' : '删除'} {batchProgress.current}/{batchProgress.total}
+            </span>
+            <span className="truncate max-w-[300px]">{batchProgress.current_file}</span>
+            {batchProgress.operation === 'export' && (
+              <button onClick={handleCancelBatch} className="ml-auto text-[#888] hover:text-white">
+                取消
+              </button>
+            )}
+          </div>
+          <div className="mt-1 h-1 bg-[#1a1a1a] rounded-full overflow-hidden">
+            <div
+              className="h-full bg-[#3b82f6] rounded-full transition-all"
+              style={{ width: `${(batchProgress.current / batchProgress.total) * 100}%` }}
+            />
+          </div>
+        </div>
+      )}
+
       {/* Batch operations toolbar */}
       {selectedIds.size > 0 && (
         <div className="flex-none flex items-center gap-3 px-4 py-2 bg-[#1a2332] border-b border-[#2563eb]/30">
@@ -2795,7 +3086,7 @@ export default function AssetManager() {
                       }
                     }
                   } catch (e: any) {
-                    showToast('error', e?.toString() || '批量添加失败');
+                    showToast('error', errorMessage(e, '批量添加失败'));
                   }
                 }}
                 title={`批量添加标签: ${tag.name}`}
@@ -3232,7 +3523,10 @@ export default function AssetManager() {
 
       {/* FFmpeg Settings */}
       {showFfmpegSettings && (
-        <FfmpegSettingsPanel onClose={() => setShowFfmpegSettings(false)} />
+        <FfmpegSettingsPanel
+          onClose={() => setShowFfmpegSettings(false)}
+          onPreviewsGenerated={() => loadAssets()}
+        />
       )}
 
       {/* Compare Panel */}
@@ -2,7 +2,6 @@ import React, { useState, useRef, useCallback, useEffect, useMemo } from 'react'
 import { Download, RotateCcw, Plus, Save, ChevronDown, Share2, X, Copy, Check } from 'lucide-react';
 import { useToast } from './Toast';
 import { invoke } from '@tauri-apps/api/tauri';
-import { open } from '@tauri-apps/api/dialog';
 import { listen } from '@tauri-apps/api/event';
 import { appWindow } from '@tauri-apps/api/window';
 import UPNG from 'upng-js';
@@ -700,8 +699,9 @@ const CPSAutomation: React.FC = () => {
 
       if (isTauri) {
         // Tauri 环境：选择目录后保存到本地文件系统
-        const selectedDir = await open({ directory: true, multiple: false, title: '选择导出目录' });
-        if (!selectedDir || typeof selectedDir !== 'string') return;
+        // 由后端弹出对话框，选择的目录本次运行内允许写入
+        const selectedDir = await invoke<string | null>('select_writable_directory', { kind: 'export', title: '选择导出目录' });
+        if (!selectedDir) return;
         const sep = (selectedDir as string).includes('/') ? '/' : '\\';
         for (const img of images) {
           const buf = await img.blob.arrayBuffer();
@@ -946,8 +946,8 @@ async function doExport(){
     if (isTauri) {
       // Tauri: 保存 HTML 文件到选择的目录
       try {
-        const selectedDir = await open({ directory: true, multiple: false, title: '选择保存目录' });
-        if (!selectedDir || typeof selectedDir !== 'string') return;
+        const selectedDir = await invoke<string | null>('select_writable_directory', { kind: 'export', title: '选择保存目录' });
+        if (!selectedDir) return;
         const sep = (selectedDir as string).includes('/') ? '/' : '\\';
         const filePath = `${selectedDir}${sep}边锋掼蛋CPS素材生成.html`;
         const buf = await blob.arrayBuffer();
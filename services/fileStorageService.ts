@@ -1,6 +1,5 @@
 // 文件存储服务 - 使用 Tauri 文件系统 API 将数据存储到用户指定的目录
 
-import { open } from '@tauri-apps/api/dialog';
 import { readTextFile, writeTextFile, exists } from '@tauri-apps/api/fs';
 import { join } from '@tauri-apps/api/path';
 
@@ -54,17 +53,18 @@ export async function selectStorageDirectory(): Promise<{ path: string } | null>
   }
 
   try {
-    const selectedPath = await open({
-      directory: true,
-      multiple: false,
+    // 由后端弹出对话框：选择的目录会登记为允许写入的存储目录
+    const { invoke } = await import('@tauri-apps/api/tauri');
+    const selectedPath = await invoke<string | null>('select_writable_directory', {
+      kind: 'storage',
       title: '选择数据存储目录',
     });
 
-    if (!selectedPath || Array.isArray(selectedPath)) {
+    if (!selectedPath) {
       return null; // 用户取消了选择
     }
 
-    const directoryPath = selectedPath as string;
+    const directoryPath = selectedPath;
     cachedStoragePath = directoryPath;
 
 
@@ -90,6 +90,15 @@ export async function getSavedStoragePath(): Promise<string | null> {
 
   const config = getStorageConfig();
   if (config.directoryPath) {
+    // 旧版本选择的存储目录没有登记到后端，首次使用时补登记（后端只接受一次）
+    if (isTauriEnvironment()) {
+      try {
+        const { invoke } = await import('@tauri-apps/api/tauri');
+        await invoke('adopt_storage_root', { path: config.directoryPath });
+      } catch (error) {
+        console.warn('登记存储目录失败:', error);
+      }
+    }
     cachedStoragePath = config.directoryPath;
     return config.directoryPath;
   }
@@ -231,7 +240,9 @@ export async function importAllDataFromFile(): Promise<void> {
     let text: string;
     try {
       const { invoke } = await import('@tauri-apps/api/tauri');
-      text = await invoke('read_file_with_path', { filePath: dataFilePath });
+      // strict：文件过大时报错而不是截断，避免下次保存时丢失数据
+      const result = await invoke<{ content: string; truncated: boolean }>('read_file_with_path', { filePath: dataFilePath, strict: true });
+      text = result.content;
     } catch (error) {
       // 如果 Rust 命令失败，尝试使用 Tauri FS API
       try {
@@ -139,7 +139,9 @@ export async function loadNotesFromFile(presetId?: string): Promise<string | nul
     // 读取文件内容（优先使用 Rust 命令）
     let text: string;
     try {
-      text = await invoke('read_file_with_path', { filePath: filePath });
+      // strict：文件过大时报错而不是截断，避免下次保存时丢失内容
+      const result = await invoke<{ content: string; truncated: boolean }>('read_file_with_path', { filePath, strict: true });
+      text = result.content;
     } catch (error) {
       // 如果 Rust 命令失败，尝试使用 Tauri FS API
       try {
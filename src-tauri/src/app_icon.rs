@@ -0,0 +1,351 @@
+// 应用程序图标提取
+use serde::Serialize;
+
+/// 提取结果：图标 data URI + 实际像素尺寸（前端据此决定缩放）
+#[derive(Debug, Clone, Serialize)]
+pub struct AppIcon {
+    pub data_uri: String,
+    pub size: u32,
+}
+
+/// 图标组中常见的尺寸，从大到小依次尝试
+#[cfg(target_os = "windows")]
+const ICON_SIZE_LADDER: [u32; 7] = [256, 128, 96, 64, 48, 32, 16];
+
+/// 按目标尺寸提取图标：先取不小于目标的最近档位，失败后沿档位向下回退
+/// 都失败时（如 .lnk / .bat）退回 windows-icons 的默认小图标
+#[cfg(target_os = "windows")]
+pub fn extract_icon(path: &str, size: u32) -> Result<AppIcon, String> {
+    use base64::Engine;
+
+    let target = size.clamp(16, 256);
+    let start = ICON_SIZE_LADDER.iter()
+        .rposition(|&s| s >= target)
+        .unwrap_or(0);
+
+    for &s in &ICON_SIZE_LADDER[start..] {
+        if let Some((png, actual)) = extract_icon_png(path, s) {
+            return Ok(AppIcon {
+                data_uri: format!(
+                    "data:image/png;base64,{}",
+                    base64::engine::general_purpose::STANDARD.encode(png)
+                ),
+                size: actual,
+            });
+        }
+    }
+
+    let base64_icon = windows_icons::get_icon_base64_by_path(path)
+        .map_err(|e| format!("提取图标失败: {}", e))?;
+    // windows-icons 返回的是系统默认尺寸，从 PNG 头里读出实际大小
+    let actual = base64::engine::general_purpose::STANDARD.decode(&base64_icon)
+        .ok()
+        .and_then(|bytes| image::load_from_memory(&bytes).ok())
+        .map(|img| img.width())
+        .unwrap_or(32);
+    Ok(AppIcon {
+        data_uri: format!("data:image/png;base64,{}", base64_icon),
+        size: actual,
+    })
+}
+
+/// 从 exe/dll 的图标组中取出指定尺寸的图标并编码为 PNG
+/// PrivateExtractIconsW 会在图标组里选最接近的图像，文件里没有图标资源时返回 0
+#[cfg(target_os = "windows")]
+fn extract_icon_png(path: &str, size: u32) -> Option<(Vec<u8>, u32)> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::{mem, ptr};
+    use winapi::shared::windef::HICON;
+    use winapi::um::wingdi::DeleteObject;
+    use winapi::um::winuser::{DestroyIcon, GetIconInfo, PrivateExtractIconsW, ICONINFO};
+
+    let wide: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
+    let mut hicon: HICON = ptr::null_mut();
+    let count = unsafe {
+        PrivateExtractIconsW(
+            wide.as_ptr(),
+            0,
+            size as i32,
+            size as i32,
+            &mut hicon,
+            ptr::null_mut(),
+            1,
+            0,
+        )
+    };
+    // 0 表示没有图标，0xFFFFFFFF 表示文件无法读取
+    if count == 0 || count == u32::MAX || hicon.is_null() {
+        return None;
+    }
+
+    unsafe {
+        let mut info: ICONINFO = mem::zeroed();
+        let result = if GetIconInfo(hicon, &mut info) != 0 {
+            let png = bitmap_to_png(info.hbmColor);
+            if !info.hbmColor.is_null() {
+                DeleteObject(info.hbmColor as _);
+            }
+            if !info.hbmMask.is_null() {
+                DeleteObject(info.hbmMask as _);
+            }
+            png
+        } else {
+            None
+        };
+        DestroyIcon(hicon);
+        result
+    }
+}
+
+/// 读取 32 位彩色位图（BGRA）并转换为 PNG
+#[cfg(target_os = "windows")]
+unsafe fn bitmap_to_png(hbm: winapi::shared::windef::HBITMAP) -> Option<(Vec<u8>, u32)> {
+    use std::{mem, ptr};
+    use winapi::um::wingdi::{
+        GetDIBits, GetObjectW, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+    };
+    use winapi::um::winuser::{GetDC, ReleaseDC};
+
+    if hbm.is_null() {
+        return None;
+    }
+
+    let mut bmp: BITMAP = mem::zeroed();
+    if GetObjectW(hbm as _, mem::size_of::<BITMAP>() as i32, &mut bmp as *mut _ as _) == 0 {
+        return None;
+    }
+    let (width, height) = (bmp.bmWidth, bmp.bmHeight);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    let mut bi: BITMAPINFO = mem::zeroed();
+    bi.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as u32;
+    bi.bmiHeader.biWidth = width;
+    bi.bmiHeader.biHeight = -height; // 负值表示自上而下的行序
+    bi.bmiHeader.biPlanes = 1;
+    bi.bmiHeader.biBitCount = 32;
+    bi.bmiHeader.biCompression = BI_RGB;
+
+    let mut buf = vec![0u8; (width * height * 4) as usize];
+    let hdc = GetDC(ptr::null_mut());
+    let lines = GetDIBits(
+        hdc,
+        hbm,
+        0,
+        height as u32,
+        buf.as_mut_ptr() as _,
+        &mut bi,
+        DIB_RGB_COLORS,
+    );
+    ReleaseDC(ptr::null_mut(), hdc);
+    if lines == 0 {
+        return None;
+    }
+
+    // 老式图标没有 alpha 通道（全 0），此时按不透明处理
+    let no_alpha = buf.chunks_exact(4).all(|p| p[3] == 0);
+    for p in buf.chunks_exact_mut(4) {
+        p.swap(0, 2);
+        if no_alpha {
+            p[3] = 255;
+        }
+    }
+
+    let img = image::RgbaImage::from_raw(width as u32, height as u32, buf)?;
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgba8(img)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .ok()?;
+    Some((png, width as u32))
+}
+
+/// 在候选尺寸中选择：优先取不小于目标的最小尺寸，没有则取最大的
+#[cfg(not(target_os = "windows"))]
+fn pick_size(sizes: impl IntoIterator<Item = u32>, target: u32) -> Option<u32> {
+    let sizes: Vec<u32> = sizes.into_iter().collect();
+    sizes.iter().copied().filter(|&s| s >= target).min()
+        .or_else(|| sizes.iter().copied().max())
+}
+
+/// macOS：读取 .app/Contents/Info.plist 的 CFBundleIconFile，把 .icns 转为 PNG
+#[cfg(target_os = "macos")]
+pub fn extract_icon(path: &str, size: u32) -> Result<AppIcon, String> {
+    use base64::Engine;
+    use std::path::Path;
+
+    let bundle = Path::new(path);
+    if bundle.extension().and_then(|e| e.to_str()) != Some("app") {
+        return Err("不支持的文件类型".to_string());
+    }
+
+    let contents = bundle.join("Contents");
+    let info = plist::Value::from_file(contents.join("Info.plist"))
+        .map_err(|e| format!("读取 Info.plist 失败: {}", e))?;
+    let icon_name = info.as_dictionary()
+        .and_then(|d| d.get("CFBundleIconFile"))
+        .and_then(|v| v.as_string())
+        .ok_or_else(|| "应用未声明图标 (CFBundleIconFile)".to_string())?;
+
+    // CFBundleIconFile 可以省略 .icns 扩展名
+    let mut icns_path = contents.join("Resources").join(icon_name);
+    if icns_path.extension().is_none() {
+        icns_path.set_extension("icns");
+    }
+
+    let file = std::fs::File::open(&icns_path)
+        .map_err(|e| format!("打开图标文件失败: {}", e))?;
+    let family = icns::IconFamily::read(std::io::BufReader::new(file))
+        .map_err(|e| format!("解析 icns 失败: {}", e))?;
+
+    let types = family.available_icons();
+    let best = pick_size(types.iter().map(|t| t.pixel_width()), size.max(1))
+        .and_then(|w| types.iter().find(|t| t.pixel_width() == w).copied())
+        .ok_or_else(|| "icns 中没有可用的图标".to_string())?;
+    let image = family.get_icon_with_type(best)
+        .map_err(|e| format!("提取图标失败: {}", e))?;
+
+    let mut png = Vec::new();
+    image.write_png(&mut png)
+        .map_err(|e| format!("编码 PNG 失败: {}", e))?;
+    Ok(AppIcon {
+        data_uri: format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        ),
+        size: best.pixel_width(),
+    })
+}
+
+/// Linux 图标主题中常见的尺寸目录
+#[cfg(all(unix, not(target_os = "macos")))]
+const THEME_SIZES: [u32; 9] = [512, 256, 192, 128, 96, 64, 48, 32, 16];
+
+/// Linux：解析 .desktop 文件的 Icon= 键，并在图标主题 / pixmaps 中查找对应文件
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn extract_icon(path: &str, size: u32) -> Result<AppIcon, String> {
+    use base64::Engine;
+    use std::path::PathBuf;
+
+    if !path.to_lowercase().ends_with(".desktop") {
+        return Err("不支持的文件类型".to_string());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("读取 .desktop 文件失败: {}", e))?;
+    let icon = desktop_entry_icon(&content)
+        .ok_or_else(|| "应用未声明图标 (Icon=)".to_string())?;
+
+    // Icon= 可以是绝对路径，也可以是主题中的图标名
+    let icon_path = if icon.starts_with('/') {
+        Some(PathBuf::from(&icon)).filter(|p| p.is_file())
+    } else {
+        find_theme_icon(&icon, size.max(1))
+    }
+    .ok_or_else(|| format!("找不到图标: {}", icon))?;
+
+    let bytes = std::fs::read(&icon_path)
+        .map_err(|e| format!("读取图标失败: {}", e))?;
+    let ext = icon_path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let (mime, bytes, actual) = if ext == "svg" {
+        // 矢量图标可任意缩放，直接按请求尺寸返回
+        ("image/svg+xml", bytes, size)
+    } else {
+        let img = image::load_from_memory(&bytes)
+            .map_err(|e| format!("解析图标失败: {}", e))?;
+        let bytes = if ext == "png" {
+            bytes
+        } else {
+            // xpm 等非 PNG 位图统一转为 PNG
+            let mut png = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                .map_err(|e| format!("编码 PNG 失败: {}", e))?;
+            png
+        };
+        ("image/png", bytes, img.width())
+    };
+
+    Ok(AppIcon {
+        data_uri: format!(
+            "data:{};base64,{}",
+            mime,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+        size: actual,
+    })
+}
+
+/// 读取 [Desktop Entry] 段中的 Icon= 值（忽略本地化的 Icon[xx]=）
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_entry_icon(content: &str) -> Option<String> {
+    let mut in_entry = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "Icon" && !value.trim().is_empty() {
+                return Some(value.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+/// 按 XDG 图标规范的常见布局查找：hicolor 各尺寸目录 → scalable → pixmaps
+#[cfg(all(unix, not(target_os = "macos")))]
+fn find_theme_icon(name: &str, size: u32) -> Option<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    let mut data_dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(home) = std::env::var("XDG_DATA_HOME") {
+        data_dirs.push(PathBuf::from(home));
+    } else if let Ok(home) = std::env::var("HOME") {
+        data_dirs.push(PathBuf::from(home).join(".local/share"));
+    }
+    let system_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    data_dirs.extend(system_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+
+    let theme_dirs: Vec<PathBuf> = data_dirs.iter().map(|d| d.join("icons/hicolor")).collect();
+
+    // 先在各尺寸目录中收集存在的位图，再按目标尺寸挑选
+    let mut found: Vec<(u32, PathBuf)> = Vec::new();
+    for dir in &theme_dirs {
+        for s in THEME_SIZES {
+            let candidate = dir.join(format!("{0}x{0}/apps/{1}.png", s, name));
+            if candidate.is_file() {
+                found.push((s, candidate));
+            }
+        }
+    }
+    if let Some(best) = pick_size(found.iter().map(|(s, _)| *s), size) {
+        return found.into_iter().find(|(s, _)| *s == best).map(|(_, p)| p);
+    }
+
+    let scalable = theme_dirs.iter()
+        .map(|d| d.join(format!("scalable/apps/{}.svg", name)))
+        .find(|p| p.is_file());
+    if scalable.is_some() {
+        return scalable;
+    }
+
+    ["png", "svg", "xpm"].iter()
+        .map(|ext| PathBuf::from(format!("/usr/share/pixmaps/{}.{}", name, ext)))
+        .find(|p| p.is_file())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn extract_icon(_path: &str, _size: u32) -> Result<AppIcon, String> {
+    Err("当前平台不支持图标提取".to_string())
+}
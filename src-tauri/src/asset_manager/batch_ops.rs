@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 进行中的批量操作（导出/转换）的取消标记
+///
+/// 前端先用 reserve 领取操作 ID，再把 ID 传给批量命令；
+/// 命令在处理每个文件之前检查标记，被取消时返回已完成的部分结果
+#[derive(Default)]
+pub struct BatchOps {
+    next_id: AtomicU64,
+    tokens: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+}
+
+impl BatchOps {
+    /// 预留一个操作 ID（批量命令开始前即可取消）
+    pub fn reserve(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.insert(id, Arc::new(AtomicBool::new(false)));
+        id
+    }
+
+    /// 开始跟踪批量操作。op_id 为 None 时分配一个新 ID（此时无法从外部取消）
+    pub fn track(&self, op_id: Option<u64>) -> BatchTicket<'_> {
+        let id = op_id.unwrap_or_else(|| self.reserve());
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let cancelled = tokens.entry(id).or_default().clone();
+        BatchTicket { ops: self, id, cancelled }
+    }
+
+    /// 请求取消，返回该操作是否存在
+    pub fn cancel(&self, op_id: u64) -> bool {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        match tokens.get(&op_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&self, op_id: u64) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.remove(&op_id);
+    }
+}
+
+/// 批量操作票据：持有期间可被取消，释放时自动移除取消标记
+pub struct BatchTicket<'a> {
+    ops: &'a BatchOps,
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl BatchTicket<'_> {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for BatchTicket<'_> {
+    fn drop(&mut self) {
+        self.ops.remove(self.id);
+    }
+}
@@ -0,0 +1,28 @@
+use std::borrow::Cow;
+
+/// 把文件引用放到剪贴板（CF_HDROP），在资源管理器中粘贴即复制文件
+#[cfg(target_os = "windows")]
+pub fn copy_files(paths: &[String]) -> Result<(), String> {
+    use clipboard_win::{formats, set_clipboard};
+
+    set_clipboard(formats::FileList, paths)
+        .map_err(|e| format!("写入剪贴板失败: {}", e))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn copy_files(_paths: &[String]) -> Result<(), String> {
+    Err("当前平台暂不支持复制文件到剪贴板".to_string())
+}
+
+/// 把解码后的位图放到剪贴板（Windows 上为 CF_DIB），可直接粘贴到图像编辑器
+pub fn copy_image(img: &image::DynamicImage) -> Result<(), String> {
+    let rgba = img.to_rgba8();
+    let data = arboard::ImageData {
+        width: rgba.width() as usize,
+        height: rgba.height() as usize,
+        bytes: Cow::Owned(rgba.into_raw()),
+    };
+    arboard::Clipboard::new()
+        .and_then(|mut cb| cb.set_image(data))
+        .map_err(|e| format!("写入剪贴板失败: {}", e))
+}
@@ -1,9 +1,24 @@
+use std::sync::atomic::Ordering;
 use tauri::{AppHandle, Manager};
-use crate::asset_manager::db::{self, AssetManagerState, AssetQueryParams, AssetQueryResult, FolderInfo, FolderStats, ScanProgress, TagInfo, AssetDetail, SmartFolder};
+use crate::asset_manager::db::{self, AssetManagerState, AssetQueryParams, AssetQueryResult, FolderInfo, FolderStats, SubfolderInfo, ScanError, ScanProgress, ScanSummary, ScanWarning, VerifySummary, ThumbnailProgress, BatchProgress, BatchResult, TagInfo, AssetDetail, SmartFolder};
 use crate::asset_manager::scanner;
+use crate::asset_manager::scan_queue;
+use crate::asset_manager::progress::ProgressTimer;
+use crate::asset_manager::importer;
+use crate::asset_manager::sidecar;
+use crate::asset_manager::contact_sheet;
 use crate::asset_manager::thumbnail;
+use crate::asset_manager::thumb_provider;
+use crate::asset_manager::placeholder;
+use crate::asset_manager::formats;
 use crate::asset_manager::team;
+use crate::asset_manager::shell;
+use crate::asset_manager::clipboard;
 use crate::asset_manager::ffmpeg;
+use crate::asset_manager::transform::{self, ImageOp};
+use crate::asset_manager::optimize;
+use crate::asset_manager::convert::{self, ConvertSummary, ExportSummary, OutputFailure, OutputFile, OutputFormat};
+use crate::error::ArtHubError;
 
 // ---- 初始化 ----
 
@@ -12,18 +27,25 @@ use crate::asset_manager::ffmpeg;
 pub fn asset_get_folders(
     state: tauri::State<'_, AssetManagerState>,
     space_type: Option<String>,
-) -> Result<Vec<FolderInfo>, String> {
-    let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
+) -> Result<Vec<FolderInfo>, ArtHubError> {
+    let conn = state.db.read()?;
     db::get_folders(&conn, space_type.as_deref())
 }
 
-/// 添加文件夹
+/// 添加文件夹。auto_scan 为 true 时新添加的文件夹立即在后台开始扫描，命令不等待扫描完成，
+/// 进度通过 "asset-scan-progress" 事件通知，失败时发送 "asset-scan-error"；已添加过的文件夹不会重新扫描
 #[tauri::command]
 pub fn asset_add_folder(
+    app: AppHandle,
     state: tauri::State<'_, AssetManagerState>,
     path: String,
     space_type: String,
-) -> Result<FolderInfo, String> {
+    allow_nested: Option<bool>,
+    auto_scan: Option<bool>,
+) -> Result<FolderInfo, ArtHubError> {
+    // C:\Art\、C:/Art、c:\art 等写法统一为同一个路径
+    let path = scanner::normalize_folder_path(&path).map_err(ArtHubError::InvalidInput)?;
+
     // 提取文件夹名
     let name = std::path::Path::new(&path)
         .file_name()
@@ -31,8 +53,93 @@ pub fn asset_add_folder(
         .to_string_lossy()
         .to_string();
 
-    let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
-    db::insert_folder(&conn, &path, &name, &space_type)
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+
+    // 已添加过的目录直接返回；与已有文件夹互相嵌套时同一批文件会被扫描两次、重复计数，
+    // 除非调用方确认（allow_nested）否则返回 NestedFolder 由前端提示
+    let allow_nested = allow_nested.unwrap_or(false);
+    let key = scanner::folder_path_key(&path);
+    for folder in db::get_folders(&conn, None)? {
+        let existing = scanner::folder_path_key(&folder.path);
+        if existing == key {
+            return Ok(folder);
+        }
+        if allow_nested {
+            continue;
+        }
+        if scanner::is_path_key_within(&key, &existing) {
+            return Err(ArtHubError::NestedFolder(format!("该目录位于已添加的文件夹 {} 中", folder.path)));
+        }
+        if scanner::is_path_key_within(&existing, &key) {
+            return Err(ArtHubError::NestedFolder(format!("该目录包含已添加的文件夹 {}", folder.path)));
+        }
+    }
+
+    let folder = db::insert_folder(&conn, &path, &name, &space_type)?;
+    if auto_scan.unwrap_or(false) {
+        let folder_id = folder.id;
+        tauri::async_runtime::spawn(async move {
+            let handle = app.clone();
+            if let Err(e) = asset_scan_folder(app, handle.state::<AssetManagerState>(), folder_id).await {
+                log::error!("[AssetManager] 自动扫描失败 (folder {}): {}", folder_id, e);
+                let _ = handle.emit_all("asset-scan-error", ScanError { folder_id, message: e.to_string() });
+            }
+        });
+    }
+    Ok(folder)
+}
+
+/// 获取扫描根目录下的子目录列表（含资产数），供前端构建目录树
+#[tauri::command]
+pub fn asset_get_subfolders(
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+) -> Result<Vec<SubfolderInfo>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_subfolders(&conn, folder_id)
+}
+
+/// 统计子目录（含所有下级目录）的资产数和总大小，供目录树显示占用空间
+#[tauri::command]
+pub fn asset_get_subtree_stats(
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+    subpath_prefix: String,
+) -> Result<db::SubtreeStats, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_subtree_stats(&conn, folder_id, &subpath_prefix)
+}
+
+/// 设置文件夹只读（只读文件夹内的文件不会被移动、删除、恢复版本或写入 .arthub 元数据）
+#[tauri::command]
+pub fn asset_set_folder_readonly(
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+    readonly: bool,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_folder_readonly(&conn, folder_id, readonly)
+}
+
+/// 设置文件夹扫描时是否读取旁注文件（foo.png.txt / foo.txt 逗号分隔标签，或 foo.json 的 tags/rating/caption），
+/// 下次扫描时生效
+#[tauri::command]
+pub fn asset_set_folder_sidecars(
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+    enabled: bool,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_folder_read_sidecars(&conn, folder_id, enabled)
+}
+
+/// 写磁盘前检查目标路径不在只读文件夹内
+fn ensure_writable(state: &AssetManagerState, path: &str) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    if db::is_path_readonly(&conn, path)? {
+        return Err(ArtHubError::ReadOnly(format!("该文件位于只读文件夹中，禁止修改: {}", path)));
+    }
+    Ok(())
 }
 
 /// 移除文件夹
@@ -40,11 +147,11 @@ pub fn asset_add_folder(
 pub fn asset_remove_folder(
     state: tauri::State<'_, AssetManagerState>,
     folder_id: i64,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
 
-    // 获取文件夹下所有资产路径，用于清理缩略图
-    let mut stmt = conn.prepare("SELECT file_path FROM assets WHERE folder_id = ?1")
+    // 获取文件夹下所有资产的缩略图路径，用于清理缩略图
+    let mut stmt = conn.prepare("SELECT thumb_path FROM assets WHERE folder_id = ?1")
         .map_err(|e| format!("查询失败: {}", e))?;
     let paths: Vec<String> = stmt.query_map(rusqlite::params![folder_id], |row| row.get(0))
         .map_err(|e| format!("查询失败: {}", e))?
@@ -58,113 +165,411 @@ pub fn asset_remove_folder(
     db::remove_folder(&conn, folder_id)
 }
 
-/// 扫描文件夹（异步，发送进度事件）
+/// 单个扫描文件的缩略图处理结果
+pub(crate) struct ScanThumb {
+    pub(crate) thumb_path: String,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    failure: Option<String>,
+    unsupported: bool,
+    /// 声明尺寸超过像素上限，未解码
+    oversized: Option<(u32, u32)>,
+    /// 留给后台缩略图队列生成（只扫描元数据时）
+    deferred: bool,
+}
+
+impl ScanThumb {
+    /// 只记录元数据，缩略图由后台队列生成
+    fn deferred() -> Self {
+        ScanThumb {
+            thumb_path: String::new(), width: 0, height: 0,
+            failure: None, unsupported: false, oversized: None, deferred: true,
+        }
+    }
+
+    pub(crate) fn succeeded(&self) -> bool {
+        self.failure.is_none() && self.oversized.is_none()
+    }
+
+    /// 写入 assets.thumb_status 的状态
+    pub(crate) fn status(&self, ext: &str) -> &'static str {
+        if self.deferred {
+            db::THUMB_STATUS_PENDING
+        } else if self.failure.is_some() || self.oversized.is_some() {
+            db::THUMB_STATUS_FAILED
+        } else if self.unsupported && (thumbnail::is_video(ext) || thumbnail::is_audio(ext)) {
+            // 视频缩略图/音频波形图需要 FFmpeg，之后由 asset_generate_missing_media_thumbnails 补齐
+            db::THUMB_STATUS_PENDING
+        } else if self.unsupported {
+            db::THUMB_STATUS_NONE
+        } else {
+            db::THUMB_STATUS_OK
+        }
+    }
+}
+
+/// 为扫描到的文件生成缩略图（在阻塞线程中调用）
+pub(crate) fn scan_thumbnail(path: &str, ext: &str, thumb_dir: &std::path::Path, use_placeholders: bool) -> ScanThumb {
+    if thumbnail::can_generate_thumbnail(ext) {
+        // 先按文件头检查尺寸，超大的文件直接跳过，避免解码时占用数 GB 内存
+        if let Some((w, h)) = thumb_provider::exceeds_pixel_budget(std::path::Path::new(path), ext) {
+            return ScanThumb {
+                thumb_path: String::new(), width: w, height: h,
+                failure: None, unsupported: false, oversized: Some((w, h)), deferred: false,
+            };
+        }
+        match thumbnail::generate_thumbnail(path, thumb_dir, 300) {
+            Ok(result) => ScanThumb {
+                thumb_path: result.thumb_path, width: result.width, height: result.height,
+                failure: None, unsupported: false, oversized: None, deferred: false,
+            },
+            Err(e) => ScanThumb {
+                thumb_path: String::new(), width: 0, height: 0,
+                failure: Some(e), unsupported: false, oversized: None, deferred: false,
+            },
+        }
+    } else {
+        // 无法预览的格式，使用按扩展名缓存的分类占位图
+        let thumb_path = if use_placeholders {
+            placeholder::generate_placeholder_thumbnail(ext, thumb_dir)
+                .map(|r| r.thumb_path)
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+        ScanThumb { thumb_path, width: 0, height: 0, failure: None, unsupported: true, oversized: None, deferred: false }
+    }
+}
+
+/// 扫描时每写入这么多条资产做一次 PASSIVE 检查点
+const SCAN_CHECKPOINT_INTERVAL: usize = 2000;
+
+/// 扫描文件夹（异步，发送进度事件），返回扫描汇总（含缩略图失败明细）
+/// 扫描通过 scan_queue 排队：最多 MAX_CONCURRENT_SCANS 个同时运行，同一文件夹的重复请求会被拒绝
 #[tauri::command]
 pub async fn asset_scan_folder(
     app: AppHandle,
     state: tauri::State<'_, AssetManagerState>,
     folder_id: i64,
-) -> Result<u32, String> {
+) -> Result<ScanSummary, ArtHubError> {
+    let fid = folder_id;
+
+    // 0. 加入扫描队列并等待名额
+    let mut ticket = state.scan_queue.enqueue(fid)?;
+    let _ = app.emit_all("asset-scan-progress", ScanProgress {
+        folder_id: fid,
+        current: 0,
+        total: 0,
+        file_name: String::new(),
+        phase: "queued".to_string(),
+        elapsed_secs: 0.0,
+        items_per_sec: 0.0,
+        eta_secs: None,
+    });
+    ticket.wait_for_slot().await?;
+    let mut timer = ProgressTimer::rolling();
+
+    let use_placeholders = state.placeholder_thumbnails.load(Ordering::Relaxed);
+    let defer_thumbnails = state.thumb_worker.is_enabled();
+
     // 1. 获取文件夹路径
-    let (folder_path, thumb_dir) = {
-        let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
-        let path: String = conn.query_row(
-            "SELECT path FROM folders WHERE id = ?1",
+    let (folder_path, space_type, read_sidecars, thumb_dir) = {
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        let (path, space_type, read_sidecars): (String, String, bool) = conn.query_row(
+            "SELECT path, space_type, read_sidecars FROM folders WHERE id = ?1",
             rusqlite::params![folder_id],
-            |row| row.get(0),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         ).map_err(|e| format!("查询文件夹失败: {}", e))?;
 
         // 清空旧记录
         db::clear_folder_assets(&conn, folder_id)?;
 
-        (path, state.thumb_dir.clone())
+        (path, space_type, read_sidecars, state.thumb_dir.clone())
     };
 
     // 2. 扫描文件系统（在阻塞线程中执行）
-    let app_clone = app.clone();
-    let fid = folder_id;
-
-    let files = tokio::task::spawn_blocking(move || {
+    let scanner::DirectoryScan { files, inaccessible, truncated } = tokio::task::spawn_blocking(move || {
         scanner::scan_directory(&folder_path)
     }).await.map_err(|e| format!("扫描线程失败: {}", e))??;
 
+    if inaccessible.count > 0 || truncated {
+        let _ = app.emit_all("asset-scan-warning", ScanWarning {
+            folder_id: fid,
+            inaccessible: inaccessible.clone(),
+            truncated,
+        });
+    }
+
     let total = files.len() as u32;
 
     // 发送扫描开始事件
-    let _ = app.emit_all("asset-scan-progress", ScanProgress {
-        folder_id: fid,
-        current: 0,
-        total,
-        file_name: String::new(),
-        phase: "scanning".to_string(),
-    });
+    let _ = app.emit_all("asset-scan-progress", timer.progress(fid, 0, total, String::new(), "scanning"));
 
-    // 3. 逐个处理文件：生成缩略图 + 写入数据库
+    // 3. 分批处理：缩略图在阻塞线程中生成，每批在一个短事务中写入数据库
     let mut processed = 0u32;
+    let mut thumbnail_failures = Vec::new();
+    let mut unsupported = 0u32;
+    let mut oversized = Vec::new();
+    let mut sidecars_applied = 0u32;
     let batch_size = 20;
-    let mut batch = Vec::with_capacity(batch_size);
-
-    for file in &files {
-        // 尝试生成缩略图
-        let (thumb_path, width, height) = if thumbnail::can_generate_thumbnail(&file.ext) {
-            match thumbnail::generate_thumbnail(&file.path, &thumb_dir, 300) {
-                Ok(result) => (result.thumb_path, result.width, result.height),
-                Err(_) => (String::new(), 0, 0),
+    let mut since_checkpoint = 0usize;
+
+    for chunk in files.chunks(batch_size) {
+        let batch = chunk.to_vec();
+        let dir = thumb_dir.clone();
+        let results = tokio::task::spawn_blocking(move || {
+            batch.into_iter()
+                .map(|f| {
+                    // 后台队列开启时只记录元数据，图片缩略图稍后生成（占位图很快，仍然直接生成）
+                    let thumb = if defer_thumbnails && thumbnail::can_generate_thumbnail(&f.ext) {
+                        ScanThumb::deferred()
+                    } else {
+                        scan_thumbnail(&f.path, &f.ext, &dir, use_placeholders)
+                    };
+                    let meta = if read_sidecars {
+                        sidecar::read_sidecar(std::path::Path::new(&f.path)).unwrap_or_else(|e| {
+                            log::warn!("[AssetManager] {}", e);
+                            None
+                        })
+                    } else {
+                        None
+                    };
+                    (f, thumb, meta)
+                })
+                .collect::<Vec<_>>()
+        }).await.map_err(|e| format!("缩略图线程失败: {}", e))?;
+
+        // 批量写入数据库
+        {
+            let mut conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+            let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+            let rows: Vec<db::AssetRow> = results.iter()
+                .map(|(f, t, _)| db::AssetRow {
+                    file: f, width: t.width, height: t.height,
+                    thumb_path: &t.thumb_path, thumb_status: t.status(&f.ext),
+                })
+                .collect();
+            db::upsert_assets(&tx, fid, &rows)?;
+            for (f, _, meta) in &results {
+                let Some(meta) = meta else { continue };
+                match db::apply_sidecar(&tx, &f.path, meta, &space_type) {
+                    Ok(()) => sidecars_applied += 1,
+                    Err(e) => log::warn!("[AssetManager] 应用旁注失败: {}", e),
+                }
             }
-        } else {
-            // 非图片格式，暂时不生成缩略图
-            (String::new(), 0, 0)
-        };
-
-        batch.push((file, thumb_path, width, height));
-
-        if batch.len() >= batch_size {
-            // 批量写入数据库
-            {
-                let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
-                for (f, tp, w, h) in &batch {
-                    let _ = db::upsert_asset(
-                        &conn, fid, &f.path, &f.name, &f.ext,
-                        f.size as i64, *w, *h, tp, f.modified as i64,
-                    );
+            tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+
+            // 大量写入时读连接可能一直阻止自动检查点回绕 WAL，定期主动做一次 PASSIVE 检查点
+            since_checkpoint += results.len();
+            if since_checkpoint >= SCAN_CHECKPOINT_INTERVAL {
+                since_checkpoint = 0;
+                if let Err(e) = db::checkpoint_wal(&conn, db::CheckpointMode::Passive) {
+                    log::warn!("[AssetManager] {}", e);
                 }
             }
-            processed += batch.len() as u32;
-            batch.clear();
-
-            // 发送进度
-            let _ = app.emit_all("asset-scan-progress", ScanProgress {
-                folder_id: fid,
-                current: processed,
-                total,
-                file_name: file.name.clone(),
-                phase: "thumbnails".to_string(),
-            });
         }
-    }
 
-    // 处理剩余批次
-    if !batch.is_empty() {
-        let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
-        for (f, tp, w, h) in &batch {
-            let _ = db::upsert_asset(
-                &conn, fid, &f.path, &f.name, &f.ext,
-                f.size as i64, *w, *h, tp, f.modified as i64,
-            );
+        for (f, t, _) in results {
+            if let Some((w, h)) = t.oversized {
+                oversized.push((f.path.clone(), w, h));
+            }
+            if let Some(reason) = t.failure {
+                thumbnail_failures.push((f.path, reason));
+            }
+            if t.unsupported {
+                unsupported += 1;
+            }
         }
-        processed += batch.len() as u32;
+        processed += chunk.len() as u32;
+
+        // 发送进度
+        let file_name = chunk.last().map(|f| f.name.clone()).unwrap_or_default();
+        let _ = app.emit_all("asset-scan-progress", timer.progress(fid, processed, total, file_name, "thumbnails"));
     }
 
     // 发送完成事件
-    let _ = app.emit_all("asset-scan-progress", ScanProgress {
+    let _ = app.emit_all("asset-scan-progress", timer.progress(fid, processed, total, String::new(), "complete"));
+
+    // 元数据已入库，开始在后台生成缩略图
+    if defer_thumbnails {
+        state.thumb_worker.kick(&app);
+    }
+
+    Ok(ScanSummary {
         folder_id: fid,
-        current: processed,
         total,
-        file_name: String::new(),
-        phase: "complete".to_string(),
-    });
+        processed,
+        thumbnail_failures,
+        oversized,
+        unsupported,
+        inaccessible,
+        truncated,
+        sidecars_applied,
+    })
+}
 
-    Ok(processed)
+/// 导入从系统拖入的文件/目录到管理文件夹（copy=false 时移动），生成缩略图并登记资产
+/// 目录会递归导入并保留目录结构。返回新资产 ID 供前端选中，单个文件失败不会中断导入。
+#[tauri::command]
+pub async fn asset_import_dropped(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+    paths: Vec<String>,
+    copy: bool,
+) -> Result<Vec<i64>, ArtHubError> {
+    let root = {
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        let path: String = conn.query_row(
+            "SELECT path FROM folders WHERE id = ?1",
+            rusqlite::params![folder_id],
+            |row| row.get(0),
+        ).map_err(|e| format!("查询文件夹失败: {}", e))?;
+        if db::is_path_readonly(&conn, &path)? {
+            return Err(ArtHubError::ReadOnly(format!("目标文件夹为只读，无法导入: {}", path)));
+        }
+        std::path::PathBuf::from(path)
+    };
+    let thumb_dir = state.thumb_dir.clone();
+    let use_placeholders = state.placeholder_thumbnails.load(Ordering::Relaxed);
+
+    let dropped = tokio::task::spawn_blocking(move || importer::collect_dropped(&paths))
+        .await.map_err(|e| format!("扫描线程失败: {}", e))?;
+    let total = dropped.len() as u32;
+    let mut asset_ids = Vec::new();
+    let mut timer = ProgressTimer::rolling();
+
+    for (i, file) in dropped.into_iter().enumerate() {
+        let file_name = file.source.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let root = root.clone();
+        let thumb_dir = thumb_dir.clone();
+        let imported = tokio::task::spawn_blocking(move || {
+            importer::import_file(&file, &root, copy).map(|f| {
+                let thumb = scan_thumbnail(&f.path, &f.ext, &thumb_dir, use_placeholders);
+                (f, thumb)
+            })
+        }).await.map_err(|e| format!("导入线程失败: {}", e))?;
+
+        match imported {
+            Ok((f, t)) => {
+                let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+                asset_ids.push(db::upsert_asset(
+                    &conn, folder_id, &f.path, &f.name, &f.ext,
+                    f.size as i64, t.width, t.height, &t.thumb_path, f.modified as i64, &f.rel_dir,
+                    t.status(&f.ext),
+                )?);
+            }
+            Err(e) => log::error!("[AssetManager] 导入失败: {}", e),
+        }
+
+        let _ = app.emit_all("asset-import-progress", timer.progress(folder_id, i as u32 + 1, total, file_name, "importing"));
+    }
+
+    let _ = app.emit_all("asset-import-progress", timer.progress(folder_id, total, total, String::new(), "complete"));
+
+    Ok(asset_ids)
+}
+
+/// 校验文件夹内的资产文件是否仍然存在，把缺失的标记为 is_missing（发送 "asset-verify-progress" 事件）
+/// 只更新标记、不删除记录；确认后可调用 asset_purge_missing 清理
+#[tauri::command]
+pub async fn asset_verify_folder(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+) -> Result<VerifySummary, ArtHubError> {
+    let assets = {
+        let conn = state.db.read()?;
+        db::get_folder_asset_paths(&conn, folder_id)?
+    };
+    let total = assets.len() as u32;
+    let mut checked = 0u32;
+    let mut timer = ProgressTimer::average();
+    let mut missing = 0u32;
+
+    for chunk in assets.chunks(200) {
+        let paths: Vec<String> = chunk.iter().map(|(_, p)| p.clone()).collect();
+        let exists = tokio::task::spawn_blocking(move || crate::fs_util::paths_exist(&paths))
+            .await.map_err(|e| format!("校验线程失败: {}", e))?;
+
+        let flags: Vec<(i64, bool)> = chunk.iter()
+            .zip(exists)
+            .map(|((id, _), exists)| (*id, !exists))
+            .collect();
+        missing += flags.iter().filter(|(_, m)| *m).count() as u32;
+        {
+            let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+            let tx = conn.unchecked_transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+            db::set_assets_missing(&tx, &flags)?;
+            tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+        }
+
+        checked += chunk.len() as u32;
+        let file_name = chunk.last().map(|(_, p)| p.clone()).unwrap_or_default();
+        let _ = app.emit_all("asset-verify-progress", timer.progress(folder_id, checked, total, file_name, "verifying"));
+    }
+
+    let _ = app.emit_all("asset-verify-progress", timer.progress(folder_id, total, total, String::new(), "complete"));
+
+    Ok(VerifySummary { folder_id, checked, missing })
+}
+
+/// 删除文件夹内所有标记为缺失的资产记录及其缩略图，返回删除数量
+#[tauri::command]
+pub fn asset_purge_missing(
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: i64,
+) -> Result<u32, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    let thumbs = db::purge_missing_assets(&conn, folder_id)?;
+    thumbnail::cleanup_thumbnails(&state.thumb_dir, &thumbs);
+    Ok(thumbs.len() as u32)
+}
+
+/// 生成联系表：把文件夹内（或指定资产）的缩略图排成网格输出为一张 PNG/JPEG
+/// 指定 asset_ids 时按其顺序排列，否则使用 folder_id 下的全部资产。返回放入的图片数量。
+#[tauri::command]
+pub async fn asset_generate_contact_sheet(
+    state: tauri::State<'_, AssetManagerState>,
+    folder_id: Option<i64>,
+    asset_ids: Option<Vec<i64>>,
+    columns: u32,
+    cell_size: u32,
+    caption: bool,
+    output_path: String,
+) -> Result<u32, ArtHubError> {
+    let assets = {
+        let conn = state.db.read()?;
+        match (asset_ids, folder_id) {
+            (Some(ids), _) => db::get_asset_details_bulk(&conn, &ids)?
+                .into_iter()
+                .map(|d| d.asset)
+                .collect(),
+            (None, Some(fid)) => db::get_folder_assets(&conn, fid)?,
+            (None, None) => return Err(ArtHubError::InvalidInput("需要指定 folder_id 或 asset_ids".to_string())),
+        }
+    };
+    let thumb_dir = state.thumb_dir.clone();
+
+    let placed = tokio::task::spawn_blocking(move || {
+        contact_sheet::generate_contact_sheet(
+            &assets,
+            &thumb_dir,
+            columns,
+            cell_size,
+            caption,
+            std::path::Path::new(&output_path),
+        )
+    }).await.map_err(|e| format!("生成联系表线程失败: {}", e))??;
+    Ok(placed)
+}
+
+/// 获取扫描队列状态（排队中 / 运行中的文件夹）
+#[tauri::command]
+pub fn asset_get_scan_queue(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<scan_queue::ScanQueueStatus, ArtHubError> {
+    Ok(state.scan_queue.status())
 }
 
 /// 查询资产（分页 + 筛选）
@@ -172,8 +577,8 @@ pub async fn asset_scan_folder(
 pub fn asset_query(
     state: tauri::State<'_, AssetManagerState>,
     params: AssetQueryParams,
-) -> Result<AssetQueryResult, String> {
-    let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
+) -> Result<AssetQueryResult, ArtHubError> {
+    let conn = state.db.read()?;
     db::query_assets(&conn, &params)
 }
 
@@ -181,45 +586,571 @@ pub fn asset_query(
 #[tauri::command]
 pub fn asset_get_stats(
     state: tauri::State<'_, AssetManagerState>,
-) -> Result<FolderStats, String> {
-    let conn = state.db.lock().map_err(|e| format!("锁定数据库失败: {}", e))?;
+) -> Result<FolderStats, ArtHubError> {
+    let conn = state.db.read()?;
     db::get_stats(&conn)
 }
 
+/// 为单个资产重新生成缩略图，并更新数据库中的尺寸和缩略图路径
+fn regenerate_asset_thumbnail(
+    app: &AppHandle,
+    state: &AssetManagerState,
+    asset_id: i64,
+) -> Result<(String, thumbnail::ThumbResult), ArtHubError> {
+    let (file_path, file_name) = {
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        db::get_asset_path(&conn, asset_id)?
+    };
+
+    let app_data = app.path_resolver().app_data_dir();
+    let ffmpeg_path = app_data.as_deref().and_then(ffmpeg::get_ffmpeg_path);
+    let ffprobe_path = app_data.as_deref().and_then(ffmpeg::get_ffprobe_path);
+
+    let ext = std::path::Path::new(&file_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+
+    // 没有 FFmpeg 时音频仍使用占位图，并保持 pending 等待之后补齐
+    let is_media = thumbnail::is_video(&ext) || thumbnail::is_audio(&ext);
+    let (result, status) = if !thumbnail::can_generate_thumbnail(&ext)
+        && !thumbnail::is_video(&ext)
+        && !(is_media && ffmpeg_path.is_some())
+        && state.placeholder_thumbnails.load(Ordering::Relaxed)
+    {
+        let status = if is_media { db::THUMB_STATUS_PENDING } else { db::THUMB_STATUS_NONE };
+        (placeholder::generate_placeholder_thumbnail(&ext, &state.thumb_dir)?, status)
+    } else {
+        let regenerated = thumbnail::regenerate_thumbnail(
+            &file_path,
+            &state.thumb_dir,
+            300,
+            ffmpeg_path.as_deref(),
+            ffprobe_path.as_deref(),
+        );
+        match regenerated {
+            Ok(result) => (result, db::THUMB_STATUS_OK),
+            Err(e) => {
+                // 缺少 FFmpeg 的视频/音频保持 pending，安装后由 asset_generate_missing_media_thumbnails 补齐
+                let missing_ffmpeg = is_media && ffmpeg_path.is_none();
+                let status = if missing_ffmpeg { db::THUMB_STATUS_PENDING } else { db::THUMB_STATUS_FAILED };
+                let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+                db::set_thumb_status(&conn, asset_id, status)?;
+                return Err(if missing_ffmpeg { ArtHubError::Unsupported(e) } else { e.into() });
+            }
+        }
+    };
+
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::update_asset_thumbnail(&conn, asset_id, &result.thumb_path, result.width, result.height, status)?;
+
+    Ok((file_name, result))
+}
+
+/// 检查已生成的缩略图文件能否读取，损坏或丢失的从源文件重新生成（在阻塞线程中调用）
+pub(crate) fn validate_thumbnails(
+    app: &AppHandle,
+    state: &AssetManagerState,
+    folder_id: Option<i64>,
+) -> Result<db::ThumbValidationSummary, ArtHubError> {
+    let thumbs = {
+        let conn = state.db.read()?;
+        db::get_generated_thumbnails(&conn, folder_id)?
+    };
+
+    let broken: Vec<i64> = thumbs.iter()
+        .filter(|(_, path)| !thumbnail::is_thumbnail_readable(path))
+        .map(|(id, _)| *id)
+        .collect();
+
+    let mut failures = Vec::new();
+    for &asset_id in &broken {
+        if let Err(e) = regenerate_asset_thumbnail(app, state, asset_id) {
+            failures.push((asset_id, e.to_string()));
+        }
+    }
+
+    let summary = db::ThumbValidationSummary {
+        folder_id,
+        checked: thumbs.len() as u32,
+        broken: broken.len() as u32,
+        repaired: (broken.len() - failures.len()) as u32,
+        failures,
+    };
+    if summary.broken > 0 {
+        log::warn!(
+            "[AssetManager] 缩略图校验: 检查 {} 个，损坏 {} 个，已修复 {} 个",
+            summary.checked, summary.broken, summary.repaired
+        );
+    }
+    Ok(summary)
+}
+
+/// 校验缩略图缓存（folder_id 为 None 时校验所有文件夹），损坏的自动从源文件重新生成
+#[tauri::command]
+pub async fn asset_validate_thumbnails(
+    app: AppHandle,
+    folder_id: Option<i64>,
+) -> Result<db::ThumbValidationSummary, ArtHubError> {
+    tokio::task::spawn_blocking(move || {
+        validate_thumbnails(&app, &app.state::<AssetManagerState>(), folder_id)
+    }).await.map_err(|e| format!("校验线程失败: {}", e))?
+}
+
+/// 启动维护任务：开启了 thumbnail.validate_on_startup 时在后台校验所有缩略图，
+/// 有损坏的发送 "asset-thumbnails-validated" 事件
+pub fn run_startup_thumbnail_check(app: &AppHandle) {
+    let state = app.state::<AssetManagerState>();
+    let enabled = state.db.read()
+        .and_then(|conn| db::get_setting(&conn, db::SETTING_VALIDATE_THUMBNAILS_ON_STARTUP))
+        .ok()
+        .flatten()
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+
+    let app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("thumb-validate".to_string())
+        .spawn(move || {
+            match validate_thumbnails(&app, &app.state::<AssetManagerState>(), None) {
+                Ok(summary) if summary.broken > 0 => {
+                    let _ = app.emit_all("asset-thumbnails-validated", summary);
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("[AssetManager] 启动时校验缩略图失败: {}", e),
+            }
+        });
+    if let Err(e) = spawned {
+        log::error!("[AssetManager] 启动缩略图校验线程失败: {}", e);
+    }
+}
+
+/// 设置启动时是否自动校验缩略图缓存
+#[tauri::command]
+pub fn asset_set_validate_thumbnails_on_startup(
+    state: tauri::State<'_, AssetManagerState>,
+    enabled: bool,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_setting(&conn, db::SETTING_VALIDATE_THUMBNAILS_ON_STARTUP, &serde_json::json!(enabled))
+}
+
+/// 启动时是否自动校验缩略图缓存（默认关闭）
+#[tauri::command]
+pub fn asset_get_validate_thumbnails_on_startup(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<bool, ArtHubError> {
+    let conn = state.db.read()?;
+    Ok(db::get_setting(&conn, db::SETTING_VALIDATE_THUMBNAILS_ON_STARTUP)?
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false))
+}
+
+/// 重新生成单个资产的缩略图
+#[tauri::command]
+pub async fn asset_regenerate_thumbnail(
+    app: AppHandle,
+    asset_id: i64,
+) -> Result<thumbnail::ThumbResult, ArtHubError> {
+    tokio::task::spawn_blocking(move || {
+        regenerate_asset_thumbnail(&app, &app.state::<AssetManagerState>(), asset_id).map(|(_, result)| result)
+    }).await.map_err(|e| format!("缩略图线程失败: {}", e))?
+}
+
+/// 旋转/翻转图片资产并写回原文件，更新尺寸后重新生成缩略图
+/// op: "rotate90" | "rotate180" | "rotate270" | "flip_h" | "flip_v"
+#[tauri::command]
+pub async fn asset_transform_image(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+    op: ImageOp,
+) -> Result<thumbnail::ThumbResult, ArtHubError> {
+    let (file_path, _) = {
+        let conn = state.db.read()?;
+        db::get_asset_path(&conn, asset_id)?
+    };
+    ensure_writable(&state, &file_path)?;
+    if !std::path::Path::new(&file_path).exists() {
+        return Err(ArtHubError::NotFound(format!("文件不存在: {}", file_path)));
+    }
+    let ext = std::path::Path::new(&file_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    if !transform::can_transform(&ext) {
+        return Err(ArtHubError::Unsupported(format!("该格式不支持旋转/翻转后写回: {}", ext)));
+    }
+
+    let path = file_path.clone();
+    let (width, height) = tokio::task::spawn_blocking(move || {
+        transform::transform_image_file(std::path::Path::new(&path), &ext, op)
+    }).await.map_err(|e| format!("变换线程失败: {}", e))??;
+
+    let metadata = std::fs::metadata(&file_path)?;
+    let modified_at = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    {
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        db::update_asset_file_info(&conn, asset_id, metadata.len() as i64, modified_at, width, height)?;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        regenerate_asset_thumbnail(&app, &app.state::<AssetManagerState>(), asset_id).map(|(_, result)| result)
+    }).await.map_err(|e| format!("缩略图线程失败: {}", e))?
+}
+
+/// 批量重新生成缩略图（发送进度事件），返回成功数量
+#[tauri::command]
+pub async fn asset_batch_regenerate_thumbnails(
+    app: AppHandle,
+    asset_ids: Vec<i64>,
+) -> Result<u32, ArtHubError> {
+    tokio::task::spawn_blocking(move || batch_regenerate_thumbnails(&app, &asset_ids))
+        .await
+        .map_err(|e| format!("缩略图线程失败: {}", e).into())
+}
+
+/// 逐个重新生成缩略图并发送进度事件（在阻塞线程中调用）
+fn batch_regenerate_thumbnails(app: &AppHandle, asset_ids: &[i64]) -> u32 {
+    let state = app.state::<AssetManagerState>();
+    let total = asset_ids.len() as u32;
+    let mut succeeded = 0u32;
+
+    for (i, aid) in asset_ids.iter().enumerate() {
+        let (file_name, success) = match regenerate_asset_thumbnail(app, &state, *aid) {
+            Ok((name, _)) => (name, true),
+            Err(e) => {
+                log::error!("[AssetManager] 重新生成缩略图失败 (asset {}): {}", aid, e);
+                (String::new(), false)
+            }
+        };
+        if success {
+            succeeded += 1;
+        }
+
+        let _ = app.emit_all("asset-thumbnail-progress", ThumbnailProgress {
+            current: i as u32 + 1,
+            total,
+            asset_id: *aid,
+            file_name,
+            success,
+        });
+    }
+
+    succeeded
+}
+
+/// 为扫描时因缺少 FFmpeg 而没有预览的视频/音频（thumb_status = pending）补生成缩略图/波形图，
+/// 逐个发送 "asset-thumbnail-progress" 事件，返回成功数量
+#[tauri::command]
+pub async fn asset_generate_missing_media_thumbnails(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<u32, ArtHubError> {
+    let app_data = app.path_resolver().app_data_dir();
+    if app_data.as_deref().and_then(ffmpeg::get_ffmpeg_path).is_none() {
+        return Err(ArtHubError::Unsupported("FFmpeg 未安装，无法生成视频/音频预览".to_string()));
+    }
+
+    let pending: Vec<i64> = {
+        let conn = state.db.read()?;
+        db::get_pending_thumb_assets(&conn)?
+            .into_iter()
+            .filter(|(_, ext)| thumbnail::is_video(ext) || thumbnail::is_audio(ext))
+            .map(|(id, _)| id)
+            .collect()
+    };
+    tokio::task::spawn_blocking(move || generate_media_thumbnails(&app, &pending))
+        .await
+        .map_err(|e| format!("缩略图线程失败: {}", e).into())
+}
+
+/// 逐个补生成视频/音频预览并发送进度事件（在阻塞线程中调用）
+fn generate_media_thumbnails(app: &AppHandle, pending: &[i64]) -> u32 {
+    let state = app.state::<AssetManagerState>();
+    let total = pending.len() as u32;
+    let mut succeeded = 0u32;
+
+    for (i, aid) in pending.iter().enumerate() {
+        let (file_name, success) = match regenerate_asset_thumbnail(app, &state, *aid) {
+            Ok((name, _)) => (name, true),
+            Err(e) => {
+                log::warn!("[AssetManager] 补生成媒体预览失败 (asset {}): {}", aid, e);
+                (String::new(), false)
+            }
+        };
+        if success {
+            succeeded += 1;
+        }
+
+        let _ = app.emit_all("asset-thumbnail-progress", ThumbnailProgress {
+            current: i as u32 + 1,
+            total,
+            asset_id: *aid,
+            file_name,
+            success,
+        });
+    }
+
+    log::info!("[AssetManager] 补生成媒体预览完成: {}/{}", succeeded, total);
+    succeeded
+}
+
+/// 启动时数据库是否因损坏被重置；返回损坏文件被移到的路径（None 表示正常）
+#[tauri::command]
+pub fn asset_get_db_recovery(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<Option<String>, ArtHubError> {
+    Ok(state.recovered_db.as_ref().map(|p| p.to_string_lossy().to_string()))
+}
+
+/// 备份资源库数据库到指定路径（在线备份，期间持有数据库锁，不会与写入冲突）
+#[tauri::command]
+pub fn asset_backup_db(
+    state: tauri::State<'_, AssetManagerState>,
+    dest_path: String,
+) -> Result<u64, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::backup_db(&conn, std::path::Path::new(&dest_path))
+}
+
+/// 手动执行 WAL 检查点并截断 WAL 文件（有读者占用时 busy = true）
+#[tauri::command]
+pub fn asset_checkpoint_wal(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<db::CheckpointReport, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::checkpoint_wal(&conn, db::CheckpointMode::Truncate)
+}
+
+/// 整理数据库（VACUUM + 截断 WAL），返回整理前后的文件大小
+#[tauri::command]
+pub fn asset_vacuum_db(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<db::DbSizeReport, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::vacuum_db(&conn)
+}
+
+/// 读取设置（key 按功能命名空间划分，如 "thumbnail.format"），不存在时返回 null
+#[tauri::command]
+pub fn asset_get_setting(
+    state: tauri::State<'_, AssetManagerState>,
+    key: String,
+) -> Result<Option<serde_json::Value>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_setting(&conn, &key)
+}
+
+/// 保存设置（任意 JSON 值）
+#[tauri::command]
+pub fn asset_set_setting(
+    state: tauri::State<'_, AssetManagerState>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_setting(&conn, &key, &value)
+}
+
+/// 设置是否为无法预览的格式生成占位缩略图（下次扫描生效）
+#[tauri::command]
+pub fn asset_set_placeholder_thumbnails(
+    state: tauri::State<'_, AssetManagerState>,
+    enabled: bool,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_setting(&conn, db::SETTING_PLACEHOLDER_THUMBNAILS, &serde_json::json!(enabled))?;
+    state.placeholder_thumbnails.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// 获取占位缩略图开关状态
+#[tauri::command]
+pub fn asset_get_placeholder_thumbnails(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<bool, ArtHubError> {
+    Ok(state.placeholder_thumbnails.load(Ordering::Relaxed))
+}
+
+/// 设置缩略图输出格式（"jpeg" 或 "webp"），已有缩略图在下次扫描时按新格式重新生成
+#[tauri::command]
+pub fn asset_set_thumbnail_format(
+    state: tauri::State<'_, AssetManagerState>,
+    format: String,
+) -> Result<(), ArtHubError> {
+    let format = thumbnail::ThumbFormat::parse(&format)
+        .ok_or_else(|| format!("不支持的缩略图格式: {}", format))?;
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_setting(&conn, db::SETTING_THUMB_FORMAT, &serde_json::json!(format))?;
+    thumbnail::set_thumb_format(format);
+    Ok(())
+}
+
+/// 设置解码的像素上限（宽×高），超过的图片扫描时跳过并列在扫描汇总的 oversized 中
+#[tauri::command]
+pub fn asset_set_max_decode_pixels(
+    state: tauri::State<'_, AssetManagerState>,
+    pixels: u64,
+) -> Result<(), ArtHubError> {
+    if pixels == 0 {
+        return Err(ArtHubError::InvalidInput("像素上限必须大于 0".to_string()));
+    }
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_setting(&conn, db::SETTING_MAX_DECODE_PIXELS, &serde_json::json!(pixels))?;
+    thumb_provider::set_pixel_budget(pixels);
+    Ok(())
+}
+
+/// 开启/关闭后台缩略图队列：开启时扫描只写入元数据，图片缩略图在后台生成；
+/// 开启时立即处理之前留下的待生成资产
+#[tauri::command]
+pub fn asset_set_thumbnail_worker_enabled(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    enabled: bool,
+) -> Result<(), ArtHubError> {
+    {
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        db::set_setting(&conn, db::SETTING_BACKGROUND_THUMBNAILS, &serde_json::json!(enabled))?;
+    }
+    state.thumb_worker.set_enabled(enabled);
+    state.thumb_worker.kick(&app);
+    Ok(())
+}
+
+/// 把资产（通常是当前可见的）移到后台缩略图队列最前面，已生成的会被跳过
+#[tauri::command]
+pub fn asset_prioritize_thumbnails(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+) {
+    state.thumb_worker.prioritize(&asset_ids);
+    state.thumb_worker.kick(&app);
+}
+
+/// 后台缩略图队列是否开启
+#[tauri::command]
+pub fn asset_get_thumbnail_worker_enabled(
+    state: tauri::State<'_, AssetManagerState>,
+) -> bool {
+    state.thumb_worker.is_enabled()
+}
+
+/// 获取当前解码像素上限
+#[tauri::command]
+pub fn asset_get_max_decode_pixels() -> u64 {
+    thumb_provider::pixel_budget()
+}
+
+/// 获取当前缩略图输出格式
+#[tauri::command]
+pub fn asset_get_thumbnail_format() -> Result<thumbnail::ThumbFormat, ArtHubError> {
+    Ok(thumbnail::thumb_format())
+}
+
+/// 获取会被扫描的格式列表（扩展名、分类、能否生成缩略图），供前端构建类型筛选
+#[tauri::command]
+pub fn asset_get_supported_extensions() -> Result<Vec<formats::SupportedExtension>, ArtHubError> {
+    Ok(formats::supported_extensions())
+}
+
+/// 注册自定义扩展名（下次扫描生效）
+#[tauri::command]
+pub fn asset_add_custom_extension(
+    state: tauri::State<'_, AssetManagerState>,
+    ext: String,
+    category: String,
+) -> Result<(), ArtHubError> {
+    let ext = formats::register_custom_extension(&ext, &category)?;
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::add_custom_extension(&conn, &ext, &category)
+}
+
+/// 移除自定义扩展名
+#[tauri::command]
+pub fn asset_remove_custom_extension(
+    state: tauri::State<'_, AssetManagerState>,
+    ext: String,
+) -> Result<(), ArtHubError> {
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    formats::unregister_custom_extension(&ext);
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::remove_custom_extension(&conn, &ext)
+}
+
 // ============================================================
 // Phase 2: Tags, Ratings, Notes, Smart Folders
 // ============================================================
 
-/// 获取所有标签
+/// 获取所有标签（可按文件夹统计数量；指定 space_type 时只返回该空间和通用的标签）
 #[tauri::command]
 pub fn asset_get_tags(
     state: tauri::State<'_, AssetManagerState>,
-) -> Result<Vec<TagInfo>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::get_all_tags(&conn)
+    folder_id: Option<i64>,
+    space_type: Option<String>,
+) -> Result<Vec<TagInfo>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_all_tags(&conn, folder_id, space_type.as_deref())
 }
 
-/// 创建标签
+/// 创建标签（不传 color 时自动分配颜色，不传 space_type 时所有空间通用），返回 { tag, created }
 #[tauri::command]
 pub fn asset_create_tag(
     state: tauri::State<'_, AssetManagerState>,
     name: String,
-    color: String,
-) -> Result<TagInfo, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::create_tag(&conn, &name, &color)
+    color: Option<String>,
+    space_type: Option<String>,
+) -> Result<db::CreateTagResult, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::create_tag(&conn, &name, color.as_deref(), space_type.as_deref())
+}
+
+/// 设置标签所属空间（space_type 为空时改为所有空间通用）
+#[tauri::command]
+pub fn asset_set_tag_space(
+    state: tauri::State<'_, AssetManagerState>,
+    tag_id: i64,
+    space_type: Option<String>,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_tag_space(&conn, tag_id, space_type.as_deref())
 }
 
-/// 更新标签
+/// 更新标签名称和颜色，返回更新后的标签 ID
+/// 新名称与其他标签重名时：merge=true 则把该标签合并到已有标签（颜色应用到已有标签），
+/// 否则返回 already_exists 错误，由前端询问是否合并
 #[tauri::command]
 pub fn asset_update_tag(
     state: tauri::State<'_, AssetManagerState>,
     tag_id: i64,
     name: String,
     color: String,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::update_tag(&conn, tag_id, &name, &color)
+    merge: Option<bool>,
+) -> Result<i64, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    match db::find_other_tag_by_name(&conn, &name, tag_id)? {
+        Some(existing) if merge.unwrap_or(false) => {
+            db::merge_tags(&conn, tag_id, existing)?;
+            conn.execute("UPDATE tags SET color = ?1 WHERE id = ?2", rusqlite::params![color, existing])?;
+            Ok(existing)
+        }
+        Some(_) => Err(ArtHubError::AlreadyExists(format!("标签名已存在: {}", name))),
+        None => {
+            db::update_tag(&conn, tag_id, &name, &color)?;
+            Ok(tag_id)
+        }
+    }
 }
 
 /// 删除标签
@@ -227,8 +1158,8 @@ pub fn asset_update_tag(
 pub fn asset_delete_tag(
     state: tauri::State<'_, AssetManagerState>,
     tag_id: i64,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::delete_tag(&conn, tag_id)
 }
 
@@ -238,8 +1169,8 @@ pub fn asset_add_tag(
     state: tauri::State<'_, AssetManagerState>,
     asset_id: i64,
     tag_id: i64,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::add_tag_to_asset(&conn, asset_id, tag_id, "")
 }
 
@@ -249,52 +1180,210 @@ pub fn asset_remove_tag(
     state: tauri::State<'_, AssetManagerState>,
     asset_id: i64,
     tag_id: i64,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::remove_tag_from_asset(&conn, asset_id, tag_id)
 }
 
-/// 批量添加标签
+/// 批量添加标签
+#[tauri::command]
+pub fn asset_batch_add_tag(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+    tag_id: i64,
+) -> Result<u32, ArtHubError> {
+    let changes = db::MetadataChanges { add_tag_ids: vec![tag_id], ..Default::default() };
+    let mut conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    Ok(db::batch_apply_metadata(&mut conn, "add_tag", &asset_ids, &changes, "")?.assets)
+}
+
+/// 设置评分
+#[tauri::command]
+pub fn asset_set_rating(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+    rating: i32,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_rating(&conn, asset_id, rating, "")
+}
+
+/// 设置备注
+#[tauri::command]
+pub fn asset_set_note(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+    note: String,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::set_note(&conn, asset_id, &note, "")
+}
+
+/// 获取资产详情（含标签+评分+备注）
+#[tauri::command]
+pub fn asset_get_detail(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+) -> Result<AssetDetail, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_asset_detail(&conn, asset_id)
+}
+
+/// 记录资产被查看（用于按最近查看排序）
+#[tauri::command]
+pub fn asset_mark_viewed(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::mark_asset_viewed(&conn, asset_id)
+}
+
+/// 用默认程序（app_path 为空）或指定程序打开资产文件
+#[tauri::command]
+pub fn asset_open_with(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+    app_path: Option<String>,
+) -> Result<(), ArtHubError> {
+    let (file_path, _) = {
+        let conn = state.db.read()?;
+        db::get_asset_path(&conn, asset_id)?
+    };
+    let path = std::path::Path::new(&file_path);
+    if !path.exists() {
+        return Err(ArtHubError::NotFound(format!("文件不存在: {}", file_path)));
+    }
+    Ok(shell::open_with(path, app_path.as_deref().map(std::path::Path::new))?)
+}
+
+/// 复制资产到系统剪贴板
+/// - mode = "files": 放入文件引用，可在资源管理器中粘贴（仅 Windows）
+/// - mode = "image": 放入第一个资产解码后的位图，可粘贴到 Photoshop 等编辑器
+///
+/// 返回放入剪贴板的资产数量
+#[tauri::command]
+pub async fn asset_copy_to_clipboard(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+    mode: String,
+) -> Result<u32, ArtHubError> {
+    let paths: Vec<String> = {
+        let conn = state.db.read()?;
+        asset_ids.iter()
+            .filter_map(|id| db::get_asset_path(&conn, *id).ok())
+            .map(|(path, _)| path)
+            .filter(|path| std::path::Path::new(path).exists())
+            .collect()
+    };
+    if paths.is_empty() {
+        return Err(ArtHubError::NotFound("没有可复制的文件".to_string()));
+    }
+
+    match mode.as_str() {
+        "files" => {
+            clipboard::copy_files(&paths)?;
+            Ok(paths.len() as u32)
+        }
+        "image" => {
+            let path = paths[0].clone();
+            tokio::task::spawn_blocking(move || {
+                let ext = std::path::Path::new(&path)
+                    .extension()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase();
+                if !thumbnail::can_generate_thumbnail(&ext) {
+                    return Err(format!("该格式无法作为图片复制: {}", ext));
+                }
+                let img = thumbnail::decode_image(&path, &ext)?;
+                clipboard::copy_image(&img)
+            }).await.map_err(|e| format!("复制线程失败: {}", e))??;
+            Ok(1)
+        }
+        _ => Err(ArtHubError::InvalidInput(format!("未知的复制模式: {}", mode))),
+    }
+}
+
+/// 预览图允许的最大宽度
+const MAX_PREVIEW_WIDTH: u32 = 4096;
+
+/// 解码资产源文件（含 PSD/EXR/DDS 等浏览器无法显示的格式），
+/// 缩小到 max_width 后以 data:image/...;base64,... 返回，用于全尺寸预览
+#[tauri::command]
+pub async fn asset_get_preview_data_uri(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_id: i64,
+    max_width: u32,
+) -> Result<String, ArtHubError> {
+    let (file_path, _) = {
+        let conn = state.db.read()?;
+        db::get_asset_path(&conn, asset_id)?
+    };
+    if !std::path::Path::new(&file_path).exists() {
+        return Err(ArtHubError::NotFound(format!("文件不存在: {}", file_path)));
+    }
+    let ext = std::path::Path::new(&file_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    if !thumbnail::can_generate_thumbnail(&ext) {
+        return Err(ArtHubError::Unsupported(format!("该格式无法预览: {}", ext)));
+    }
+
+    let max_width = max_width.clamp(1, MAX_PREVIEW_WIDTH);
+    Ok(tokio::task::spawn_blocking(move || {
+        thumbnail::preview_data_uri(&file_path, &ext, max_width)
+    }).await.map_err(|e| format!("预览线程失败: {}", e))??)
+}
+
+/// 批量获取资产详情（按输入顺序返回，供网格一次性加载标签和评分）
 #[tauri::command]
-pub fn asset_batch_add_tag(
+pub fn asset_get_details_bulk(
     state: tauri::State<'_, AssetManagerState>,
     asset_ids: Vec<i64>,
-    tag_id: i64,
-) -> Result<u32, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::batch_add_tag(&conn, &asset_ids, tag_id, "")
+) -> Result<Vec<AssetDetail>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_asset_details_bulk(&conn, &asset_ids)
 }
 
-/// 设置评分
+/// 获取所有 AI 工具配置
 #[tauri::command]
-pub fn asset_set_rating(
+pub fn ai_tools_list(
     state: tauri::State<'_, AssetManagerState>,
-    asset_id: i64,
-    rating: i32,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::set_rating(&conn, asset_id, rating, "")
+) -> Result<Vec<db::AiTool>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_ai_tools(&conn)
 }
 
-/// 设置备注
+/// 新建或更新 AI 工具配置
 #[tauri::command]
-pub fn asset_set_note(
+pub fn ai_tool_save(
     state: tauri::State<'_, AssetManagerState>,
-    asset_id: i64,
-    note: String,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::set_note(&conn, asset_id, &note, "")
+    tool: db::AiTool,
+) -> Result<(), ArtHubError> {
+    if tool.id.trim().is_empty() || tool.name.trim().is_empty() {
+        return Err(ArtHubError::InvalidInput("AI 工具的 id 和名称不能为空".to_string()));
+    }
+    if !tool.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(ArtHubError::InvalidInput(format!("AI 工具 id 只能包含字母、数字、- 和 _: {}", tool.id)));
+    }
+    if !db::AI_INJECT_MODES.contains(&tool.inject_mode.as_str()) {
+        return Err(ArtHubError::InvalidInput(format!("未知的注入方式: {}", tool.inject_mode)));
+    }
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::save_ai_tool(&conn, &tool)
 }
 
-/// 获取资产详情（含标签+评分+备注）
+/// 删除 AI 工具配置
 #[tauri::command]
-pub fn asset_get_detail(
+pub fn ai_tool_delete(
     state: tauri::State<'_, AssetManagerState>,
-    asset_id: i64,
-) -> Result<AssetDetail, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::get_asset_detail(&conn, asset_id)
+    id: String,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::delete_ai_tool(&conn, &id)
 }
 
 /// 获取智能文件夹列表
@@ -302,32 +1391,40 @@ pub fn asset_get_detail(
 pub fn asset_get_smart_folders(
     state: tauri::State<'_, AssetManagerState>,
     space_type: Option<String>,
-) -> Result<Vec<SmartFolder>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<Vec<SmartFolder>, ArtHubError> {
+    let conn = state.db.read()?;
     db::get_smart_folders(&conn, space_type.as_deref())
 }
 
-/// 创建智能文件夹
+/// 校验智能文件夹条件 JSON，返回规范化后再序列化的字符串（入库的总是合法条件）
+fn validated_conditions(conditions: &str) -> Result<String, ArtHubError> {
+    let conds = db::parse_smart_folder_conditions(conditions)?;
+    serde_json::to_string(&conds).map_err(|e| ArtHubError::Internal(format!("序列化条件失败: {}", e)))
+}
+
+/// 创建智能文件夹（conditions 不合法时返回 invalid_input）
 #[tauri::command]
 pub fn asset_create_smart_folder(
     state: tauri::State<'_, AssetManagerState>,
     name: String,
     conditions: String,
     space_type: String,
-) -> Result<SmartFolder, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<SmartFolder, ArtHubError> {
+    let conditions = validated_conditions(&conditions)?;
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::create_smart_folder(&conn, &name, &conditions, &space_type)
 }
 
-/// 更新智能文件夹
+/// 更新智能文件夹（conditions 不合法时返回 invalid_input）
 #[tauri::command]
 pub fn asset_update_smart_folder(
     state: tauri::State<'_, AssetManagerState>,
     id: i64,
     name: String,
     conditions: String,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<(), ArtHubError> {
+    let conditions = validated_conditions(&conditions)?;
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::update_smart_folder(&conn, id, &name, &conditions)
 }
 
@@ -336,11 +1433,84 @@ pub fn asset_update_smart_folder(
 pub fn asset_delete_smart_folder(
     state: tauri::State<'_, AssetManagerState>,
     id: i64,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::delete_smart_folder(&conn, id)
 }
 
+/// 保存当前搜索条件
+#[tauri::command]
+pub fn asset_save_search(
+    state: tauri::State<'_, AssetManagerState>,
+    name: String,
+    params: AssetQueryParams,
+) -> Result<db::SavedSearch, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::create_saved_search(&conn, &name, &params)
+}
+
+/// 获取保存的搜索列表
+#[tauri::command]
+pub fn asset_get_saved_searches(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<Vec<db::SavedSearch>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_saved_searches(&conn)
+}
+
+/// 删除保存的搜索
+#[tauri::command]
+pub fn asset_delete_saved_search(
+    state: tauri::State<'_, AssetManagerState>,
+    id: i64,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::delete_saved_search(&conn, id)
+}
+
+/// 执行保存的搜索（可指定页码）
+#[tauri::command]
+pub fn asset_run_saved_search(
+    state: tauri::State<'_, AssetManagerState>,
+    id: i64,
+    page: Option<i64>,
+) -> Result<AssetQueryResult, ArtHubError> {
+    let conn = state.db.read()?;
+    let mut params = db::get_saved_search(&conn, id)?.params;
+    if page.is_some() {
+        params.page = page;
+    }
+    db::query_assets(&conn, &params)
+}
+
+/// 记录一条搜索历史（前端在用户确认搜索时调用，而不是每次输入）
+#[tauri::command]
+pub fn asset_add_search_history(
+    state: tauri::State<'_, AssetManagerState>,
+    query: String,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::add_search_history(&conn, &query)
+}
+
+/// 获取最近的搜索历史（新的在前）
+#[tauri::command]
+pub fn asset_get_search_history(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<Vec<String>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_search_history(&conn)
+}
+
+/// 清空搜索历史
+#[tauri::command]
+pub fn asset_clear_search_history(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<(), ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::clear_search_history(&conn)
+}
+
 // ============================================================
 // Phase 2 补全: Favorites + Batch Operations
 // ============================================================
@@ -350,8 +1520,8 @@ pub fn asset_delete_smart_folder(
 pub fn asset_toggle_favorite(
     state: tauri::State<'_, AssetManagerState>,
     asset_id: i64,
-) -> Result<bool, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<bool, ArtHubError> {
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::toggle_favorite(&conn, asset_id, "")
 }
 
@@ -360,8 +1530,8 @@ pub fn asset_toggle_favorite(
 pub fn asset_is_favorite(
     state: tauri::State<'_, AssetManagerState>,
     asset_id: i64,
-) -> Result<bool, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<bool, ArtHubError> {
+    let conn = state.db.read()?;
     Ok(db::is_favorite(&conn, asset_id))
 }
 
@@ -369,8 +1539,8 @@ pub fn asset_is_favorite(
 #[tauri::command]
 pub fn asset_get_favorite_ids(
     state: tauri::State<'_, AssetManagerState>,
-) -> Result<Vec<i64>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<Vec<i64>, ArtHubError> {
+    let conn = state.db.read()?;
     Ok(db::get_favorite_ids(&conn))
 }
 
@@ -380,9 +1550,10 @@ pub fn asset_batch_favorite(
     state: tauri::State<'_, AssetManagerState>,
     asset_ids: Vec<i64>,
     favorite: bool,
-) -> Result<u32, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::batch_toggle_favorite(&conn, &asset_ids, favorite, "")
+) -> Result<u32, ArtHubError> {
+    let changes = db::MetadataChanges { favorite: Some(favorite), ..Default::default() };
+    let mut conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    Ok(db::batch_apply_metadata(&mut conn, "favorite", &asset_ids, &changes, "")?.assets)
 }
 
 /// 批量设置评分
@@ -391,99 +1562,499 @@ pub fn asset_batch_set_rating(
     state: tauri::State<'_, AssetManagerState>,
     asset_ids: Vec<i64>,
     rating: i32,
-) -> Result<u32, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    db::batch_set_rating(&conn, &asset_ids, rating, "")
+) -> Result<u32, ArtHubError> {
+    let changes = db::MetadataChanges { rating: Some(rating), ..Default::default() };
+    let mut conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    Ok(db::batch_apply_metadata(&mut conn, "set_rating", &asset_ids, &changes, "")?.assets)
 }
 
-/// 批量删除资产（从数据库中删除记录+清理缩略图）
+/// 一次整理操作：对选中资产同时添加/移除标签、设置评分、收藏和备注，在同一个事务内完成
 #[tauri::command]
-pub fn asset_batch_delete(
+pub fn asset_batch_apply_metadata(
     state: tauri::State<'_, AssetManagerState>,
     asset_ids: Vec<i64>,
-) -> Result<u32, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    add_tag_ids: Option<Vec<i64>>,
+    remove_tag_ids: Option<Vec<i64>>,
+    rating: Option<i32>,
+    favorite: Option<bool>,
+    note: Option<String>,
+) -> Result<db::BatchMetadataResult, ArtHubError> {
+    let changes = db::MetadataChanges {
+        add_tag_ids: add_tag_ids.unwrap_or_default(),
+        remove_tag_ids: remove_tag_ids.unwrap_or_default(),
+        rating,
+        favorite,
+        note: note.map(|n| n.trim().to_string()),
+    };
+    if let Some(r) = changes.rating.filter(|r| !(0..=5).contains(r)) {
+        return Err(ArtHubError::InvalidInput(format!("评分必须在 0-5 之间: {}", r)));
+    }
+    if let Some(tag_id) = changes.add_tag_ids.iter().find(|id| changes.remove_tag_ids.contains(id)) {
+        return Err(ArtHubError::InvalidInput(format!("标签 {} 不能同时添加和移除", tag_id)));
+    }
 
-    // 先获取文件路径用于清理缩略图
-    let mut paths = Vec::new();
-    for aid in &asset_ids {
-        if let Ok(path) = conn.query_row(
-            "SELECT file_path FROM assets WHERE id = ?1",
-            rusqlite::params![aid],
-            |row| row.get::<_, String>(0),
-        ) {
-            paths.push(path);
-        }
+    let mut conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    db::batch_apply_metadata(&mut conn, "apply_metadata", &asset_ids, &changes, "")
+}
+
+/// 获取可撤销的批量元数据操作（最新的在前，最多保留最近 20 次）
+#[tauri::command]
+pub fn asset_get_undo_stack(
+    state: tauri::State<'_, AssetManagerState>,
+) -> Result<Vec<db::MetadataOp>, ArtHubError> {
+    let conn = state.db.read()?;
+    db::get_metadata_ops(&conn)
+}
+
+/// 撤销最近一次批量元数据操作（添加/移除标签、评分、收藏、备注），返回被撤销的操作。
+/// 传入 op_id 时必须是最近一次操作，防止界面上的过期操作跳过更新的改动被撤销
+#[tauri::command]
+pub fn asset_undo_last(
+    state: tauri::State<'_, AssetManagerState>,
+    op_id: Option<i64>,
+) -> Result<db::MetadataOp, ArtHubError> {
+    let mut conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    let Some(op) = db::get_metadata_ops(&conn)?.into_iter().next() else {
+        return Err(ArtHubError::NotFound("没有可撤销的操作".to_string()));
+    };
+    if op_id.is_some_and(|id| id != op.id) {
+        return Err(ArtHubError::InvalidInput("只能撤销最近一次操作".to_string()));
     }
+    let restored = db::undo_metadata_op(&mut conn, op.id)?;
+    log::info!("[AssetManager] 已撤销操作 {} ({})，恢复 {} 项改动", op.id, op.kind, restored);
+    Ok(op)
+}
 
-    // 清理缩略图
-    thumbnail::cleanup_thumbnails(&state.thumb_dir, &paths);
+/// 预留批量操作 ID：传给 asset_batch_export / asset_convert_format / asset_export_resized 后可随时取消
+#[tauri::command]
+pub fn asset_begin_batch(state: tauri::State<'_, AssetManagerState>) -> u64 {
+    state.batch_ops.reserve()
+}
+
+/// 取消批量操作：当前文件处理完后停止，返回该操作是否存在
+#[tauri::command]
+pub fn asset_cancel_batch(state: tauri::State<'_, AssetManagerState>, op_id: u64) -> bool {
+    state.batch_ops.cancel(op_id)
+}
+
+/// 批量删除资产（从数据库中删除记录+清理缩略图），逐个发送 "asset-batch-progress" 事件
+#[tauri::command]
+pub async fn asset_batch_delete(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+) -> Result<u32, ArtHubError> {
+    let total = asset_ids.len() as u32;
+
+    // 逐个清理缩略图
+    for (i, aid) in asset_ids.iter().enumerate() {
+        let (file_name, thumb_path) = {
+            let conn = state.db.read()?;
+            let file_name = db::get_asset_path(&conn, *aid).map(|(_, name)| name).unwrap_or_default();
+            (file_name, db::get_asset_thumb_path(&conn, *aid).ok().flatten())
+        };
+        if let Some(thumb_path) = thumb_path {
+            let thumb_dir = state.thumb_dir.clone();
+            tokio::task::spawn_blocking(move || thumbnail::cleanup_thumbnails(&thumb_dir, &[thumb_path]))
+                .await.map_err(|e| format!("清理缩略图线程失败: {}", e))?;
+        }
+
+        let _ = app.emit_all("asset-batch-progress", BatchProgress {
+            operation: "delete".to_string(),
+            current: i as u32 + 1,
+            total,
+            current_file: file_name,
+        });
+    }
 
     // 删除数据库记录
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
     db::batch_delete_assets(&conn, &asset_ids)
 }
 
-/// 批量导出资产（复制文件到目标目录）
+/// 批量导出资产（复制文件到目标目录），逐个发送 "asset-batch-progress" 事件
+/// op_id 来自 asset_begin_batch，可通过 asset_cancel_batch 中途取消
 #[tauri::command]
-pub fn asset_batch_export(
+pub async fn asset_batch_export(
+    app: AppHandle,
     state: tauri::State<'_, AssetManagerState>,
     asset_ids: Vec<i64>,
     target_dir: String,
-) -> Result<u32, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    let target = std::path::Path::new(&target_dir);
+    op_id: Option<u64>,
+) -> Result<BatchResult, ArtHubError> {
+    let ticket = state.batch_ops.track(op_id);
+    let target = std::path::PathBuf::from(&target_dir);
 
     if !target.exists() {
-        std::fs::create_dir_all(target).map_err(|e| format!("创建目标目录失败: {}", e))?;
+        std::fs::create_dir_all(&target).map_err(|e| format!("创建目标目录失败: {}", e))?;
     }
 
+    let total = asset_ids.len() as u32;
     let mut count = 0u32;
-    for aid in &asset_ids {
-        if let Ok((file_path, file_name)) = conn.query_row(
-            "SELECT file_path, file_name FROM assets WHERE id = ?1",
-            rusqlite::params![aid],
-            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
-        ) {
-            let src = std::path::Path::new(&file_path);
-            let mut dest = target.join(&file_name);
-
-            // 避免文件名冲突
-            if dest.exists() {
-                let stem = std::path::Path::new(&file_name)
-                    .file_stem().unwrap_or_default().to_string_lossy().to_string();
-                let ext = std::path::Path::new(&file_name)
-                    .extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
-                let mut n = 1;
-                loop {
-                    let new_name = if ext.is_empty() {
-                        format!("{}_{}", stem, n)
-                    } else {
-                        format!("{}_{}.{}", stem, n, ext)
-                    };
-                    dest = target.join(&new_name);
-                    if !dest.exists() { break; }
-                    n += 1;
+    for (i, aid) in asset_ids.iter().enumerate() {
+        if ticket.is_cancelled() {
+            return Ok(BatchResult { count, cancelled: true });
+        }
+        let source = {
+            let conn = state.db.read()?;
+            db::get_asset_path(&conn, *aid)
+        };
+        let file_name = match source {
+            Ok((file_path, file_name)) => {
+                let target = target.clone();
+                let name = file_name.clone();
+                let copied = tokio::task::spawn_blocking(move || {
+                    let mut dest = target.join(&name);
+
+                    // 避免文件名冲突
+                    if dest.exists() {
+                        let stem = std::path::Path::new(&name)
+                            .file_stem().unwrap_or_default().to_string_lossy().to_string();
+                        let ext = std::path::Path::new(&name)
+                            .extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+                        let mut n = 1;
+                        loop {
+                            let new_name = if ext.is_empty() {
+                                format!("{}_{}", stem, n)
+                            } else {
+                                format!("{}_{}.{}", stem, n, ext)
+                            };
+                            dest = target.join(&new_name);
+                            if !dest.exists() { break; }
+                            n += 1;
+                        }
+                    }
+
+                    std::fs::copy(&file_path, &dest).is_ok()
+                }).await.map_err(|e| format!("导出线程失败: {}", e))?;
+                if copied {
+                    count += 1;
+                }
+                file_name
+            }
+            Err(_) => String::new(),
+        };
+
+        let _ = app.emit_all("asset-batch-progress", BatchProgress {
+            operation: "export".to_string(),
+            current: i as u32 + 1,
+            total,
+            current_file: file_name,
+        });
+    }
+    Ok(BatchResult { count, cancelled: false })
+}
+
+/// 批量格式转换：解码资产（图片/PSD）并以 target_format（png/jpg/webp/tga）写入 output_dir
+/// - 发送 "asset-convert-progress" 事件，无法解码的文件跳过并记录在 failed 中
+/// - register=true 且 output_dir 位于管理文件夹内时，把转换结果登记为新资产
+/// - delete_source=true 时把转换成功的源文件移到回收站并删除其资产记录（只读文件夹中的源文件保留）
+#[tauri::command]
+pub async fn asset_convert_format(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+    target_format: String,
+    output_dir: String,
+    delete_source: bool,
+    register: Option<bool>,
+    op_id: Option<u64>,
+) -> Result<ConvertSummary, ArtHubError> {
+    let format = OutputFormat::parse(&target_format)
+        .ok_or_else(|| ArtHubError::InvalidInput(format!("不支持的目标格式: {}", target_format)))?;
+    ensure_writable(&state, &output_dir)?;
+    let output = std::path::PathBuf::from(&output_dir);
+    std::fs::create_dir_all(&output)?;
+
+    let register_folder = if register.unwrap_or(false) {
+        let conn = state.db.read()?;
+        db::find_folder_for_path(&conn, &output_dir)?
+    } else {
+        None
+    };
+    let thumb_dir = state.thumb_dir.clone();
+    let use_placeholders = state.placeholder_thumbnails.load(Ordering::Relaxed);
+
+    let total = asset_ids.len() as u32;
+    let mut summary = ConvertSummary::default();
+    let ticket = state.batch_ops.track(op_id);
+
+    for (i, aid) in asset_ids.iter().enumerate() {
+        if ticket.is_cancelled() {
+            summary.cancelled = true;
+            break;
+        }
+        let source = {
+            let conn = state.db.read()?;
+            db::get_asset_path(&conn, *aid)
+        };
+        let (file_path, file_name) = match source {
+            Ok(source) => source,
+            Err(e) => {
+                summary.failed.push(OutputFailure { asset_id: *aid, file_name: String::new(), error: e.to_string() });
+                continue;
+            }
+        };
+
+        let output = output.clone();
+        let source = std::path::PathBuf::from(&file_path);
+        let converted = tokio::task::spawn_blocking(move || convert::convert_file(&source, format, &output))
+            .await.map_err(|e| format!("转换线程失败: {}", e))?;
+
+        let success = converted.is_ok();
+        match converted {
+            Ok(dest) => {
+                let output_path = dest.to_string_lossy().to_string();
+                if let Some((folder_id, root)) = &register_folder {
+                    let root = std::path::PathBuf::from(root);
+                    let thumb_dir = thumb_dir.clone();
+                    let scanned = tokio::task::spawn_blocking(move || {
+                        scanner::scan_file(&root, &dest).map(|f| {
+                            let thumb = scan_thumbnail(&f.path, &f.ext, &thumb_dir, use_placeholders);
+                            (f, thumb)
+                        })
+                    }).await.map_err(|e| format!("登记线程失败: {}", e))?;
+                    if let Some((f, t)) = scanned {
+                        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+                        summary.new_asset_ids.push(db::upsert_asset(
+                            &conn, *folder_id, &f.path, &f.name, &f.ext,
+                            f.size as i64, t.width, t.height, &t.thumb_path, f.modified as i64, &f.rel_dir,
+                            t.status(&f.ext),
+                        )?);
+                    }
+                }
+
+                if delete_source {
+                    if let Err(e) = trash_asset_source(&state, *aid, &file_path) {
+                        log::warn!("[AssetManager] 删除源文件失败 {}: {}", file_path, e);
+                    }
+                }
+
+                summary.converted.push(OutputFile { asset_id: *aid, output_path });
+            }
+            Err(error) => summary.failed.push(OutputFailure { asset_id: *aid, file_name: file_name.clone(), error }),
+        }
+
+        let _ = app.emit_all("asset-convert-progress", ThumbnailProgress {
+            current: i as u32 + 1,
+            total,
+            asset_id: *aid,
+            file_name,
+            success,
+        });
+    }
+
+    Ok(summary)
+}
+
+/// 批量导出缩小后的副本：最长边缩小到 max_dimension（不放大），
+/// 按 format（png/jpg/webp/tga）和 quality（仅 JPEG 有效）编码写入 output_dir，
+/// 发送 "asset-export-progress" 事件并返回每个文件的成功/失败
+#[tauri::command]
+pub async fn asset_export_resized(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+    max_dimension: u32,
+    format: String,
+    quality: u8,
+    output_dir: String,
+    op_id: Option<u64>,
+) -> Result<ExportSummary, ArtHubError> {
+    let output_format = OutputFormat::parse(&format)
+        .ok_or_else(|| ArtHubError::InvalidInput(format!("不支持的导出格式: {}", format)))?;
+    if max_dimension == 0 {
+        return Err(ArtHubError::InvalidInput("最大边长必须大于 0".to_string()));
+    }
+    let output = std::path::PathBuf::from(&output_dir);
+    std::fs::create_dir_all(&output)?;
+
+    let total = asset_ids.len() as u32;
+    let mut summary = ExportSummary::default();
+    let ticket = state.batch_ops.track(op_id);
+
+    for (i, aid) in asset_ids.iter().enumerate() {
+        if ticket.is_cancelled() {
+            summary.cancelled = true;
+            break;
+        }
+        let source = {
+            let conn = state.db.read()?;
+            db::get_asset_path(&conn, *aid)
+        };
+        let (file_path, file_name) = match source {
+            Ok(source) => source,
+            Err(e) => {
+                summary.failed.push(OutputFailure { asset_id: *aid, file_name: String::new(), error: e.to_string() });
+                continue;
+            }
+        };
+
+        let output = output.clone();
+        let exported = tokio::task::spawn_blocking(move || {
+            convert::export_resized(std::path::Path::new(&file_path), max_dimension, output_format, quality, &output)
+        }).await.map_err(|e| format!("导出线程失败: {}", e))?;
+
+        let success = exported.is_ok();
+        match exported {
+            Ok(dest) => summary.exported.push(OutputFile {
+                asset_id: *aid,
+                output_path: dest.to_string_lossy().to_string(),
+            }),
+            Err(error) => summary.failed.push(OutputFailure { asset_id: *aid, file_name: file_name.clone(), error }),
+        }
+
+        let _ = app.emit_all("asset-export-progress", ThumbnailProgress {
+            current: i as u32 + 1,
+            total,
+            asset_id: *aid,
+            file_name,
+            success,
+        });
+    }
+
+    Ok(summary)
+}
+
+/// 把资产的标签、评分和备注写成源文件旁边的旁注文件（format: "txt" 逗号分隔标签 / "json"），
+/// 供外部训练/管线工具使用。只读文件夹中的资产记为失败；overwrite 为 false 时跳过已存在的旁注文件
+#[tauri::command]
+pub async fn asset_export_sidecars(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+    format: String,
+    overwrite: bool,
+) -> Result<sidecar::SidecarExportSummary, ArtHubError> {
+    let format = sidecar::SidecarFormat::parse(&format)
+        .ok_or_else(|| ArtHubError::InvalidInput(format!("不支持的旁注格式: {}", format)))?;
+
+    let mut summary = sidecar::SidecarExportSummary::default();
+    let mut jobs = Vec::with_capacity(asset_ids.len());
+    {
+        let conn = state.db.read()?;
+        for &aid in &asset_ids {
+            let (file_path, file_name) = match db::get_asset_path(&conn, aid) {
+                Ok(source) => source,
+                Err(error) => {
+                    summary.failed.push(OutputFailure { asset_id: aid, file_name: String::new(), error: error.to_string() });
+                    continue;
                 }
+            };
+            if db::is_path_readonly(&conn, &file_path)? {
+                let error = "该文件位于只读文件夹中，禁止写入旁注".to_string();
+                summary.failed.push(OutputFailure { asset_id: aid, file_name, error });
+                continue;
+            }
+            let meta = sidecar::SidecarMeta {
+                tags: db::get_asset_tags(&conn, aid)?.into_iter().map(|t| t.name).collect(),
+                rating: Some(db::get_rating(&conn, aid)).filter(|r| *r > 0),
+                caption: Some(db::get_note(&conn, aid)).filter(|n| !n.is_empty()),
+            };
+            jobs.push((aid, file_path, file_name, meta));
+        }
+    }
+
+    tokio::task::spawn_blocking(move || {
+        for (asset_id, file_path, file_name, meta) in jobs {
+            match sidecar::write_sidecar(std::path::Path::new(&file_path), format, &meta, overwrite) {
+                Ok(Some(dest)) => summary.written.push(OutputFile {
+                    asset_id,
+                    output_path: dest.to_string_lossy().to_string(),
+                }),
+                Ok(None) => summary.skipped.push(asset_id),
+                Err(error) => summary.failed.push(OutputFailure { asset_id, file_name, error }),
             }
+        }
+        summary
+    }).await.map_err(|e| ArtHubError::Internal(format!("导出线程失败: {}", e)))
+}
 
-            if std::fs::copy(src, &dest).is_ok() {
-                count += 1;
+/// 以 quality 重新压缩 JPEG 资产以节省空间（mozjpeg，保留 EXIF/ICC），
+/// 只在结果更小时写回。非 JPEG 和只读文件夹中的资产跳过，返回共节省的字节数
+#[tauri::command]
+pub async fn asset_optimize_jpeg(
+    state: tauri::State<'_, AssetManagerState>,
+    asset_ids: Vec<i64>,
+    quality: u8,
+) -> Result<u64, ArtHubError> {
+    let mut saved = 0u64;
+
+    for aid in &asset_ids {
+        let (file_path, _) = {
+            let conn = state.db.read()?;
+            match db::get_asset_path(&conn, *aid) {
+                Ok(source) => source,
+                Err(_) => continue,
             }
+        };
+        let ext = std::path::Path::new(&file_path)
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        if !matches!(ext.as_str(), "jpg" | "jpeg") {
+            continue;
         }
+        if let Err(e) = ensure_writable(&state, &file_path) {
+            log::warn!("[AssetManager] 跳过 JPEG 压缩: {}", e);
+            continue;
+        }
+
+        let path = std::path::PathBuf::from(&file_path);
+        let optimized = tokio::task::spawn_blocking(move || optimize::optimize_jpeg(&path, quality))
+            .await.map_err(|e| format!("压缩线程失败: {}", e))?;
+        let result = match optimized {
+            Ok(Some(result)) => result,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("[AssetManager] JPEG 压缩失败 (asset {}): {}", aid, e);
+                continue;
+            }
+        };
+
+        let modified_at = std::fs::metadata(&file_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        db::update_asset_file_info(&conn, *aid, result.new_size as i64, modified_at, result.width, result.height)?;
+        saved += result.old_size - result.new_size;
+    }
+
+    Ok(saved)
+}
+
+/// 把资产源文件移到回收站，并删除资产记录和缩略图
+fn trash_asset_source(state: &AssetManagerState, asset_id: i64, file_path: &str) -> Result<(), ArtHubError> {
+    ensure_writable(state, file_path)?;
+    trash::delete(file_path).map_err(|e| ArtHubError::Io(format!("移到回收站失败: {}", e)))?;
+
+    let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+    if let Some(thumb_path) = db::get_asset_thumb_path(&conn, asset_id)? {
+        thumbnail::cleanup_thumbnails(&state.thumb_dir, &[thumb_path]);
     }
-    Ok(count)
+    db::batch_delete_assets(&conn, &[asset_id])?;
+    Ok(())
 }
 
 /// 获取当前操作系统用户名
 #[tauri::command]
 pub fn asset_get_os_username() -> String {
-    whoami::username()
+    team::local_identity().username.clone()
 }
 
 // ============================================================
 // Phase 3: Team Collaboration Commands
 // ============================================================
 
+/// 获取本机身份（团队操作记录的用户名和机器名，由后端解析）
+#[tauri::command]
+pub fn team_get_identity() -> team::Identity {
+    team::local_identity().clone()
+}
+
 /// 获取文件锁状态
 #[tauri::command]
 pub fn team_check_lock(
@@ -493,43 +2064,76 @@ pub fn team_check_lock(
     team::check_lock(std::path::Path::new(&shared_root), &file_path)
 }
 
-/// 获取锁
+/// 以本机身份获取锁
 #[tauri::command]
 pub fn team_acquire_lock(
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
     file_path: String,
-    username: String,
-    machine: String,
-) -> Result<bool, String> {
-    team::acquire_lock(std::path::Path::new(&shared_root), &file_path, &username, &machine)
+) -> Result<bool, ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    let root = std::path::Path::new(&shared_root);
+    let me = team::local_identity();
+    let acquired = team::acquire_lock(root, &file_path, &me.username, &me.machine)?;
+    if acquired {
+        // 持有期间自动刷新心跳，避免长时间编辑时锁过期
+        state.lock_sessions.start(root, &file_path, &me.username);
+    }
+    Ok(acquired)
 }
 
-/// 释放锁
+/// 释放本机身份持有的锁（同时结束自动心跳）
 #[tauri::command]
 pub fn team_release_lock(
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
     file_path: String,
-    username: String,
-) -> Result<bool, String> {
-    team::release_lock(std::path::Path::new(&shared_root), &file_path, &username)
+) -> Result<bool, ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    let root = std::path::Path::new(&shared_root);
+    state.lock_sessions.end(root, &file_path);
+    Ok(team::release_lock(root, &file_path, &team::local_identity().username)?)
+}
+
+/// 为已持有的锁开始自动刷新心跳（team_acquire_lock 已自动调用）
+#[tauri::command]
+pub fn team_start_lock_session(
+    state: tauri::State<'_, AssetManagerState>,
+    shared_root: String,
+    file_path: String,
+) -> Result<(), ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    state.lock_sessions.start(std::path::Path::new(&shared_root), &file_path, &team::local_identity().username);
+    Ok(())
+}
+
+/// 停止自动刷新心跳（不释放锁），返回是否存在该会话
+#[tauri::command]
+pub fn team_end_lock_session(
+    state: tauri::State<'_, AssetManagerState>,
+    shared_root: String,
+    file_path: String,
+) -> bool {
+    state.lock_sessions.end(std::path::Path::new(&shared_root), &file_path)
 }
 
 /// 刷新心跳
 #[tauri::command]
 pub fn team_refresh_heartbeat(
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
     file_path: String,
-    username: String,
-) -> Result<bool, String> {
-    team::refresh_heartbeat(std::path::Path::new(&shared_root), &file_path, &username)
+) -> Result<bool, ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    Ok(team::refresh_heartbeat(std::path::Path::new(&shared_root), &file_path, &team::local_identity().username)?)
 }
 
 /// 获取所有活跃锁
 #[tauri::command]
 pub fn team_get_all_locks(
     shared_root: String,
-) -> Result<Vec<team::FileLock>, String> {
-    team::get_all_locks(std::path::Path::new(&shared_root))
+) -> Result<Vec<team::FileLock>, ArtHubError> {
+    Ok(team::get_all_locks(std::path::Path::new(&shared_root))?)
 }
 
 /// 获取文件版本历史
@@ -537,62 +2141,98 @@ pub fn team_get_all_locks(
 pub fn team_get_history(
     shared_root: String,
     file_path: String,
-) -> Result<Option<team::FileHistory>, String> {
-    team::get_file_history(std::path::Path::new(&shared_root), &file_path)
+) -> Result<Option<team::FileHistory>, ArtHubError> {
+    Ok(team::get_file_history(std::path::Path::new(&shared_root), &file_path)?)
 }
 
-/// 创建版本
+/// 创建版本（作者为本机用户）。版本库超出软配额时发送 "team-store-quota-warning" 事件，
+/// 配置为拒绝时返回 quota_exceeded
 #[tauri::command]
 pub fn team_create_version(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
     file_path: String,
     actual_file_path: String,
-    author: String,
     comment: String,
-) -> Result<team::FileVersion, String> {
-    team::create_version(
+) -> Result<team::FileVersion, ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    let incoming = std::fs::metadata(&actual_file_path).map(|m| m.len()).unwrap_or(0);
+    let warning = team::check_store_quota(std::path::Path::new(&shared_root), incoming)
+        .map_err(ArtHubError::QuotaExceeded)?;
+    if let Some(warning) = warning {
+        let _ = app.emit_all("team-store-quota-warning", warning);
+    }
+    Ok(team::create_version(
         std::path::Path::new(&shared_root),
         &file_path,
         std::path::Path::new(&actual_file_path),
-        &author,
+        &team::local_identity().username,
         &comment,
-    )
+    )?)
 }
 
 /// 恢复版本
 #[tauri::command]
 pub fn team_restore_version(
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
     file_path: String,
     version: u32,
     target_path: String,
-) -> Result<(), String> {
-    team::restore_version(
+) -> Result<(), ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    ensure_writable(&state, &target_path)?;
+    Ok(team::restore_version(
         std::path::Path::new(&shared_root),
         &file_path,
         version,
         std::path::Path::new(&target_path),
-    )
+    )?)
+}
+
+/// 文件在共享目录内改名/移动后，把版本历史和锁迁移到新路径（自动心跳也随之切换），并记录操作日志
+#[tauri::command]
+pub fn team_relink_history(
+    state: tauri::State<'_, AssetManagerState>,
+    shared_root: String,
+    old_path: String,
+    new_path: String,
+) -> Result<team::RelinkResult, ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    let root = std::path::Path::new(&shared_root);
+    let me = team::local_identity();
+    let result = team::relink_history(root, &old_path, &new_path, &me.username)?;
+    if result.lock_moved && state.lock_sessions.end(root, &old_path) {
+        state.lock_sessions.start(root, &new_path, &me.username);
+    }
+    if result.history_moved || result.lock_moved {
+        if let Err(e) = team::append_action(root, &me.username, &me.machine, "relink", &new_path, &old_path) {
+            log::warn!("[Team] 记录迁移日志失败: {}", e);
+        }
+    }
+    Ok(result)
 }
 
-/// 记录操作日志
+/// 以本机身份记录操作日志
 #[tauri::command]
 pub fn team_log_action(
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
-    username: String,
-    machine: String,
     action: String,
     target_path: String,
     data: String,
-) -> Result<(), String> {
-    team::append_action(
+) -> Result<(), ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    let me = team::local_identity();
+    Ok(team::append_action(
         std::path::Path::new(&shared_root),
-        &username,
-        &machine,
+        &me.username,
+        &me.machine,
         &action,
         &target_path,
         &data,
-    )
+    )?)
 }
 
 /// 读取操作日志
@@ -600,32 +2240,122 @@ pub fn team_log_action(
 pub fn team_read_actions(
     shared_root: String,
     since_timestamp: u64,
-) -> Result<Vec<team::ActionLog>, String> {
-    team::read_actions_since(std::path::Path::new(&shared_root), since_timestamp)
+) -> Result<Vec<team::ActionLog>, ArtHubError> {
+    Ok(team::read_actions_since(std::path::Path::new(&shared_root), since_timestamp)?)
+}
+
+/// 初始化共享目录（创建目录结构、写入版本标记并做读写测试）
+#[tauri::command]
+pub fn team_init_shared_root(
+    state: tauri::State<'_, AssetManagerState>,
+    shared_root: String,
+) -> Result<team::SharedRootStatus, ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    Ok(team::init_shared_root(std::path::Path::new(&shared_root), &team::local_identity().username)?)
+}
+
+/// 检查共享目录的可访问性、可写性和版本
+#[tauri::command]
+pub fn team_check_shared_root(shared_root: String) -> team::SharedRootStatus {
+    team::check_shared_root(std::path::Path::new(&shared_root))
+}
+
+/// 更新本机用户的在线状态（前端定时调用）
+#[tauri::command]
+pub fn team_update_presence(
+    state: tauri::State<'_, AssetManagerState>,
+    shared_root: String,
+) -> Result<(), ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    let me = team::local_identity();
+    Ok(team::update_presence(std::path::Path::new(&shared_root), &me.username, &me.machine)?)
+}
+
+/// 获取 within_secs 秒内活跃的用户
+#[tauri::command]
+pub fn team_get_active_users(
+    shared_root: String,
+    within_secs: u64,
+) -> Result<Vec<team::Presence>, ArtHubError> {
+    Ok(team::get_active_users(std::path::Path::new(&shared_root), within_secs)?)
+}
+
+/// 导出所有用户的操作日志为 CSV，返回导出条数
+#[tauri::command]
+pub fn team_export_actions_csv(
+    shared_root: String,
+    since: u64,
+    dest_path: String,
+) -> Result<usize, ArtHubError> {
+    Ok(team::export_actions_csv(
+        std::path::Path::new(&shared_root),
+        since,
+        std::path::Path::new(&dest_path),
+    )?)
+}
+
+/// 比较文件两个版本的差异（大小变化 + 文本行数或二进制分块统计）
+#[tauri::command]
+pub async fn team_version_diff(
+    shared_root: String,
+    file_path: String,
+    from_ver: u32,
+    to_ver: u32,
+) -> Result<team::VersionDiff, ArtHubError> {
+    Ok(tokio::task::spawn_blocking(move || {
+        team::version_diff(std::path::Path::new(&shared_root), &file_path, from_ver, to_ver)
+    }).await.map_err(|e| format!("对比线程失败: {}", e))??)
+}
+
+/// 统计共享目录中版本库、锁和日志的占用（含每个文件的版本占用）
+#[tauri::command]
+pub async fn team_get_store_usage(shared_root: String) -> Result<team::StoreUsage, ArtHubError> {
+    Ok(tokio::task::spawn_blocking(move || {
+        team::get_store_usage(std::path::Path::new(&shared_root))
+    }).await.map_err(|e| format!("统计线程失败: {}", e))??)
+}
+
+/// 读取版本库配额配置
+#[tauri::command]
+pub fn team_get_store_config(shared_root: String) -> Result<team::StoreConfig, ArtHubError> {
+    Ok(team::load_store_config(std::path::Path::new(&shared_root))?)
+}
+
+/// 设置版本库软配额（soft_quota_bytes 为 None 时不限制）；refuse_over_quota 为 true 时超出后拒绝创建版本
+#[tauri::command]
+pub fn team_set_store_config(
+    state: tauri::State<'_, AssetManagerState>,
+    shared_root: String,
+    config: team::StoreConfig,
+) -> Result<(), ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    Ok(team::save_store_config(std::path::Path::new(&shared_root), &config)?)
 }
 
 /// 加载权限配置
 #[tauri::command]
 pub fn team_load_permissions(
     shared_root: String,
-) -> Result<team::PermissionsConfig, String> {
-    team::load_permissions(std::path::Path::new(&shared_root))
+) -> Result<team::PermissionsConfig, ArtHubError> {
+    Ok(team::load_permissions(std::path::Path::new(&shared_root))?)
 }
 
 /// 设置用户权限
 #[tauri::command]
 pub fn team_set_permission(
+    state: tauri::State<'_, AssetManagerState>,
     shared_root: String,
     username: String,
     role: String,
     project_path: Option<String>,
-) -> Result<(), String> {
-    team::set_user_permission(
+) -> Result<(), ArtHubError> {
+    ensure_writable(&state, &shared_root)?;
+    Ok(team::set_user_permission(
         std::path::Path::new(&shared_root),
         &username,
         &role,
         project_path.as_deref(),
-    )
+    )?)
 }
 
 /// 获取用户角色
@@ -634,7 +2364,7 @@ pub fn team_get_user_role(
     shared_root: String,
     username: String,
     project_path: Option<String>,
-) -> Result<String, String> {
+) -> Result<String, ArtHubError> {
     let cfg = team::load_permissions(std::path::Path::new(&shared_root))?;
     Ok(team::get_user_role(&cfg, &username, project_path.as_deref()))
 }
@@ -649,11 +2379,42 @@ pub fn ffmpeg_check() -> ffmpeg::FfmpegStatus {
     ffmpeg::check_ffmpeg()
 }
 
+/// 使用已有的 FFmpeg（可执行文件或其所在目录）；path 为 None 时恢复自动查找。
+/// 路径经 -version 校验后保存到设置，并重新注册视频缩略图提供者
+#[tauri::command]
+pub fn ffmpeg_set_custom_path(
+    app: AppHandle,
+    state: tauri::State<'_, AssetManagerState>,
+    path: Option<String>,
+) -> Result<ffmpeg::FfmpegStatus, ArtHubError> {
+    let binary = match path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => Some(ffmpeg::validate_ffmpeg(std::path::Path::new(p))
+            .map_err(ArtHubError::InvalidInput)?),
+        None => None,
+    };
+
+    {
+        let conn = state.db.lock().map_err(ArtHubError::db_lock)?;
+        match &binary {
+            Some(b) => db::set_setting(&conn, db::SETTING_FFMPEG_PATH, &serde_json::json!(b.to_string_lossy()))?,
+            None => db::delete_setting(&conn, db::SETTING_FFMPEG_PATH)?,
+        }
+    }
+    ffmpeg::set_custom_ffmpeg_path(binary);
+
+    if let Some(video) = app.path_resolver().app_data_dir()
+        .and_then(|dir| thumb_provider::VideoProvider::detect(&dir))
+    {
+        thumb_provider::register_provider(std::sync::Arc::new(video));
+    }
+    Ok(ffmpeg::check_ffmpeg())
+}
+
 /// 下载安装 FFmpeg
 #[tauri::command]
 pub async fn ffmpeg_download(
     app: AppHandle,
-) -> Result<String, String> {
+) -> Result<String, ArtHubError> {
     let app_data = app.path_resolver().app_data_dir()
         .ok_or_else(|| "无法获取应用数据目录".to_string())?;
 
@@ -667,27 +2428,32 @@ pub async fn ffmpeg_download(
         }
     });
 
-    ffmpeg::download_ffmpeg(&app_data, tx).await
+    Ok(ffmpeg::download_ffmpeg(&app_data, tx).await?)
 }
 
-/// 提取视频缩略图
+/// 提取视频缩略图；timestamp 为截帧位置（秒数/时长百分比/多帧取最亮），默认时长的 10% 处
 #[tauri::command]
 pub fn ffmpeg_extract_thumbnail(
     app: AppHandle,
     video_path: String,
     output_path: String,
     width: u32,
-) -> Result<(), String> {
+    timestamp: Option<ffmpeg::FrameTimestamp>,
+) -> Result<(), ArtHubError> {
     let app_data = app.path_resolver().app_data_dir()
         .ok_or_else(|| "无法获取应用数据目录".to_string())?;
 
     let ffmpeg_path = ffmpeg::get_ffmpeg_path(&app_data)
         .ok_or_else(|| "FFmpeg 未安装".to_string())?;
 
-    ffmpeg::extract_video_thumbnail(
+    let ffprobe_path = ffmpeg::get_ffprobe_path(&app_data);
+
+    Ok(ffmpeg::extract_video_thumbnail(
         &ffmpeg_path,
+        ffprobe_path.as_deref(),
         std::path::Path::new(&video_path),
         std::path::Path::new(&output_path),
         width,
-    )
+        timestamp.unwrap_or_default(),
+    )?)
 }
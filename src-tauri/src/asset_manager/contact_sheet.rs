@@ -0,0 +1,118 @@
+use image::{imageops, ImageFormat, Rgb, RgbImage};
+use std::path::Path;
+
+use crate::asset_manager::db::AssetInfo;
+use crate::asset_manager::placeholder;
+use crate::asset_manager::thumbnail;
+
+/// 单元格之间的间距
+const CELL_PADDING: u32 = 12;
+
+/// 文件名文字缩放倍数（5x7 点阵 * 2）
+const CAPTION_SCALE: u32 = 2;
+
+/// 文件名区域高度
+const CAPTION_HEIGHT: u32 = 7 * CAPTION_SCALE + 8;
+
+/// 把资产缩略图按网格排列到一张大图上（联系表 / 样张）
+/// - 优先使用已有缩略图，缺失时重新生成（写入缩略图缓存）
+/// - caption 为 true 时在每张图下方绘制文件名
+/// - 输出格式由 output_path 的扩展名决定（png / jpg）
+///
+/// 返回实际放入的图片数量
+pub fn generate_contact_sheet(
+    assets: &[AssetInfo],
+    thumb_dir: &Path,
+    columns: u32,
+    cell_size: u32,
+    caption: bool,
+    output_path: &Path,
+) -> Result<u32, String> {
+    if assets.is_empty() {
+        return Err("没有可用于生成联系表的资产".to_string());
+    }
+
+    let format = match output_path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("png") => ImageFormat::Png,
+        Some("jpg") | Some("jpeg") => ImageFormat::Jpeg,
+        _ => return Err("联系表只支持输出 PNG 或 JPEG".to_string()),
+    };
+
+    let columns = columns.clamp(1, 50);
+    let cell_size = cell_size.clamp(32, 1024);
+    let caption_h = if caption { CAPTION_HEIGHT } else { 0 };
+    let rows = (assets.len() as u32).div_ceil(columns);
+
+    let sheet_w = columns * cell_size + (columns + 1) * CELL_PADDING;
+    let sheet_h = rows * (cell_size + caption_h) + (rows + 1) * CELL_PADDING;
+    let mut sheet = RgbImage::from_pixel(sheet_w, sheet_h, Rgb([255, 255, 255]));
+
+    let mut placed = 0u32;
+    for (i, asset) in assets.iter().enumerate() {
+        let col = i as u32 % columns;
+        let row = i as u32 / columns;
+        let x = CELL_PADDING + col * (cell_size + CELL_PADDING);
+        let y = CELL_PADDING + row * (cell_size + caption_h + CELL_PADDING);
+
+        if let Some(img) = load_cell_image(asset, thumb_dir, cell_size) {
+            // 在单元格内居中
+            let (w, h) = img.dimensions();
+            let offset_x = x + (cell_size - w) / 2;
+            let offset_y = y + (cell_size - h) / 2;
+            imageops::overlay(&mut sheet, &img, offset_x as i64, offset_y as i64);
+            placed += 1;
+        }
+
+        if caption {
+            let max_chars = ((cell_size + 1) / (6 * CAPTION_SCALE)) as usize;
+            let name = truncate_caption(&asset.file_name, max_chars);
+            let text_x = x + cell_size.saturating_sub(placeholder::text_width(&name, CAPTION_SCALE)) / 2;
+            placeholder::draw_text(&mut sheet, &name, text_x, y + cell_size + 4, CAPTION_SCALE, Rgb([55, 65, 81]));
+        }
+    }
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("创建输出目录失败: {}", e))?;
+    }
+    sheet.save_with_format(output_path, format)
+        .map_err(|e| format!("保存联系表失败: {}", e))?;
+
+    Ok(placed)
+}
+
+/// 读取单元格图片并缩放到 cell_size 以内（透明部分按白底合成）
+fn load_cell_image(asset: &AssetInfo, thumb_dir: &Path, cell_size: u32) -> Option<RgbImage> {
+    let from_thumb = if asset.thumb_path.is_empty() {
+        None
+    } else {
+        image::open(&asset.thumb_path).ok()
+    };
+
+    let img = match from_thumb {
+        Some(img) => img,
+        None if thumbnail::can_generate_thumbnail(&asset.file_ext) => {
+            // 与扫描时的缩略图宽度一致，避免缓存中出现不同尺寸的缩略图
+            let result = thumbnail::generate_thumbnail(&asset.file_path, thumb_dir, 300).ok()?;
+            image::open(&result.thumb_path).ok()?
+        }
+        None => return None,
+    };
+
+    let img = img.thumbnail(cell_size, cell_size).to_rgba8();
+    let mut out = RgbImage::from_pixel(img.width(), img.height(), Rgb([255, 255, 255]));
+    for (x, y, p) in img.enumerate_pixels() {
+        let a = p[3] as u32;
+        let blend = |c: u8| ((c as u32 * a + 255 * (255 - a)) / 255) as u8;
+        out.put_pixel(x, y, Rgb([blend(p[0]), blend(p[1]), blend(p[2])]));
+    }
+    Some(out)
+}
+
+/// 文件名超出单元格宽度时截断并加 ".."
+fn truncate_caption(name: &str, max_chars: usize) -> String {
+    if name.chars().count() <= max_chars {
+        return name.to_string();
+    }
+    let keep = max_chars.saturating_sub(2);
+    format!("{}..", name.chars().take(keep).collect::<String>())
+}
@@ -0,0 +1,152 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::asset_manager::{importer, thumbnail};
+use crate::fs_util::write_atomic;
+
+/// 转换/导出的目标格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Tga,
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "webp" => Some(OutputFormat::Webp),
+            "tga" => Some(OutputFormat::Tga),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Tga => "tga",
+        }
+    }
+}
+
+/// 单个文件的输出结果
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputFile {
+    pub asset_id: i64,
+    pub output_path: String,
+}
+
+/// 单个文件的失败原因
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputFailure {
+    pub asset_id: i64,
+    pub file_name: String,
+    pub error: String,
+}
+
+/// 批量格式转换结果
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ConvertSummary {
+    pub converted: Vec<OutputFile>,
+    pub failed: Vec<OutputFailure>,
+    /// register=true 时新登记的资产 ID
+    pub new_asset_ids: Vec<i64>,
+    /// 被 asset_cancel_batch 取消时为 true，结果只包含取消前处理的文件
+    pub cancelled: bool,
+}
+
+/// 批量缩放导出结果
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ExportSummary {
+    pub exported: Vec<OutputFile>,
+    pub failed: Vec<OutputFailure>,
+    /// 被 asset_cancel_batch 取消时为 true
+    pub cancelled: bool,
+}
+
+/// 按比例缩小，使最长边不超过 max_dimension（不放大）
+pub fn fit_within(img: DynamicImage, max_dimension: u32) -> DynamicImage {
+    let (w, h) = (img.width(), img.height());
+    let longest = w.max(h);
+    if longest <= max_dimension || max_dimension == 0 {
+        return img;
+    }
+    let ratio = max_dimension as f64 / longest as f64;
+    let new_w = ((w as f64 * ratio).round() as u32).max(1);
+    let new_h = ((h as f64 * ratio).round() as u32).max(1);
+    img.resize_exact(new_w, new_h, image::imageops::FilterType::Lanczos3)
+}
+
+/// 按目标格式编码图像。
+/// - JPEG 不支持 alpha，转换为 8 位 RGB，quality 取 1-100
+/// - 其余格式统一转为 8 位 RGBA（16 位/浮点像素编码器不一定支持）
+/// - image crate 只提供无损 WebP 编码，quality 对 PNG/WebP/TGA 无效
+pub fn encode(img: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    match format {
+        OutputFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100))),
+        _ => {
+            let image_format = match format {
+                OutputFormat::Png => ImageFormat::Png,
+                OutputFormat::Webp => ImageFormat::WebP,
+                _ => ImageFormat::Tga,
+            };
+            DynamicImage::ImageRgba8(img.to_rgba8())
+                .write_to(&mut std::io::Cursor::new(&mut bytes), image_format)
+        }
+    }
+    .map_err(|e| format!("编码图片失败: {}", e))?;
+    Ok(bytes)
+}
+
+/// 把 img 编码后写入 output_dir，文件名沿用源文件名并替换扩展名，重名时追加序号
+pub fn write_output(
+    img: &DynamicImage,
+    source: &Path,
+    format: OutputFormat,
+    quality: u8,
+    output_dir: &Path,
+) -> Result<PathBuf, String> {
+    let stem = source.file_stem()
+        .ok_or_else(|| format!("无效的文件路径: {}", source.display()))?
+        .to_string_lossy();
+    let bytes = encode(img, format, quality)?;
+    let dest = importer::unique_destination(output_dir, &format!("{}.{}", stem, format.extension()));
+    write_atomic(&dest, &bytes)
+        .map_err(|e| format!("写入文件失败 {}: {}", dest.display(), e))?;
+    Ok(dest)
+}
+
+/// 解码源文件（图片 / PSD）并转换为目标格式写入 output_dir，返回输出路径
+pub fn convert_file(source: &Path, format: OutputFormat, output_dir: &Path) -> Result<PathBuf, String> {
+    let ext = source.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    if !thumbnail::can_generate_thumbnail(&ext) {
+        return Err(format!("该格式无法解码: {}", ext));
+    }
+    let img = thumbnail::decode_image(&source.to_string_lossy(), &ext)?;
+    write_output(&img, source, format, 90, output_dir)
+}
+
+/// 解码源文件，缩小到最长边不超过 max_dimension 后按目标格式/质量写入 output_dir
+pub fn export_resized(
+    source: &Path,
+    max_dimension: u32,
+    format: OutputFormat,
+    quality: u8,
+    output_dir: &Path,
+) -> Result<PathBuf, String> {
+    let ext = source.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    if !thumbnail::can_generate_thumbnail(&ext) {
+        return Err(format!("该格式无法解码: {}", ext));
+    }
+    let img = thumbnail::decode_image(&source.to_string_lossy(), &ext)?;
+    write_output(&fit_within(img, max_dimension), source, format, quality, output_dir)
+}
@@ -1,37 +1,255 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Serialize, Deserialize};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use crate::asset_manager::ffmpeg;
+use crate::asset_manager::formats;
+use crate::asset_manager::pool::DbPool;
+use crate::asset_manager::scanner;
+use crate::asset_manager::sidecar;
+use crate::asset_manager::thumbnail;
+use crate::asset_manager::thumb_provider;
+use crate::asset_manager::team;
+use crate::asset_manager::scan_queue::{ScanQueue, MAX_CONCURRENT_SCANS};
+use crate::asset_manager::batch_ops::BatchOps;
+use crate::error::ArtHubError;
+use crate::asset_manager::thumb_worker::ThumbWorker;
 
 // ---- State ----
 
 pub struct AssetManagerState {
-    pub db: Mutex<Connection>,
+    pub db: DbPool,
     pub thumb_dir: PathBuf,
+    /// 是否为无法预览的格式生成分类占位缩略图
+    pub placeholder_thumbnails: AtomicBool,
+    pub scan_queue: ScanQueue,
+    /// 可取消的批量导出/转换
+    pub batch_ops: BatchOps,
+    /// 扫描后在后台生成缩略图的队列
+    pub thumb_worker: ThumbWorker,
+    /// 启动时数据库损坏被重置：记录损坏文件被移到的位置，供前端提示用户
+    pub recovered_db: Option<PathBuf>,
+    /// 团队文件锁的自动心跳会话
+    pub lock_sessions: team::LockSessions,
 }
 
 impl AssetManagerState {
     pub fn new(db_path: PathBuf, thumb_dir: PathBuf) -> Result<Self, String> {
-        let conn = Connection::open(&db_path)
-            .map_err(|e| format!("打开数据库失败: {}", e))?;
-
-        // WAL 模式：允许并发读 + 串行写，性能更好
-        conn.execute_batch(
-            "PRAGMA journal_mode=WAL;
-             PRAGMA synchronous=NORMAL;
-             PRAGMA foreign_keys=ON;
-             PRAGMA cache_size=-8000;"
-        ).map_err(|e| format!("设置数据库参数失败: {}", e))?;
+        let (conn, recovered_db) = open_database(&db_path)?;
 
         init_tables(&conn)?;
 
+        // 迁移旧版平铺的缩略图到分片子目录，并同步数据库中的路径
+        let moved = thumbnail::migrate_flat_thumbnails(&thumb_dir);
+        if !moved.is_empty() {
+            update_thumb_paths(&conn, &moved)?;
+            log::info!("[AssetManager] 已迁移 {} 个缩略图到分片目录", moved.len());
+        }
+
+        // 加载缩略图相关设置
+        let thumb_format = get_setting(&conn, SETTING_THUMB_FORMAT)?
+            .and_then(|v| v.as_str().and_then(thumbnail::ThumbFormat::parse))
+            .unwrap_or(thumbnail::ThumbFormat::Jpeg);
+        thumbnail::set_thumb_format(thumb_format);
+        if let Some(pixels) = get_setting(&conn, SETTING_MAX_DECODE_PIXELS)?.and_then(|v| v.as_u64()) {
+            thumb_provider::set_pixel_budget(pixels);
+        }
+        if let Some(path) = get_setting(&conn, SETTING_FFMPEG_PATH)?.and_then(|v| v.as_str().map(PathBuf::from)) {
+            ffmpeg::set_custom_ffmpeg_path(Some(path));
+        }
+        let placeholder_thumbnails = get_setting(&conn, SETTING_PLACEHOLDER_THUMBNAILS)?
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let background_thumbnails = get_setting(&conn, SETTING_BACKGROUND_THUMBNAILS)?
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        // 加载用户注册的自定义扩展名
+        for (ext, category) in get_custom_extensions(&conn)? {
+            if let Err(e) = formats::register_custom_extension(&ext, &category) {
+                log::error!("[AssetManager] 加载自定义扩展名 {} 失败: {}", ext, e);
+            }
+        }
+
         Ok(Self {
-            db: Mutex::new(conn),
+            db: DbPool::new(db_path, conn),
             thumb_dir,
+            placeholder_thumbnails: AtomicBool::new(placeholder_thumbnails),
+            scan_queue: ScanQueue::new(MAX_CONCURRENT_SCANS),
+            batch_ops: BatchOps::default(),
+            thumb_worker: ThumbWorker::new(background_thumbnails),
+            recovered_db,
+            lock_sessions: team::LockSessions::default(),
         })
     }
 }
 
+/// 数据库被其他进程锁定时的最大重试次数
+const DB_OPEN_RETRIES: u32 = 5;
+
+enum OpenError {
+    /// 文件损坏或不是数据库，可以移走后重建
+    Corrupt(String),
+    Other(String),
+}
+
+/// 打开数据库：被锁定时退避重试；损坏时把旧文件改名为 <name>.corrupt-<时间戳> 并新建空库。
+/// 返回连接和（如果发生重置）损坏文件的新路径。
+fn open_database(db_path: &Path) -> Result<(Connection, Option<PathBuf>), String> {
+    match open_with_retry(db_path) {
+        Ok(conn) => Ok((conn, None)),
+        Err(OpenError::Corrupt(reason)) => {
+            log::warn!("[AssetManager] 数据库已损坏，将重置: {}", reason);
+            let moved = quarantine_db(db_path)?;
+            let conn = open_with_retry(db_path).map_err(|e| match e {
+                OpenError::Corrupt(e) | OpenError::Other(e) => format!("重建数据库失败: {}", e),
+            })?;
+            Ok((conn, Some(moved)))
+        }
+        Err(OpenError::Other(e)) => Err(e),
+    }
+}
+
+fn open_with_retry(db_path: &Path) -> Result<Connection, OpenError> {
+    let mut delay = std::time::Duration::from_millis(200);
+    let mut attempt = 1;
+    loop {
+        match open_and_check(db_path) {
+            Err(e) if is_locked(&e) && attempt < DB_OPEN_RETRIES => {
+                log::warn!("[AssetManager] 数据库被锁定，{}ms 后重试 ({}/{})", delay.as_millis(), attempt, DB_OPEN_RETRIES);
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) if is_corrupt(&e) => return Err(OpenError::Corrupt(e.to_string())),
+            Err(e) => return Err(OpenError::Other(format!("打开数据库失败: {}", e))),
+            Ok(Ok(conn)) => return Ok(conn),
+            Ok(Err(report)) => return Err(OpenError::Corrupt(report)),
+        }
+    }
+}
+
+/// 打开数据库、设置参数并做快速完整性检查。外层 Err 为 SQLite 错误，内层 Err 为检查报告。
+/// 用 quick_check 而不是 integrity_check：后者要校验全部索引内容，大库启动时会卡住数秒，
+/// quick_check 仍能发现页结构损坏，开库时遇到 CORRUPT/NOTADB 也会直接按损坏处理
+fn open_and_check(db_path: &Path) -> rusqlite::Result<Result<Connection, String>> {
+    let conn = Connection::open(db_path)?;
+    configure_connection(&conn)?;
+
+    // WAL 模式：允许并发读 + 串行写，性能更好（记录在数据库文件中，只需写连接设置）
+    conn.execute_batch("PRAGMA journal_mode=WAL;")?;
+
+    let report: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if report == "ok" {
+        Ok(Ok(conn))
+    } else {
+        Ok(Err(report))
+    }
+}
+
+/// 每个连接都要单独设置的参数（synchronous / foreign_keys / cache_size 都是连接级的）
+pub(crate) fn configure_connection(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    // 默认只缓存 16 条；query_assets 会按过滤条件组合生成不同的 SQL，适当放大
+    conn.set_prepared_statement_cache_capacity(64);
+    // 文件名自然排序（tex_2 排在 tex_10 前面），供 sort_by = "name_natural" 使用。
+    // 不能叫 NATURAL：它是 SQL 关键字（NATURAL JOIN），COLLATE NATURAL 会报语法错误
+    conn.create_collation("NATURAL_NAME", natural_cmp)?;
+    conn.execute_batch(&format!(
+        "PRAGMA synchronous=NORMAL;
+         PRAGMA foreign_keys=ON;
+         PRAGMA cache_size=-8000;
+         PRAGMA wal_autocheckpoint={};
+         PRAGMA journal_size_limit={};",
+        WAL_AUTOCHECKPOINT_PAGES, WAL_SIZE_LIMIT
+    ))
+}
+
+/// 自动检查点的 WAL 页数阈值（SQLite 默认 1000 页，约 4MB）
+const WAL_AUTOCHECKPOINT_PAGES: u32 = 1000;
+/// 检查点后 WAL 文件超过此大小会被截断，避免大批量扫描后 WAL 长期占用数 GB 磁盘
+const WAL_SIZE_LIMIT: i64 = 64 * 1024 * 1024;
+
+/// 自然排序比较：连续数字按数值比较，其余字符忽略大小写逐个比较
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let take_digits = |it: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = it.peek().copied().filter(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                        it.next();
+                    }
+                    digits
+                };
+                let (da, db) = (take_digits(&mut a), take_digits(&mut b));
+                let (ta, tb) = (da.trim_start_matches('0'), db.trim_start_matches('0'));
+                // 去掉前导零后位数多的更大；位数相同按字典序；数值相同时前导零少的在前
+                let ord = ta.len().cmp(&tb.len())
+                    .then_with(|| ta.cmp(tb))
+                    .then_with(|| da.len().cmp(&db.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn sqlite_code(e: &rusqlite::Error) -> Option<rusqlite::ErrorCode> {
+    match e {
+        rusqlite::Error::SqliteFailure(err, _) => Some(err.code),
+        _ => None,
+    }
+}
+
+fn is_locked(e: &rusqlite::Error) -> bool {
+    matches!(sqlite_code(e), Some(rusqlite::ErrorCode::DatabaseBusy) | Some(rusqlite::ErrorCode::DatabaseLocked))
+}
+
+fn is_corrupt(e: &rusqlite::Error) -> bool {
+    matches!(sqlite_code(e), Some(rusqlite::ErrorCode::DatabaseCorrupt) | Some(rusqlite::ErrorCode::NotADatabase))
+}
+
+/// 把损坏的数据库（连同 -wal / -shm）改名移开，返回新路径
+fn quarantine_db(db_path: &Path) -> Result<PathBuf, String> {
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let suffix = format!("corrupt-{}", ts);
+    let moved = PathBuf::from(format!("{}.{}", db_path.display(), suffix));
+
+    std::fs::rename(db_path, &moved)
+        .map_err(|e| format!("移走损坏的数据库失败: {}", e))?;
+    for side in ["-wal", "-shm"] {
+        let from = PathBuf::from(format!("{}{}", db_path.display(), side));
+        if from.exists() {
+            let _ = std::fs::rename(&from, format!("{}{}.{}", db_path.display(), side, suffix));
+        }
+    }
+    Ok(moved)
+}
+
+/// orientation = "square" 的容差（相对长边）
+const SQUARE_TOLERANCE: f64 = 0.02;
+
 // ---- Data Types ----
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +259,11 @@ pub struct FolderInfo {
     pub name: String,
     pub space_type: String,
     pub asset_count: i64,
+    pub is_readonly: bool,
+    pub favorite_count: i64,
+    pub rated_count: i64,
+    /// 扫描时读取资产旁边的 .txt/.json 旁注文件，自动打标签/评分/写备注
+    pub read_sidecars: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,19 +278,40 @@ pub struct AssetInfo {
     pub height: u32,
     pub thumb_path: String,
     pub modified_at: i64,
+    pub is_missing: bool, // 上次校验时文件已不存在
+    pub thumb_status: String, // THUMB_STATUS_*
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// 资产缩略图状态（assets.thumb_status）
+pub const THUMB_STATUS_NONE: &str = "none"; // 不支持预览的格式（可能使用占位图）
+pub const THUMB_STATUS_OK: &str = "ok";
+pub const THUMB_STATUS_FAILED: &str = "failed"; // 生成失败，可重试
+pub const THUMB_STATUS_PENDING: &str = "pending"; // 视频，等待 FFmpeg 生成
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetQueryParams {
     pub folder_id: Option<i64>,
+    pub space_type: Option<String>,   // "personal" / "team"：只返回该空间文件夹内的资产，可与 folder_id 同时使用
     pub search: Option<String>,
     pub extensions: Option<Vec<String>>,
     pub min_width: Option<u32>,
     pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+    pub min_size: Option<i64>,        // 字节
+    pub max_size: Option<i64>,
+    pub modified_after: Option<i64>,  // unix 秒
+    pub modified_before: Option<i64>,
+    pub orientation: Option<String>,  // "landscape", "portrait", "square"
+    pub aspect_min: Option<f64>,      // 宽/高
+    pub aspect_max: Option<f64>,
     pub tag_ids: Option<Vec<i64>>,
     pub min_rating: Option<i32>,
     pub favorite_only: Option<bool>,
-    pub sort_by: Option<String>,   // "name", "size", "modified", "width"
+    pub subpath: Option<String>,          // 相对扫描根目录的子目录，"/" 分隔
+    pub include_subpaths: Option<bool>,   // 是否包含 subpath 下更深层的子目录
+    pub only_missing: Option<bool>,       // 只返回校验时标记为缺失的资产
+    pub sort_by: Option<String>,   // "name", "name_natural", "size", "modified", "width", "height", "ext", "rating", "favorite", "viewed", "tags"
     pub sort_order: Option<String>, // "asc", "desc"
     pub page: Option<i64>,
     pub page_size: Option<i64>,
@@ -88,6 +332,107 @@ pub struct ScanProgress {
     pub total: u32,
     pub file_name: String,
     pub phase: String, // "scanning", "thumbnails", "complete"
+    /// 从扫描开始（拿到扫描名额）起的秒数
+    pub elapsed_secs: f64,
+    /// 处理速度（个/秒），缩略图阶段为滚动平均
+    pub items_per_sec: f64,
+    /// 预计剩余秒数，速度未知时为 None
+    pub eta_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanSummary {
+    pub folder_id: i64,
+    pub total: u32,
+    pub processed: u32,
+    pub thumbnail_failures: Vec<(String, String)>, // (文件路径, 失败原因)
+    pub oversized: Vec<(String, u32, u32)>, // 超过像素上限而跳过解码的文件 (路径, 宽, 高)
+    pub unsupported: u32, // 无法生成真实缩略图的文件数
+    pub inaccessible: scanner::InaccessibleReport, // 因权限等原因无法访问而跳过的条目
+    pub truncated: bool, // 条目数超过上限，扫描提前结束
+    pub sidecars_applied: u32, // 读取并应用了旁注文件的资产数
+}
+
+/// 后台扫描失败（"asset-scan-error" 事件，添加文件夹时自动扫描用）
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanError {
+    pub folder_id: i64,
+    pub message: String,
+}
+
+/// 扫描时遇到无法访问的条目（"asset-scan-warning" 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanWarning {
+    pub folder_id: i64,
+    pub inaccessible: scanner::InaccessibleReport,
+    pub truncated: bool,
+}
+
+/// 缩略图缓存校验结果（asset_validate_thumbnails 的返回值和 "asset-thumbnails-validated" 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct ThumbValidationSummary {
+    pub folder_id: Option<i64>,
+    pub checked: u32,
+    pub broken: u32,
+    pub repaired: u32,
+    pub failures: Vec<(i64, String)>, // 重新生成失败的 (资产 ID, 原因)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifySummary {
+    pub folder_id: i64,
+    pub checked: u32,
+    pub missing: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThumbnailProgress {
+    pub current: u32,
+    pub total: u32,
+    pub asset_id: i64,
+    pub file_name: String,
+    pub success: bool,
+}
+
+/// 后台缩略图队列的进度（"asset-thumbnail-worker-progress" 事件）；队列清空时发送 idle = true
+#[derive(Debug, Clone, Serialize)]
+pub struct ThumbWorkerProgress {
+    pub asset_id: i64,
+    pub success: bool,
+    /// 本次运行已处理的数量
+    pub done: u32,
+    pub idle: bool,
+}
+
+/// 批量导出/删除的进度（"asset-batch-progress" 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    pub operation: String, // "export", "delete"
+    pub current: u32,
+    pub total: u32,
+    pub current_file: String,
+}
+
+/// 可取消的批量操作结果：cancelled 为 true 时 count 为取消前完成的数量
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    pub count: u32,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubfolderInfo {
+    pub path: String, // 相对扫描根目录，"/" 分隔，根目录为空字符串
+    pub asset_count: i64,
+}
+
+/// 子目录及其所有下级目录的资产汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtreeStats {
+    pub folder_id: i64,
+    pub subpath: String,
+    pub asset_count: i64,
+    pub total_size: i64, // 字节
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -186,79 +531,517 @@ fn init_tables(conn: &Connection) -> Result<(), String> {
         );
 
         CREATE INDEX IF NOT EXISTS idx_asset_tags_asset ON asset_tags(asset_id);
-        CREATE INDEX IF NOT EXISTS idx_asset_tags_tag ON asset_tags(tag_id);"
+        CREATE INDEX IF NOT EXISTS idx_asset_tags_tag ON asset_tags(tag_id);
+
+        -- 设置（key 按功能加前缀，如 thumbnail.format；value 为 JSON）
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );
+
+        -- 用户自定义扩展名
+        CREATE TABLE IF NOT EXISTS custom_extensions (
+            ext TEXT PRIMARY KEY,
+            category TEXT NOT NULL
+        );"
     ).map_err(|e| format!("创建数据表失败: {}", e))?;
 
+    run_migrations(conn)
+}
+
+// ---- Schema Migrations ----
+
+/// 迁移步骤，按顺序执行。第 N 步执行完后 PRAGMA user_version = N。
+/// 只能在末尾追加新步骤，已发布的步骤不能修改或调整顺序。
+const MIGRATIONS: &[fn(&Connection) -> Result<(), String>] = &[
+    // 1: 只读文件夹
+    |conn| {
+        add_column_if_missing(conn, "folders", "is_readonly", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    },
+    // 2: 资产相对扫描根目录的子目录
+    |conn| {
+        if add_column_if_missing(conn, "assets", "rel_dir", "TEXT NOT NULL DEFAULT ''")? {
+            backfill_rel_dirs(conn)?;
+        }
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_assets_rel_dir ON assets(folder_id, rel_dir);")
+            .map_err(|e| format!("创建索引失败: {}", e))
+    },
+    // 3: 最近查看时间
+    |conn| {
+        conn.execute_batch(
+            "ALTER TABLE assets ADD COLUMN viewed_at INTEGER;
+             CREATE INDEX IF NOT EXISTS idx_assets_viewed ON assets(viewed_at);"
+        ).map_err(|e| format!("添加 viewed_at 列失败: {}", e))
+    },
+    // 4: 保存的搜索 + 搜索历史
+    |conn| {
+        conn.execute_batch(
+            "CREATE TABLE saved_searches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                params TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+            );
+            CREATE TABLE search_history (
+                query TEXT PRIMARY KEY,
+                searched_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+            );"
+        ).map_err(|e| format!("创建搜索表失败: {}", e))
+    },
+    // 5: 缺失文件标记
+    |conn| {
+        conn.execute_batch(
+            "ALTER TABLE assets ADD COLUMN is_missing INTEGER NOT NULL DEFAULT 0;
+             CREATE INDEX IF NOT EXISTS idx_assets_missing ON assets(folder_id, is_missing);"
+        ).map_err(|e| format!("添加 is_missing 列失败: {}", e))
+    },
+    // 6: 标签所属空间（NULL 表示所有空间通用）
+    |conn| {
+        add_column_if_missing(conn, "tags", "space_type", "TEXT")?;
+        Ok(())
+    },
+    // 7: AI 工具配置（AI 标签页的地址、输入框选择器和注入方式）
+    |conn| {
+        conn.execute_batch(
+            "CREATE TABLE ai_tools (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                url TEXT NOT NULL,
+                input_selector TEXT,
+                inject_mode TEXT NOT NULL DEFAULT 'auto',
+                sort_order INTEGER NOT NULL DEFAULT 0
+            );"
+        ).map_err(|e| format!("创建 AI 工具表失败: {}", e))
+    },
+    // 8: 按高度过滤/排序
+    |conn| {
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_assets_height ON assets(height);")
+            .map_err(|e| format!("创建索引失败: {}", e))
+    },
+    // 9: 缩略图状态（区分不支持的格式和生成失败）；已有缩略图的记为 ok
+    |conn| {
+        if add_column_if_missing(conn, "assets", "thumb_status", "TEXT NOT NULL DEFAULT 'none'")? {
+            conn.execute("UPDATE assets SET thumb_status = 'ok' WHERE thumb_path != ''", [])
+                .map_err(|e| format!("回填缩略图状态失败: {}", e))?;
+        }
+        Ok(())
+    },
+    // 10: 扫描时读取旁注文件（按文件夹开启）
+    |conn| {
+        add_column_if_missing(conn, "folders", "read_sidecars", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    },
+    // 11: 批量元数据操作日志（撤销用）
+    |conn| {
+        conn.execute_batch(
+            "CREATE TABLE metadata_ops (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                asset_count INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+            );
+            CREATE TABLE metadata_op_changes (
+                op_id INTEGER NOT NULL REFERENCES metadata_ops(id) ON DELETE CASCADE,
+                asset_id INTEGER NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+                field TEXT NOT NULL,
+                value
+            );
+            CREATE INDEX idx_metadata_op_changes_op ON metadata_op_changes(op_id);
+            CREATE INDEX idx_metadata_op_changes_asset ON metadata_op_changes(asset_id);"
+        ).map_err(|e| format!("创建操作日志表失败: {}", e))
+    },
+    // 12: 标签名改为按空间唯一（不同空间可以有同名标签）。
+    // SQLite 不能修改列约束，需要重建 tags 表；DROP TABLE 会级联删除 asset_tags，先备份再恢复
+    |conn| {
+        conn.execute_batch(
+            "CREATE TEMP TABLE asset_tags_backup AS SELECT * FROM asset_tags;
+            CREATE TABLE tags_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL COLLATE NOCASE,
+                color TEXT NOT NULL DEFAULT '#6b7280',
+                space_type TEXT,
+                UNIQUE (name COLLATE NOCASE, space_type)
+            );
+            INSERT INTO tags_new (id, name, color, space_type) SELECT id, name, color, space_type FROM tags;
+            DROP TABLE tags;
+            ALTER TABLE tags_new RENAME TO tags;
+            INSERT INTO asset_tags (asset_id, tag_id, tagged_by, tagged_at)
+                SELECT asset_id, tag_id, tagged_by, tagged_at FROM asset_tags_backup;
+            DROP TABLE asset_tags_backup;
+            -- UNIQUE 约束中 NULL 互不相等，通用标签（space_type 为 NULL）单独建唯一索引
+            CREATE UNIQUE INDEX idx_tags_common_name ON tags(name COLLATE NOCASE) WHERE space_type IS NULL;"
+        ).map_err(|e| format!("重建标签表失败: {}", e))
+    },
+];
+
+/// 执行尚未应用的迁移。每一步在独立事务中执行并记录版本号，重复调用不会重复执行。
+fn run_migrations(conn: &Connection) -> Result<(), String> {
+    let current: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("读取数据库版本失败: {}", e))?;
+
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(current.max(0) as usize) {
+        let version = i + 1;
+        let tx = conn.unchecked_transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+        step(&tx).map_err(|e| format!("数据库迁移 {} 失败: {}", version, e))?;
+        tx.execute_batch(&format!("PRAGMA user_version = {}", version))
+            .map_err(|e| format!("更新数据库版本失败: {}", e))?;
+        tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 为旧数据推导每个资产相对扫描根目录的子目录
+fn backfill_rel_dirs(conn: &Connection) -> Result<(), String> {
+    let rows: Vec<(i64, String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT a.id, a.file_path, f.path FROM assets a JOIN folders f ON f.id = a.folder_id"
+        ).map_err(|e| format!("准备查询失败: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| format!("执行查询失败: {}", e))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    // 在迁移事务内执行
+    let mut stmt = conn.prepare("UPDATE assets SET rel_dir = ?1 WHERE id = ?2")
+        .map_err(|e| format!("准备更新失败: {}", e))?;
+    for (id, file_path, root) in &rows {
+        let rel = scanner::relative_dir(Path::new(root), Path::new(file_path));
+        stmt.execute(params![rel, id]).map_err(|e| format!("更新子目录失败: {}", e))?;
+    }
     Ok(())
 }
 
+/// 如果表中没有该列则添加（CREATE TABLE IF NOT EXISTS 不会更新已有表），返回是否新增
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<bool, String> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    ).map_err(|e| format!("查询表结构失败: {}", e))?;
+
+    if exists == 0 {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+            .map_err(|e| format!("添加列 {}.{} 失败: {}", table, column, e))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// ---- Maintenance ----
+
+/// 数据库整理前后的文件大小（字节，含 WAL）
+#[derive(Debug, Clone, Serialize)]
+pub struct DbSizeReport {
+    pub before: u64,
+    pub after: u64,
+}
+
+/// 数据库文件 + WAL 文件的总大小
+fn db_file_size(conn: &Connection) -> u64 {
+    let path = match conn.path() {
+        Some(p) if !p.is_empty() => PathBuf::from(p),
+        _ => return 0,
+    };
+    let wal = PathBuf::from(format!("{}-wal", path.display()));
+    [path, wal].iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// 使用 SQLite 在线备份 API 备份数据库（应用运行中也能得到一致的快照），返回备份文件大小
+pub fn backup_db(conn: &Connection, dest: &Path) -> Result<u64, ArtHubError> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| ArtHubError::Io(format!("创建备份目录失败: {}", e)))?;
+    }
+    conn.backup(rusqlite::DatabaseName::Main, dest, None)
+        .map_err(ArtHubError::db("备份数据库失败"))?;
+    std::fs::metadata(dest)
+        .map(|m| m.len())
+        .map_err(|e| ArtHubError::Io(format!("读取备份文件失败: {}", e)))
+}
+
+/// WAL 检查点方式
+#[derive(Debug, Clone, Copy)]
+pub enum CheckpointMode {
+    /// 不等待读者，尽量把已提交的页写回数据库文件（扫描过程中使用）
+    Passive,
+    /// 等待读者结束后写回全部页并把 WAL 截断为 0
+    Truncate,
+}
+
+/// 检查点结果：busy 为 true 表示有读者/写者占用，未能完成全部写回
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointReport {
+    pub busy: bool,
+    pub wal_frames: i64,
+    pub checkpointed_frames: i64,
+}
+
+/// 执行 WAL 检查点
+pub fn checkpoint_wal(conn: &Connection, mode: CheckpointMode) -> Result<CheckpointReport, ArtHubError> {
+    let sql = match mode {
+        CheckpointMode::Passive => "PRAGMA wal_checkpoint(PASSIVE)",
+        CheckpointMode::Truncate => "PRAGMA wal_checkpoint(TRUNCATE)",
+    };
+    conn.query_row(sql, [], |row| {
+        Ok(CheckpointReport {
+            busy: row.get::<_, i64>(0)? != 0,
+            wal_frames: row.get(1)?,
+            checkpointed_frames: row.get(2)?,
+        })
+    }).map_err(ArtHubError::db("WAL 检查点失败"))
+}
+
+/// VACUUM 整理碎片并截断 WAL，返回整理前后的大小
+pub fn vacuum_db(conn: &Connection) -> Result<DbSizeReport, ArtHubError> {
+    let before = db_file_size(conn);
+    conn.execute_batch("VACUUM;")
+        .map_err(ArtHubError::db("整理数据库失败"))?;
+    checkpoint_wal(conn, CheckpointMode::Truncate)?;
+    Ok(DbSizeReport { before, after: db_file_size(conn) })
+}
+
 // ---- CRUD Operations ----
 
-pub fn insert_folder(conn: &Connection, path: &str, name: &str, space_type: &str) -> Result<FolderInfo, String> {
+/// 设置项 key（按功能命名空间划分）
+pub const SETTING_THUMB_FORMAT: &str = "thumbnail.format";
+pub const SETTING_PLACEHOLDER_THUMBNAILS: &str = "thumbnail.placeholders";
+pub const SETTING_MAX_DECODE_PIXELS: &str = "thumbnail.max_pixels";
+pub const SETTING_BACKGROUND_THUMBNAILS: &str = "thumbnail.background";
+pub const SETTING_ICON_POSITION: &str = "ui.icon_position";
+/// 用户在存储目录对话框中选择过的目录（JSON 数组），前端写入命令允许写入其中
+pub const SETTING_STORAGE_ROOTS: &str = "storage.roots";
+pub const SETTING_FFMPEG_PATH: &str = "ffmpeg.path";
+pub const SETTING_VALIDATE_THUMBNAILS_ON_STARTUP: &str = "thumbnail.validate_on_startup";
+
+/// 读取设置，不存在时返回 None
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<serde_json::Value>, ArtHubError> {
+    let raw: Option<String> = conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    ).optional().map_err(ArtHubError::db("读取设置失败"))?;
+
+    match raw {
+        Some(text) => serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| ArtHubError::Internal(format!("解析设置 {} 失败: {}", key, e))),
+        None => Ok(None),
+    }
+}
+
+/// 写入设置（JSON 值）
+pub fn set_setting(conn: &Connection, key: &str, value: &serde_json::Value) -> Result<(), ArtHubError> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = strftime('%s','now')",
+        params![key, value.to_string()],
+    ).map_err(ArtHubError::db("保存设置失败"))?;
+    Ok(())
+}
+
+/// 删除设置（恢复默认值）
+pub fn delete_setting(conn: &Connection, key: &str) -> Result<(), ArtHubError> {
+    conn.execute("DELETE FROM settings WHERE key = ?1", params![key])
+        .map_err(ArtHubError::db("删除设置失败"))?;
+    Ok(())
+}
+
+pub fn get_custom_extensions(conn: &Connection) -> Result<Vec<(String, String)>, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT ext, category FROM custom_extensions ORDER BY ext")
+        .map_err(ArtHubError::db("准备查询失败"))?;
+    let exts = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(ArtHubError::db("执行查询失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(exts)
+}
+
+pub fn add_custom_extension(conn: &Connection, ext: &str, category: &str) -> Result<(), ArtHubError> {
+    conn.execute(
+        "INSERT INTO custom_extensions (ext, category) VALUES (?1, ?2)
+         ON CONFLICT(ext) DO UPDATE SET category = excluded.category",
+        params![ext, category],
+    ).map_err(ArtHubError::db("保存自定义扩展名失败"))?;
+    Ok(())
+}
+
+pub fn remove_custom_extension(conn: &Connection, ext: &str) -> Result<(), ArtHubError> {
+    conn.execute("DELETE FROM custom_extensions WHERE ext = ?1", params![ext])
+        .map_err(ArtHubError::db("删除自定义扩展名失败"))?;
+    Ok(())
+}
+
+pub fn insert_folder(conn: &Connection, path: &str, name: &str, space_type: &str) -> Result<FolderInfo, ArtHubError> {
     conn.execute(
         "INSERT OR IGNORE INTO folders (path, name, space_type) VALUES (?1, ?2, ?3)",
         params![path, name, space_type],
-    ).map_err(|e| format!("插入文件夹失败: {}", e))?;
+    ).map_err(ArtHubError::db("插入文件夹失败"))?;
 
     let folder = conn.query_row(
-        "SELECT id, path, name, space_type, 
-                (SELECT COUNT(*) FROM assets WHERE folder_id = folders.id) as cnt
-         FROM folders WHERE path = ?1",
+        &format!("{} WHERE f.path = ?1 GROUP BY f.id", FOLDER_SELECT),
         params![path],
-        |row| {
-            Ok(FolderInfo {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                name: row.get(2)?,
-                space_type: row.get(3)?,
-                asset_count: row.get(4)?,
-            })
-        },
-    ).map_err(|e| format!("查询文件夹失败: {}", e))?;
+        folder_from_row,
+    ).map_err(ArtHubError::db("查询文件夹失败"))?;
 
     Ok(folder)
 }
 
-pub fn remove_folder(conn: &Connection, folder_id: i64) -> Result<(), String> {
+pub fn remove_folder(conn: &Connection, folder_id: i64) -> Result<(), ArtHubError> {
     conn.execute("DELETE FROM assets WHERE folder_id = ?1", params![folder_id])
-        .map_err(|e| format!("删除资产失败: {}", e))?;
+        .map_err(ArtHubError::db("删除资产失败"))?;
     conn.execute("DELETE FROM folders WHERE id = ?1", params![folder_id])
-        .map_err(|e| format!("删除文件夹失败: {}", e))?;
+        .map_err(ArtHubError::db("删除文件夹失败"))?;
     Ok(())
 }
 
-pub fn get_folders(conn: &Connection, space_type: Option<&str>) -> Result<Vec<FolderInfo>, String> {
-    let base = "SELECT f.id, f.path, f.name, f.space_type,
-                (SELECT COUNT(*) FROM assets WHERE folder_id = f.id) as cnt
-         FROM folders f";
+pub fn set_folder_readonly(conn: &Connection, folder_id: i64, readonly: bool) -> Result<(), ArtHubError> {
+    let changed = conn.execute(
+        "UPDATE folders SET is_readonly = ?1 WHERE id = ?2",
+        params![readonly, folder_id],
+    ).map_err(ArtHubError::db("更新文件夹失败"))?;
+    if changed == 0 {
+        return Err(ArtHubError::NotFound(format!("文件夹不存在: {}", folder_id)));
+    }
+    Ok(())
+}
 
-    if let Some(st) = space_type {
-        let sql = format!("{} WHERE f.space_type = ?1 ORDER BY f.name", base);
-        let mut stmt = conn.prepare(&sql).map_err(|e| format!("准备查询失败: {}", e))?;
-        let folders = stmt.query_map(params![st], |row| {
-            Ok(FolderInfo {
-                id: row.get(0)?, path: row.get(1)?, name: row.get(2)?,
-                space_type: row.get(3)?, asset_count: row.get(4)?,
-            })
-        }).map_err(|e| format!("执行查询失败: {}", e))?
-          .filter_map(|r| r.ok())
-          .collect();
-        Ok(folders)
-    } else {
-        let sql = format!("{} ORDER BY f.name", base);
-        let mut stmt = conn.prepare(&sql).map_err(|e| format!("准备查询失败: {}", e))?;
-        let folders = stmt.query_map([], |row| {
-            Ok(FolderInfo {
-                id: row.get(0)?, path: row.get(1)?, name: row.get(2)?,
-                space_type: row.get(3)?, asset_count: row.get(4)?,
-            })
-        }).map_err(|e| format!("执行查询失败: {}", e))?
-          .filter_map(|r| r.ok())
-          .collect();
-        Ok(folders)
+pub fn set_folder_read_sidecars(conn: &Connection, folder_id: i64, enabled: bool) -> Result<(), ArtHubError> {
+    let changed = conn.execute(
+        "UPDATE folders SET read_sidecars = ?1 WHERE id = ?2",
+        params![enabled, folder_id],
+    ).map_err(ArtHubError::db("更新文件夹失败"))?;
+    if changed == 0 {
+        return Err(ArtHubError::NotFound(format!("文件夹不存在: {}", folder_id)));
     }
+    Ok(())
+}
+
+/// path 是否就是 root 或位于 root 之下。
+/// 按 folder_path_key 比较：前端传来的路径分隔符和大小写（Windows）可能与入库的根路径不同
+fn path_in_folder(path_key: &str, root: &str) -> bool {
+    let root_key = scanner::folder_path_key(root);
+    path_key == root_key || scanner::is_path_key_within(path_key, &root_key)
+}
+
+/// 路径是否位于某个只读文件夹内
+pub fn is_path_readonly(conn: &Connection, path: &str) -> Result<bool, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT path FROM folders WHERE is_readonly = 1")
+        .map_err(ArtHubError::db("准备查询失败"))?;
+    let readonly: Vec<String> = stmt.query_map([], |row| row.get(0))
+        .map_err(ArtHubError::db("执行查询失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    let key = scanner::folder_path_key(path);
+    Ok(readonly.iter().any(|root| path_in_folder(&key, root)))
+}
+
+/// 查找包含 path 的管理文件夹（嵌套时取最深的一个），返回 (folder_id, 根路径)
+pub fn find_folder_for_path(conn: &Connection, path: &str) -> Result<Option<(i64, String)>, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT id, path FROM folders")
+        .map_err(ArtHubError::db("准备查询失败"))?;
+    let folders: Vec<(i64, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(ArtHubError::db("执行查询失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    let key = scanner::folder_path_key(path);
+    Ok(folders.into_iter()
+        .filter(|(_, root)| path_in_folder(&key, root))
+        .max_by_key(|(_, root)| root.len()))
+}
+
+/// 获取扫描根目录下所有包含资产的子目录及其（直接）资产数
+pub fn get_subfolders(conn: &Connection, folder_id: i64) -> Result<Vec<SubfolderInfo>, ArtHubError> {
+    let mut stmt = conn.prepare(
+        "SELECT rel_dir, COUNT(*) FROM assets WHERE folder_id = ?1 GROUP BY rel_dir ORDER BY rel_dir"
+    ).map_err(ArtHubError::db("准备查询失败"))?;
+    let subfolders = stmt.query_map(params![folder_id], |row| {
+        Ok(SubfolderInfo { path: row.get(0)?, asset_count: row.get(1)? })
+    }).map_err(ArtHubError::db("执行查询失败"))?
+      .filter_map(|r| r.ok())
+      .collect();
+    Ok(subfolders)
+}
+
+/// 统计 subpath 及其下所有子目录中的资产数和总大小（subpath 为空时统计整个文件夹）
+/// 用 rel_dir 的范围比较代替 LIKE 'prefix/%'：可以走 idx_assets_rel_dir 索引，也不用转义路径中的 % 和 _
+/// （'0' 是 '/' 的下一个字符，[prefix/, prefix0) 恰好覆盖所有以 prefix/ 开头的路径）
+pub fn get_subtree_stats(conn: &Connection, folder_id: i64, subpath: &str) -> Result<SubtreeStats, ArtHubError> {
+    let subpath = subpath.trim_matches('/');
+    let (asset_count, total_size) = if subpath.is_empty() {
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(file_size), 0) FROM assets WHERE folder_id = ?1",
+            params![folder_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    } else {
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(file_size), 0) FROM assets
+             WHERE folder_id = ?1 AND (rel_dir = ?2 OR (rel_dir >= ?2 || '/' AND rel_dir < ?2 || '0'))",
+            params![folder_id, subpath],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }.map_err(ArtHubError::db("统计子目录失败"))?;
+
+    Ok(SubtreeStats { folder_id, subpath: subpath.to_string(), asset_count, total_size })
+}
+
+/// 文件夹及其资产/收藏/已评分数量：一次 LEFT JOIN 聚合，而不是每个文件夹一个 COUNT 子查询
+/// （收藏和评分表每个资产最多一行，不会重复计数）
+const FOLDER_SELECT: &str = "SELECT f.id, f.path, f.name, f.space_type,
+            COUNT(a.id) AS cnt,
+            f.is_readonly,
+            COUNT(fav.asset_id) AS fav_cnt,
+            COUNT(r.asset_id) AS rated_cnt,
+            f.read_sidecars
+     FROM folders f
+     LEFT JOIN assets a ON a.folder_id = f.id
+     LEFT JOIN asset_favorites fav ON fav.asset_id = a.id
+     LEFT JOIN asset_ratings r ON r.asset_id = a.id";
+
+fn folder_from_row(row: &rusqlite::Row) -> rusqlite::Result<FolderInfo> {
+    Ok(FolderInfo {
+        id: row.get(0)?, path: row.get(1)?, name: row.get(2)?,
+        space_type: row.get(3)?, asset_count: row.get(4)?,
+        is_readonly: row.get(5)?,
+        favorite_count: row.get(6)?,
+        rated_count: row.get(7)?,
+        read_sidecars: row.get(8)?,
+    })
+}
+
+pub fn get_folders(conn: &Connection, space_type: Option<&str>) -> Result<Vec<FolderInfo>, ArtHubError> {
+    let sql = format!(
+        "{} WHERE ?1 IS NULL OR f.space_type = ?1 GROUP BY f.id ORDER BY f.name",
+        FOLDER_SELECT
+    );
+    let mut stmt = conn.prepare_cached(&sql).map_err(ArtHubError::db("准备查询失败"))?;
+    let folders = stmt.query_map(params![space_type], folder_from_row)
+        .map_err(ArtHubError::db("执行查询失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(folders)
 }
 
+/// 插入或更新资产。RETURNING 在插入和冲突更新时都返回正确的 id（last_insert_rowid 只对插入有效）
+const UPSERT_ASSET_SQL: &str =
+    "INSERT INTO assets (folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at, rel_dir, thumb_status)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+     ON CONFLICT(file_path) DO UPDATE SET
+        file_size = excluded.file_size,
+        width = excluded.width,
+        height = excluded.height,
+        thumb_path = excluded.thumb_path,
+        thumb_status = excluded.thumb_status,
+        modified_at = excluded.modified_at,
+        rel_dir = excluded.rel_dir,
+        is_missing = 0,
+        scanned_at = strftime('%s','now')
+     RETURNING id";
+
 pub fn upsert_asset(
     conn: &Connection,
     folder_id: i64,
@@ -270,66 +1053,279 @@ pub fn upsert_asset(
     height: u32,
     thumb_path: &str,
     modified_at: i64,
-) -> Result<i64, String> {
-    conn.execute(
-        "INSERT INTO assets (folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-         ON CONFLICT(file_path) DO UPDATE SET
-            file_size = excluded.file_size,
-            width = excluded.width,
-            height = excluded.height,
-            thumb_path = excluded.thumb_path,
-            modified_at = excluded.modified_at,
-            scanned_at = strftime('%s','now')",
-        params![folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at],
-    ).map_err(|e| format!("插入资产失败: {}", e))?;
-
-    let id = conn.last_insert_rowid();
-    Ok(id)
+    rel_dir: &str,
+    thumb_status: &str,
+) -> Result<i64, ArtHubError> {
+    conn.prepare_cached(UPSERT_ASSET_SQL)
+        .and_then(|mut stmt| stmt.query_row(
+            params![folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at, rel_dir, thumb_status],
+            |row| row.get(0),
+        ))
+        .map_err(ArtHubError::db("插入资产失败"))
 }
 
-pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<AssetQueryResult, String> {
-    let page = params.page.unwrap_or(1).max(1);
-    let page_size = params.page_size.unwrap_or(100).clamp(1, 500);
-    let offset = (page - 1) * page_size;
-
-    let mut conditions = Vec::new();
-    let mut bind_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-
-    if let Some(fid) = params.folder_id {
-        conditions.push(format!("folder_id = ?{}", bind_values.len() + 1));
-        bind_values.push(Box::new(fid));
-    }
+/// 批量写入时的一条资产（扫描结果 + 缩略图信息）
+pub struct AssetRow<'a> {
+    pub file: &'a scanner::ScannedFile,
+    pub width: u32,
+    pub height: u32,
+    pub thumb_path: &'a str,
+    pub thumb_status: &'a str,
+}
 
-    if let Some(ref search) = params.search {
-        if !search.is_empty() {
-            conditions.push(format!("file_name LIKE ?{}", bind_values.len() + 1));
-            bind_values.push(Box::new(format!("%{}%", search)));
+/// 批量插入或更新资产：整批复用同一条预编译语句，应在调用方的事务内执行。
+/// 单条失败只记录日志并跳过，返回成功写入的条数
+pub fn upsert_assets(conn: &Connection, folder_id: i64, rows: &[AssetRow]) -> Result<u32, ArtHubError> {
+    let mut stmt = conn.prepare_cached(UPSERT_ASSET_SQL)
+        .map_err(ArtHubError::db("准备插入语句失败"))?;
+    let mut count = 0u32;
+    for row in rows {
+        let f = row.file;
+        let result = stmt.query_row(
+            params![folder_id, f.path, f.name, f.ext, f.size as i64, row.width, row.height, row.thumb_path, f.modified as i64, f.rel_dir, row.thumb_status],
+            |r| r.get::<_, i64>(0),
+        );
+        match result {
+            Ok(_) => count += 1,
+            Err(e) => log::warn!("[AssetManager] 插入资产失败 {}: {}", f.path, e),
         }
     }
+    Ok(count)
+}
 
-    if let Some(ref exts) = params.extensions {
-        if !exts.is_empty() {
-            let placeholders: Vec<String> = exts.iter().enumerate().map(|(i, _)| {
-                format!("?{}", bind_values.len() + i + 1)
-            }).collect();
-            conditions.push(format!("file_ext IN ({})", placeholders.join(",")));
-            for ext in exts {
-                bind_values.push(Box::new(ext.to_lowercase()));
-            }
-        }
-    }
+/// 记录资产最近查看时间
+pub fn mark_asset_viewed(conn: &Connection, asset_id: i64) -> Result<(), ArtHubError> {
+    conn.execute(
+        "UPDATE assets SET viewed_at = strftime('%s','now') WHERE id = ?1",
+        params![asset_id],
+    ).map_err(ArtHubError::db("更新查看时间失败"))?;
+    Ok(())
+}
 
-    if let Some(min_w) = params.min_width {
-        conditions.push(format!("width >= ?{}", bind_values.len() + 1));
-        bind_values.push(Box::new(min_w));
-    }
-    if let Some(max_w) = params.max_width {
-        conditions.push(format!("width <= ?{}", bind_values.len() + 1));
-        bind_values.push(Box::new(max_w));
-    }
+/// 获取资产文件路径和名称
+pub fn get_asset_path(conn: &Connection, asset_id: i64) -> Result<(String, String), ArtHubError> {
+    conn.prepare_cached("SELECT file_path, file_name FROM assets WHERE id = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![asset_id], |row| Ok((row.get(0)?, row.get(1)?))))
+        .map_err(ArtHubError::db("查询资产失败"))
+}
 
-    // 评分过滤
+/// 获取资产的缩略图路径（资产不存在时返回 None）
+pub fn get_asset_thumb_path(conn: &Connection, asset_id: i64) -> Result<Option<String>, ArtHubError> {
+    conn.prepare_cached("SELECT thumb_path FROM assets WHERE id = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![asset_id], |row| row.get(0)).optional())
+        .map_err(ArtHubError::db("查询资产失败"))
+}
+
+/// 文件被原地修改后更新大小、修改时间和尺寸
+pub fn update_asset_file_info(
+    conn: &Connection,
+    asset_id: i64,
+    file_size: i64,
+    modified_at: i64,
+    width: u32,
+    height: u32,
+) -> Result<(), ArtHubError> {
+    conn.execute(
+        "UPDATE assets SET file_size = ?1, modified_at = ?2, width = ?3, height = ?4 WHERE id = ?5",
+        params![file_size, modified_at, width, height, asset_id],
+    ).map_err(ArtHubError::db("更新资产信息失败"))?;
+    Ok(())
+}
+
+/// 更新资产的缩略图路径和尺寸
+pub fn update_asset_thumbnail(
+    conn: &Connection,
+    asset_id: i64,
+    thumb_path: &str,
+    width: u32,
+    height: u32,
+    thumb_status: &str,
+) -> Result<(), ArtHubError> {
+    conn.execute(
+        "UPDATE assets SET thumb_path = ?1, width = ?2, height = ?3, thumb_status = ?4, scanned_at = strftime('%s','now') WHERE id = ?5",
+        params![thumb_path, width, height, thumb_status, asset_id],
+    ).map_err(ArtHubError::db("更新缩略图失败"))?;
+    Ok(())
+}
+
+/// 缩略图状态为 pending 且文件仍存在的资产（id, 扩展名）
+pub fn get_pending_thumb_assets(conn: &Connection) -> Result<Vec<(i64, String)>, ArtHubError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_ext FROM assets WHERE thumb_status = ?1 AND is_missing = 0 ORDER BY id"
+    ).map_err(ArtHubError::db("查询失败"))?;
+    let rows = stmt.query_map(params![THUMB_STATUS_PENDING], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(ArtHubError::db("查询失败"))?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(ArtHubError::db("读取失败"))
+}
+
+/// 已生成缩略图的资产 (id, 缩略图路径)，folder_id 为 None 时返回所有文件夹的
+pub fn get_generated_thumbnails(conn: &Connection, folder_id: Option<i64>) -> Result<Vec<(i64, String)>, ArtHubError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, thumb_path FROM assets
+         WHERE thumb_status = ?1 AND thumb_path != '' AND is_missing = 0 AND (?2 IS NULL OR folder_id = ?2)
+         ORDER BY id"
+    ).map_err(ArtHubError::db("查询失败"))?;
+    let rows = stmt.query_map(params![THUMB_STATUS_OK, folder_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(ArtHubError::db("查询失败"))?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(ArtHubError::db("读取失败"))
+}
+
+/// 后台缩略图队列的下一批：id 大于 after 的 pending 资产（id, 路径, 扩展名），按 id 升序
+pub fn next_pending_thumbnails(conn: &Connection, after: i64, limit: u32) -> Result<Vec<(i64, String, String)>, ArtHubError> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, file_path, file_ext FROM assets
+         WHERE thumb_status = ?1 AND is_missing = 0 AND id > ?2
+         ORDER BY id LIMIT ?3"
+    ).map_err(ArtHubError::db("查询失败"))?;
+    let rows = stmt.query_map(params![THUMB_STATUS_PENDING, after, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    }).map_err(ArtHubError::db("查询失败"))?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(ArtHubError::db("读取失败"))
+}
+
+/// 缩略图仍为 pending 的单个资产（路径, 扩展名），已生成或不存在时返回 None
+pub fn get_pending_thumbnail(conn: &Connection, asset_id: i64) -> Result<Option<(String, String)>, ArtHubError> {
+    conn.query_row(
+        "SELECT file_path, file_ext FROM assets WHERE id = ?1 AND thumb_status = ?2 AND is_missing = 0",
+        params![asset_id, THUMB_STATUS_PENDING],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(ArtHubError::db("查询资产失败"))
+}
+
+/// 单独更新缩略图状态（如重新生成失败时标记为 failed）
+pub fn set_thumb_status(conn: &Connection, asset_id: i64, status: &str) -> Result<(), ArtHubError> {
+    conn.execute(
+        "UPDATE assets SET thumb_status = ?1 WHERE id = ?2",
+        params![status, asset_id],
+    ).map_err(ArtHubError::db("更新缩略图状态失败"))?;
+    Ok(())
+}
+
+/// 批量替换缩略图路径（旧路径 -> 新路径），在一个事务中完成
+pub fn update_thumb_paths(conn: &Connection, moves: &[(String, String)]) -> Result<(), ArtHubError> {
+    let tx = conn.unchecked_transaction().map_err(ArtHubError::db("开启事务失败"))?;
+    {
+        let mut stmt = tx.prepare("UPDATE assets SET thumb_path = ?2 WHERE thumb_path = ?1")
+            .map_err(ArtHubError::db("准备更新失败"))?;
+        for (old, new) in moves {
+            stmt.execute(params![old, new]).map_err(ArtHubError::db("更新缩略图路径失败"))?;
+        }
+    }
+    tx.commit().map_err(ArtHubError::db("提交事务失败"))
+}
+
+pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<AssetQueryResult, ArtHubError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(100).clamp(1, 500);
+    let offset = (page - 1) * page_size;
+
+    let mut conditions = Vec::new();
+    let mut bind_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(fid) = params.folder_id {
+        conditions.push(format!("folder_id = ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(fid));
+    }
+
+    // 空间过滤：经 folders 表解析，不需要前端先取文件夹 ID
+    if let Some(ref space) = params.space_type {
+        conditions.push(format!(
+            "folder_id IN (SELECT id FROM folders WHERE space_type = ?{})",
+            bind_values.len() + 1
+        ));
+        bind_values.push(Box::new(space.clone()));
+    }
+
+    if let Some(ref search) = params.search {
+        if !search.is_empty() {
+            conditions.push(format!("file_name LIKE ?{}", bind_values.len() + 1));
+            bind_values.push(Box::new(format!("%{}%", search)));
+        }
+    }
+
+    if let Some(ref exts) = params.extensions {
+        if !exts.is_empty() {
+            let placeholders: Vec<String> = exts.iter().enumerate().map(|(i, _)| {
+                format!("?{}", bind_values.len() + i + 1)
+            }).collect();
+            conditions.push(format!("file_ext IN ({})", placeholders.join(",")));
+            for ext in exts {
+                bind_values.push(Box::new(ext.to_lowercase()));
+            }
+        }
+    }
+
+    if let Some(min_w) = params.min_width {
+        conditions.push(format!("width >= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(min_w));
+    }
+    if let Some(max_w) = params.max_width {
+        conditions.push(format!("width <= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(max_w));
+    }
+    if let Some(min_h) = params.min_height {
+        conditions.push(format!("height >= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(min_h));
+    }
+    if let Some(max_h) = params.max_height {
+        conditions.push(format!("height <= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(max_h));
+    }
+
+    // 文件大小/修改时间范围（走 idx_assets_size / idx_assets_modified 索引）
+    if let Some(min_size) = params.min_size {
+        conditions.push(format!("file_size >= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(min_size));
+    }
+    if let Some(max_size) = params.max_size {
+        conditions.push(format!("file_size <= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(max_size));
+    }
+    if let Some(after) = params.modified_after {
+        conditions.push(format!("modified_at >= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(after));
+    }
+    if let Some(before) = params.modified_before {
+        conditions.push(format!("modified_at <= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(before));
+    }
+
+    // 方向/宽高比过滤：没有尺寸的资产（非图片）不参与
+    if let Some(ref orientation) = params.orientation {
+        // 宽高相差不超过长边的 2% 视为正方形
+        let square = format!("ABS(width - height) <= MAX(width, height) * {}", SQUARE_TOLERANCE);
+        let cond = match orientation.as_str() {
+            "landscape" => format!("width > height AND NOT ({})", square),
+            "portrait" => format!("height > width AND NOT ({})", square),
+            "square" => square,
+            other => return Err(ArtHubError::InvalidInput(format!("无效的方向: {}", other))),
+        };
+        conditions.push(format!("width > 0 AND height > 0 AND {}", cond));
+    }
+    if let Some(aspect_min) = params.aspect_min {
+        conditions.push(format!("width > 0 AND height > 0 AND CAST(width AS REAL) / height >= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(aspect_min));
+    }
+    if let Some(aspect_max) = params.aspect_max {
+        conditions.push(format!("width > 0 AND height > 0 AND CAST(width AS REAL) / height <= ?{}", bind_values.len() + 1));
+        bind_values.push(Box::new(aspect_max));
+    }
+
+    // 子目录过滤
+    if let Some(ref sub) = params.subpath {
+        let sub = sub.trim_matches('/');
+        let include_subpaths = params.include_subpaths.unwrap_or(false);
+        if include_subpaths && !sub.is_empty() {
+            let n = bind_values.len() + 1;
+            conditions.push(format!("(rel_dir = ?{n} OR substr(rel_dir, 1, length(?{n}) + 1) = ?{n} || '/')", n = n));
+            bind_values.push(Box::new(sub.to_string()));
+        } else if !include_subpaths {
+            conditions.push(format!("rel_dir = ?{}", bind_values.len() + 1));
+            bind_values.push(Box::new(sub.to_string()));
+        }
+    }
+
+    // 评分过滤
     if let Some(min_r) = params.min_rating {
         if min_r > 0 {
             conditions.push(format!(
@@ -340,6 +1336,11 @@ pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<Asse
         }
     }
 
+    // 缺失文件过滤
+    if params.only_missing.unwrap_or(false) {
+        conditions.push("is_missing = 1".to_string());
+    }
+
     // 收藏过滤
     if params.favorite_only.unwrap_or(false) {
         conditions.push("id IN (SELECT asset_id FROM asset_favorites)".to_string());
@@ -372,32 +1373,62 @@ pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<Asse
         format!("WHERE {}", conditions.join(" AND "))
     };
 
-    let sort_col = match params.sort_by.as_deref() {
-        Some("size") => "file_size",
-        Some("modified") => "modified_at",
-        Some("width") => "width",
-        Some("ext") => "file_ext",
-        _ => "file_name",
-    };
     let sort_dir = match params.sort_order.as_deref() {
         Some("desc") => "DESC",
         _ => "ASC",
     };
 
+    // 评分/收藏/标签数排序需要关联其他表；这些表每个资产最多一行（标签先聚合），
+    // 不会产生重复行，且只用于数据查询，不影响计数
+    let (sort_join, order_clause) = match params.sort_by.as_deref() {
+        Some("rating") => (
+            "LEFT JOIN asset_ratings r ON r.asset_id = assets.id",
+            // 未评分的始终排在最后（NULLS LAST）
+            format!("r.rating IS NULL, r.rating {}, file_name", sort_dir),
+        ),
+        Some("favorite") => (
+            "LEFT JOIN asset_favorites fav ON fav.asset_id = assets.id",
+            format!("fav.asset_id IS NOT NULL {}, file_name", sort_dir),
+        ),
+        Some("viewed") => (
+            "",
+            // 从未查看过的排在最后
+            format!("viewed_at IS NULL, viewed_at {}, file_name", sort_dir),
+        ),
+        Some("tags") => (
+            "LEFT JOIN (SELECT asset_id, COUNT(*) AS tag_count FROM asset_tags GROUP BY asset_id) tc ON tc.asset_id = assets.id",
+            format!("COALESCE(tc.tag_count, 0) {}, file_name", sort_dir),
+        ),
+        Some("name_natural") => ("", format!("file_name COLLATE NATURAL_NAME {}", sort_dir)),
+        other => {
+            let sort_col = match other {
+                Some("size") => "file_size",
+                Some("modified") => "modified_at",
+                Some("width") => "width",
+                Some("height") => "height",
+                Some("ext") => "file_ext",
+                _ => "file_name",
+            };
+            ("", format!("{} {}", sort_col, sort_dir))
+        }
+    };
+
     // Count total
     let count_sql = format!("SELECT COUNT(*) FROM assets {}", where_clause);
     let params_refs: Vec<&dyn rusqlite::types::ToSql> = bind_values.iter().map(|b| b.as_ref()).collect();
 
-    let total: i64 = conn.query_row(&count_sql, params_refs.as_slice(), |row| row.get(0))
-        .map_err(|e| format!("计数查询失败: {}", e))?;
+    // 生成的 SQL 只取决于用到了哪些过滤/排序条件，组合数有限，按 SQL 文本缓存预编译语句
+    let total: i64 = conn.prepare_cached(&count_sql)
+        .and_then(|mut stmt| stmt.query_row(params_refs.as_slice(), |row| row.get(0)))
+        .map_err(ArtHubError::db("计数查询失败"))?;
 
     // Query assets
     let query_sql = format!(
-        "SELECT id, folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at
-         FROM assets {}
-         ORDER BY {} {}
+        "SELECT id, folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at, is_missing, thumb_status
+         FROM assets {} {}
+         ORDER BY {}
          LIMIT ?{} OFFSET ?{}",
-        where_clause, sort_col, sort_dir,
+        sort_join, where_clause, order_clause,
         bind_values.len() + 1, bind_values.len() + 2
     );
 
@@ -405,7 +1436,7 @@ pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<Asse
     bind_values.push(Box::new(offset));
     let params_refs2: Vec<&dyn rusqlite::types::ToSql> = bind_values.iter().map(|b| b.as_ref()).collect();
 
-    let mut stmt = conn.prepare(&query_sql).map_err(|e| format!("准备查询失败: {}", e))?;
+    let mut stmt = conn.prepare_cached(&query_sql).map_err(ArtHubError::db("准备查询失败"))?;
     let assets = stmt.query_map(params_refs2.as_slice(), |row| {
         Ok(AssetInfo {
             id: row.get(0)?,
@@ -418,8 +1449,10 @@ pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<Asse
             height: row.get::<_, u32>(7).unwrap_or(0),
             thumb_path: row.get(8)?,
             modified_at: row.get(9)?,
+            is_missing: row.get(10)?,
+            thumb_status: row.get(11)?,
         })
-    }).map_err(|e| format!("查询资产失败: {}", e))?
+    }).map_err(ArtHubError::db("查询资产失败"))?
       .filter_map(|r| r.ok())
       .collect();
 
@@ -431,7 +1464,7 @@ pub fn query_assets(conn: &Connection, params: &AssetQueryParams) -> Result<Asse
     })
 }
 
-pub fn get_stats(conn: &Connection) -> Result<FolderStats, String> {
+pub fn get_stats(conn: &Connection) -> Result<FolderStats, ArtHubError> {
     let total_assets: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |row| row.get(0))
         .unwrap_or(0);
     let total_folders: i64 = conn.query_row("SELECT COUNT(*) FROM folders", [], |row| row.get(0))
@@ -440,10 +1473,10 @@ pub fn get_stats(conn: &Connection) -> Result<FolderStats, String> {
         .unwrap_or(0);
 
     let mut stmt = conn.prepare("SELECT file_ext, COUNT(*) as cnt FROM assets GROUP BY file_ext ORDER BY cnt DESC")
-        .map_err(|e| format!("统计查询失败: {}", e))?;
+        .map_err(ArtHubError::db("统计查询失败"))?;
     let format_counts: Vec<(String, i64)> = stmt.query_map([], |row| {
         Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
-    }).map_err(|e| format!("统计查询失败: {}", e))?
+    }).map_err(ArtHubError::db("统计查询失败"))?
       .filter_map(|r| r.ok())
       .collect();
 
@@ -455,9 +1488,27 @@ pub fn get_stats(conn: &Connection) -> Result<FolderStats, String> {
     })
 }
 
-pub fn clear_folder_assets(conn: &Connection, folder_id: i64) -> Result<(), String> {
+/// 获取文件夹下所有资产（按文件名排序）
+pub fn get_folder_assets(conn: &Connection, folder_id: i64) -> Result<Vec<AssetInfo>, ArtHubError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at, is_missing, thumb_status
+         FROM assets WHERE folder_id = ?1 ORDER BY file_name COLLATE NOCASE"
+    ).map_err(ArtHubError::db("准备查询失败"))?;
+    let assets = stmt.query_map(params![folder_id], |row| Ok(AssetInfo {
+        id: row.get(0)?, folder_id: row.get(1)?, file_path: row.get(2)?,
+        file_name: row.get(3)?, file_ext: row.get(4)?, file_size: row.get(5)?,
+        width: row.get::<_, u32>(6).unwrap_or(0), height: row.get::<_, u32>(7).unwrap_or(0),
+        thumb_path: row.get(8)?, modified_at: row.get(9)?, is_missing: row.get(10)?,
+        thumb_status: row.get(11)?,
+    })).map_err(ArtHubError::db("查询资产失败"))?
+      .filter_map(|r| r.ok())
+      .collect();
+    Ok(assets)
+}
+
+pub fn clear_folder_assets(conn: &Connection, folder_id: i64) -> Result<(), ArtHubError> {
     conn.execute("DELETE FROM assets WHERE folder_id = ?1", params![folder_id])
-        .map_err(|e| format!("清空资产失败: {}", e))?;
+        .map_err(ArtHubError::db("清空资产失败"))?;
     Ok(())
 }
 
@@ -471,6 +1522,9 @@ pub struct TagInfo {
     pub name: String,
     pub color: String,
     pub asset_count: i64,
+    /// 所属空间，None 表示所有空间通用
+    #[serde(default)]
+    pub space_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -481,6 +1535,14 @@ pub struct AssetDetail {
     pub note: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub params: AssetQueryParams,
+    pub created_at: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SmartFolder {
     pub id: i64,
@@ -490,156 +1552,351 @@ pub struct SmartFolder {
     pub space_type: String,
 }
 
+/// 智能文件夹条件（conditions 字段的 JSON 结构）；未知字段视为错误
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmartFolderConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_contains: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<i64>, // 字节
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rating: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_ids: Option<Vec<i64>>,
+}
+
+/// 名称关键字的最大长度（字符数）
+const MAX_NAME_CONTAINS_LEN: usize = 256;
+
+/// 解析并校验智能文件夹条件，返回规范化后的条件（扩展名转小写、去掉前导点）
+/// 空字符串视为没有条件
+pub fn parse_smart_folder_conditions(json: &str) -> Result<SmartFolderConditions, ArtHubError> {
+    if json.trim().is_empty() {
+        return Ok(SmartFolderConditions::default());
+    }
+    let mut conds: SmartFolderConditions = serde_json::from_str(json)
+        .map_err(|e| ArtHubError::InvalidInput(format!("智能文件夹条件格式错误: {}", e)))?;
+
+    if let Some(ref text) = conds.name_contains {
+        if text.chars().count() > MAX_NAME_CONTAINS_LEN {
+            return Err(ArtHubError::InvalidInput(format!("名称关键字不能超过 {} 个字符", MAX_NAME_CONTAINS_LEN)));
+        }
+        if text.chars().any(char::is_control) {
+            return Err(ArtHubError::InvalidInput("名称关键字不能包含控制字符".to_string()));
+        }
+    }
+
+    if let Some(ref mut exts) = conds.extensions {
+        for ext in exts.iter_mut() {
+            let normalized = ext.trim().trim_start_matches('.').to_lowercase();
+            if normalized.is_empty() || normalized.len() > 16 || !normalized.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(ArtHubError::InvalidInput(format!("无效的扩展名: {:?}", ext)));
+            }
+            *ext = normalized;
+        }
+        exts.sort();
+        exts.dedup();
+    }
+
+    for size in [conds.min_size, conds.max_size].into_iter().flatten() {
+        if size < 0 {
+            return Err(ArtHubError::InvalidInput(format!("文件大小不能为负数: {}", size)));
+        }
+    }
+    if let (Some(min), Some(max)) = (conds.min_size, conds.max_size) {
+        if min > max {
+            return Err(ArtHubError::InvalidInput(format!("最小文件大小 ({}) 大于最大文件大小 ({})", min, max)));
+        }
+    }
+
+    if let Some(rating) = conds.min_rating {
+        if !(0..=5).contains(&rating) {
+            return Err(ArtHubError::InvalidInput(format!("评分必须在 0-5 之间: {}", rating)));
+        }
+    }
+
+    if let Some(ref tag_ids) = conds.tag_ids {
+        if let Some(id) = tag_ids.iter().find(|id| **id <= 0) {
+            return Err(ArtHubError::InvalidInput(format!("无效的标签 ID: {}", id)));
+        }
+    }
+
+    Ok(conds)
+}
+
 // ---- Tag CRUD ----
 
-pub fn create_tag(conn: &Connection, name: &str, color: &str) -> Result<TagInfo, String> {
-    conn.execute(
-        "INSERT OR IGNORE INTO tags (name, color) VALUES (?1, ?2)",
-        params![name, color],
-    ).map_err(|e| format!("创建标签失败: {}", e))?;
+/// 新建标签未指定颜色时依次轮换的默认颜色
+const TAG_COLOR_PALETTE: &[&str] = &[
+    "#ef4444", "#f97316", "#eab308", "#22c55e", "#14b8a6",
+    "#3b82f6", "#8b5cf6", "#ec4899", "#6b7280",
+];
+
+/// create_tag 的结果：created = false 表示同名（忽略大小写）标签已存在
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateTagResult {
+    pub tag: TagInfo,
+    pub created: bool,
+}
 
+/// 按名称查找 space_type 空间可见的标签：优先该空间自己的标签，其次通用标签
+fn query_tag_by_name(conn: &Connection, name: &str, space_type: Option<&str>) -> rusqlite::Result<TagInfo> {
     conn.query_row(
-        "SELECT id, name, color, (SELECT COUNT(*) FROM asset_tags WHERE tag_id = tags.id) FROM tags WHERE name = ?1 COLLATE NOCASE",
-        params![name],
-        |row| Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)? }),
-    ).map_err(|e| format!("查询标签失败: {}", e))
+        "SELECT id, name, color, (SELECT COUNT(*) FROM asset_tags WHERE tag_id = tags.id), space_type FROM tags
+         WHERE name = ?1 COLLATE NOCASE AND (space_type IS ?2 OR space_type IS NULL)
+         ORDER BY space_type IS NULL LIMIT 1",
+        params![name, space_type],
+        |row| Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)?, space_type: row.get(4)? }),
+    )
 }
 
-pub fn update_tag(conn: &Connection, tag_id: i64, name: &str, color: &str) -> Result<(), String> {
+/// 创建标签
+/// - color 为 None 时按现有标签数从调色板中轮换取色
+/// - 该空间可见的同名标签（本空间或通用）已存在时不新建；若显式指定了不同的颜色，则更新已有标签的颜色
+/// - space_type 为 None 时标签在所有空间可见
+pub fn create_tag(
+    conn: &Connection,
+    name: &str,
+    color: Option<&str>,
+    space_type: Option<&str>,
+) -> Result<CreateTagResult, ArtHubError> {
+    match query_tag_by_name(conn, name, space_type) {
+        Ok(mut tag) => {
+            if let Some(color) = color.filter(|c| !c.eq_ignore_ascii_case(&tag.color)) {
+                conn.execute("UPDATE tags SET color = ?1 WHERE id = ?2", params![color, tag.id])
+                    .map_err(ArtHubError::db("更新标签颜色失败"))?;
+                tag.color = color.to_string();
+            }
+            return Ok(CreateTagResult { tag, created: false });
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+        Err(e) => return Err(ArtHubError::db("查询标签失败")(e)),
+    }
+
+    let color = match color {
+        Some(color) => color.to_string(),
+        None => {
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))
+                .map_err(ArtHubError::db("查询标签失败"))?;
+            TAG_COLOR_PALETTE[count as usize % TAG_COLOR_PALETTE.len()].to_string()
+        }
+    };
+    conn.execute(
+        "INSERT INTO tags (name, color, space_type) VALUES (?1, ?2, ?3)",
+        params![name, color, space_type],
+    )
+        .map_err(ArtHubError::db("创建标签失败"))?;
+
+    let tag = query_tag_by_name(conn, name, space_type).map_err(ArtHubError::db("查询标签失败"))?;
+    Ok(CreateTagResult { tag, created: true })
+}
+
+pub fn update_tag(conn: &Connection, tag_id: i64, name: &str, color: &str) -> Result<(), ArtHubError> {
     conn.execute(
         "UPDATE tags SET name = ?1, color = ?2 WHERE id = ?3",
         params![name, color, tag_id],
-    ).map_err(|e| format!("更新标签失败: {}", e))?;
+    ).map_err(ArtHubError::db("更新标签失败"))?;
     Ok(())
 }
 
-pub fn delete_tag(conn: &Connection, tag_id: i64) -> Result<(), String> {
+/// 查找与 exclude_id 同一空间、与 name 同名（忽略大小写）的其他标签
+pub fn find_other_tag_by_name(conn: &Connection, name: &str, exclude_id: i64) -> Result<Option<i64>, ArtHubError> {
+    conn.query_row(
+        "SELECT id FROM tags WHERE name = ?1 COLLATE NOCASE AND id != ?2
+         AND space_type IS (SELECT space_type FROM tags WHERE id = ?2)",
+        params![name, exclude_id],
+        |row| row.get(0),
+    ).optional().map_err(ArtHubError::db("查询标签失败"))
+}
+
+/// 把 source 标签合并到 target：资产关联转移到 target（已有的忽略），然后删除 source。
+/// 只能合并同一空间的标签
+pub fn merge_tags(conn: &Connection, source_id: i64, target_id: i64) -> Result<(), ArtHubError> {
+    let same_space: bool = conn.query_row(
+        "SELECT s.space_type IS t.space_type FROM tags s, tags t WHERE s.id = ?1 AND t.id = ?2",
+        params![source_id, target_id],
+        |row| row.get(0),
+    ).map_err(ArtHubError::db("查询标签失败"))?;
+    if !same_space {
+        return Err(ArtHubError::InvalidInput("只能合并同一空间的标签".to_string()));
+    }
+    let tx = conn.unchecked_transaction().map_err(ArtHubError::db("开启事务失败"))?;
+    tx.execute(
+        "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, tagged_by, tagged_at)
+         SELECT asset_id, ?2, tagged_by, tagged_at FROM asset_tags WHERE tag_id = ?1",
+        params![source_id, target_id],
+    ).map_err(ArtHubError::db("合并标签失败"))?;
+    tx.execute("DELETE FROM asset_tags WHERE tag_id = ?1", params![source_id])
+        .map_err(ArtHubError::db("删除标签关联失败"))?;
+    tx.execute("DELETE FROM tags WHERE id = ?1", params![source_id])
+        .map_err(ArtHubError::db("删除标签失败"))?;
+    tx.commit().map_err(ArtHubError::db("提交事务失败"))
+}
+
+pub fn delete_tag(conn: &Connection, tag_id: i64) -> Result<(), ArtHubError> {
     conn.execute("DELETE FROM asset_tags WHERE tag_id = ?1", params![tag_id])
-        .map_err(|e| format!("删除标签关联失败: {}", e))?;
+        .map_err(ArtHubError::db("删除标签关联失败"))?;
     conn.execute("DELETE FROM tags WHERE id = ?1", params![tag_id])
-        .map_err(|e| format!("删除标签失败: {}", e))?;
+        .map_err(ArtHubError::db("删除标签失败"))?;
     Ok(())
 }
 
-pub fn get_all_tags(conn: &Connection) -> Result<Vec<TagInfo>, String> {
-    let mut stmt = conn.prepare(
-        "SELECT t.id, t.name, t.color, (SELECT COUNT(*) FROM asset_tags WHERE tag_id = t.id) as cnt
-         FROM tags t ORDER BY cnt DESC, t.name"
-    ).map_err(|e| e.to_string())?;
-
-    let tags = stmt.query_map([], |row| {
-        Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)? })
-    }).map_err(|e| e.to_string())?
+/// 获取所有标签及其资产数；指定 folder_id 时只统计该文件夹内的资产。
+/// 指定 space_type 时只返回该空间的标签和通用标签（space_type 为 NULL），并只统计该空间文件夹内的资产
+pub fn get_all_tags(conn: &Connection, folder_id: Option<i64>, space_type: Option<&str>) -> Result<Vec<TagInfo>, ArtHubError> {
+    // 不限定范围时不过滤资产：COUNT(at.tag_id) 只需读 idx_asset_tags_tag，不必回表
+    let scope = if folder_id.is_none() && space_type.is_none() {
+        ""
+    } else {
+        "AND at.asset_id IN (
+             SELECT id FROM assets WHERE (?1 IS NULL OR folder_id = ?1)
+                 AND (?2 IS NULL OR folder_id IN (SELECT id FROM folders WHERE space_type = ?2)))"
+    };
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT t.id, t.name, t.color, COUNT(at.tag_id) as cnt, t.space_type
+         FROM tags t
+         LEFT JOIN asset_tags at ON at.tag_id = t.id
+             {}
+         WHERE ?2 IS NULL OR t.space_type IS NULL OR t.space_type = ?2
+         GROUP BY t.id
+         ORDER BY cnt DESC, t.name",
+        scope
+    ))?;
+
+    let tags = stmt.query_map(params![folder_id, space_type], |row| {
+        Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)?, space_type: row.get(4)? })
+    })?
       .filter_map(|r| r.ok())
       .collect();
     Ok(tags)
 }
 
-pub fn add_tag_to_asset(conn: &Connection, asset_id: i64, tag_id: i64, user: &str) -> Result<(), String> {
+/// 设置标签所属空间（None 表示所有空间通用）
+pub fn set_tag_space(conn: &Connection, tag_id: i64, space_type: Option<&str>) -> Result<(), ArtHubError> {
+    conn.execute("UPDATE tags SET space_type = ?1 WHERE id = ?2", params![space_type, tag_id])
+        .map_err(ArtHubError::db("更新标签空间失败"))?;
+    Ok(())
+}
+
+pub fn add_tag_to_asset(conn: &Connection, asset_id: i64, tag_id: i64, user: &str) -> Result<(), ArtHubError> {
     conn.execute(
         "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, tagged_by) VALUES (?1, ?2, ?3)",
         params![asset_id, tag_id, user],
-    ).map_err(|e| format!("添加标签失败: {}", e))?;
+    ).map_err(ArtHubError::db("添加标签失败"))?;
     Ok(())
 }
 
-pub fn remove_tag_from_asset(conn: &Connection, asset_id: i64, tag_id: i64) -> Result<(), String> {
+pub fn remove_tag_from_asset(conn: &Connection, asset_id: i64, tag_id: i64) -> Result<(), ArtHubError> {
     conn.execute(
         "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag_id = ?2",
         params![asset_id, tag_id],
-    ).map_err(|e| format!("移除标签失败: {}", e))?;
+    ).map_err(ArtHubError::db("移除标签失败"))?;
     Ok(())
 }
 
-pub fn get_asset_tags(conn: &Connection, asset_id: i64) -> Result<Vec<TagInfo>, String> {
-    let mut stmt = conn.prepare(
-        "SELECT t.id, t.name, t.color, 0 FROM tags t
+pub fn get_asset_tags(conn: &Connection, asset_id: i64) -> Result<Vec<TagInfo>, ArtHubError> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT t.id, t.name, t.color, 0, t.space_type FROM tags t
          JOIN asset_tags at ON t.id = at.tag_id WHERE at.asset_id = ?1 ORDER BY t.name"
-    ).map_err(|e| e.to_string())?;
+    )?;
 
     let tags = stmt.query_map(params![asset_id], |row| {
-        Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)? })
-    }).map_err(|e| e.to_string())?
+        Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)?, space_type: row.get(4)? })
+    })?
       .filter_map(|r| r.ok())
       .collect();
     Ok(tags)
 }
 
-pub fn batch_add_tag(conn: &Connection, asset_ids: &[i64], tag_id: i64, user: &str) -> Result<u32, String> {
-    let mut count = 0u32;
-    for aid in asset_ids {
-        if conn.execute(
-            "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, tagged_by) VALUES (?1, ?2, ?3)",
-            params![aid, tag_id, user],
-        ).is_ok() {
-            count += 1;
-        }
-    }
-    Ok(count)
-}
-
 // ---- Rating CRUD ----
 
-pub fn set_rating(conn: &Connection, asset_id: i64, rating: i32, user: &str) -> Result<(), String> {
+pub fn set_rating(conn: &Connection, asset_id: i64, rating: i32, user: &str) -> Result<(), ArtHubError> {
     if rating == 0 {
         conn.execute("DELETE FROM asset_ratings WHERE asset_id = ?1", params![asset_id])
-            .map_err(|e| e.to_string())?;
+            .map_err(ArtHubError::db("删除评分失败"))?;
     } else {
         conn.execute(
             "INSERT INTO asset_ratings (asset_id, rating, rated_by) VALUES (?1, ?2, ?3)
              ON CONFLICT(asset_id) DO UPDATE SET rating = excluded.rating, rated_by = excluded.rated_by, rated_at = strftime('%s','now')",
             params![asset_id, rating, user],
-        ).map_err(|e| format!("设置评分失败: {}", e))?;
+        ).map_err(ArtHubError::db("设置评分失败"))?;
     }
     Ok(())
 }
 
 pub fn get_rating(conn: &Connection, asset_id: i64) -> i32 {
-    conn.query_row(
-        "SELECT rating FROM asset_ratings WHERE asset_id = ?1",
-        params![asset_id],
-        |row| row.get(0),
-    ).unwrap_or(0)
+    conn.prepare_cached("SELECT rating FROM asset_ratings WHERE asset_id = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![asset_id], |row| row.get(0)))
+        .unwrap_or(0)
+}
+
+// ---- Sidecar ----
+
+/// 把旁注文件的内容应用到资产：按名称查找或新建标签（归属 space_type 空间）并添加，
+/// 有评分/描述时覆盖已有的评分/备注。应在调用方的事务内执行
+pub fn apply_sidecar(
+    conn: &Connection,
+    asset_path: &str,
+    meta: &sidecar::SidecarMeta,
+    space_type: &str,
+) -> Result<(), ArtHubError> {
+    let asset_id: i64 = conn.prepare_cached("SELECT id FROM assets WHERE file_path = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![asset_path], |row| row.get(0)))
+        .map_err(|e| ArtHubError::sqlite(format!("查询资产失败 {}: {}", asset_path, e), e))?;
+
+    for name in &meta.tags {
+        let tag = create_tag(conn, name, None, Some(space_type))?.tag;
+        add_tag_to_asset(conn, asset_id, tag.id, "")?;
+    }
+    if let Some(rating) = meta.rating {
+        set_rating(conn, asset_id, rating, "")?;
+    }
+    if let Some(ref caption) = meta.caption {
+        set_note(conn, asset_id, caption, "")?;
+    }
+    Ok(())
 }
 
 // ---- Note CRUD ----
 
-pub fn set_note(conn: &Connection, asset_id: i64, note: &str, user: &str) -> Result<(), String> {
+pub fn set_note(conn: &Connection, asset_id: i64, note: &str, user: &str) -> Result<(), ArtHubError> {
     if note.is_empty() {
         conn.execute("DELETE FROM asset_notes WHERE asset_id = ?1", params![asset_id])
-            .map_err(|e| e.to_string())?;
+            .map_err(ArtHubError::db("删除备注失败"))?;
     } else {
         conn.execute(
             "INSERT INTO asset_notes (asset_id, note, updated_by) VALUES (?1, ?2, ?3)
              ON CONFLICT(asset_id) DO UPDATE SET note = excluded.note, updated_by = excluded.updated_by, updated_at = strftime('%s','now')",
             params![asset_id, note, user],
-        ).map_err(|e| format!("设置备注失败: {}", e))?;
+        ).map_err(ArtHubError::db("设置备注失败"))?;
     }
     Ok(())
 }
 
 pub fn get_note(conn: &Connection, asset_id: i64) -> String {
-    conn.query_row(
-        "SELECT note FROM asset_notes WHERE asset_id = ?1",
-        params![asset_id],
-        |row| row.get(0),
-    ).unwrap_or_default()
+    conn.prepare_cached("SELECT note FROM asset_notes WHERE asset_id = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![asset_id], |row| row.get(0)))
+        .unwrap_or_default()
 }
 
 // ---- Asset Detail (tags + rating + note in one call) ----
 
-pub fn get_asset_detail(conn: &Connection, asset_id: i64) -> Result<AssetDetail, String> {
-    let asset = conn.query_row(
-        "SELECT id, folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at
-         FROM assets WHERE id = ?1",
-        params![asset_id],
-        |row| Ok(AssetInfo {
-            id: row.get(0)?, folder_id: row.get(1)?, file_path: row.get(2)?,
-            file_name: row.get(3)?, file_ext: row.get(4)?, file_size: row.get(5)?,
-            width: row.get::<_, u32>(6).unwrap_or(0), height: row.get::<_, u32>(7).unwrap_or(0),
-            thumb_path: row.get(8)?, modified_at: row.get(9)?,
-        }),
-    ).map_err(|e| format!("查询资产失败: {}", e))?;
+pub fn get_asset_detail(conn: &Connection, asset_id: i64) -> Result<AssetDetail, ArtHubError> {
+    let asset = conn.prepare_cached(
+        "SELECT id, folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at, is_missing, thumb_status
+         FROM assets WHERE id = ?1"
+    ).and_then(|mut stmt| stmt.query_row(params![asset_id], |row| Ok(AssetInfo {
+        id: row.get(0)?, folder_id: row.get(1)?, file_path: row.get(2)?,
+        file_name: row.get(3)?, file_ext: row.get(4)?, file_size: row.get(5)?,
+        width: row.get::<_, u32>(6).unwrap_or(0), height: row.get::<_, u32>(7).unwrap_or(0),
+        thumb_path: row.get(8)?, modified_at: row.get(9)?, is_missing: row.get(10)?,
+        thumb_status: row.get(11)?,
+    }))).map_err(ArtHubError::db("查询资产失败"))?;
 
     let tags = get_asset_tags(conn, asset_id)?;
     let rating = get_rating(conn, asset_id);
@@ -648,35 +1905,257 @@ pub fn get_asset_detail(conn: &Connection, asset_id: i64) -> Result<AssetDetail,
     Ok(AssetDetail { asset, tags, rating, note })
 }
 
+/// 批量获取资产详情：资产、标签、评分、备注各用一次集合查询，避免逐个调用。
+/// 按输入 id 的顺序返回，不存在的 id 会被跳过。
+pub fn get_asset_details_bulk(conn: &Connection, asset_ids: &[i64]) -> Result<Vec<AssetDetail>, ArtHubError> {
+    use std::collections::HashMap;
+
+    let mut assets: HashMap<i64, AssetInfo> = HashMap::new();
+    let mut tags: HashMap<i64, Vec<TagInfo>> = HashMap::new();
+    let mut ratings: HashMap<i64, i32> = HashMap::new();
+    let mut notes: HashMap<i64, String> = HashMap::new();
+
+    // 分批查询，避免超出 SQLite 绑定参数上限
+    for chunk in asset_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, folder_id, file_path, file_name, file_ext, file_size, width, height, thumb_path, modified_at, is_missing, thumb_status
+             FROM assets WHERE id IN ({})", placeholders
+        )).map_err(ArtHubError::db("准备查询失败"))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| Ok(AssetInfo {
+            id: row.get(0)?, folder_id: row.get(1)?, file_path: row.get(2)?,
+            file_name: row.get(3)?, file_ext: row.get(4)?, file_size: row.get(5)?,
+            width: row.get::<_, u32>(6).unwrap_or(0), height: row.get::<_, u32>(7).unwrap_or(0),
+            thumb_path: row.get(8)?, modified_at: row.get(9)?, is_missing: row.get(10)?,
+            thumb_status: row.get(11)?,
+        })).map_err(ArtHubError::db("查询资产失败"))?;
+        for asset in rows.filter_map(|r| r.ok()) {
+            assets.insert(asset.id, asset);
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT at.asset_id, t.id, t.name, t.color, t.space_type FROM tags t
+             JOIN asset_tags at ON t.id = at.tag_id
+             WHERE at.asset_id IN ({}) ORDER BY t.name", placeholders
+        )).map_err(ArtHubError::db("准备查询失败"))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| {
+            Ok((row.get::<_, i64>(0)?, TagInfo { id: row.get(1)?, name: row.get(2)?, color: row.get(3)?, asset_count: 0, space_type: row.get(4)? }))
+        }).map_err(ArtHubError::db("查询标签失败"))?;
+        for (aid, tag) in rows.filter_map(|r| r.ok()) {
+            tags.entry(aid).or_default().push(tag);
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT asset_id, rating FROM asset_ratings WHERE asset_id IN ({})", placeholders
+        )).map_err(ArtHubError::db("准备查询失败"))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?)))
+            .map_err(ArtHubError::db("查询评分失败"))?;
+        ratings.extend(rows.filter_map(|r| r.ok()));
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT asset_id, note FROM asset_notes WHERE asset_id IN ({})", placeholders
+        )).map_err(ArtHubError::db("准备查询失败"))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .map_err(ArtHubError::db("查询备注失败"))?;
+        notes.extend(rows.filter_map(|r| r.ok()));
+    }
+
+    Ok(asset_ids.iter().filter_map(|id| {
+        let asset = assets.get(id)?.clone();
+        Some(AssetDetail {
+            asset,
+            tags: tags.get(id).cloned().unwrap_or_default(),
+            rating: ratings.get(id).copied().unwrap_or(0),
+            note: notes.get(id).cloned().unwrap_or_default(),
+        })
+    }).collect())
+}
+
 // ---- Smart Folder CRUD ----
 
-pub fn create_smart_folder(conn: &Connection, name: &str, conditions: &str, space_type: &str) -> Result<SmartFolder, String> {
+pub fn create_smart_folder(conn: &Connection, name: &str, conditions: &str, space_type: &str) -> Result<SmartFolder, ArtHubError> {
     conn.execute(
         "INSERT INTO smart_folders (name, conditions, space_type) VALUES (?1, ?2, ?3)",
         params![name, conditions, space_type],
-    ).map_err(|e| format!("创建智能文件夹失败: {}", e))?;
+    ).map_err(ArtHubError::db("创建智能文件夹失败"))?;
 
     let id = conn.last_insert_rowid();
     Ok(SmartFolder { id, name: name.to_string(), icon: "folder".to_string(), conditions: conditions.to_string(), space_type: space_type.to_string() })
 }
 
-pub fn update_smart_folder(conn: &Connection, id: i64, name: &str, conditions: &str) -> Result<(), String> {
+pub fn update_smart_folder(conn: &Connection, id: i64, name: &str, conditions: &str) -> Result<(), ArtHubError> {
     conn.execute(
         "UPDATE smart_folders SET name = ?1, conditions = ?2 WHERE id = ?3",
         params![name, conditions, id],
-    ).map_err(|e| format!("更新智能文件夹失败: {}", e))?;
+    ).map_err(ArtHubError::db("更新智能文件夹失败"))?;
     Ok(())
 }
 
-pub fn delete_smart_folder(conn: &Connection, id: i64) -> Result<(), String> {
+pub fn delete_smart_folder(conn: &Connection, id: i64) -> Result<(), ArtHubError> {
     conn.execute("DELETE FROM smart_folders WHERE id = ?1", params![id])
-        .map_err(|e| format!("删除智能文件夹失败: {}", e))?;
+        .map_err(ArtHubError::db("删除智能文件夹失败"))?;
+    Ok(())
+}
+
+// ---- AI Tools ----
+
+/// AI 工具配置，id 即 open_ai_tab 的 config_id
+/// inject_mode: "auto"（写入存储并自动填充输入框）| "storage"（只写入 localStorage）| "none"（不注入）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiTool {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub input_selector: Option<String>,
+    #[serde(default = "default_inject_mode")]
+    pub inject_mode: String,
+    #[serde(default)]
+    pub sort_order: i64,
+}
+
+fn default_inject_mode() -> String {
+    "auto".to_string()
+}
+
+pub const AI_INJECT_MODES: &[&str] = &["auto", "storage", "none"];
+
+fn ai_tool_from_row(row: &rusqlite::Row) -> rusqlite::Result<AiTool> {
+    Ok(AiTool {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        url: row.get(2)?,
+        input_selector: row.get(3)?,
+        inject_mode: row.get(4)?,
+        sort_order: row.get(5)?,
+    })
+}
+
+pub fn get_ai_tools(conn: &Connection) -> Result<Vec<AiTool>, ArtHubError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, url, input_selector, inject_mode, sort_order FROM ai_tools ORDER BY sort_order, name"
+    ).map_err(ArtHubError::db("准备查询失败"))?;
+    let tools = stmt.query_map([], ai_tool_from_row)
+        .map_err(ArtHubError::db("执行查询失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(tools)
+}
+
+pub fn get_ai_tool(conn: &Connection, id: &str) -> Result<Option<AiTool>, ArtHubError> {
+    conn.query_row(
+        "SELECT id, name, url, input_selector, inject_mode, sort_order FROM ai_tools WHERE id = ?1",
+        params![id],
+        ai_tool_from_row,
+    ).optional().map_err(ArtHubError::db("查询 AI 工具失败"))
+}
+
+/// 新建或更新 AI 工具配置
+pub fn save_ai_tool(conn: &Connection, tool: &AiTool) -> Result<(), ArtHubError> {
+    conn.execute(
+        "INSERT INTO ai_tools (id, name, url, input_selector, inject_mode, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name,
+            url = excluded.url,
+            input_selector = excluded.input_selector,
+            inject_mode = excluded.inject_mode,
+            sort_order = excluded.sort_order",
+        params![tool.id, tool.name, tool.url, tool.input_selector, tool.inject_mode, tool.sort_order],
+    ).map_err(ArtHubError::db("保存 AI 工具失败"))?;
+    Ok(())
+}
+
+pub fn delete_ai_tool(conn: &Connection, id: &str) -> Result<(), ArtHubError> {
+    conn.execute("DELETE FROM ai_tools WHERE id = ?1", params![id])
+        .map_err(ArtHubError::db("删除 AI 工具失败"))?;
+    Ok(())
+}
+
+// ---- Saved Searches / Search History ----
+
+/// 搜索历史最多保留的条数
+const SEARCH_HISTORY_LIMIT: i64 = 50;
+
+pub fn create_saved_search(conn: &Connection, name: &str, query: &AssetQueryParams) -> Result<SavedSearch, ArtHubError> {
+    let json = serde_json::to_string(query).map_err(|e| ArtHubError::Internal(format!("序列化搜索条件失败: {}", e)))?;
+    conn.execute(
+        "INSERT INTO saved_searches (name, params) VALUES (?1, ?2)",
+        params![name, json],
+    ).map_err(ArtHubError::db("保存搜索失败"))?;
+    get_saved_search(conn, conn.last_insert_rowid())
+}
+
+pub fn get_saved_search(conn: &Connection, id: i64) -> Result<SavedSearch, ArtHubError> {
+    let (name, json, created_at): (String, String, i64) = conn.query_row(
+        "SELECT name, params, created_at FROM saved_searches WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).map_err(ArtHubError::db("查询保存的搜索失败"))?;
+    let query = serde_json::from_str(&json).map_err(|e| ArtHubError::Internal(format!("解析搜索条件失败: {}", e)))?;
+    Ok(SavedSearch { id, name, params: query, created_at })
+}
+
+pub fn get_saved_searches(conn: &Connection) -> Result<Vec<SavedSearch>, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT id, name, params, created_at FROM saved_searches ORDER BY name")
+        .map_err(ArtHubError::db("查询保存的搜索失败"))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, i64>(3)?))
+    })?;
+
+    // 无法解析的旧条目直接跳过
+    Ok(rows.filter_map(|r| r.ok())
+        .filter_map(|(id, name, json, created_at)| {
+            serde_json::from_str(&json).ok().map(|query| SavedSearch { id, name, params: query, created_at })
+        })
+        .collect())
+}
+
+pub fn delete_saved_search(conn: &Connection, id: i64) -> Result<(), ArtHubError> {
+    conn.execute("DELETE FROM saved_searches WHERE id = ?1", params![id])
+        .map_err(ArtHubError::db("删除保存的搜索失败"))?;
+    Ok(())
+}
+
+/// 记录一条搜索（重复的搜索移到最前），超出上限时删除最旧的
+pub fn add_search_history(conn: &Connection, query: &str) -> Result<(), ArtHubError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+    // 先删后插，让重复的搜索获得新的 rowid 排到最前
+    conn.execute("DELETE FROM search_history WHERE query = ?1", params![query])
+        .map_err(ArtHubError::db("记录搜索历史失败"))?;
+    conn.execute("INSERT INTO search_history (query) VALUES (?1)", params![query])
+        .map_err(ArtHubError::db("记录搜索历史失败"))?;
+    conn.execute(
+        "DELETE FROM search_history WHERE rowid NOT IN
+            (SELECT rowid FROM search_history ORDER BY rowid DESC LIMIT ?1)",
+        params![SEARCH_HISTORY_LIMIT],
+    ).map_err(ArtHubError::db("清理搜索历史失败"))?;
+    Ok(())
+}
+
+pub fn get_search_history(conn: &Connection) -> Result<Vec<String>, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT query FROM search_history ORDER BY rowid DESC")
+        .map_err(ArtHubError::db("查询搜索历史失败"))?;
+    let history = stmt.query_map([], |row| row.get(0))
+        .map_err(ArtHubError::db("查询搜索历史失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(history)
+}
+
+pub fn clear_search_history(conn: &Connection) -> Result<(), ArtHubError> {
+    conn.execute("DELETE FROM search_history", [])
+        .map_err(ArtHubError::db("清空搜索历史失败"))?;
     Ok(())
 }
 
 // ---- Favorites ----
 
-pub fn toggle_favorite(conn: &Connection, asset_id: i64, user: &str) -> Result<bool, String> {
+pub fn toggle_favorite(conn: &Connection, asset_id: i64, user: &str) -> Result<bool, ArtHubError> {
     let exists: bool = conn.query_row(
         "SELECT COUNT(*) > 0 FROM asset_favorites WHERE asset_id = ?1",
         params![asset_id],
@@ -685,23 +2164,21 @@ pub fn toggle_favorite(conn: &Connection, asset_id: i64, user: &str) -> Result<b
 
     if exists {
         conn.execute("DELETE FROM asset_favorites WHERE asset_id = ?1", params![asset_id])
-            .map_err(|e| e.to_string())?;
+            .map_err(ArtHubError::db("取消收藏失败"))?;
         Ok(false) // 取消收藏
     } else {
         conn.execute(
             "INSERT OR IGNORE INTO asset_favorites (asset_id, favorited_by) VALUES (?1, ?2)",
             params![asset_id, user],
-        ).map_err(|e| e.to_string())?;
+        )?;
         Ok(true) // 已收藏
     }
 }
 
 pub fn is_favorite(conn: &Connection, asset_id: i64) -> bool {
-    conn.query_row(
-        "SELECT COUNT(*) > 0 FROM asset_favorites WHERE asset_id = ?1",
-        params![asset_id],
-        |row| row.get(0),
-    ).unwrap_or(false)
+    conn.prepare_cached("SELECT COUNT(*) > 0 FROM asset_favorites WHERE asset_id = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![asset_id], |row| row.get(0)))
+        .unwrap_or(false)
 }
 
 pub fn get_favorite_ids(conn: &Connection) -> Vec<i64> {
@@ -712,26 +2189,9 @@ pub fn get_favorite_ids(conn: &Connection) -> Vec<i64> {
         .collect()
 }
 
-pub fn batch_toggle_favorite(conn: &Connection, asset_ids: &[i64], favorite: bool, user: &str) -> Result<u32, String> {
-    let mut count = 0u32;
-    for aid in asset_ids {
-        if favorite {
-            if conn.execute(
-                "INSERT OR IGNORE INTO asset_favorites (asset_id, favorited_by) VALUES (?1, ?2)",
-                params![aid, user],
-            ).is_ok() { count += 1; }
-        } else {
-            if conn.execute("DELETE FROM asset_favorites WHERE asset_id = ?1", params![aid]).is_ok() {
-                count += 1;
-            }
-        }
-    }
-    Ok(count)
-}
-
 // ---- Batch Operations ----
 
-pub fn batch_delete_assets(conn: &Connection, asset_ids: &[i64]) -> Result<u32, String> {
+pub fn batch_delete_assets(conn: &Connection, asset_ids: &[i64]) -> Result<u32, ArtHubError> {
     let mut count = 0u32;
     for aid in asset_ids {
         // 级联删除会自动清理 asset_tags, asset_ratings, asset_notes, asset_favorites
@@ -742,42 +2202,572 @@ pub fn batch_delete_assets(conn: &Connection, asset_ids: &[i64]) -> Result<u32,
     Ok(count)
 }
 
-pub fn batch_set_rating(conn: &Connection, asset_ids: &[i64], rating: i32, user: &str) -> Result<u32, String> {
-    let mut count = 0u32;
-    for aid in asset_ids {
-        if set_rating(conn, *aid, rating, user).is_ok() {
-            count += 1;
+/// 获取文件夹下所有资产的 (id, 文件路径)，用于校验文件是否存在
+pub fn get_folder_asset_paths(conn: &Connection, folder_id: i64) -> Result<Vec<(i64, String)>, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT id, file_path FROM assets WHERE folder_id = ?1 ORDER BY id")
+        .map_err(ArtHubError::db("准备查询失败"))?;
+    let rows = stmt.query_map(params![folder_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(ArtHubError::db("执行查询失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+/// 批量更新缺失标记
+pub fn set_assets_missing(conn: &Connection, flags: &[(i64, bool)]) -> Result<(), ArtHubError> {
+    let mut stmt = conn.prepare_cached("UPDATE assets SET is_missing = ?2 WHERE id = ?1")
+        .map_err(ArtHubError::db("准备更新失败"))?;
+    for (id, missing) in flags {
+        stmt.execute(params![id, missing])
+            .map_err(ArtHubError::db("更新缺失标记失败"))?;
+    }
+    Ok(())
+}
+
+/// 删除文件夹下所有标记为缺失的资产，返回被删除资产的缩略图路径（供清理缓存）
+pub fn purge_missing_assets(conn: &Connection, folder_id: i64) -> Result<Vec<String>, ArtHubError> {
+    let mut stmt = conn.prepare(
+        "DELETE FROM assets WHERE folder_id = ?1 AND is_missing = 1 RETURNING thumb_path"
+    ).map_err(ArtHubError::db("准备删除失败"))?;
+    let thumbs = stmt.query_map(params![folder_id], |row| row.get(0))
+        .map_err(ArtHubError::db("删除缺失资产失败"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(thumbs)
+}
+
+/// 一次整理操作要应用到选中资产的元数据改动；None / 空列表表示该项不改
+#[derive(Debug, Clone, Default)]
+pub struct MetadataChanges {
+    pub add_tag_ids: Vec<i64>,
+    pub remove_tag_ids: Vec<i64>,
+    pub rating: Option<i32>, // 0 为清除评分
+    pub favorite: Option<bool>,
+    pub note: Option<String>, // 空字符串为清除备注
+}
+
+/// 批量应用元数据的结果，各项为实际发生变化的数量（已是目标状态的不计）
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct BatchMetadataResult {
+    /// 操作日志 ID，传给 asset_undo_last 撤销；没有任何改动时为 None
+    pub op_id: Option<i64>,
+    pub assets: u32, // 存在的资产数（已删除的 ID 被跳过）
+    pub tags_added: u32,
+    pub tags_removed: u32,
+    pub ratings_set: u32,
+    pub favorites_changed: u32,
+    pub notes_set: u32,
+}
+
+/// 撤销栈保留的最近操作数，更早的日志在记录新操作时删除
+const MAX_UNDO_OPS: i64 = 20;
+
+/// 操作日志中记录的改动类型，value 为撤销时要恢复的值
+const CHANGE_TAG_ADDED: &str = "tag_added"; // value: 标签 ID
+const CHANGE_TAG_REMOVED: &str = "tag_removed"; // value: 标签 ID
+const CHANGE_RATING: &str = "rating"; // value: 原评分（0 为无评分）
+const CHANGE_FAVORITE: &str = "favorite"; // value: 原收藏状态 0/1
+const CHANGE_NOTE: &str = "note"; // value: 原备注
+
+/// 可撤销的批量元数据操作
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataOp {
+    pub id: i64,
+    pub kind: String, // "apply_metadata", "add_tag", "set_rating", "favorite"
+    pub asset_count: i64,
+    pub created_at: i64,
+}
+
+fn record_change(
+    conn: &Connection,
+    op_id: i64,
+    asset_id: i64,
+    field: &str,
+    value: impl rusqlite::ToSql,
+) -> Result<(), ArtHubError> {
+    conn.prepare_cached("INSERT INTO metadata_op_changes (op_id, asset_id, field, value) VALUES (?1, ?2, ?3, ?4)")
+        .and_then(|mut stmt| stmt.execute(params![op_id, asset_id, field, value]))
+        .map_err(ArtHubError::db("写入操作日志失败"))?;
+    Ok(())
+}
+
+/// 在一个事务内对多个资产应用标签/评分/收藏/备注改动，任一步失败时全部回滚。
+/// 改动前的状态记入操作日志（kind 为操作类型），可用 undo_metadata_op 撤销
+pub fn batch_apply_metadata(
+    conn: &mut Connection,
+    kind: &str,
+    asset_ids: &[i64],
+    changes: &MetadataChanges,
+    user: &str,
+) -> Result<BatchMetadataResult, ArtHubError> {
+    let tx = conn.transaction().map_err(ArtHubError::db("开启事务失败"))?;
+    tx.execute("INSERT INTO metadata_ops (kind) VALUES (?1)", params![kind])
+        .map_err(ArtHubError::db("创建操作日志失败"))?;
+    let op_id = tx.last_insert_rowid();
+
+    let mut result = BatchMetadataResult::default();
+    let mut changed_assets = 0i64;
+    for &aid in asset_ids {
+        let exists: bool = tx.prepare_cached("SELECT COUNT(*) > 0 FROM assets WHERE id = ?1")
+            .and_then(|mut stmt| stmt.query_row(params![aid], |row| row.get(0)))
+            .map_err(ArtHubError::db("查询资产失败"))?;
+        if !exists {
+            continue;
+        }
+        result.assets += 1;
+        let mut changed = false;
+
+        for &tag_id in &changes.add_tag_ids {
+            if tx.execute(
+                "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, tagged_by) VALUES (?1, ?2, ?3)",
+                params![aid, tag_id, user],
+            ).map_err(ArtHubError::db("添加标签失败"))? > 0 {
+                record_change(&tx, op_id, aid, CHANGE_TAG_ADDED, tag_id)?;
+                result.tags_added += 1;
+                changed = true;
+            }
+        }
+        for &tag_id in &changes.remove_tag_ids {
+            if tx.execute(
+                "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag_id = ?2",
+                params![aid, tag_id],
+            ).map_err(ArtHubError::db("移除标签失败"))? > 0 {
+                record_change(&tx, op_id, aid, CHANGE_TAG_REMOVED, tag_id)?;
+                result.tags_removed += 1;
+                changed = true;
+            }
+        }
+        if let Some(rating) = changes.rating {
+            let previous = get_rating(&tx, aid);
+            if previous != rating {
+                set_rating(&tx, aid, rating, user)?;
+                record_change(&tx, op_id, aid, CHANGE_RATING, previous)?;
+                result.ratings_set += 1;
+                changed = true;
+            }
+        }
+        if let Some(favorite) = changes.favorite {
+            let updated = if favorite {
+                tx.execute(
+                    "INSERT OR IGNORE INTO asset_favorites (asset_id, favorited_by) VALUES (?1, ?2)",
+                    params![aid, user],
+                )
+            } else {
+                tx.execute("DELETE FROM asset_favorites WHERE asset_id = ?1", params![aid])
+            }.map_err(ArtHubError::db("设置收藏失败"))?;
+            if updated > 0 {
+                record_change(&tx, op_id, aid, CHANGE_FAVORITE, !favorite)?;
+                result.favorites_changed += 1;
+                changed = true;
+            }
         }
+        if let Some(ref note) = changes.note {
+            let previous = get_note(&tx, aid);
+            if previous != *note {
+                set_note(&tx, aid, note, user)?;
+                record_change(&tx, op_id, aid, CHANGE_NOTE, previous)?;
+                result.notes_set += 1;
+                changed = true;
+            }
+        }
+        changed_assets += changed as i64;
     }
-    Ok(count)
+
+    if changed_assets == 0 {
+        // 没有实际改动，不占用撤销栈
+        tx.execute("DELETE FROM metadata_ops WHERE id = ?1", params![op_id])
+            .map_err(ArtHubError::db("删除操作日志失败"))?;
+    } else {
+        tx.execute("UPDATE metadata_ops SET asset_count = ?2 WHERE id = ?1", params![op_id, changed_assets])
+            .map_err(ArtHubError::db("更新操作日志失败"))?;
+        tx.execute(
+            "DELETE FROM metadata_ops WHERE id NOT IN (SELECT id FROM metadata_ops ORDER BY id DESC LIMIT ?1)",
+            params![MAX_UNDO_OPS],
+        ).map_err(ArtHubError::db("清理操作日志失败"))?;
+        result.op_id = Some(op_id);
+    }
+    tx.commit().map_err(ArtHubError::db("提交事务失败"))?;
+    Ok(result)
+}
+
+/// 撤销栈中的操作，最新的在前
+pub fn get_metadata_ops(conn: &Connection) -> Result<Vec<MetadataOp>, ArtHubError> {
+    let mut stmt = conn.prepare("SELECT id, kind, asset_count, created_at FROM metadata_ops ORDER BY id DESC")
+        .map_err(ArtHubError::db("准备查询失败"))?;
+    let ops = stmt.query_map([], |row| Ok(MetadataOp {
+        id: row.get(0)?, kind: row.get(1)?, asset_count: row.get(2)?, created_at: row.get(3)?,
+    })).map_err(ArtHubError::db("查询操作日志失败"))?
+      .filter_map(|r| r.ok())
+      .collect();
+    Ok(ops)
+}
+
+/// 撤销一次批量元数据操作：按记录的逆序恢复改动前的状态，然后删除该日志。
+/// 之后被删除的标签不再恢复；期间手动修改过的评分/备注会被改回记录的值
+pub fn undo_metadata_op(conn: &mut Connection, op_id: i64) -> Result<u32, ArtHubError> {
+    use rusqlite::types::Value;
+
+    let tx = conn.transaction().map_err(ArtHubError::db("开启事务失败"))?;
+    let changes: Vec<(i64, String, rusqlite::types::Value)> = {
+        let mut stmt = tx.prepare(
+            "SELECT asset_id, field, value FROM metadata_op_changes WHERE op_id = ?1 ORDER BY rowid DESC"
+        ).map_err(ArtHubError::db("准备查询失败"))?;
+        stmt.query_map(params![op_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(ArtHubError::db("读取操作日志失败"))?
+    };
+
+    let mut restored = 0u32;
+    for (aid, field, value) in changes {
+        match (field.as_str(), value) {
+            (CHANGE_TAG_ADDED, Value::Integer(tag_id)) => {
+                remove_tag_from_asset(&tx, aid, tag_id)?;
+            }
+            (CHANGE_TAG_REMOVED, Value::Integer(tag_id)) => {
+                tx.execute(
+                    "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id) SELECT ?1, id FROM tags WHERE id = ?2",
+                    params![aid, tag_id],
+                ).map_err(ArtHubError::db("恢复标签失败"))?;
+            }
+            (CHANGE_RATING, Value::Integer(rating)) => set_rating(&tx, aid, rating as i32, "")?,
+            (CHANGE_FAVORITE, Value::Integer(favorite)) => {
+                if favorite != 0 {
+                    tx.execute("INSERT OR IGNORE INTO asset_favorites (asset_id) VALUES (?1)", params![aid])
+                } else {
+                    tx.execute("DELETE FROM asset_favorites WHERE asset_id = ?1", params![aid])
+                }.map_err(ArtHubError::db("恢复收藏失败"))?;
+            }
+            (CHANGE_NOTE, Value::Text(note)) => set_note(&tx, aid, &note, "")?,
+            (field, value) => {
+                log::warn!("[AssetManager] 无法识别的操作日志记录: {} = {:?}", field, value);
+                continue;
+            }
+        }
+        restored += 1;
+    }
+
+    tx.execute("DELETE FROM metadata_ops WHERE id = ?1", params![op_id])
+        .map_err(ArtHubError::db("删除操作日志失败"))?;
+    tx.commit().map_err(ArtHubError::db("提交事务失败"))?;
+    Ok(restored)
 }
 
-pub fn get_smart_folders(conn: &Connection, space_type: Option<&str>) -> Result<Vec<SmartFolder>, String> {
+pub fn get_smart_folders(conn: &Connection, space_type: Option<&str>) -> Result<Vec<SmartFolder>, ArtHubError> {
     let base = "SELECT id, name, icon, conditions, space_type FROM smart_folders";
 
     if let Some(st) = space_type {
         let sql = format!("{} WHERE space_type = ?1 ORDER BY name", base);
-        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(&sql)?;
         let folders = stmt.query_map(params![st], |row| {
             Ok(SmartFolder {
                 id: row.get(0)?, name: row.get(1)?, icon: row.get(2)?,
                 conditions: row.get(3)?, space_type: row.get(4)?,
             })
-        }).map_err(|e| e.to_string())?
+        })?
           .filter_map(|r| r.ok())
           .collect();
         Ok(folders)
     } else {
         let sql = format!("{} ORDER BY name", base);
-        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(&sql)?;
         let folders = stmt.query_map([], |row| {
             Ok(SmartFolder {
                 id: row.get(0)?, name: row.get(1)?, icon: row.get(2)?,
                 conditions: row.get(3)?, space_type: row.get(4)?,
             })
-        }).map_err(|e| e.to_string())?
+        })?
           .filter_map(|r| r.ok())
           .collect();
         Ok(folders)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        configure_connection(&conn).unwrap();
+        init_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let conn = test_conn();
+        // 再次执行建表和迁移：已应用的步骤会被跳过，不应报错
+        init_tables(&conn).unwrap();
+        run_migrations(&conn).unwrap();
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        assert_eq!(version, 12);
+    }
+
+    #[test]
+    fn name_natural_sort_orders_numbers_by_value() {
+        let conn = test_conn();
+        let folder = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        for name in ["tex_10.png", "Tex_2.png", "tex_1.png"] {
+            conn.execute(
+                "INSERT INTO assets (folder_id, file_path, file_name, file_ext) VALUES (?1, ?2, ?3, 'png')",
+                params![folder.id, format!("/art/{}", name), name],
+            ).unwrap();
+        }
+        let query: AssetQueryParams = serde_json::from_value(serde_json::json!({
+            "folder_id": folder.id, "sort_by": "name_natural",
+        })).unwrap();
+        let names: Vec<String> = query_assets(&conn, &query).unwrap().assets.into_iter().map(|a| a.file_name).collect();
+        assert_eq!(names, ["tex_1.png", "Tex_2.png", "tex_10.png"]);
+    }
+
+    /// 预编译语句缓存的微基准：同一连接上反复执行 query_assets / get_asset_detail，
+    /// 对比缓存开启与关闭（容量为 0 时 prepare_cached 每次重新编译）的耗时。
+    /// 耗时受机器影响，默认不运行：cargo test --release -- --ignored statement_cache
+    #[test]
+    #[ignore]
+    fn bench_statement_cache() {
+        const ITERATIONS: u32 = 2000;
+        let conn = test_conn();
+        let folder = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        for i in 0..500 {
+            conn.execute(
+                "INSERT INTO assets (folder_id, file_path, file_name, file_ext, width, height) VALUES (?1, ?2, ?3, 'png', ?4, 256)",
+                params![folder.id, format!("/art/tex_{}.png", i), format!("tex_{}.png", i), 128 + i],
+            ).unwrap();
+        }
+        let query: AssetQueryParams = serde_json::from_value(serde_json::json!({
+            "folder_id": folder.id, "search": "tex", "min_width": 200, "sort_by": "name_natural", "page_size": 100,
+        })).unwrap();
+
+        let run = |conn: &Connection| {
+            let start = std::time::Instant::now();
+            for i in 0..ITERATIONS {
+                query_assets(conn, &query).unwrap();
+                get_asset_detail(conn, (i % 500 + 1) as i64).unwrap();
+            }
+            start.elapsed()
+        };
+        run(&conn); // 预热
+        let cached = run(&conn);
+        conn.set_prepared_statement_cache_capacity(0);
+        let uncached = run(&conn);
+        println!(
+            "statement cache: cached {:?} / uncached {:?} ({} 次, {:.2}x)",
+            cached, uncached, ITERATIONS, uncached.as_secs_f64() / cached.as_secs_f64()
+        );
+        assert!(cached < uncached);
+    }
+
+    /// 对比 upsert_assets（单个事务 + 复用预编译语句）与逐条 execute 自动提交的耗时。
+    /// 用临时文件库而不是内存库，逐条提交的落盘开销才能体现出来。
+    /// 默认不运行：cargo test --release -- --ignored upsert_assets
+    #[test]
+    #[ignore]
+    fn bench_upsert_assets() {
+        const ROWS: usize = 10_000;
+        let dir = std::env::temp_dir().join(format!("arthub_bench_upsert_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let open = |name: &str| {
+            let conn = Connection::open(dir.join(name)).unwrap();
+            conn.execute_batch("PRAGMA journal_mode=WAL;").unwrap();
+            configure_connection(&conn).unwrap();
+            init_tables(&conn).unwrap();
+            conn
+        };
+        let files: Vec<scanner::ScannedFile> = (0..ROWS)
+            .map(|i| scanner::ScannedFile {
+                path: format!("/art/tex_{}.png", i),
+                name: format!("tex_{}.png", i),
+                ext: "png".to_string(),
+                size: 1024,
+                modified: 1_700_000_000,
+                rel_dir: String::new(),
+            })
+            .collect();
+
+        let mut conn = open("batch.db");
+        let folder = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        let rows: Vec<AssetRow> = files.iter()
+            .map(|file| AssetRow { file, width: 256, height: 256, thumb_path: "", thumb_status: "pending" })
+            .collect();
+        let start = std::time::Instant::now();
+        let tx = conn.transaction().unwrap();
+        assert_eq!(upsert_assets(&tx, folder.id, &rows).unwrap() as usize, ROWS);
+        tx.commit().unwrap();
+        let batched = start.elapsed();
+
+        let conn = open("per_row.db");
+        let folder = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        // execute 不接受带结果的语句，去掉 RETURNING
+        let sql = UPSERT_ASSET_SQL.trim_end_matches("RETURNING id");
+        let start = std::time::Instant::now();
+        for f in &files {
+            conn.execute(
+                sql,
+                params![folder.id, f.path, f.name, f.ext, f.size as i64, 256, 256, "", f.modified as i64, f.rel_dir, "pending"],
+            ).unwrap();
+        }
+        let per_row = start.elapsed();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        println!(
+            "upsert_assets: batched {:?} / per-row {:?} ({} 行, {:.2}x)",
+            batched, per_row, ROWS, per_row.as_secs_f64() / batched.as_secs_f64()
+        );
+        assert!(batched < per_row);
+    }
+
+    /// 对比旧的逐标签相关子查询与 get_all_tags 的 LEFT JOIN + GROUP BY 统计资产数的耗时。
+    /// 默认不运行：cargo test --release -- --ignored all_tags
+    #[test]
+    #[ignore]
+    fn bench_get_all_tags() {
+        const TAGS: i64 = 500;
+        const ITERATIONS: u32 = 200;
+        let conn = test_conn();
+        let folder = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        for i in 0..TAGS {
+            conn.execute("INSERT INTO tags (name) VALUES (?1)", params![format!("tag_{}", i)]).unwrap();
+        }
+        for i in 0..5000i64 {
+            conn.execute(
+                "INSERT INTO assets (folder_id, file_path, file_name, file_ext) VALUES (?1, ?2, ?3, 'png')",
+                params![folder.id, format!("/art/tex_{}.png", i), format!("tex_{}.png", i)],
+            ).unwrap();
+            let asset_id = conn.last_insert_rowid();
+            for k in 0..3 {
+                add_tag_to_asset(&conn, asset_id, (i * 7 + k * 131) % TAGS + 1, "bench").unwrap();
+            }
+        }
+        // 旧实现：每个标签执行一次相关子查询
+        let subquery = |conn: &Connection| -> Vec<TagInfo> {
+            let mut stmt = conn.prepare_cached(
+                "SELECT t.id, t.name, t.color, (SELECT COUNT(*) FROM asset_tags WHERE tag_id = t.id) as cnt, t.space_type
+                 FROM tags t ORDER BY cnt DESC, t.name"
+            ).unwrap();
+            stmt.query_map([], |row| {
+                Ok(TagInfo { id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, asset_count: row.get(3)?, space_type: row.get(4)? })
+            }).unwrap().map(|r| r.unwrap()).collect()
+        };
+        let grouped = |conn: &Connection| get_all_tags(conn, None, None).unwrap();
+        let counts = |tags: Vec<TagInfo>| tags.into_iter().map(|t| (t.id, t.asset_count)).collect::<Vec<_>>();
+        assert_eq!(counts(subquery(&conn)), counts(grouped(&conn)));
+
+        let time = |f: &dyn Fn(&Connection) -> Vec<TagInfo>| {
+            let start = std::time::Instant::now();
+            for _ in 0..ITERATIONS {
+                f(&conn);
+            }
+            start.elapsed()
+        };
+        let old = time(&subquery);
+        let new = time(&grouped);
+        println!(
+            "get_all_tags: subquery {:?} / group by {:?} ({} 个标签, {} 次, {:.2}x)",
+            old, new, TAGS, ITERATIONS, old.as_secs_f64() / new.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn readonly_check_matches_path_spellings_and_nested_folders() {
+        let conn = test_conn();
+        let sep = std::path::MAIN_SEPARATOR;
+        let art = format!("{}Art", sep);
+        let locked = format!("{}{}Locked", art, sep);
+        let outer = insert_folder(&conn, &art, "Art", "personal").unwrap();
+        let inner = insert_folder(&conn, &locked, "Locked", "personal").unwrap();
+        set_folder_readonly(&conn, inner.id, true).unwrap();
+
+        let file = |dir: &str, name: &str| format!("{}{}{}", dir, sep, name);
+        assert!(is_path_readonly(&conn, &file(&locked, "x.psd")).unwrap());
+        assert!(is_path_readonly(&conn, &locked).unwrap());
+        assert!(is_path_readonly(&conn, &format!("{}{}", locked, sep)).unwrap());
+        assert!(!is_path_readonly(&conn, &file(&art, "x.psd")).unwrap());
+        assert!(!is_path_readonly(&conn, &file(&format!("{}ness", locked), "x.psd")).unwrap());
+
+        // 嵌套时取最深的文件夹
+        assert_eq!(find_folder_for_path(&conn, &file(&locked, "x.psd")).unwrap().map(|f| f.0), Some(inner.id));
+        assert_eq!(find_folder_for_path(&conn, &file(&art, "x.psd")).unwrap().map(|f| f.0), Some(outer.id));
+
+        // Windows 上分隔符和大小写不同也视为同一路径
+        if cfg!(target_os = "windows") {
+            assert!(is_path_readonly(&conn, "/art/locked/x.psd").unwrap());
+            assert!(is_path_readonly(&conn, r"\ART\LOCKED\sub\x.psd").unwrap());
+            assert_eq!(find_folder_for_path(&conn, "/ART/locked/x.psd").unwrap().map(|f| f.0), Some(inner.id));
+        }
+    }
+
+    #[test]
+    fn tag_names_are_unique_per_space() {
+        let conn = test_conn();
+        let personal = create_tag(&conn, "Hero", None, Some("personal")).unwrap();
+        let team = create_tag(&conn, "hero", None, Some("team")).unwrap();
+        assert!(personal.created && team.created);
+        assert_ne!(personal.tag.id, team.tag.id);
+
+        // 同一空间内忽略大小写视为同名
+        let again = create_tag(&conn, "HERO", None, Some("personal")).unwrap();
+        assert!(!again.created);
+        assert_eq!(again.tag.id, personal.tag.id);
+
+        // 通用标签之间也不能重名
+        create_tag(&conn, "Prop", None, None).unwrap();
+        let dup = conn.execute("INSERT INTO tags (name) VALUES ('prop')", []);
+        assert!(matches!(ArtHubError::from(dup.unwrap_err()), ArtHubError::AlreadyExists(_)));
+
+        // 重命名冲突和合并只看同一空间
+        assert_eq!(find_other_tag_by_name(&conn, "hero", team.tag.id).unwrap(), None);
+        assert!(matches!(merge_tags(&conn, team.tag.id, personal.tag.id), Err(ArtHubError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn tag_counts_scoped_to_folder_keep_unused_tags() {
+        let conn = test_conn();
+        let art = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        let refs = insert_folder(&conn, "/refs", "refs", "personal").unwrap();
+        let hero = create_tag(&conn, "hero", None, None).unwrap().tag;
+        let prop = create_tag(&conn, "prop", None, None).unwrap().tag;
+        let unused = create_tag(&conn, "unused", None, None).unwrap().tag;
+        for (folder, path) in [(art.id, "/art/a.png"), (art.id, "/art/b.png"), (refs.id, "/refs/c.png")] {
+            conn.execute(
+                "INSERT INTO assets (folder_id, file_path, file_name, file_ext) VALUES (?1, ?2, 'x.png', 'png')",
+                params![folder, path],
+            ).unwrap();
+            let asset_id = conn.last_insert_rowid();
+            let tag = if folder == art.id { hero.id } else { prop.id };
+            add_tag_to_asset(&conn, asset_id, tag, "test").unwrap();
+        }
+
+        let counts = |folder_id: Option<i64>| -> Vec<(i64, i64)> {
+            let mut tags: Vec<_> = get_all_tags(&conn, folder_id, None).unwrap()
+                .into_iter().map(|t| (t.id, t.asset_count)).collect();
+            tags.sort();
+            tags
+        };
+        assert_eq!(counts(None), vec![(hero.id, 2), (prop.id, 1), (unused.id, 0)]);
+        // 文件夹内没有资产的标签计为 0，但仍然返回
+        assert_eq!(counts(Some(art.id)), vec![(hero.id, 2), (prop.id, 0), (unused.id, 0)]);
+        assert_eq!(counts(Some(refs.id)), vec![(hero.id, 0), (prop.id, 1), (unused.id, 0)]);
+    }
+
+    #[test]
+    fn tag_table_rebuild_keeps_asset_tags() {
+        let conn = test_conn();
+        let folder = insert_folder(&conn, "/art", "art", "personal").unwrap();
+        conn.execute(
+            "INSERT INTO assets (folder_id, file_path, file_name, file_ext) VALUES (?1, '/art/a.png', 'a.png', 'png')",
+            params![folder.id],
+        ).unwrap();
+        let asset_id = conn.last_insert_rowid();
+        let tag = create_tag(&conn, "Hero", None, Some("personal")).unwrap().tag;
+        add_tag_to_asset(&conn, asset_id, tag.id, "tester").unwrap();
+
+        // 重建标签表的迁移步骤再执行一次：DROP TABLE 级联删除的关联必须恢复
+        MIGRATIONS[11](&conn).unwrap();
+        let tags = get_asset_tags(&conn, asset_id).unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].id, tag.id);
+    }
+}
@@ -1,35 +1,155 @@
+use image::{DynamicImage, GenericImageView};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+
+use crate::asset_manager::thumbnail;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FfmpegStatus {
     pub installed: bool,
     pub path: Option<String>,
+    /// First line of `ffmpeg -version`
     pub version: Option<String>,
+    /// Parsed version number, e.g. "6.1.1" or "N-112345-g1234abcd" for git builds
+    pub version_number: Option<String>,
+    /// Enabled build features (`--enable-*` from the configuration line, without the prefix)
+    pub features: Vec<String>,
+    /// Filters the app relies on that this build lacks
+    pub missing_filters: Vec<String>,
+    /// First line of `ffprobe -version` (None when ffprobe is not found)
+    pub ffprobe_version: Option<String>,
+}
+
+impl FfmpegStatus {
+    fn not_installed() -> Self {
+        FfmpegStatus {
+            installed: false,
+            path: None,
+            version: None,
+            version_number: None,
+            features: Vec::new(),
+            missing_filters: Vec::new(),
+            ffprobe_version: None,
+        }
+    }
+}
+
+/// Filters used by thumbnails and waveforms
+const REQUIRED_FILTERS: &[&str] = &["scale", "aformat", "showwavespic"];
+
+/// 视频缩略图的截帧位置
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum FrameTimestamp {
+    /// 固定秒数
+    Seconds { value: f64 },
+    /// 时长的百分比（0-100），需要 ffprobe 获取时长
+    Percent { value: f64 },
+    /// 在时长范围内均匀取 samples 帧，选平均亮度最高的一帧（避开黑场）
+    Brightest { samples: u32 },
+}
+
+impl Default for FrameTimestamp {
+    /// 默认取 10% 处，片头常见的黑场淡入一般已经结束
+    fn default() -> Self {
+        FrameTimestamp::Percent { value: 10.0 }
+    }
+}
+
+/// 无法获取时长时使用的截帧位置（秒）
+const FALLBACK_SEEK_SECS: f64 = 1.0;
+
+/// Brightest 模式最多取的帧数
+const MAX_BRIGHTNESS_SAMPLES: u32 = 10;
+
+impl FrameTimestamp {
+    /// 换算为实际的截帧秒数；duration 未知（None 或 0）时百分比模式退回 1 秒
+    fn seek_points(self, duration: Option<f64>) -> Vec<f64> {
+        let duration = duration.filter(|d| *d > 0.0);
+        // 不超过结尾，否则 ffmpeg 取不到帧
+        let clamp = |secs: f64| match duration {
+            Some(d) => secs.clamp(0.0, d * 0.95),
+            None => secs.max(0.0),
+        };
+        match (self, duration) {
+            (FrameTimestamp::Seconds { value }, _) => vec![clamp(value)],
+            (FrameTimestamp::Percent { value }, Some(d)) => vec![clamp(d * value.clamp(0.0, 100.0) / 100.0)],
+            (FrameTimestamp::Brightest { samples }, Some(d)) => {
+                let n = samples.clamp(1, MAX_BRIGHTNESS_SAMPLES);
+                (1..=n).map(|i| clamp(d * i as f64 / (n + 1) as f64)).collect()
+            }
+            (_, None) => vec![FALLBACK_SEEK_SECS],
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadProgress {
-    pub phase: String,       // "downloading", "extracting", "complete", "error"
+    pub phase: String,       // "downloading", "extracting", "verifying", "complete", "error"
     pub progress: f64,       // 0.0 - 1.0
     pub message: String,
 }
 
+/// User-configured ffmpeg binary (setting "ffmpeg.path", loaded at startup), consulted before anything else
+static CUSTOM_FFMPEG: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn custom_ffmpeg_path() -> Option<PathBuf> {
+    CUSTOM_FFMPEG.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn set_custom_ffmpeg_path(path: Option<PathBuf>) {
+    *CUSTOM_FFMPEG.write().unwrap_or_else(|e| e.into_inner()) = path;
+}
+
+fn ffmpeg_binary_name() -> &'static str {
+    if cfg!(target_os = "windows") { "ffmpeg.exe" } else { "ffmpeg" }
+}
+
+/// Resolve a user-supplied path (the binary itself or the folder containing it) and
+/// make sure it runs. Returns the binary path
+pub fn validate_ffmpeg(path: &Path) -> Result<PathBuf, String> {
+    let binary = if path.is_dir() {
+        // Accept both the install root and its bin folder
+        [path.join(ffmpeg_binary_name()), path.join("bin").join(ffmpeg_binary_name())]
+            .into_iter()
+            .find(|p| p.is_file())
+            .ok_or_else(|| format!("目录中未找到 {}: {}", ffmpeg_binary_name(), path.display()))?
+    } else if path.is_file() {
+        path.to_path_buf()
+    } else {
+        return Err(format!("路径不存在: {}", path.display()));
+    };
+
+    check_version(&binary, "ffmpeg version")?;
+    Ok(binary)
+}
+
+/// Run `-version` and check the first line starts with `expected` (e.g. "ffmpeg version")
+fn check_version(binary: &Path, expected: &str) -> Result<(), String> {
+    let output = Command::new(binary)
+        .arg("-version")
+        .output()
+        .map_err(|e| format!("无法运行 {}: {}", binary.display(), e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || !stdout.starts_with(expected) {
+        return Err(format!("不是有效的程序: {}", binary.display()));
+    }
+    Ok(())
+}
+
 /// Check if ffmpeg is available on the system
 pub fn check_ffmpeg() -> FfmpegStatus {
-    // First check in PATH
-    if let Ok(output) = Command::new("ffmpeg").arg("-version").output() {
-        if output.status.success() {
-            let version_str = String::from_utf8_lossy(&output.stdout);
-            let version = version_str.lines().next().unwrap_or("").to_string();
-            return FfmpegStatus {
-                installed: true,
-                path: Some("ffmpeg".into()),
-                version: Some(version),
-            };
-        }
+    // A configured custom path wins
+    if let Some(status) = custom_ffmpeg_path().and_then(|p| probe_ffmpeg(&p.to_string_lossy())) {
+        return status;
+    }
+
+    // Then check in PATH
+    if let Some(status) = probe_ffmpeg("ffmpeg") {
+        return status;
     }
 
     // Check common locations on Windows
@@ -43,26 +163,114 @@ pub fn check_ffmpeg() -> FfmpegStatus {
 
         for p in &common_paths {
             if Path::new(p).exists() {
-                if let Ok(output) = Command::new(p).arg("-version").output() {
-                    if output.status.success() {
-                        let version_str = String::from_utf8_lossy(&output.stdout);
-                        let version = version_str.lines().next().unwrap_or("").to_string();
-                        return FfmpegStatus {
-                            installed: true,
-                            path: Some(p.to_string()),
-                            version: Some(version),
-                        };
-                    }
+                if let Some(status) = probe_ffmpeg(p) {
+                    return status;
                 }
             }
         }
     }
 
-    FfmpegStatus { installed: false, path: None, version: None }
+    FfmpegStatus::not_installed()
+}
+
+/// Run `-version` / `-filters` on the given binary and collect its build details
+fn probe_ffmpeg(path: &str) -> Option<FfmpegStatus> {
+    let output = Command::new(path).arg("-version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version_str = String::from_utf8_lossy(&output.stdout);
+    let version = version_str.lines().next().unwrap_or("").to_string();
+
+    let filters = Command::new(path)
+        .args(["-hide_banner", "-filters"])
+        .output()
+        .map(|o| parse_filter_names(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or_default();
+    // An empty list means the filter query itself failed; don't report everything as missing
+    let missing_filters = if filters.is_empty() {
+        Vec::new()
+    } else {
+        REQUIRED_FILTERS.iter()
+            .filter(|f| !filters.iter().any(|name| name == *f))
+            .map(|f| f.to_string())
+            .collect()
+    };
+
+    Some(FfmpegStatus {
+        installed: true,
+        path: Some(path.to_string()),
+        version_number: parse_version_number(&version),
+        features: parse_enabled_features(&version_str),
+        missing_filters,
+        ffprobe_version: ffprobe_version(&sibling_ffprobe(path)),
+        version: Some(version),
+    })
 }
 
-/// Get the ffmpeg binary path (from app data dir for local install)
+/// "ffmpeg version 6.1.1-essentials_build-www.gyan.dev Copyright ..." -> "6.1.1"
+fn parse_version_number(first_line: &str) -> Option<String> {
+    let raw = first_line.split_whitespace()
+        .skip_while(|w| *w != "version")
+        .nth(1)?;
+    // Release builds carry a suffix after the number; git builds (N-...) are kept whole
+    let number = if raw.starts_with(|c: char| c.is_ascii_digit()) {
+        raw.split(|c: char| c != '.' && !c.is_ascii_digit()).next().unwrap_or(raw)
+    } else {
+        raw
+    };
+    Some(number.to_string())
+}
+
+/// Collect `--enable-xxx` flags from the "configuration:" line
+fn parse_enabled_features(version_output: &str) -> Vec<String> {
+    version_output.lines()
+        .find_map(|l| l.trim().strip_prefix("configuration:"))
+        .map(|conf| {
+            conf.split_whitespace()
+                .filter_map(|flag| flag.strip_prefix("--enable-"))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Filter names from `ffmpeg -filters` (lines look like " T.. scale  V->V  Scale the input video size")
+fn parse_filter_names(filters_output: &str) -> Vec<String> {
+    // Legend lines above the list have no "->" column
+    filters_output.lines()
+        .filter_map(|l| {
+            let mut cols = l.split_whitespace();
+            let name = cols.nth(1)?;
+            cols.next().filter(|io| io.contains("->")).map(|_| name.to_string())
+        })
+        .collect()
+}
+
+/// ffprobe next to the given ffmpeg binary ("ffmpeg" on PATH maps to "ffprobe")
+fn sibling_ffprobe(ffmpeg_path: &str) -> PathBuf {
+    let path = Path::new(ffmpeg_path);
+    let name = if cfg!(target_os = "windows") { "ffprobe.exe" } else { "ffprobe" };
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from("ffprobe"),
+    }
+}
+
+fn ffprobe_version(ffprobe_path: &Path) -> Option<String> {
+    let output = Command::new(ffprobe_path).arg("-version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).lines().next().map(str::to_string)
+}
+
+/// Get the ffmpeg binary path (custom path, then app data dir for local install, then PATH)
 pub fn get_ffmpeg_path(app_data_dir: &Path) -> Option<PathBuf> {
+    if let Some(custom) = custom_ffmpeg_path().filter(|p| p.exists()) {
+        return Some(custom);
+    }
+
     let local_path = app_data_dir.join("ffmpeg").join("ffmpeg.exe");
     if local_path.exists() {
         return Some(local_path);
@@ -76,28 +284,113 @@ pub fn get_ffmpeg_path(app_data_dir: &Path) -> Option<PathBuf> {
     None
 }
 
+/// Get the ffprobe binary path (installed next to ffmpeg, or from PATH)
+pub fn get_ffprobe_path(app_data_dir: &Path) -> Option<PathBuf> {
+    if let Some(ffprobe) = custom_ffmpeg_path()
+        .map(|p| sibling_ffprobe(&p.to_string_lossy()))
+        .filter(|p| p.exists())
+    {
+        return Some(ffprobe);
+    }
+
+    let local_path = app_data_dir.join("ffmpeg").join("ffprobe.exe");
+    if local_path.exists() {
+        return Some(local_path);
+    }
+
+    if Command::new("ffprobe").arg("-version").output().map(|o| o.status.success()).unwrap_or(false) {
+        return Some(PathBuf::from("ffprobe"));
+    }
+
+    None
+}
+
 /// Download and install ffmpeg to app data dir (Windows)
 /// Returns the path to the installed ffmpeg binary
+///
+/// The temp zip is always removed, and on failure so are any partially written binaries,
+/// so `ffmpeg.exe` only exists after it has been verified to run
 #[cfg(target_os = "windows")]
 pub async fn download_ffmpeg(
     app_data_dir: &Path,
     progress_sender: tokio::sync::mpsc::Sender<DownloadProgress>,
 ) -> Result<String, String> {
-    use std::io::Write;
-
     let ffmpeg_dir = app_data_dir.join("ffmpeg");
-    fs::create_dir_all(&ffmpeg_dir).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&ffmpeg_dir).map_err(|e| format!("创建 FFmpeg 目录失败: {}", e))?;
 
     let target_path = ffmpeg_dir.join("ffmpeg.exe");
+    let probe_path = ffmpeg_dir.join("ffprobe.exe");
+    let zip_path = ffmpeg_dir.join("ffmpeg_download.zip");
 
-    // If already exists, just return
+    // If already exists and runs, just return; a corrupt leftover from an earlier attempt is reinstalled
     if target_path.exists() {
-        let _ = progress_sender.send(DownloadProgress {
-            phase: "complete".into(), progress: 1.0,
-            message: "FFmpeg 已存在".into(),
-        }).await;
-        return Ok(target_path.to_string_lossy().to_string());
+        if check_version(&target_path, "ffmpeg version").is_ok() {
+            let _ = progress_sender.send(DownloadProgress {
+                phase: "complete".into(), progress: 1.0,
+                message: "FFmpeg 已存在".into(),
+            }).await;
+            return Ok(target_path.to_string_lossy().to_string());
+        }
+        log::warn!("[FFmpeg] 已有的 {} 无法运行，重新安装", target_path.display());
+        fs::remove_file(&target_path).ok();
+        fs::remove_file(&probe_path).ok();
+    }
+
+    let result = install_ffmpeg(&zip_path, &target_path, &probe_path, &progress_sender).await;
+    fs::remove_file(&zip_path).ok();
+
+    match result {
+        Ok(()) => {
+            let _ = progress_sender.send(DownloadProgress {
+                phase: "complete".into(), progress: 1.0,
+                message: "FFmpeg 安装完成".into(),
+            }).await;
+            Ok(target_path.to_string_lossy().to_string())
+        }
+        Err(e) => {
+            fs::remove_file(&target_path).ok();
+            fs::remove_file(&probe_path).ok();
+            let message = e.to_string();
+            let _ = progress_sender.send(DownloadProgress {
+                phase: "error".into(), progress: 0.0,
+                message: message.clone(),
+            }).await;
+            Err(message)
+        }
     }
+}
+
+/// Which install step failed
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+enum InstallError {
+    Download(String),
+    Extract(String),
+    Verify(String),
+}
+
+#[cfg(target_os = "windows")]
+impl std::fmt::Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallError::Download(e) => write!(f, "下载 FFmpeg 失败: {}", e),
+            InstallError::Extract(e) => write!(f, "解压 FFmpeg 失败: {}", e),
+            InstallError::Verify(e) => write!(f, "FFmpeg 校验失败: {}", e),
+        }
+    }
+}
+
+/// Download the archive to `zip_path`, extract ffmpeg/ffprobe and verify them.
+/// Cleanup of whatever was written is left to the caller
+#[cfg(target_os = "windows")]
+async fn install_ffmpeg(
+    zip_path: &Path,
+    target_path: &Path,
+    probe_path: &Path,
+    progress_sender: &tokio::sync::mpsc::Sender<DownloadProgress>,
+) -> Result<(), InstallError> {
+    use futures_util::StreamExt;
+    use std::io::Write;
 
     // Download ffmpeg essentials build (smaller)
     let url = "https://www.gyan.dev/ffmpeg/builds/ffmpeg-release-essentials.zip";
@@ -110,21 +403,22 @@ pub async fn download_ffmpeg(
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(600))
         .build()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| InstallError::Download(e.to_string()))?;
 
-    let response = client.get(url).send().await.map_err(|e| format!("下载失败: {}", e))?;
+    let response = client.get(url).send().await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| InstallError::Download(e.to_string()))?;
     let total_size = response.content_length().unwrap_or(0);
 
-    let zip_path = ffmpeg_dir.join("ffmpeg_download.zip");
-    let mut file = fs::File::create(&zip_path).map_err(|e| e.to_string())?;
+    let mut file = fs::File::create(zip_path)
+        .map_err(|e| InstallError::Download(format!("创建临时文件失败: {}", e)))?;
     let mut downloaded: u64 = 0;
 
     let mut stream = response.bytes_stream();
-    use futures_util::StreamExt;
-
     while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("下载中断: {}", e))?;
-        file.write_all(&chunk).map_err(|e| e.to_string())?;
+        let chunk = chunk.map_err(|e| InstallError::Download(format!("下载中断: {}", e)))?;
+        file.write_all(&chunk)
+            .map_err(|e| InstallError::Download(format!("写入临时文件失败: {}", e)))?;
         downloaded += chunk.len() as u64;
 
         if total_size > 0 {
@@ -136,6 +430,7 @@ pub async fn download_ffmpeg(
             }).await;
         }
     }
+    file.flush().map_err(|e| InstallError::Download(format!("写入临时文件失败: {}", e)))?;
     drop(file);
 
     // Extract ffmpeg.exe from the zip
@@ -144,41 +439,53 @@ pub async fn download_ffmpeg(
         message: "正在解压...".into(),
     }).await;
 
-    let zip_file = fs::File::open(&zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(zip_file).map_err(|e| format!("解压失败: {}", e))?;
+    let zip_file = fs::File::open(zip_path).map_err(|e| InstallError::Extract(e.to_string()))?;
+    let mut archive = zip::ZipArchive::new(zip_file).map_err(|e| InstallError::Extract(e.to_string()))?;
 
     let mut found = false;
+    let mut found_probe = false;
     for i in 0..archive.len() {
-        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        let mut entry = archive.by_index(i).map_err(|e| InstallError::Extract(e.to_string()))?;
         let name = entry.name().to_string();
+        if name.contains("__MACOSX") {
+            continue;
+        }
 
-        // Look for ffmpeg.exe in the archive
-        if name.ends_with("ffmpeg.exe") && !name.contains("__MACOSX") {
-            let mut out = fs::File::create(&target_path).map_err(|e| e.to_string())?;
-            std::io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+        // Look for ffmpeg.exe in the archive; also extract ffprobe.exe if found
+        let dest = if name.ends_with("ffmpeg.exe") {
             found = true;
-        }
-        // Also extract ffprobe.exe if found
-        if name.ends_with("ffprobe.exe") && !name.contains("__MACOSX") {
-            let probe_path = ffmpeg_dir.join("ffprobe.exe");
-            let mut out = fs::File::create(&probe_path).map_err(|e| e.to_string())?;
-            std::io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
-        }
+            target_path
+        } else if name.ends_with("ffprobe.exe") {
+            found_probe = true;
+            probe_path
+        } else {
+            continue;
+        };
+        let mut out = fs::File::create(dest)
+            .map_err(|e| InstallError::Extract(format!("创建 {} 失败: {}", dest.display(), e)))?;
+        std::io::copy(&mut entry, &mut out)
+            .and_then(|_| out.flush())
+            .map_err(|e| InstallError::Extract(format!("写入 {} 失败: {}", dest.display(), e)))?;
     }
 
-    // Clean up zip
-    fs::remove_file(&zip_path).ok();
-
     if !found {
-        return Err("ZIP 中未找到 ffmpeg.exe".into());
+        return Err(InstallError::Extract("ZIP 中未找到 ffmpeg.exe".into()));
     }
 
     let _ = progress_sender.send(DownloadProgress {
-        phase: "complete".into(), progress: 1.0,
-        message: "FFmpeg 安装完成".into(),
+        phase: "verifying".into(), progress: 0.95,
+        message: "正在校验...".into(),
     }).await;
 
-    Ok(target_path.to_string_lossy().to_string())
+    check_version(target_path, "ffmpeg version").map_err(InstallError::Verify)?;
+    // ffprobe is optional: a broken one is dropped rather than failing the install
+    if found_probe {
+        if let Err(e) = check_version(probe_path, "ffprobe version") {
+            log::warn!("[FFmpeg] ffprobe 校验失败，已删除: {}", e);
+            fs::remove_file(probe_path).ok();
+        }
+    }
+    Ok(())
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -196,27 +503,82 @@ pub async fn download_ffmpeg(
 /// Extract a video thumbnail using ffmpeg
 pub fn extract_video_thumbnail(
     ffmpeg_path: &Path,
+    ffprobe_path: Option<&Path>,
     video_path: &Path,
     output_path: &Path,
     width: u32,
+    timestamp: FrameTimestamp,
 ) -> Result<(), String> {
+    let frame = grab_frame(ffmpeg_path, ffprobe_path, video_path, timestamp)?;
+    let (w, h) = frame.dimensions();
+    let frame = if width > 0 && w > width {
+        let new_h = (h as f64 * width as f64 / w as f64) as u32;
+        frame.resize_exact(width, new_h.max(1), image::imageops::FilterType::Lanczos3)
+    } else {
+        frame
+    };
+
+    // JPEG 不支持透明通道
+    let is_jpeg = output_path.extension()
+        .map(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+        .unwrap_or(false);
+    let frame = if is_jpeg { DynamicImage::ImageRgb8(frame.to_rgb8()) } else { frame };
+    frame.save(output_path).map_err(|e| format!("保存缩略图失败: {}", e))
+}
+
+/// 按截帧位置从视频中取一帧；Brightest 模式下取多帧，返回平均亮度最高的一帧
+pub fn grab_frame(
+    ffmpeg_path: &Path,
+    ffprobe_path: Option<&Path>,
+    video_path: &Path,
+    timestamp: FrameTimestamp,
+) -> Result<DynamicImage, String> {
+    let needs_duration = !matches!(timestamp, FrameTimestamp::Seconds { .. });
+    let duration = ffprobe_path
+        .filter(|_| needs_duration)
+        .and_then(|p| thumbnail::get_media_info(p, &video_path.to_string_lossy()))
+        .map(|(_, _, d)| d);
+
+    let mut best: Option<(f64, DynamicImage)> = None;
+    let mut last_err = None;
+    for secs in timestamp.seek_points(duration) {
+        match grab_frame_at(ffmpeg_path, video_path, secs) {
+            Ok(frame) => {
+                let luma = mean_luma(&frame);
+                if !matches!(&best, Some((b, _)) if *b >= luma) {
+                    best = Some((luma, frame));
+                }
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    best.map(|(_, frame)| frame)
+        .ok_or_else(|| last_err.unwrap_or_else(|| "未能截取视频帧".to_string()))
+}
+
+/// 截取指定秒数处的一帧（通过管道输出 PNG，不写临时文件）
+fn grab_frame_at(ffmpeg_path: &Path, video_path: &Path, secs: f64) -> Result<DynamicImage, String> {
     let output = Command::new(ffmpeg_path)
-        .args(&[
-            "-y",
-            "-i", &video_path.to_string_lossy(),
-            "-vframes", "1",
-            "-ss", "00:00:01",
-            "-vf", &format!("scale={}:-1", width),
-            "-q:v", "3",
-            &output_path.to_string_lossy(),
-        ])
+        .args(["-v", "error", "-ss", &format!("{:.3}", secs), "-i"])
+        .arg(video_path)
+        .args(["-vframes", "1", "-f", "image2pipe", "-vcodec", "png", "pipe:1"])
+        .stdin(Stdio::null())
         .output()
-        .map_err(|e| format!("ffmpeg 执行失败: {}", e))?;
+        .map_err(|e| format!("执行FFmpeg失败: {}", e))?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffmpeg 错误: {}", stderr));
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!(
+            "FFmpeg缩略图生成失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
     }
+    image::load_from_memory(&output.stdout)
+        .map_err(|e| format!("解码视频帧失败: {}", e))
+}
 
-    Ok(())
+/// 平均亮度（0-255），缩小后计算
+fn mean_luma(img: &DynamicImage) -> f64 {
+    let luma = img.thumbnail(64, 64).to_luma8();
+    let count = luma.pixels().len().max(1);
+    luma.pixels().map(|p| p.0[0] as u64).sum::<u64>() as f64 / count as f64
 }
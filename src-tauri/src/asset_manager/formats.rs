@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// 格式信息：所属分类 + 是否能通过 generate_thumbnail 生成缩略图
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatInfo {
+    pub category: &'static str,
+    pub can_thumbnail: bool,
+}
+
+/// 所有分类
+pub const CATEGORIES: &[&str] = &["image", "video", "audio", "3d", "spine", "other"];
+
+/// 内置格式表（扩展名 -> 分类 / 能否生成缩略图），扫描和缩略图都以此为准
+const BUILTIN_FORMATS: &[(&str, &str, bool)] = &[
+    // 图片（image crate 可解码）
+    ("png", "image", true),
+    ("jpg", "image", true),
+    ("jpeg", "image", true),
+    ("gif", "image", true),
+    ("bmp", "image", true),
+    ("webp", "image", true),
+    ("tiff", "image", true),
+    ("tif", "image", true),
+    ("ico", "image", true),
+    ("tga", "image", true),
+    ("dds", "image", true),
+    ("hdr", "image", true),
+    ("exr", "image", true),
+    ("psd", "image", true),
+    // 图片（暂无解码器）
+    ("psb", "image", false),
+    ("svg", "image", false),
+    ("ai", "image", false),
+    ("eps", "image", false),
+    // 视频（需要 FFmpeg）
+    ("mp4", "video", false),
+    ("avi", "video", false),
+    ("mov", "video", false),
+    ("mkv", "video", false),
+    ("wmv", "video", false),
+    ("flv", "video", false),
+    ("webm", "video", false),
+    ("m4v", "video", false),
+    ("mpg", "video", false),
+    ("mpeg", "video", false),
+    // 音频
+    ("mp3", "audio", false),
+    ("wav", "audio", false),
+    ("ogg", "audio", false),
+    ("flac", "audio", false),
+    ("aac", "audio", false),
+    ("wma", "audio", false),
+    ("m4a", "audio", false),
+    ("opus", "audio", false),
+    // 3D 模型（glTF 可提取内嵌纹理）
+    ("gltf", "3d", true),
+    ("glb", "3d", true),
+    ("fbx", "3d", false),
+    ("obj", "3d", false),
+    ("blend", "3d", false),
+    ("3ds", "3d", false),
+    ("dae", "3d", false),
+    ("stl", "3d", false),
+    // Spine 动画
+    ("spine", "spine", false),
+    ("skel", "spine", false),
+    ("atlas", "spine", false),
+];
+
+/// 用户注册的自定义扩展名（启动时从数据库加载）
+fn custom_formats() -> &'static RwLock<HashMap<String, FormatInfo>> {
+    static CUSTOM: OnceLock<RwLock<HashMap<String, FormatInfo>>> = OnceLock::new();
+    CUSTOM.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn normalize_ext(ext: &str) -> String {
+    ext.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// 查询扩展名对应的格式信息
+pub fn lookup(ext: &str) -> Option<FormatInfo> {
+    let e = normalize_ext(ext);
+    if let Some((_, category, can_thumbnail)) = BUILTIN_FORMATS.iter().find(|(x, _, _)| *x == e) {
+        return Some(FormatInfo { category, can_thumbnail: *can_thumbnail });
+    }
+    custom_formats().read().ok()?.get(&e).copied()
+}
+
+/// 是否是可管理（会被扫描）的格式
+pub fn is_supported(ext: &str) -> bool {
+    lookup(ext).is_some()
+}
+
+/// 获取文件分类，未知格式返回 "other"
+pub fn get_file_category(ext: &str) -> &'static str {
+    lookup(ext).map(|f| f.category).unwrap_or("other")
+}
+
+/// 是否可以生成缩略图
+pub fn can_thumbnail(ext: &str) -> bool {
+    lookup(ext).map(|f| f.can_thumbnail).unwrap_or(false)
+}
+
+/// 所有可管理的扩展名（内置 + 自定义）
+pub fn all_extensions() -> Vec<String> {
+    let mut exts: Vec<String> = BUILTIN_FORMATS.iter().map(|(e, _, _)| e.to_string()).collect();
+    if let Ok(custom) = custom_formats().read() {
+        exts.extend(custom.keys().cloned());
+    }
+    exts
+}
+
+/// 前端使用的格式列表项
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportedExtension {
+    pub ext: String,
+    pub category: &'static str,
+    /// 能否直接生成缩略图（视频/音频需要 FFmpeg，不计在内）
+    pub can_thumbnail: bool,
+    /// 用户注册的自定义扩展名
+    pub custom: bool,
+}
+
+/// 所有可管理的格式（内置 + 自定义），按分类、扩展名排序
+pub fn supported_extensions() -> Vec<SupportedExtension> {
+    let mut list: Vec<SupportedExtension> = BUILTIN_FORMATS.iter()
+        .map(|(ext, category, can_thumbnail)| SupportedExtension {
+            ext: ext.to_string(),
+            category,
+            can_thumbnail: *can_thumbnail,
+            custom: false,
+        })
+        .collect();
+    if let Ok(custom) = custom_formats().read() {
+        list.extend(custom.iter().map(|(ext, info)| SupportedExtension {
+            ext: ext.clone(),
+            category: info.category,
+            can_thumbnail: info.can_thumbnail,
+            custom: true,
+        }));
+    }
+    let category_rank = |c: &str| CATEGORIES.iter().position(|x| *x == c).unwrap_or(CATEGORIES.len());
+    list.sort_by(|a, b| {
+        category_rank(a.category).cmp(&category_rank(b.category)).then_with(|| a.ext.cmp(&b.ext))
+    });
+    list
+}
+
+/// 注册自定义扩展名。图片分类会尝试用 image crate 解码生成缩略图，其余分类不生成。
+/// 返回规范化后的扩展名。
+pub fn register_custom_extension(ext: &str, category: &str) -> Result<String, String> {
+    let e = normalize_ext(ext);
+    if e.is_empty() || !e.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("无效的扩展名: {}", ext));
+    }
+    if BUILTIN_FORMATS.iter().any(|(x, _, _)| *x == e) {
+        return Err(format!("扩展名 {} 已是内置格式", e));
+    }
+    let category = CATEGORIES.iter().find(|c| **c == category)
+        .ok_or_else(|| format!("未知分类: {}", category))?;
+
+    custom_formats().write()
+        .map_err(|e| format!("锁定格式表失败: {}", e))?
+        .insert(e.clone(), FormatInfo { category, can_thumbnail: *category == "image" });
+    Ok(e)
+}
+
+/// 移除自定义扩展名
+pub fn unregister_custom_extension(ext: &str) {
+    if let Ok(mut custom) = custom_formats().write() {
+        custom.remove(&normalize_ext(ext));
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::asset_manager::scanner::{self, ScannedFile};
+
+/// 待导入的文件：源路径 + 放入目标文件夹后的相对子目录
+#[derive(Debug, Clone)]
+pub struct DroppedFile {
+    pub source: PathBuf,
+    pub rel_dir: String,
+}
+
+/// 展开拖入的路径：文件直接导入，目录递归扫描并保留目录结构
+pub fn collect_dropped(paths: &[String]) -> Vec<DroppedFile> {
+    let mut files = Vec::new();
+
+    for p in paths {
+        let path = Path::new(p);
+        if path.is_dir() {
+            let dir_name = path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let scanned = match scanner::scan_directory(p) {
+                Ok(scan) => scan.files,
+                Err(_) => continue,
+            };
+            for f in scanned {
+                let rel_dir = if f.rel_dir.is_empty() {
+                    dir_name.clone()
+                } else {
+                    format!("{}/{}", dir_name, f.rel_dir)
+                };
+                files.push(DroppedFile { source: PathBuf::from(f.path), rel_dir });
+            }
+        } else if path.is_file() && scanner::scan_file(path.parent().unwrap_or(path), path).is_some() {
+            files.push(DroppedFile { source: path.to_path_buf(), rel_dir: String::new() });
+        }
+    }
+
+    files
+}
+
+/// 目标文件已存在时追加序号：name (1).ext、name (2).ext ...
+pub(crate) fn unique_destination(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let name = Path::new(file_name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let ext = name.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|i| dir.join(format!("{} ({}){}", stem, i, ext)))
+        .find(|p| !p.exists())
+        .unwrap_or(candidate)
+}
+
+/// 复制或移动文件。跨盘移动时 rename 会失败，退化为复制后删除源文件。
+fn transfer_file(source: &Path, dest: &Path, copy: bool) -> Result<(), String> {
+    if !copy && fs::rename(source, dest).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, dest)
+        .map_err(|e| format!("复制文件失败 {}: {}", source.display(), e))?;
+    if !copy {
+        fs::remove_file(source)
+            .map_err(|e| format!("删除源文件失败 {}: {}", source.display(), e))?;
+    }
+    Ok(())
+}
+
+/// 把单个文件导入到管理文件夹 root 中，返回导入后文件的扫描信息。
+/// 已经位于 root 内的文件不复制/移动，直接登记。
+pub fn import_file(file: &DroppedFile, root: &Path, copy: bool) -> Result<ScannedFile, String> {
+    if file.source.starts_with(root) {
+        return scanner::scan_file(root, &file.source)
+            .ok_or_else(|| format!("无法读取文件: {}", file.source.display()));
+    }
+
+    let file_name = file.source.file_name()
+        .ok_or_else(|| format!("无效的文件路径: {}", file.source.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let dest_dir = file.rel_dir.split('/')
+        .filter(|s| !s.is_empty())
+        .fold(root.to_path_buf(), |dir, part| dir.join(part));
+    fs::create_dir_all(&dest_dir)
+        .map_err(|e| format!("创建目录失败 {}: {}", dest_dir.display(), e))?;
+
+    let dest = unique_destination(&dest_dir, &file_name);
+    transfer_file(&file.source, &dest, copy)?;
+
+    scanner::scan_file(root, &dest)
+        .ok_or_else(|| format!("无法读取导入后的文件: {}", dest.display()))
+}
@@ -1,9 +1,26 @@
 pub mod db;
+pub mod pool;
+pub mod formats;
 pub mod scanner;
+pub mod scan_queue;
+pub mod batch_ops;
+pub mod progress;
+pub mod importer;
+pub mod sidecar;
 pub mod thumbnail;
+pub mod thumb_provider;
+pub mod thumb_worker;
+pub mod placeholder;
+pub mod contact_sheet;
 pub mod commands;
 pub mod team;
+pub mod shell;
+pub mod clipboard;
 pub mod ffmpeg;
+pub mod protocol;
+pub mod transform;
+pub mod convert;
+pub mod optimize;
 
 pub use commands::*;
 pub use db::AssetManagerState;
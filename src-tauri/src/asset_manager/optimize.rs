@@ -0,0 +1,57 @@
+use image::GenericImageView;
+use img_parts::jpeg::Jpeg;
+use img_parts::{Bytes, ImageEXIF, ImageICC};
+use std::fs;
+use std::path::Path;
+
+use crate::fs_util::write_atomic;
+
+/// JPEG 重新压缩结果
+#[derive(Debug, Clone, Copy)]
+pub struct OptimizeResult {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 用 mozjpeg 按 quality 重新压缩 JPEG，保留原文件的 EXIF 和 ICC 配置。
+/// 只有结果更小时才原子写回，返回 None 表示没有收益、原文件保持不变。
+pub fn optimize_jpeg(path: &Path, quality: u8) -> Result<Option<OptimizeResult>, String> {
+    let original = fs::read(path)
+        .map_err(|e| format!("读取文件失败 {}: {}", path.display(), e))?;
+    let img = image::load_from_memory_with_format(&original, image::ImageFormat::Jpeg)
+        .map_err(|e| format!("无法解码 JPEG {}: {}", path.display(), e))?;
+    let (width, height) = img.dimensions();
+    let rgb = img.to_rgb8();
+
+    // libjpeg 出错时 mozjpeg 以 panic 的方式报告
+    let compressed = std::panic::catch_unwind(|| -> std::io::Result<Vec<u8>> {
+        let mut comp = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
+        comp.set_size(width as usize, height as usize);
+        comp.set_quality(quality.clamp(1, 100) as f32);
+        let mut started = comp.start_compress(Vec::new())?;
+        started.write_scanlines(rgb.as_raw())?;
+        started.finish()
+    })
+    .map_err(|_| "mozjpeg 压缩失败".to_string())?
+    .map_err(|e| format!("mozjpeg 压缩失败: {}", e))?;
+
+    // 把原文件的 EXIF / ICC 拷贝到新文件
+    let source = Jpeg::from_bytes(Bytes::from(original.clone()))
+        .map_err(|e| format!("解析 JPEG 失败: {}", e))?;
+    let mut output = Jpeg::from_bytes(Bytes::from(compressed))
+        .map_err(|e| format!("解析压缩结果失败: {}", e))?;
+    output.set_exif(source.exif());
+    output.set_icc_profile(source.icc_profile());
+    let bytes = output.encoder().bytes();
+
+    let old_size = original.len() as u64;
+    let new_size = bytes.len() as u64;
+    if new_size >= old_size {
+        return Ok(None);
+    }
+    write_atomic(path, &bytes)
+        .map_err(|e| format!("写入文件失败 {}: {}", path.display(), e))?;
+    Ok(Some(OptimizeResult { old_size, new_size, width, height }))
+}
@@ -0,0 +1,160 @@
+use image::{ImageFormat, Rgb, RgbImage};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::asset_manager::thumbnail::{self, ThumbResult};
+
+/// 占位缩略图尺寸
+const PLACEHOLDER_SIZE: u32 = 300;
+
+/// 占位缩略图缓存目录（位于缩略图目录下）
+const PLACEHOLDER_DIR: &str = "placeholders";
+
+/// 5x7 点阵字体（每行低 5 位有效）
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        ' ' => [0x00; 7],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// 文字按点阵字体绘制后的像素宽度（每字符 5 像素 + 1 像素间距）
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32 * 6).saturating_sub(1) * scale
+}
+
+/// 用内置点阵字体绘制文字（小写转大写，不支持的字符显示为 ?），超出画布的部分被裁剪
+pub fn draw_text(img: &mut RgbImage, text: &str, x: u32, y: u32, scale: u32, color: Rgb<u8>) {
+    let (w, h) = img.dimensions();
+    for (i, c) in text.to_uppercase().chars().enumerate() {
+        let rows = glyph(c);
+        let gx = x + i as u32 * 6 * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..5u32 {
+                if bits & (0x10 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (gx + col * scale + dx, y + row as u32 * scale + dy);
+                        if px < w && py < h {
+                            img.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 分类对应的卡片背景色
+fn category_color(category: &str) -> Rgb<u8> {
+    match category {
+        "image" => Rgb([59, 130, 246]),
+        "video" => Rgb([139, 92, 246]),
+        "audio" => Rgb([16, 185, 129]),
+        "3d" => Rgb([249, 115, 22]),
+        "spine" => Rgb([236, 72, 153]),
+        _ => Rgb([107, 114, 128]),
+    }
+}
+
+/// 获取某扩展名的占位缩略图路径（按扩展名缓存，而非按文件）
+pub fn get_placeholder_path(thumb_dir: &Path, ext: &str) -> PathBuf {
+    let safe: String = ext.to_lowercase().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    let name = if safe.is_empty() { "unknown".to_string() } else { safe };
+    thumb_dir.join(PLACEHOLDER_DIR).join(format!("{}.jpg", name))
+}
+
+/// 是否是占位缩略图（多个资产共用，不能随单个资产删除）
+pub fn is_placeholder_path(thumb_dir: &Path, path: &Path) -> bool {
+    path.starts_with(thumb_dir.join(PLACEHOLDER_DIR))
+}
+
+/// 为无法预览的格式生成分类占位缩略图（彩色卡片 + 扩展名文字）
+/// 同一扩展名只生成一次，返回的尺寸为 0（原文件尺寸未知）
+pub fn generate_placeholder_thumbnail(ext: &str, thumb_dir: &Path) -> Result<ThumbResult, String> {
+    let path = get_placeholder_path(thumb_dir, ext);
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建占位图目录失败: {}", e))?;
+        }
+        render_placeholder(ext)
+            .save_with_format(&path, ImageFormat::Jpeg)
+            .map_err(|e| format!("保存占位缩略图失败: {}", e))?;
+    }
+
+    Ok(ThumbResult {
+        thumb_path: path.to_string_lossy().to_string(),
+        width: 0,
+        height: 0,
+    })
+}
+
+/// 绘制占位卡片
+fn render_placeholder(ext: &str) -> RgbImage {
+    let size = PLACEHOLDER_SIZE;
+    let bg = category_color(thumbnail::get_file_category(ext));
+    let fg = Rgb([255u8, 255, 255]);
+    let mut img = RgbImage::from_pixel(size, size, Rgb([31, 41, 55]));
+
+    // 内部卡片（留出边距）
+    let margin = size / 10;
+    for y in margin..size - margin {
+        for x in margin..size - margin {
+            img.put_pixel(x, y, bg);
+        }
+    }
+
+    // 扩展名文字，最多 6 个字符，居中绘制
+    let text: String = ext.to_uppercase().chars().take(6).collect();
+    if text.is_empty() {
+        return img;
+    }
+    let scale = ((size - margin * 4) / text_width(&text, 1)).clamp(1, 16);
+    let start_x = (size - text_width(&text, scale)) / 2;
+    let start_y = (size - 7 * scale) / 2;
+    draw_text(&mut img, &text, start_x, start_y, scale, fg);
+
+    img
+}
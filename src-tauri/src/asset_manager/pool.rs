@@ -0,0 +1,93 @@
+use rusqlite::{Connection, OpenFlags};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{LockResult, Mutex, MutexGuard};
+
+use crate::asset_manager::db;
+use crate::error::ArtHubError;
+
+/// 空闲读连接的最大保留数量，超出的连接用完即关闭
+const MAX_IDLE_READERS: usize = 4;
+
+/// 数据库连接池：
+/// - 一个写连接，用 Mutex 串行化所有写操作（与原先单连接的语义相同）
+/// - 若干只读连接，WAL 模式下可与写连接及彼此并发执行，长查询不会阻塞缩略图写入
+pub struct DbPool {
+    path: PathBuf,
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+}
+
+impl DbPool {
+    pub fn new(path: PathBuf, writer: Connection) -> Self {
+        DbPool {
+            path,
+            writer: Mutex::new(writer),
+            readers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 数据库文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 获取写连接（会修改数据库或需要事务的操作都应使用它）
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, Connection>> {
+        self.writer.lock()
+    }
+
+    /// 获取一个只读连接，优先复用空闲连接
+    pub fn read(&self) -> Result<PooledConnection<'_>, ArtHubError> {
+        let idle = self.readers.lock()
+            .map_err(|e| ArtHubError::DbLocked(format!("获取读连接失败: {}", e)))?
+            .pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => self.open_reader()?,
+        };
+        Ok(PooledConnection { pool: self, conn: Some(conn) })
+    }
+
+    fn open_reader(&self) -> Result<Connection, ArtHubError> {
+        let conn = Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+        ).map_err(ArtHubError::db("打开读连接失败"))?;
+        db::configure_connection(&conn)
+            .map_err(ArtHubError::db("设置读连接参数失败"))?;
+
+        // journal_mode 记录在数据库文件中，由写连接设置；这里确认读连接确实处于 WAL 模式，
+        // 否则读操作会阻塞写入
+        let mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .map_err(ArtHubError::db("读取 journal_mode 失败"))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            log::warn!("[AssetManager] 读连接未处于 WAL 模式 (journal_mode={})，并发读可能阻塞写入", mode);
+        }
+        Ok(conn)
+    }
+}
+
+/// 借出的只读连接，drop 时归还到池中
+pub struct PooledConnection<'a> {
+    pool: &'a DbPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("连接已归还")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let (Some(conn), Ok(mut readers)) = (self.conn.take(), self.pool.readers.lock()) {
+            if readers.len() < MAX_IDLE_READERS {
+                readers.push(conn);
+            }
+        }
+    }
+}
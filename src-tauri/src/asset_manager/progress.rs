@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::asset_manager::db::ScanProgress;
+
+/// 滚动平均保留的采样数（扫描每批 20 个文件发一次进度，约为最近 200 个文件）
+const RATE_WINDOW: usize = 10;
+
+/// 进度计时：从创建时开始计时，为进度事件计算已用时间、速度和预计剩余时间
+///
+/// 缩略图阶段每个文件的耗时差别很大（小 PNG 和大 PSD 相差几个数量级），
+/// 用最近几次采样的滚动平均估算速度；其余场景用开始以来的平均速度
+pub struct ProgressTimer {
+    start: Instant,
+    /// 滚动平均的采样 (时间, 已处理数)；None 时使用整体平均
+    window: Option<VecDeque<(Instant, u32)>>,
+}
+
+impl ProgressTimer {
+    /// 按开始以来的平均速度估算
+    pub fn average() -> Self {
+        ProgressTimer { start: Instant::now(), window: None }
+    }
+
+    /// 按最近 RATE_WINDOW 次采样的滚动平均估算
+    pub fn rolling() -> Self {
+        ProgressTimer { start: Instant::now(), window: Some(VecDeque::with_capacity(RATE_WINDOW + 1)) }
+    }
+
+    /// 记录一次进度并生成进度事件
+    pub fn progress(
+        &mut self,
+        folder_id: i64,
+        current: u32,
+        total: u32,
+        file_name: String,
+        phase: &str,
+    ) -> ScanProgress {
+        let now = Instant::now();
+        let elapsed_secs = now.duration_since(self.start).as_secs_f64();
+
+        let items_per_sec = match &mut self.window {
+            Some(window) => {
+                // 已处理数回退（换了阶段）时重新采样
+                if window.back().is_some_and(|(_, n)| *n > current) {
+                    window.clear();
+                }
+                window.push_back((now, current));
+                if window.len() > RATE_WINDOW + 1 {
+                    window.pop_front();
+                }
+                let (since, from) = window[0];
+                rate(current - from, now.duration_since(since).as_secs_f64())
+            }
+            None => rate(current, elapsed_secs),
+        };
+
+        let eta_secs = (items_per_sec > 0.0)
+            .then(|| total.saturating_sub(current) as f64 / items_per_sec);
+
+        ScanProgress {
+            folder_id,
+            current,
+            total,
+            file_name,
+            phase: phase.to_string(),
+            elapsed_secs,
+            items_per_sec,
+            eta_secs,
+        }
+    }
+}
+
+fn rate(items: u32, secs: f64) -> f64 {
+    if secs > 0.0 { items as f64 / secs } else { 0.0 }
+}
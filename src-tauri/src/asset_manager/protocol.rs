@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::{AppHandle, Manager};
+
+use crate::asset_manager::db::{self, AssetManagerState};
+use crate::asset_manager::thumbnail;
+
+type ProtocolResult = Result<Response, Box<dyn std::error::Error>>;
+
+/// thumb://<asset_id>[?w=<宽度>] 协议处理：按资产 id 返回缓存的缩略图，前端不再需要直接读取文件路径。
+/// Windows 上 WebView2 会把自定义协议改写为 https://thumb.localhost/<asset_id>。
+pub fn handle_thumb_request(app: &AppHandle, request: &Request) -> ProtocolResult {
+    let Some(asset_id) = parse_asset_id(request.uri()) else {
+        return not_found();
+    };
+
+    let thumb_path = {
+        let state = app.state::<AssetManagerState>();
+        let conn = state.db.read()?;
+        db::get_asset_thumb_path(&conn, asset_id)?
+    };
+    let Some(thumb_path) = thumb_path.filter(|p| !p.is_empty()) else {
+        return not_found();
+    };
+    let mut path = PathBuf::from(&thumb_path);
+
+    // ?w=150：按需缩放，解码失败时退回原缩略图
+    if let Some(width) = parse_width(request.uri()) {
+        let thumb_dir = app.state::<AssetManagerState>().thumb_dir.clone();
+        match thumbnail::resized_thumbnail(&thumb_dir, &path, width) {
+            Ok(resized) => path = resized,
+            Err(e) => log::warn!("缩放缩略图失败 {}: {}", thumb_path, e),
+        }
+    }
+    let path = path.as_path();
+
+    let Ok(meta) = std::fs::metadata(path) else {
+        return not_found();
+    };
+
+    // 缩略图重新生成后路径不变，用修改时间 + 大小作为 ETag，让 WebView 每次重新验证
+    let mtime = meta.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let etag = format!("\"{:x}-{:x}\"", mtime, meta.len());
+    let if_none_match = request.headers().get("If-None-Match").and_then(|v| v.to_str().ok());
+    if if_none_match == Some(etag.as_str()) {
+        return ResponseBuilder::new()
+            .status(304)
+            .header("ETag", etag)
+            .body(Vec::new());
+    }
+
+    let Ok(bytes) = std::fs::read(path) else {
+        return not_found();
+    };
+    ResponseBuilder::new()
+        .status(200)
+        .mimetype(mime_for(path))
+        .header("Cache-Control", "no-cache")
+        .header("ETag", etag)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(bytes)
+}
+
+/// 从 thumb://123、thumb://localhost/123 或 https://thumb.localhost/123 中取出资产 id
+fn parse_asset_id(uri: &str) -> Option<i64> {
+    let rest = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    rest.trim_matches('/').rsplit('/').next()?.parse().ok()
+}
+
+/// 查询参数中的 w（目标宽度）
+fn parse_width(uri: &str) -> Option<u32> {
+    let query = uri.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or_default();
+    query.split('&')
+        .find_map(|pair| pair.strip_prefix("w="))
+        .and_then(|w| w.parse().ok())
+        .filter(|w| *w > 0)
+}
+
+fn mime_for(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+fn not_found() -> ProtocolResult {
+    ResponseBuilder::new()
+        .status(404)
+        .mimetype("text/plain")
+        .body(b"thumbnail not found".to_vec())
+}
@@ -0,0 +1,89 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// 同时运行的最大扫描数
+pub const MAX_CONCURRENT_SCANS: usize = 2;
+
+/// 扫描队列状态（供前端展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanQueueStatus {
+    pub pending: Vec<i64>,
+    pub running: Vec<i64>,
+    pub max_concurrent: usize,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    pending: VecDeque<i64>,
+    running: Vec<i64>,
+}
+
+/// 扫描队列：限制并发扫描数，并对同一文件夹的重复扫描请求去重
+pub struct ScanQueue {
+    inner: Mutex<QueueInner>,
+    slots: Semaphore,
+}
+
+impl ScanQueue {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            inner: Mutex::new(QueueInner::default()),
+            slots: Semaphore::new(max_concurrent),
+        }
+    }
+
+    /// 将文件夹加入等待队列。如果该文件夹已在排队或正在扫描，返回错误。
+    pub fn enqueue(&self, folder_id: i64) -> Result<ScanTicket<'_>, String> {
+        let mut inner = self.inner.lock().map_err(|e| format!("锁定扫描队列失败: {}", e))?;
+        if inner.pending.contains(&folder_id) || inner.running.contains(&folder_id) {
+            return Err(format!("文件夹 {} 已在扫描队列中", folder_id));
+        }
+        inner.pending.push_back(folder_id);
+        Ok(ScanTicket { queue: self, folder_id, _permit: None })
+    }
+
+    /// 当前队列状态
+    pub fn status(&self) -> ScanQueueStatus {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        ScanQueueStatus {
+            pending: inner.pending.iter().copied().collect(),
+            running: inner.running.clone(),
+            max_concurrent: MAX_CONCURRENT_SCANS,
+        }
+    }
+
+    fn remove(&self, folder_id: i64) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.pending.retain(|id| *id != folder_id);
+        inner.running.retain(|id| *id != folder_id);
+    }
+}
+
+/// 扫描票据：持有期间文件夹处于队列中，释放时自动出队并归还并发名额
+pub struct ScanTicket<'a> {
+    queue: &'a ScanQueue,
+    folder_id: i64,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl<'a> ScanTicket<'a> {
+    /// 等待空闲的扫描名额，然后标记为运行中
+    pub async fn wait_for_slot(&mut self) -> Result<(), String> {
+        let permit = self.queue.slots.acquire().await
+            .map_err(|e| format!("获取扫描名额失败: {}", e))?;
+        self._permit = Some(permit);
+
+        let mut inner = self.queue.inner.lock().map_err(|e| format!("锁定扫描队列失败: {}", e))?;
+        inner.pending.retain(|id| *id != self.folder_id);
+        inner.running.push(self.folder_id);
+        Ok(())
+    }
+}
+
+impl Drop for ScanTicket<'_> {
+    fn drop(&mut self) {
+        self.queue.remove(self.folder_id);
+    }
+}
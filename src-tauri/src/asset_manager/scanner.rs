@@ -1,32 +1,14 @@
+use serde::Serialize;
+use std::collections::HashSet;
 use std::path::Path;
 use std::time::UNIX_EPOCH;
 use walkdir::WalkDir;
 
-/// 支持的图片格式
-pub const IMAGE_EXTENSIONS: &[&str] = &[
-    "png", "jpg", "jpeg", "gif", "bmp", "webp", "tiff", "tif",
-    "ico", "tga", "dds", "hdr", "exr", "svg",
-];
-
-/// 支持的视频格式（Phase 4）
-pub const VIDEO_EXTENSIONS: &[&str] = &[
-    "mp4", "mov", "avi", "mkv", "wmv", "webm", "flv",
-];
-
-/// 支持的专业格式（Phase 5）
-pub const PRO_EXTENSIONS: &[&str] = &[
-    "psd", "psb", "ai", "eps",        // Adobe
-    "spine", "skel",                    // Spine
-    "fbx", "obj", "gltf", "glb",      // 3D
-];
-
-/// 所有可管理的格式
-pub fn all_supported_extensions() -> Vec<&'static str> {
-    let mut exts = Vec::new();
-    exts.extend_from_slice(IMAGE_EXTENSIONS);
-    exts.extend_from_slice(VIDEO_EXTENSIONS);
-    exts.extend_from_slice(PRO_EXTENSIONS);
-    exts
+use crate::asset_manager::formats;
+
+/// 所有可管理的格式（内置 + 用户自定义，见 formats 模块）
+pub fn all_supported_extensions() -> Vec<String> {
+    formats::all_extensions()
 }
 
 /// 扫描结果
@@ -37,11 +19,127 @@ pub struct ScannedFile {
     pub ext: String,
     pub size: u64,
     pub modified: u64,
+    /// 相对扫描根目录的子目录（"/" 分隔，根目录下为空字符串）
+    pub rel_dir: String,
+}
+
+/// 单次扫描最多遍历的条目数（文件 + 目录），防止错误配置的共享目录导致扫描失控
+const MAX_SCAN_ENTRIES: usize = 1_000_000;
+
+/// 无法访问的条目最多记录的样例数
+const INACCESSIBLE_SAMPLE_LIMIT: usize = 20;
+
+/// 扫描时因权限不足、链接断开等原因跳过的条目：总数 + 部分样例 (路径, 原因)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InaccessibleReport {
+    pub count: u32,
+    pub samples: Vec<(String, String)>,
+}
+
+impl InaccessibleReport {
+    fn record(&mut self, path: String, reason: String) {
+        self.count += 1;
+        if self.samples.len() < INACCESSIBLE_SAMPLE_LIMIT {
+            self.samples.push((path, reason));
+        }
+    }
+}
+
+/// 目录扫描结果
+#[derive(Debug, Default)]
+pub struct DirectoryScan {
+    pub files: Vec<ScannedFile>,
+    pub inaccessible: InaccessibleReport,
+    /// 达到 MAX_SCAN_ENTRIES 上限而提前结束，结果不完整
+    pub truncated: bool,
+}
+
+/// 规范化要添加的管理文件夹路径：解析链接和 . / ..、去掉末尾分隔符，
+/// Windows 上统一为反斜杠并使用磁盘上实际的大小写。目录必须存在
+pub fn normalize_folder_path(path: &str) -> Result<String, String> {
+    let trimmed = path.trim();
+    let canonical = std::fs::canonicalize(trimmed)
+        .map_err(|e| format!("无法访问目录 {}: {}", trimmed, e))?;
+    if !canonical.is_dir() {
+        return Err(format!("不是目录: {}", trimmed));
+    }
+    Ok(strip_verbatim_prefix(&canonical.to_string_lossy()))
+}
+
+/// Windows 上 canonicalize 返回 \\?\C:\... 或 \\?\UNC\server\share\...，去掉前缀以便与用户看到的路径一致
+fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
 }
 
-/// 递归扫描目录，收集所有支持格式的文件
+/// 比较文件夹路径用的键（纯字符串处理，不访问磁盘）：统一分隔符、去掉末尾分隔符，Windows 上忽略大小写
+pub fn folder_path_key(path: &str) -> String {
+    let sep = std::path::MAIN_SEPARATOR;
+    let unified = if cfg!(target_os = "windows") {
+        path.trim().replace('/', "\\").to_lowercase()
+    } else {
+        path.trim().to_string()
+    };
+    unified.trim_end_matches(sep).to_string()
+}
+
+/// child_key 是否位于 parent_key 之下（均为 folder_path_key 的结果，相等时不算）
+pub fn is_path_key_within(child_key: &str, parent_key: &str) -> bool {
+    child_key.len() > parent_key.len()
+        && child_key.starts_with(parent_key)
+        && child_key[parent_key.len()..].starts_with(std::path::MAIN_SEPARATOR)
+}
+
+/// 计算文件所在目录相对扫描根目录的路径，统一用 "/" 分隔
+pub fn relative_dir(root: &Path, file_path: &Path) -> String {
+    file_path.parent()
+        .and_then(|p| p.strip_prefix(root).ok())
+        .map(|rel| {
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default()
+}
+
+/// 读取单个文件的扫描信息，不支持的格式或无法读取元数据时返回 None
+pub fn scan_file(root: &Path, file_path: &Path) -> Option<ScannedFile> {
+    let ext = file_path.extension()?.to_string_lossy().to_lowercase();
+    if !formats::is_supported(&ext) {
+        return None;
+    }
+
+    let metadata = std::fs::metadata(file_path).ok()?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let name = file_path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    Some(ScannedFile {
+        path: file_path.to_string_lossy().to_string(),
+        name,
+        ext,
+        size: metadata.len(),
+        modified,
+        rel_dir: relative_dir(root, file_path),
+    })
+}
+
+/// 递归扫描目录，收集所有支持格式的文件，无法访问的条目跳过并记录在 inaccessible 中
 /// 支持本地路径和 UNC 路径 (\\\\server\\share)
-pub fn scan_directory(dir_path: &str) -> Result<Vec<ScannedFile>, String> {
+pub fn scan_directory(dir_path: &str) -> Result<DirectoryScan, String> {
     let path = Path::new(dir_path);
     if !path.exists() {
         return Err(format!("目录不存在: {}", dir_path));
@@ -50,64 +148,115 @@ pub fn scan_directory(dir_path: &str) -> Result<Vec<ScannedFile>, String> {
         return Err(format!("不是目录: {}", dir_path));
     }
 
-    let supported = all_supported_extensions();
     let mut files = Vec::new();
+    let mut inaccessible = InaccessibleReport::default();
+    let mut truncated = false;
 
-    for entry in WalkDir::new(path)
+    // 跟随链接时，walkdir 只能发现指回祖先目录的循环；指向根目录内部或多个链接指向同一目录时
+    // 会重复扫描，这里按规范路径去重
+    let canonical_root = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut linked_dirs = HashSet::new();
+
+    for (walked, entry) in WalkDir::new(path)
         .follow_links(true)
         .into_iter()
         .filter_entry(|e| {
             // 跳过隐藏目录和 .arthub 元数据目录
             let name = e.file_name().to_string_lossy();
-            !name.starts_with('.') && name != "node_modules" && name != "__pycache__"
+            if name.starts_with('.') || name == "node_modules" || name == "__pycache__" {
+                return false;
+            }
+            if e.depth() > 0 && e.path_is_symlink() && e.file_type().is_dir() {
+                let Ok(target) = std::fs::canonicalize(e.path()) else { return false };
+                if target.starts_with(&canonical_root) || !linked_dirs.insert(target) {
+                    log::debug!("[AssetManager] 跳过重复的目录链接: {}", e.path().display());
+                    return false;
+                }
+            }
+            true
         })
+        .enumerate()
     {
+        if walked >= MAX_SCAN_ENTRIES {
+            truncated = true;
+            log::warn!("[AssetManager] 扫描 {} 超过 {} 个条目，已停止", dir_path, MAX_SCAN_ENTRIES);
+            break;
+        }
+
         let entry = match entry {
             Ok(e) => e,
-            Err(_) => continue, // 跳过无法访问的条目
+            Err(e) => {
+                // 跳过无法访问的条目
+                let entry_path = e.path().unwrap_or(path).to_string_lossy().to_string();
+                let reason = e.io_error().map(|io| io.to_string()).unwrap_or_else(|| e.to_string());
+                inaccessible.record(entry_path, reason);
+                continue;
+            }
         };
 
         if !entry.file_type().is_file() {
             continue;
         }
 
-        let file_path = entry.path();
-        let ext = match file_path.extension() {
-            Some(e) => e.to_string_lossy().to_lowercase(),
-            None => continue,
-        };
-
-        if !supported.contains(&ext.as_str()) {
-            continue;
+        if let Some(file) = scan_file(path, entry.path()) {
+            files.push(file);
         }
-
-        let metadata = match entry.metadata() {
-            Ok(m) => m,
-            Err(_) => continue,
-        };
-
-        let modified = metadata.modified()
-            .ok()
-            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-
-        let name = file_path.file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        files.push(ScannedFile {
-            path: file_path.to_string_lossy().to_string(),
-            name,
-            ext,
-            size: metadata.len(),
-            modified,
-        });
     }
 
     // 按文件名排序
     files.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
 
-    Ok(files)
+    if inaccessible.count > 0 {
+        log::warn!("[AssetManager] 扫描 {} 时有 {} 个条目无法访问", dir_path, inaccessible.count);
+    }
+
+    Ok(DirectoryScan { files, inaccessible, truncated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn equivalent_spellings_resolve_to_one_folder() {
+        let root = std::env::temp_dir().join(format!("arthub_scanner_paths_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("Art").join("sub")).unwrap();
+        let art = root.join("Art");
+        let base = art.to_string_lossy().to_string();
+        let sep = std::path::MAIN_SEPARATOR;
+
+        let mut spellings = vec![
+            base.clone(),
+            format!("{}{}", base, sep),
+            format!("  {}  ", base),
+            format!("{}{}.", base, sep),
+            format!("{}{}.{}", base, sep, sep),
+            format!("{}{}sub{}..", base, sep, sep),
+        ];
+        // Windows 上换一种大小写也是同一个目录（规范化为磁盘上实际的大小写）
+        if cfg!(target_os = "windows") {
+            let root_str = root.to_string_lossy();
+            spellings.push(format!("{}{}ART", root_str, sep));
+            spellings.push(format!("{}{}art{}", root_str, sep, sep));
+        }
+
+        let keys: HashSet<String> = spellings.iter()
+            .map(|p| folder_path_key(&normalize_folder_path(p).unwrap()))
+            .collect();
+        assert_eq!(keys.len(), 1, "{:?}", keys);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn path_key_ignores_trailing_separator_and_windows_case() {
+        let sep = std::path::MAIN_SEPARATOR;
+        assert_eq!(folder_path_key(&format!("{}art{}", sep, sep)), folder_path_key(&format!("{}art", sep)));
+        if cfg!(target_os = "windows") {
+            assert_eq!(folder_path_key("C:/Art/Textures/"), folder_path_key(r"c:\art\textures"));
+        }
+        assert!(is_path_key_within(&folder_path_key(&format!("{}art{}sub", sep, sep)), &folder_path_key(&format!("{}art", sep))));
+        assert!(!is_path_key_within(&folder_path_key(&format!("{}artwork", sep)), &folder_path_key(&format!("{}art", sep))));
+    }
 }
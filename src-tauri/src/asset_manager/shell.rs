@@ -0,0 +1,68 @@
+use std::path::Path;
+
+/// 用系统默认程序（app 为 None）或指定程序打开文件
+/// - Windows: ShellExecuteW "open"，遵循文件关联
+/// - macOS: open / open -a
+/// - Linux: xdg-open / 直接以文件为参数启动程序
+#[cfg(target_os = "windows")]
+pub fn open_with(file_path: &Path, app: Option<&Path>) -> Result<(), String> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::um::shellapi::ShellExecuteW;
+    use winapi::um::winuser::SW_SHOWNORMAL;
+
+    fn wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(Some(0)).collect()
+    }
+
+    let verb = wide(OsStr::new("open"));
+    let (target, params) = match app {
+        None => (wide(file_path.as_os_str()), None),
+        Some(app) => {
+            let arg = format!("\"{}\"", file_path.display());
+            (wide(app.as_os_str()), Some(wide(OsStr::new(&arg))))
+        }
+    };
+
+    let ret = unsafe {
+        ShellExecuteW(
+            ptr::null_mut(),
+            verb.as_ptr(),
+            target.as_ptr(),
+            params.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+
+    // ShellExecute 返回值 <= 32 表示失败
+    if ret as isize <= 32 {
+        return Err(format!("打开文件失败 (ShellExecute 错误码 {}): {}", ret as isize, file_path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn open_with(file_path: &Path, app: Option<&Path>) -> Result<(), String> {
+    let mut cmd = std::process::Command::new("open");
+    if let Some(app) = app {
+        cmd.arg("-a").arg(app);
+    }
+    cmd.arg(file_path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("打开文件失败: {}", e))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn open_with(file_path: &Path, app: Option<&Path>) -> Result<(), String> {
+    let mut cmd = match app {
+        Some(app) => std::process::Command::new(app),
+        None => std::process::Command::new("xdg-open"),
+    };
+    cmd.arg(file_path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("打开文件失败: {}", e))
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::asset_manager::convert::{OutputFailure, OutputFile};
+
+/// 超过这个大小的旁注文件不读取（正常的标签/描述文件只有几 KB）
+const MAX_SIDECAR_BYTES: u64 = 1024 * 1024;
+
+/// 单个标签名的最大长度（字符数），更长的多半是把描述误写进了标签文件
+const MAX_TAG_LEN: usize = 64;
+
+/// 从旁注文件读到的元数据
+#[derive(Debug, Clone, Default)]
+pub struct SidecarMeta {
+    pub tags: Vec<String>,
+    pub rating: Option<i32>,
+    pub caption: Option<String>,
+}
+
+impl SidecarMeta {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.rating.is_none() && self.caption.is_none()
+    }
+}
+
+/// JSON 旁注：{"tags": [...] 或 "a, b", "rating": 0-5, "caption": "..."}（也接受 description）
+#[derive(Debug, Deserialize)]
+struct JsonSidecar {
+    #[serde(default)]
+    tags: Option<JsonTags>,
+    #[serde(default)]
+    rating: Option<i32>,
+    #[serde(default, alias = "description")]
+    caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonTags {
+    List(Vec<String>),
+    Text(String),
+}
+
+/// 资产可能对应的旁注文件，按优先级排列：
+/// foo.png.json、foo.json、foo.png.txt、foo.txt（训练集常用与图片同名的 .txt 逗号分隔标签）
+fn sidecar_candidates(asset_path: &Path) -> Vec<PathBuf> {
+    let with_suffix = |suffix: &str| {
+        let mut name = asset_path.as_os_str().to_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    let mut candidates = Vec::with_capacity(4);
+    for ext in ["json", "txt"] {
+        candidates.push(with_suffix(&format!(".{}", ext)));
+        candidates.push(asset_path.with_extension(ext));
+    }
+    candidates
+}
+
+/// 读取资产旁边的第一个旁注文件；没有旁注文件或内容为空时返回 None
+pub fn read_sidecar(asset_path: &Path) -> Result<Option<SidecarMeta>, String> {
+    let Some(path) = sidecar_candidates(asset_path).into_iter().find(|p| p.is_file()) else {
+        return Ok(None);
+    };
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size > MAX_SIDECAR_BYTES {
+        return Err(format!("旁注文件过大（{} 字节），已跳过: {}", size, path.display()));
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("读取旁注文件失败 {}: {}", path.display(), e))?;
+
+    let meta = if path.extension().is_some_and(|e| e == "json") {
+        parse_json(&content).map_err(|e| format!("旁注文件格式错误 {}: {}", path.display(), e))?
+    } else {
+        SidecarMeta { tags: parse_tag_list(&content), ..Default::default() }
+    };
+    Ok(Some(meta).filter(|m| !m.is_empty()))
+}
+
+fn parse_json(content: &str) -> Result<SidecarMeta, String> {
+    let raw: JsonSidecar = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let tags = match raw.tags {
+        Some(JsonTags::List(list)) => clean_tags(list),
+        Some(JsonTags::Text(text)) => parse_tag_list(&text),
+        None => Vec::new(),
+    };
+    Ok(SidecarMeta {
+        tags,
+        rating: raw.rating.filter(|r| (1..=5).contains(r)),
+        caption: raw.caption.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+    })
+}
+
+/// 逗号或换行分隔的标签列表
+fn parse_tag_list(text: &str) -> Vec<String> {
+    clean_tags(text.split([',', '\n']).map(str::to_string).collect())
+}
+
+/// 去掉首尾空白、空标签、过长的标签，忽略大小写去重（保留第一次出现的写法）
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    tags.into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty() && t.chars().count() <= MAX_TAG_LEN && !t.chars().any(char::is_control))
+        .filter(|t| seen.insert(t.to_lowercase()))
+        .collect()
+}
+
+/// 批量导出旁注文件的结果
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct SidecarExportSummary {
+    pub written: Vec<OutputFile>,
+    /// 旁注文件已存在、没有覆盖的资产
+    pub skipped: Vec<i64>,
+    pub failed: Vec<OutputFailure>,
+}
+
+/// 导出的旁注文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    /// foo.txt：逗号分隔的标签
+    Txt,
+    /// foo.json：tags / rating / caption
+    Json,
+}
+
+impl SidecarFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "txt" => Some(SidecarFormat::Txt),
+            "json" => Some(SidecarFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            SidecarFormat::Txt => "txt",
+            SidecarFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSidecarOut<'a> {
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption: Option<&'a str>,
+}
+
+/// 在资产旁边写入与源文件同名的旁注文件（foo.png -> foo.txt / foo.json），返回写入的路径；
+/// 已存在且 overwrite 为 false 时不写入，返回 None
+pub fn write_sidecar(
+    asset_path: &Path,
+    format: SidecarFormat,
+    meta: &SidecarMeta,
+    overwrite: bool,
+) -> Result<Option<PathBuf>, String> {
+    let dest = asset_path.with_extension(format.extension());
+    if !overwrite && dest.exists() {
+        return Ok(None);
+    }
+
+    let content = match format {
+        SidecarFormat::Txt => meta.tags.join(", "),
+        SidecarFormat::Json => serde_json::to_string_pretty(&JsonSidecarOut {
+            tags: &meta.tags,
+            rating: meta.rating,
+            caption: meta.caption.as_deref(),
+        }).map_err(|e| format!("序列化旁注失败: {}", e))?,
+    };
+    crate::fs_util::write_atomic(&dest, content.as_bytes())
+        .map_err(|e| format!("写入旁注文件失败 {}: {}", dest.display(), e))?;
+    Ok(Some(dest))
+}
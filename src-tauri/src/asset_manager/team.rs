@@ -1,15 +1,28 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{BufRead, BufReader, Write};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::fs_util::write_atomic;
 
 const ARTHUB_DIR: &str = ".arthub";
 const LOCKS_DIR: &str = "locks";
 const VERSIONS_DIR: &str = "versions";
 const USERS_DIR: &str = "users";
 const PERMISSIONS_FILE: &str = "permissions.json";
+const STORE_CONFIG_FILE: &str = "store.json";
 const LOCK_TIMEOUT_SECS: u64 = 300;
+/// 锁会话自动刷新心跳的间隔，远小于 LOCK_TIMEOUT_SECS
+const HEARTBEAT_INTERVAL_SECS: u64 = 60;
+
+const ROOT_MARKER_FILE: &str = "arthub-root.json";
+/// 共享目录结构版本，结构变化时递增
+const SHARED_ROOT_VERSION: u32 = 1;
 
 fn now_secs() -> u64 {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
@@ -19,6 +32,121 @@ fn ensure_dir(path: &Path) -> Result<(), String> {
     fs::create_dir_all(path).map_err(|e| format!("mkdir fail {}: {}", path.display(), e))
 }
 
+// ==== Identity ====
+
+/// 测试时覆盖本机身份的环境变量（在一台机器上模拟多个用户）
+const IDENTITY_USER_ENV: &str = "ARTHUB_TEAM_USER";
+const IDENTITY_MACHINE_ENV: &str = "ARTHUB_TEAM_MACHINE";
+
+/// 本机身份：写入锁、版本、操作日志和在线状态的用户名/机器名。
+/// 由后端从操作系统读取，不接受前端传入，避免冒用他人身份
+#[derive(Debug, Clone, Serialize)]
+pub struct Identity {
+    pub username: String,
+    pub machine: String,
+}
+
+/// 当前用户名和主机名（首次调用时读取并缓存）
+pub fn local_identity() -> &'static Identity {
+    static IDENTITY: OnceLock<Identity> = OnceLock::new();
+    IDENTITY.get_or_init(|| {
+        let from_env = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        let username = from_env(IDENTITY_USER_ENV).unwrap_or_else(whoami::username);
+        let machine = from_env(IDENTITY_MACHINE_ENV)
+            .or_else(|| whoami::fallible::hostname().ok())
+            .unwrap_or_else(whoami::devicename);
+        if std::env::var_os(IDENTITY_USER_ENV).is_some() || std::env::var_os(IDENTITY_MACHINE_ENV).is_some() {
+            log::warn!("[Team] 使用环境变量覆盖的身份: {}@{}", username, machine);
+        }
+        Identity { username, machine }
+    })
+}
+
+// ==== Shared Root ====
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootMarker {
+    pub version: u32,
+    pub created_at: u64,
+    pub created_by: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedRootStatus {
+    /// 目录存在且可访问
+    pub reachable: bool,
+    /// 读写往返测试通过
+    pub writable: bool,
+    /// 已有 arthub-root.json 标记
+    pub initialized: bool,
+    pub version: Option<u32>,
+    /// 标记版本高于当前程序支持的版本
+    pub version_unsupported: bool,
+    pub error: Option<String>,
+}
+
+fn marker_path(root: &Path) -> PathBuf {
+    root.join(ARTHUB_DIR).join(ROOT_MARKER_FILE)
+}
+
+/// 写入临时文件再读回，确认共享目录真正可写（.arthub 不存在时在根目录测试，不创建目录）
+fn probe_writable(root: &Path) -> Result<(), String> {
+    let arthub = root.join(ARTHUB_DIR);
+    let dir = if arthub.is_dir() { arthub } else { root.to_path_buf() };
+    let probe = dir.join(format!(".probe-{}-{}", std::process::id(), now_secs()));
+    let token = format!("arthub-probe-{}", now_secs());
+    let result = fs::write(&probe, &token)
+        .and_then(|_| fs::read_to_string(&probe))
+        .map_err(|e| format!("读写测试失败: {}", e))
+        .and_then(|read| if read == token { Ok(()) } else { Err("读写测试内容不一致".to_string()) });
+    let _ = fs::remove_file(&probe);
+    result
+}
+
+/// 初始化共享目录：创建 .arthub 子目录结构并写入版本标记（已初始化的目录保留原标记）
+pub fn init_shared_root(root: &Path, user: &str) -> Result<SharedRootStatus, String> {
+    if !root.is_dir() {
+        return Err(format!("共享目录不可访问: {}", root.display()));
+    }
+    for sub in [LOCKS_DIR, VERSIONS_DIR, USERS_DIR] {
+        ensure_dir(&root.join(ARTHUB_DIR).join(sub))?;
+    }
+    probe_writable(root)?;
+    let mp = marker_path(root);
+    if !mp.exists() {
+        let marker = RootMarker { version: SHARED_ROOT_VERSION, created_at: now_secs(), created_by: user.into() };
+        write_atomic(&mp, serde_json::to_string_pretty(&marker).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    }
+    Ok(check_shared_root(root))
+}
+
+/// 检查共享目录是否可访问、可写以及初始化版本（不会创建标记文件）
+pub fn check_shared_root(root: &Path) -> SharedRootStatus {
+    let mut status = SharedRootStatus {
+        reachable: root.is_dir(), writable: false, initialized: false,
+        version: None, version_unsupported: false, error: None,
+    };
+    if !status.reachable {
+        status.error = Some(format!("共享目录不可访问: {}", root.display()));
+        return status;
+    }
+    if let Ok(c) = fs::read_to_string(marker_path(root)) {
+        match serde_json::from_str::<RootMarker>(&c) {
+            Ok(m) => {
+                status.initialized = true;
+                status.version = Some(m.version);
+                status.version_unsupported = m.version > SHARED_ROOT_VERSION;
+            }
+            Err(e) => status.error = Some(format!("标记文件损坏: {}", e)),
+        }
+    }
+    match probe_writable(root) {
+        Ok(()) => status.writable = true,
+        Err(e) => { status.error.get_or_insert(e); }
+    }
+    status
+}
+
 // ==== JSONL Action Logs ====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +196,82 @@ pub fn read_actions_since(root: &Path, since: u64) -> Result<Vec<ActionLog>, Str
     Ok(all)
 }
 
+/// Unix 秒 -> ISO-8601 UTC 时间（2024-01-02T03:04:05Z）
+fn iso8601(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // Howard Hinnant 的 civil_from_days 算法
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, m, d, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// CSV 字段转义：包含逗号、引号或换行时整体加引号，内部引号写两次
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// 合并所有用户的操作日志导出为 CSV（带 UTF-8 BOM，Excel 可直接打开中文），返回导出条数
+pub fn export_actions_csv(root: &Path, since: u64, dest: &Path) -> Result<usize, String> {
+    let actions = read_actions_since(root, since)?;
+    let mut out = String::from("\u{feff}timestamp,user,machine,action,target,data\r\n");
+    for a in &actions {
+        let row = [iso8601(a.timestamp), a.user.clone(), a.machine.clone(), a.action.clone(), a.target_path.clone(), a.data.clone()];
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push_str("\r\n");
+    }
+    if let Some(parent) = dest.parent() { ensure_dir(parent)?; }
+    write_atomic(dest, out).map_err(|e| e.to_string())?;
+    Ok(actions.len())
+}
+
+// ==== Presence ====
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presence {
+    pub user: String,
+    pub machine: String,
+    pub last_seen: u64,
+}
+
+fn presence_path(root: &Path, user: &str) -> PathBuf {
+    root.join(ARTHUB_DIR).join(USERS_DIR).join(user).join("presence.json")
+}
+
+pub fn update_presence(root: &Path, user: &str, machine: &str) -> Result<(), String> {
+    let p = presence_path(root, user);
+    ensure_dir(p.parent().unwrap())?;
+    let presence = Presence { user: user.into(), machine: machine.into(), last_seen: now_secs() };
+    write_atomic(&p, serde_json::to_string_pretty(&presence).map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+}
+
+/// within_secs 秒内更新过在线状态的用户（按最近活跃排序）
+pub fn get_active_users(root: &Path, within_secs: u64) -> Result<Vec<Presence>, String> {
+    let dir = root.join(ARTHUB_DIR).join(USERS_DIR);
+    if !dir.exists() { return Ok(vec![]); }
+    let cutoff = now_secs().saturating_sub(within_secs);
+    let mut users = vec![];
+    for entry in fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+        let p = entry.path().join("presence.json");
+        if let Ok(presence) = serde_json::from_str::<Presence>(&fs::read_to_string(&p).unwrap_or_default()) {
+            if presence.last_seen >= cutoff { users.push(presence); }
+        }
+    }
+    users.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    Ok(users)
+}
+
 // ==== File Locking ====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,7 +306,7 @@ pub fn acquire_lock(root: &Path, fp: &str, user: &str, machine: &str) -> Result<
             if now - ex.heartbeat < LOCK_TIMEOUT_SECS {
                 if ex.locked_by == user && ex.machine == machine {
                     let r = FileLock { heartbeat: now, ..ex };
-                    fs::write(&lp, serde_json::to_string_pretty(&r).unwrap()).ok();
+                    write_atomic(&lp, serde_json::to_string_pretty(&r).unwrap()).ok();
                     return Ok(true);
                 }
                 return Ok(false);
@@ -111,7 +315,7 @@ pub fn acquire_lock(root: &Path, fp: &str, user: &str, machine: &str) -> Result<
     }
     let now = now_secs();
     let lock = FileLock { file_path: fp.into(), locked_by: user.into(), machine: machine.into(), locked_at: now, heartbeat: now };
-    fs::write(&lp, serde_json::to_string_pretty(&lock).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    write_atomic(&lp, serde_json::to_string_pretty(&lock).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
@@ -132,7 +336,7 @@ pub fn refresh_heartbeat(root: &Path, fp: &str, user: &str) -> Result<bool, Stri
     if let Ok(mut lock) = serde_json::from_str::<FileLock>(&c) {
         if lock.locked_by == user {
             lock.heartbeat = now_secs();
-            fs::write(&lp, serde_json::to_string_pretty(&lock).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+            write_atomic(&lp, serde_json::to_string_pretty(&lock).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
             return Ok(true);
         }
     }
@@ -153,6 +357,79 @@ pub fn check_lock(root: &Path, fp: &str) -> LockStatus {
     }
 }
 
+// ==== Lock Sessions ====
+
+struct LockSession {
+    user: String,
+    stop: mpsc::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// 持有中的锁会话：每个会话一个后台线程，定期刷新心跳，直到结束会话或锁被他人接管
+#[derive(Default)]
+pub struct LockSessions {
+    sessions: Mutex<HashMap<(PathBuf, String), LockSession>>,
+}
+
+impl LockSessions {
+    /// 开始自动刷新心跳（同一文件已有会话时不重复启动）
+    pub fn start(&self, root: &Path, fp: &str, user: &str) {
+        let mut sessions = match self.sessions.lock() { Ok(s) => s, Err(_) => return };
+        let key = (root.to_path_buf(), fp.to_string());
+        if sessions.get(&key).map_or(false, |s| !s.handle.is_finished()) { return; }
+
+        let (stop, rx) = mpsc::channel::<()>();
+        let (root, fp_owned, user_owned) = (root.to_path_buf(), fp.to_string(), user.to_string());
+        let handle = std::thread::spawn(move || loop {
+            match rx.recv_timeout(Duration::from_secs(HEARTBEAT_INTERVAL_SECS)) {
+                Err(RecvTimeoutError::Timeout) => match refresh_heartbeat(&root, &fp_owned, &user_owned) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        log::warn!("[Team] 锁已失效，停止刷新心跳: {}", fp_owned);
+                        break;
+                    }
+                    Err(e) => log::warn!("[Team] 刷新心跳失败 {}: {}", fp_owned, e),
+                },
+                // 收到停止信号或发送端已释放
+                _ => break,
+            }
+        });
+        sessions.insert(key, LockSession { user: user.to_string(), stop, handle });
+    }
+
+    /// 结束会话并等待后台线程退出，返回是否存在该会话
+    pub fn end(&self, root: &Path, fp: &str) -> bool {
+        let session = match self.sessions.lock() {
+            Ok(mut s) => s.remove(&(root.to_path_buf(), fp.to_string())),
+            Err(_) => None,
+        };
+        match session {
+            Some(session) => { stop_session(session); true }
+            None => false,
+        }
+    }
+
+    /// 结束全部会话并释放对应的锁（应用退出时调用）
+    pub fn end_all(&self) {
+        let sessions: Vec<_> = match self.sessions.lock() {
+            Ok(mut s) => s.drain().collect(),
+            Err(_) => return,
+        };
+        for ((root, fp), session) in sessions {
+            let user = session.user.clone();
+            stop_session(session);
+            if let Err(e) = release_lock(&root, &fp, &user) {
+                log::warn!("[Team] 退出时释放锁失败 {}: {}", fp, e);
+            }
+        }
+    }
+}
+
+fn stop_session(session: LockSession) {
+    let _ = session.stop.send(());
+    let _ = session.handle.join();
+}
+
 pub fn get_all_locks(root: &Path) -> Result<Vec<FileLock>, String> {
     let dir = root.join(ARTHUB_DIR).join(LOCKS_DIR);
     if !dir.exists() { return Ok(vec![]); }
@@ -208,22 +485,125 @@ pub fn get_file_history(root: &Path, fp: &str) -> Result<Option<FileHistory>, St
     Ok(Some(serde_json::from_str(&c).map_err(|e| e.to_string())?))
 }
 
+/// history.json 的独占锁：用 create_new 原子创建锁文件，Drop 时删除。
+/// 锁文件内容是持有者的唯一标记（机器名、进程、时间），接管遗留锁时用它确认拿到的是同一个锁
+struct HistoryLock(PathBuf);
+
+/// 等待 history 锁的最长时间
+const HISTORY_LOCK_WAIT_MS: u64 = 10_000;
+/// 超过该时间的锁文件视为持有者已崩溃遗留。
+/// 持锁期间只读写 history.json 和重命名快照（快照在加锁前复制好），正常持有时间远小于该值
+const HISTORY_LOCK_STALE_SECS: u64 = 600;
+
+/// 生成本进程内唯一的标记，用于锁文件内容和临时文件名
+fn unique_token() -> String {
+    static SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let seq = SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let machine: String = local_identity().machine.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}-{}-{}-{}", machine, std::process::id(), nanos, seq)
+}
+
+impl HistoryLock {
+    fn acquire(dir: &Path) -> Result<Self, String> {
+        let p = dir.join("history.lock");
+        let start = std::time::Instant::now();
+        let mut backoff = 10u64;
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&p) {
+                Ok(mut f) => {
+                    let _ = write!(f, "{}", unique_token());
+                    return Ok(HistoryLock(p));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if Self::take_over_stale(&p) {
+                        continue;
+                    }
+                    if start.elapsed().as_millis() as u64 >= HISTORY_LOCK_WAIT_MS {
+                        let holder = fs::read_to_string(&p).unwrap_or_default();
+                        return Err(format!("Version history is busy ({}), try again later", holder.trim()));
+                    }
+                    std::thread::sleep(Duration::from_millis(backoff));
+                    backoff = (backoff * 2).min(200);
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+
+    /// 接管遗留的锁：先把锁文件改名为唯一的名字（只有一个竞争者能改名成功），
+    /// 再确认改走的正是判定为遗留的那个锁；若期间锁已被他人重新创建，则原样放回
+    fn take_over_stale(p: &Path) -> bool {
+        let stale = fs::metadata(p).and_then(|m| m.modified()).ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age.as_secs() >= HISTORY_LOCK_STALE_SECS);
+        if !stale {
+            return false;
+        }
+        let Ok(holder) = fs::read_to_string(p) else { return false };
+        let taken = p.with_file_name(format!("history.lock.stale-{}", unique_token()));
+        if fs::rename(p, &taken).is_err() {
+            // 已被其他竞争者接管
+            return false;
+        }
+        if fs::read_to_string(&taken).ok().as_deref() != Some(holder.as_str()) {
+            // 改名前锁已被他人重新创建：放回原处（hard_link 在目标已存在时失败，不会覆盖新锁）
+            let _ = fs::hard_link(&taken, p);
+            fs::remove_file(&taken).ok();
+            return false;
+        }
+        log::warn!("[Team] 接管遗留的版本历史锁 {} ({})", p.display(), holder.trim());
+        fs::remove_file(&taken).ok();
+        true
+    }
+}
+
+impl Drop for HistoryLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// 创建新版本。快照先复制到临时文件（大文件复制期间不持锁），
+/// 再持 history 锁完成 读取 history.json → 改名快照 → 追加 → 写回，
+/// 多人同时提交时版本号连续、不会互相覆盖；写回失败时删除快照
 pub fn create_version(root: &Path, fp: &str, actual: &Path, author: &str, comment: &str) -> Result<FileVersion, String> {
     let vd = ver_dir(root, fp);
     ensure_dir(&vd)?;
-    let mut hist = get_file_history(root, fp)?.unwrap_or(FileHistory {
-        file_path: fp.into(), current_version: 0, versions: vec![],
-    });
-    let nv = hist.current_version + 1;
     let ext = Path::new(fp).extension().map_or("bin".into(), |e| e.to_string_lossy().to_string());
-    let snap = format!("v{}_{}.{}", nv, now_secs(), ext);
-    fs::copy(actual, vd.join(&snap)).map_err(|e| e.to_string())?;
-    let sz = actual.metadata().map(|m| m.len()).unwrap_or(0);
-    let v = FileVersion { version: nv, author: author.into(), timestamp: now_secs(), comment: comment.into(), snapshot_name: snap, file_size: sz };
-    hist.versions.push(v.clone());
-    hist.current_version = nv;
-    fs::write(hist_path(root, fp), serde_json::to_string_pretty(&hist).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
-    Ok(v)
+    let pending = vd.join(format!(".pending-{}.{}", unique_token(), ext));
+    fs::copy(actual, &pending).map_err(|e| {
+        fs::remove_file(&pending).ok();
+        e.to_string()
+    })?;
+    let sz = fs::metadata(&pending).map(|m| m.len()).unwrap_or(0);
+
+    let result = (|| {
+        let _lock = HistoryLock::acquire(&vd)?;
+
+        let mut hist = get_file_history(root, fp)?.unwrap_or(FileHistory {
+            file_path: fp.into(), current_version: 0, versions: vec![],
+        });
+        let nv = hist.current_version + 1;
+        let snap = format!("v{}_{}.{}", nv, now_secs(), ext);
+        fs::rename(&pending, vd.join(&snap)).map_err(|e| e.to_string())?;
+        let v = FileVersion { version: nv, author: author.into(), timestamp: now_secs(), comment: comment.into(), snapshot_name: snap.clone(), file_size: sz };
+        hist.versions.push(v.clone());
+        hist.current_version = nv;
+        let written = serde_json::to_string_pretty(&hist).map_err(|e| e.to_string())
+            .and_then(|json| write_atomic(&hist_path(root, fp), json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            fs::remove_file(vd.join(&snap)).ok();
+            return Err(e);
+        }
+        Ok(v)
+    })();
+    if result.is_err() {
+        fs::remove_file(&pending).ok();
+    }
+    result
 }
 
 pub fn restore_version(root: &Path, fp: &str, ver: u32, target: &Path) -> Result<(), String> {
@@ -235,6 +615,311 @@ pub fn restore_version(root: &Path, fp: &str, ver: u32, target: &Path) -> Result
     Ok(())
 }
 
+// ==== Store Usage / Quota ====
+
+/// 版本库配置（.arthub/store.json，全团队共用）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreConfig {
+    /// 版本库软配额（字节），None 表示不限制
+    #[serde(default)]
+    pub soft_quota_bytes: Option<u64>,
+    /// 超过配额时拒绝创建新版本；为 false 时只提示
+    #[serde(default)]
+    pub refuse_over_quota: bool,
+}
+
+fn store_config_path(root: &Path) -> PathBuf {
+    root.join(ARTHUB_DIR).join(STORE_CONFIG_FILE)
+}
+
+pub fn load_store_config(root: &Path) -> Result<StoreConfig, String> {
+    let p = store_config_path(root);
+    if !p.exists() { return Ok(StoreConfig::default()); }
+    let c = fs::read_to_string(&p).map_err(|e| e.to_string())?;
+    serde_json::from_str(&c).map_err(|e| format!("版本库配置损坏: {}", e))
+}
+
+pub fn save_store_config(root: &Path, cfg: &StoreConfig) -> Result<(), String> {
+    let p = store_config_path(root);
+    ensure_dir(p.parent().unwrap())?;
+    write_atomic(&p, serde_json::to_string_pretty(cfg).map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+}
+
+/// 单个文件的版本占用
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStoreUsage {
+    pub file_path: String,
+    pub versions: u32,
+    pub bytes: u64,
+}
+
+/// 共享目录 .arthub 下各部分的占用
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreUsage {
+    pub total_bytes: u64,
+    pub versions_bytes: u64,
+    pub locks_bytes: u64,
+    /// 操作日志和在线状态（users 目录）
+    pub logs_bytes: u64,
+    /// 按占用从大到小排列
+    pub files: Vec<FileStoreUsage>,
+    pub soft_quota_bytes: Option<u64>,
+    pub over_quota: bool,
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// 统计版本库、锁和日志的占用，以及每个文件的版本占用
+pub fn get_store_usage(root: &Path) -> Result<StoreUsage, String> {
+    let base = root.join(ARTHUB_DIR);
+    let mut files = vec![];
+    let versions_dir = base.join(VERSIONS_DIR);
+    if versions_dir.exists() {
+        for entry in fs::read_dir(&versions_dir).map_err(|e| e.to_string())?.flatten() {
+            let dir = entry.path();
+            if !dir.is_dir() { continue; }
+            let hist = fs::read_to_string(dir.join("history.json")).ok()
+                .and_then(|c| serde_json::from_str::<FileHistory>(&c).ok());
+            files.push(FileStoreUsage {
+                file_path: hist.as_ref().map_or_else(|| entry.file_name().to_string_lossy().to_string(), |h| h.file_path.clone()),
+                versions: hist.map_or(0, |h| h.versions.len() as u32),
+                bytes: dir_size(&dir),
+            });
+        }
+    }
+    files.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+
+    let versions_bytes = files.iter().map(|f| f.bytes).sum();
+    let locks_bytes = dir_size(&base.join(LOCKS_DIR));
+    let logs_bytes = dir_size(&base.join(USERS_DIR));
+    let soft_quota_bytes = load_store_config(root)?.soft_quota_bytes;
+    Ok(StoreUsage {
+        total_bytes: dir_size(&base),
+        versions_bytes,
+        locks_bytes,
+        logs_bytes,
+        files,
+        soft_quota_bytes,
+        over_quota: soft_quota_bytes.is_some_and(|q| versions_bytes > q),
+    })
+}
+
+/// 版本库超出软配额的提示（"team-store-quota-warning" 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaWarning {
+    pub shared_root: String,
+    /// 加上本次快照后的版本库占用
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+/// 检查再存入 incoming 字节后版本库是否超出软配额：未超出返回 None；
+/// 超出且配置为拒绝时返回错误，否则返回提示
+pub fn check_store_quota(root: &Path, incoming: u64) -> Result<Option<QuotaWarning>, String> {
+    let cfg = load_store_config(root)?;
+    let Some(quota_bytes) = cfg.soft_quota_bytes else { return Ok(None) };
+    let used_bytes = dir_size(&root.join(ARTHUB_DIR).join(VERSIONS_DIR)) + incoming;
+    if used_bytes <= quota_bytes {
+        return Ok(None);
+    }
+    if cfg.refuse_over_quota {
+        return Err(format!(
+            "版本库已超出配额（{} / {} 字节），无法创建新版本",
+            used_bytes, quota_bytes
+        ));
+    }
+    Ok(Some(QuotaWarning { shared_root: root.to_string_lossy().to_string(), used_bytes, quota_bytes }))
+}
+
+// ==== Relink ====
+
+/// team_relink_history 的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct RelinkResult {
+    /// 版本历史已迁移到新路径
+    pub history_moved: bool,
+    /// 有效的锁已迁移到新路径
+    pub lock_moved: bool,
+}
+
+/// 文件在共享目录内改名/移动后，把旧路径的版本历史和锁迁移到新路径。
+/// 锁和历史都按路径哈希存放，不迁移的话旧锁会残留，新路径可被他人重新锁定，历史也会断开。
+/// 旧路径的锁被他人持有、或新路径已有历史/他人的锁时拒绝迁移，不做任何修改
+pub fn relink_history(root: &Path, old_fp: &str, new_fp: &str, user: &str) -> Result<RelinkResult, String> {
+    if old_fp == new_fp {
+        return Ok(RelinkResult { history_moved: false, lock_moved: false });
+    }
+
+    // 先检查锁，全部通过后再动文件
+    let old_lock = active_lock(root, old_fp);
+    if let Some(ref l) = old_lock {
+        if l.locked_by != user {
+            return Err(format!("{} 正被 {} 锁定，无法迁移", old_fp, l.locked_by));
+        }
+    }
+    if let Some(l) = active_lock(root, new_fp) {
+        if l.locked_by != user {
+            return Err(format!("{} 正被 {} 锁定，无法迁移", new_fp, l.locked_by));
+        }
+    }
+
+    let old_vd = ver_dir(root, old_fp);
+    let new_vd = ver_dir(root, new_fp);
+    let history_moved = if hist_path(root, old_fp).exists() {
+        if hist_path(root, new_fp).exists() {
+            return Err(format!("{} 已有版本历史，无法合并", new_fp));
+        }
+        let mut lock = HistoryLock::acquire(&old_vd)?;
+        fs::rename(&old_vd, &new_vd).map_err(|e| format!("迁移版本目录失败: {}", e))?;
+        // history.lock 随目录一起移动，Drop 时从新位置删除
+        lock.0 = new_vd.join("history.lock");
+
+        let mut hist = get_file_history(root, new_fp)?
+            .ok_or_else(|| "迁移后找不到版本历史".to_string())?;
+        hist.file_path = new_fp.into();
+        write_atomic(&hist_path(root, new_fp), serde_json::to_string_pretty(&hist).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        true
+    } else {
+        false
+    };
+
+    let lock_moved = match old_lock {
+        Some(l) => {
+            let moved = FileLock { file_path: new_fp.into(), ..l };
+            let lp = lock_fp(root, new_fp);
+            ensure_dir(lp.parent().unwrap())?;
+            write_atomic(&lp, serde_json::to_string_pretty(&moved).map_err(|e| e.to_string())?)
+                .map_err(|e| e.to_string())?;
+            fs::remove_file(lock_fp(root, old_fp)).ok();
+            true
+        }
+        None => false,
+    };
+
+    Ok(RelinkResult { history_moved, lock_moved })
+}
+
+/// 未过期的锁
+fn active_lock(root: &Path, fp: &str) -> Option<FileLock> {
+    let l = serde_json::from_str::<FileLock>(&fs::read_to_string(lock_fp(root, fp)).ok()?).ok()?;
+    (now_secs() - l.heartbeat < LOCK_TIMEOUT_SECS).then_some(l)
+}
+
+// ==== Version Diff ====
+
+/// 超过该大小的文件不做逐行对比，按二进制分块比较
+const DIFF_TEXT_MAX_BYTES: u64 = 8 * 1024 * 1024;
+/// 二进制比较最多读取的字节数，超出部分只计入大小变化
+const DIFF_BINARY_MAX_BYTES: u64 = 256 * 1024 * 1024;
+const DIFF_BLOCK_SIZE: usize = 4096;
+const DIFF_TIMEOUT_SECS: u64 = 2;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionDiff {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub from_size: u64,
+    pub to_size: u64,
+    pub size_delta: i64,
+    pub is_text: bool,
+    /// 文本文件：新增/删除的行数
+    pub lines_added: Option<usize>,
+    pub lines_removed: Option<usize>,
+    /// 二进制文件：按 4KB 分块比较，内容不同的块数 / 总块数
+    pub changed_blocks: Option<u64>,
+    pub total_blocks: Option<u64>,
+    /// 文件过大或对比超时，结果只是估算
+    pub truncated: bool,
+}
+
+fn snapshot_path(root: &Path, fp: &str, hist: &FileHistory, ver: u32) -> Result<PathBuf, String> {
+    let v = hist.versions.iter().find(|v| v.version == ver).ok_or(format!("Version {} not found", ver))?;
+    let snap = ver_dir(root, fp).join(&v.snapshot_name);
+    if !snap.exists() { return Err(format!("Snapshot missing for version {}", ver)); }
+    Ok(snap)
+}
+
+/// 较小且为 UTF-8、不含 NUL 的文件按文本处理
+fn read_text(p: &Path, size: u64) -> Option<String> {
+    if size > DIFF_TEXT_MAX_BYTES { return None; }
+    let bytes = fs::read(p).ok()?;
+    if bytes.contains(&0) { return None; }
+    String::from_utf8(bytes).ok()
+}
+
+/// 逐块比较两个文件，返回 (不同的块数, 总块数, 是否因过大被截断)
+fn diff_blocks(a: &Path, b: &Path) -> Result<(u64, u64, bool), String> {
+    use std::io::Read;
+    let mut ra = BufReader::new(fs::File::open(a).map_err(|e| e.to_string())?);
+    let mut rb = BufReader::new(fs::File::open(b).map_err(|e| e.to_string())?);
+    let (mut ba, mut bb) = (vec![0u8; DIFF_BLOCK_SIZE], vec![0u8; DIFF_BLOCK_SIZE]);
+    let (mut changed, mut total, mut read) = (0u64, 0u64, 0u64);
+    loop {
+        if read >= DIFF_BINARY_MAX_BYTES { return Ok((changed, total, true)); }
+        let na = read_block(&mut ra, &mut ba).map_err(|e| e.to_string())?;
+        let nb = read_block(&mut rb, &mut bb).map_err(|e| e.to_string())?;
+        if na == 0 && nb == 0 { return Ok((changed, total, false)); }
+        total += 1;
+        if ba[..na] != bb[..nb] { changed += 1; }
+        read += na.max(nb) as u64;
+    }
+
+    fn read_block(r: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            match r.read(&mut buf[n..])? {
+                0 => break,
+                k => n += k,
+            }
+        }
+        Ok(n)
+    }
+}
+
+pub fn version_diff(root: &Path, fp: &str, from_ver: u32, to_ver: u32) -> Result<VersionDiff, String> {
+    let hist = get_file_history(root, fp)?.ok_or("No history".to_string())?;
+    let (a, b) = (snapshot_path(root, fp, &hist, from_ver)?, snapshot_path(root, fp, &hist, to_ver)?);
+    let from_size = a.metadata().map_err(|e| e.to_string())?.len();
+    let to_size = b.metadata().map_err(|e| e.to_string())?.len();
+    let mut diff = VersionDiff {
+        from_version: from_ver, to_version: to_ver, from_size, to_size,
+        size_delta: to_size as i64 - from_size as i64, is_text: false,
+        lines_added: None, lines_removed: None, changed_blocks: None, total_blocks: None, truncated: false,
+    };
+
+    if let (Some(ta), Some(tb)) = (read_text(&a, from_size), read_text(&b, to_size)) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(DIFF_TIMEOUT_SECS);
+        let text_diff = similar::TextDiff::configure().deadline(deadline).diff_lines(&ta, &tb);
+        let (mut added, mut removed) = (0, 0);
+        for change in text_diff.iter_all_changes() {
+            match change.tag() {
+                similar::ChangeTag::Insert => added += 1,
+                similar::ChangeTag::Delete => removed += 1,
+                similar::ChangeTag::Equal => {}
+            }
+        }
+        diff.is_text = true;
+        diff.lines_added = Some(added);
+        diff.lines_removed = Some(removed);
+        diff.truncated = std::time::Instant::now() >= deadline;
+    } else {
+        let (changed, total, truncated) = diff_blocks(&a, &b)?;
+        diff.changed_blocks = Some(changed);
+        diff.total_blocks = Some(total);
+        diff.truncated = truncated;
+    }
+    Ok(diff)
+}
+
 // ==== Permissions ====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -269,7 +954,7 @@ pub fn load_permissions(root: &Path) -> Result<PermissionsConfig, String> {
 pub fn save_permissions(root: &Path, cfg: &PermissionsConfig) -> Result<(), String> {
     let p = perm_path(root);
     ensure_dir(p.parent().unwrap())?;
-    fs::write(&p, serde_json::to_string_pretty(cfg).map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+    write_atomic(&p, serde_json::to_string_pretty(cfg).map_err(|e| e.to_string())?).map_err(|e| e.to_string())
 }
 
 pub fn get_user_role(cfg: &PermissionsConfig, user: &str, proj: Option<&str>) -> String {
@@ -312,3 +997,95 @@ pub fn set_user_permission(root: &Path, user: &str, role: &str, proj: Option<&st
     }
     save_permissions(root, &cfg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("arthub_team_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn make_stale(p: &Path) {
+        let old = SystemTime::now() - Duration::from_secs(HISTORY_LOCK_STALE_SECS + 60);
+        fs::File::options().write(true).open(p).unwrap().set_modified(old).unwrap();
+    }
+
+    #[test]
+    fn concurrent_create_version_keeps_versions_contiguous() {
+        let root = temp_root("concurrent");
+        let source = root.join("scene.psd");
+        fs::write(&source, vec![7u8; 64 * 1024]).unwrap();
+
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = (0..2).map(|t| {
+            let (root, source, barrier) = (root.clone(), source.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                barrier.wait();
+                (0..10).map(|i| {
+                    create_version(&root, "art/scene.psd", &source, &format!("user{}", t), &format!("c{}", i))
+                        .unwrap()
+                        .version
+                }).collect::<Vec<_>>()
+            })
+        }).collect();
+        let mut versions: Vec<u32> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+        versions.sort();
+        assert_eq!(versions, (1..=20).collect::<Vec<_>>());
+
+        let hist = get_file_history(&root, "art/scene.psd").unwrap().unwrap();
+        assert_eq!(hist.current_version, 20);
+        assert_eq!(hist.versions.len(), 20);
+        let vd = ver_dir(&root, "art/scene.psd");
+        for v in &hist.versions {
+            assert!(vd.join(&v.snapshot_name).exists());
+        }
+        // 临时快照和锁文件都已清理
+        let leftovers: Vec<_> = fs::read_dir(&vd).unwrap().flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.starts_with(".pending-") || n.starts_with("history.lock"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn stale_history_lock_is_taken_over_once() {
+        let root = temp_root("stale");
+        let p = root.join("history.lock");
+        fs::write(&p, "crashed-host-1-0-0").unwrap();
+        make_stale(&p);
+
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = (0..2).map(|_| {
+            let (p, barrier) = (p.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                barrier.wait();
+                HistoryLock::take_over_stale(&p)
+            })
+        }).collect();
+        let taken = handles.into_iter().map(|h| h.join().unwrap()).filter(|t| *t).count();
+        assert_eq!(taken, 1);
+
+        // 遗留锁已被移走，可以正常加锁
+        let lock = HistoryLock::acquire(&root).unwrap();
+        assert!(p.exists());
+        drop(lock);
+        assert!(!p.exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn fresh_history_lock_is_not_taken_over() {
+        let root = temp_root("fresh");
+        let p = root.join("history.lock");
+        fs::write(&p, "other-host-1-0-0").unwrap();
+        assert!(!HistoryLock::take_over_stale(&p));
+        assert_eq!(fs::read_to_string(&p).unwrap(), "other-host-1-0-0");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
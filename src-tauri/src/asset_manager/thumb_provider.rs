@@ -0,0 +1,255 @@
+use image::metadata::Orientation;
+use image::{DynamicImage, GenericImageView, ImageDecoder};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use crate::asset_manager::ffmpeg::FrameTimestamp;
+use crate::asset_manager::{ffmpeg, formats, thumbnail};
+
+/// 默认允许解码的最大像素数（约 2.7 亿，16384×16384）；超过的视为异常文件（如解压炸弹），不解码
+pub const DEFAULT_PIXEL_BUDGET: u64 = 16384 * 16384;
+
+/// 当前像素上限（设置项 thumbnail.max_pixels，启动时从数据库加载）
+static PIXEL_BUDGET: AtomicU64 = AtomicU64::new(DEFAULT_PIXEL_BUDGET);
+
+pub fn pixel_budget() -> u64 {
+    PIXEL_BUDGET.load(Ordering::Relaxed)
+}
+
+pub fn set_pixel_budget(pixels: u64) {
+    PIXEL_BUDGET.store(pixels, Ordering::Relaxed);
+}
+
+/// 解码器单次分配内存上限（image crate 的 limits，默认 512MB 对 16K 的 16 位图不够）
+const MAX_DECODE_ALLOC: u64 = 2 * 1024 * 1024 * 1024;
+
+/// 单个文件解码的超时时间
+const DECODE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 检查尺寸是否超过解码上限
+pub fn check_pixel_limit(width: u32, height: u32) -> Result<(), String> {
+    if width as u64 * height as u64 > pixel_budget() {
+        return Err(format!("图像尺寸过大（{}×{}），跳过解码", width, height));
+    }
+    Ok(())
+}
+
+/// 缩略图解码器：把某类源文件渲染为图像，供缩略图、预览和格式转换使用
+pub trait ThumbnailProvider: Send + Sync {
+    /// 日志中显示的名称
+    fn name(&self) -> &'static str;
+
+    /// 是否能处理该扩展名（小写、不带点）
+    fn supports(&self, ext: &str) -> bool;
+
+    /// 渲染为图像。max_width 只是提示，返回的图像可以更大，由调用方负责缩放；
+    /// 返回图像的尺寸视为源文件的原始尺寸
+    fn render(&self, path: &Path, max_width: u32) -> Result<DynamicImage, String>;
+
+    /// 源文件原始尺寸（判断缓存的缩略图是否可用时调用）；默认完整渲染后取尺寸
+    fn dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        self.render(path, u32::MAX).ok().map(|img| img.dimensions())
+    }
+
+    /// 不解码、只读文件头得到的声明尺寸，用于解码前检查像素上限；无法廉价获取时返回 None
+    fn header_dimensions(&self, _path: &Path) -> Option<(u32, u32)> {
+        None
+    }
+}
+
+/// 声明尺寸超过像素上限时返回该尺寸（扫描时直接跳过，不尝试解码）
+pub fn exceeds_pixel_budget(path: &Path, ext: &str) -> Option<(u32, u32)> {
+    find_provider(ext)?
+        .header_dimensions(path)
+        .filter(|(w, h)| check_pixel_limit(*w, *h).is_err())
+}
+
+/// 普通图片（image crate），按文件头识别格式，兼容用户注册的自定义图片扩展名
+pub struct RasterProvider;
+
+impl ThumbnailProvider for RasterProvider {
+    fn name(&self) -> &'static str {
+        "raster"
+    }
+
+    fn supports(&self, ext: &str) -> bool {
+        formats::can_thumbnail(ext)
+    }
+
+    fn render(&self, path: &Path, _max_width: u32) -> Result<DynamicImage, String> {
+        // 先只读文件头检查尺寸，避免为伪造了超大尺寸的文件分配内存
+        if let Ok((w, h)) = image::image_dimensions(path) {
+            check_pixel_limit(w, h)?;
+        }
+
+        let mut reader = image::ImageReader::open(path)
+            .and_then(|r| r.with_guessed_format())
+            .map_err(|e| format!("无法打开图片 {}: {}", path.display(), e))?;
+        let mut limits = image::Limits::default();
+        limits.max_alloc = Some(MAX_DECODE_ALLOC);
+        reader.limits(limits);
+
+        // 按 EXIF 方向旋转/翻转（手机照片常见），得到的尺寸即为显示尺寸
+        let open_err = |e: image::ImageError| format!("无法打开图片 {}: {}", path.display(), e);
+        let mut decoder = reader.into_decoder().map_err(open_err)?;
+        let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+        let mut img = DynamicImage::from_decoder(decoder).map_err(open_err)?;
+        img.apply_orientation(orientation);
+        Ok(img)
+    }
+
+    fn dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        // 只读文件头；EXIF 方向为 90°/270° 时宽高互换，与 render 的结果一致
+        let mut decoder = image::ImageReader::open(path)
+            .and_then(|r| r.with_guessed_format()).ok()?
+            .into_decoder().ok()?;
+        let (w, h) = decoder.dimensions();
+        let swapped = matches!(
+            decoder.orientation().unwrap_or(Orientation::NoTransforms),
+            Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Rotate90FlipH | Orientation::Rotate270FlipH
+        );
+        Some(if swapped { (h, w) } else { (w, h) })
+    }
+
+    fn header_dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        image::image_dimensions(path).ok()
+    }
+}
+
+/// PSD 合成图
+pub struct PsdProvider;
+
+impl ThumbnailProvider for PsdProvider {
+    fn name(&self) -> &'static str {
+        "psd"
+    }
+
+    fn supports(&self, ext: &str) -> bool {
+        ext == "psd"
+    }
+
+    fn render(&self, path: &Path, _max_width: u32) -> Result<DynamicImage, String> {
+        thumbnail::generate_psd_image(&path.to_string_lossy())
+    }
+
+    fn dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        thumbnail::get_psd_dimensions(&path.to_string_lossy())
+    }
+
+    fn header_dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        self.dimensions(path)
+    }
+}
+
+/// glTF / GLB：提取内嵌的纹理作为预览
+pub struct GltfProvider;
+
+impl ThumbnailProvider for GltfProvider {
+    fn name(&self) -> &'static str {
+        "gltf"
+    }
+
+    fn supports(&self, ext: &str) -> bool {
+        matches!(ext, "gltf" | "glb")
+    }
+
+    fn render(&self, path: &Path, _max_width: u32) -> Result<DynamicImage, String> {
+        thumbnail::generate_gltf_image(&path.to_string_lossy())
+    }
+}
+
+/// 视频：用 FFmpeg 按截帧位置取一帧（默认时长的 10% 处）
+pub struct VideoProvider {
+    ffmpeg: PathBuf,
+    ffprobe: Option<PathBuf>,
+}
+
+impl VideoProvider {
+    pub fn new(ffmpeg: PathBuf, ffprobe: Option<PathBuf>) -> Self {
+        VideoProvider { ffmpeg, ffprobe }
+    }
+
+    /// 在应用数据目录或 PATH 中查找 FFmpeg，找不到时返回 None
+    pub fn detect(app_data_dir: &Path) -> Option<Self> {
+        let ffmpeg = ffmpeg::get_ffmpeg_path(app_data_dir)?;
+        Some(VideoProvider::new(ffmpeg, ffmpeg::get_ffprobe_path(app_data_dir)))
+    }
+}
+
+impl ThumbnailProvider for VideoProvider {
+    fn name(&self) -> &'static str {
+        "video"
+    }
+
+    fn supports(&self, ext: &str) -> bool {
+        thumbnail::is_video(ext)
+    }
+
+    fn render(&self, path: &Path, _max_width: u32) -> Result<DynamicImage, String> {
+        ffmpeg::grab_frame(&self.ffmpeg, self.ffprobe.as_deref(), path, FrameTimestamp::default())
+    }
+
+    fn dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        match &self.ffprobe {
+            Some(ffprobe) => thumbnail::get_media_info(ffprobe, &path.to_string_lossy())
+                .map(|(w, h, _)| (w, h))
+                .filter(|(w, h)| *w > 0 && *h > 0),
+            None => self.render(path, u32::MAX).ok().map(|img| img.dimensions()),
+        }
+    }
+}
+
+/// 在独立线程中渲染，超过 DECODE_TIMEOUT 或解码线程 panic 时返回错误（扫描时记为缩略图失败）。
+/// 超时的线程无法强制结束，会在后台自行跑完
+pub fn render_with_timeout(
+    provider: Arc<dyn ThumbnailProvider>,
+    path: &Path,
+    max_width: u32,
+) -> Result<DynamicImage, String> {
+    let (tx, rx) = mpsc::channel();
+    let owned = path.to_path_buf();
+    std::thread::Builder::new()
+        .name("thumb-decode".to_string())
+        .spawn(move || {
+            let _ = tx.send(provider.render(&owned, max_width));
+        })
+        .map_err(|e| format!("启动解码线程失败: {}", e))?;
+
+    match rx.recv_timeout(DECODE_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(format!(
+            "解码超时（{} 秒）: {}",
+            DECODE_TIMEOUT.as_secs(),
+            path.display()
+        )),
+        Err(RecvTimeoutError::Disconnected) => Err(format!("解码失败（解码线程异常退出）: {}", path.display())),
+    }
+}
+
+/// 已注册的提供者，按顺序尝试；后注册的排在前面，可以覆盖内置实现
+fn registry() -> &'static RwLock<Vec<Arc<dyn ThumbnailProvider>>> {
+    static PROVIDERS: OnceLock<RwLock<Vec<Arc<dyn ThumbnailProvider>>>> = OnceLock::new();
+    PROVIDERS.get_or_init(|| {
+        RwLock::new(vec![
+            Arc::new(PsdProvider),
+            Arc::new(GltfProvider),
+            Arc::new(RasterProvider),
+        ])
+    })
+}
+
+/// 注册自定义提供者（优先于已有的提供者）
+pub fn register_provider(provider: Arc<dyn ThumbnailProvider>) {
+    let mut providers = registry().write().unwrap_or_else(|e| e.into_inner());
+    log::info!("[AssetManager] 注册缩略图提供者: {}", provider.name());
+    providers.insert(0, provider);
+}
+
+/// 查找能处理该扩展名的提供者
+pub fn find_provider(ext: &str) -> Option<Arc<dyn ThumbnailProvider>> {
+    let providers = registry().read().unwrap_or_else(|e| e.into_inner());
+    providers.iter().find(|p| p.supports(ext)).cloned()
+}
@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tauri::{AppHandle, Manager};
+
+use crate::asset_manager::commands::scan_thumbnail;
+use crate::asset_manager::db::{self, AssetManagerState, ThumbWorkerProgress};
+use crate::asset_manager::thumbnail;
+use crate::error::ArtHubError;
+
+/// 每次从数据库取出的待生成资产数
+const WORKER_BATCH: u32 = 50;
+
+/// 优先队列最多保留的资产数（只需要覆盖当前可见的几屏）
+const MAX_PRIORITY: usize = 500;
+
+/// 后台缩略图队列：开启时扫描只写入元数据（thumb_status = pending），由工作线程按 id 顺序生成图片缩略图，
+/// 每处理一个发送 "asset-thumbnail-worker-progress" 事件，队列清空后发送 idle 事件并退出线程。
+/// 视频/音频需要 FFmpeg，仍由 asset_generate_missing_media_thumbnails 处理
+pub struct ThumbWorker {
+    enabled: AtomicBool,
+    running: Arc<AtomicBool>,
+    /// 线程运行期间又有新任务（kick 时线程已在运行）
+    dirty: Arc<AtomicBool>,
+    /// 插队的资产（当前可见的），先于按 id 顺序的队列处理
+    priority: Mutex<VecDeque<i64>>,
+}
+
+impl ThumbWorker {
+    pub fn new(enabled: bool) -> Self {
+        ThumbWorker {
+            enabled: AtomicBool::new(enabled),
+            running: Arc::new(AtomicBool::new(false)),
+            dirty: Arc::new(AtomicBool::new(false)),
+            priority: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// 把资产移到队列最前面（保持传入顺序，已在优先队列中的先移除再插入）
+    pub fn prioritize(&self, asset_ids: &[i64]) {
+        let mut priority = self.priority.lock().unwrap_or_else(|e| e.into_inner());
+        priority.retain(|id| !asset_ids.contains(id));
+        for id in asset_ids.iter().rev() {
+            priority.push_front(*id);
+        }
+        priority.truncate(MAX_PRIORITY);
+    }
+
+    fn pop_priority(&self) -> Option<i64> {
+        self.priority.lock().unwrap_or_else(|e| e.into_inner()).pop_front()
+    }
+
+    fn has_priority(&self) -> bool {
+        !self.priority.lock().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 关闭后工作线程处理完当前文件即退出，已排队的资产保持 pending
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// 有新的待生成资产时调用：没有工作线程时启动一个，否则让正在运行的线程处理完后再查一遍
+    pub fn kick(&self, app: &AppHandle) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.dirty.store(true, Ordering::SeqCst);
+        if self.running.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return;
+        }
+
+        let app = app.clone();
+        let (running, dirty) = (self.running.clone(), self.dirty.clone());
+        let spawned = std::thread::Builder::new()
+            .name("thumb-worker".to_string())
+            .spawn(move || {
+                loop {
+                    dirty.store(false, Ordering::SeqCst);
+                    run_queue(&app);
+                    running.store(false, Ordering::SeqCst);
+                    // 退出前检查是否有线程运行期间加入、但没被处理到的任务
+                    if !dirty.load(Ordering::SeqCst)
+                        || running.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err()
+                    {
+                        break;
+                    }
+                }
+                let _ = app.emit_all("asset-thumbnail-worker-progress", ThumbWorkerProgress {
+                    asset_id: 0,
+                    success: true,
+                    done: 0,
+                    idle: true,
+                });
+            });
+        if let Err(e) = spawned {
+            log::error!("[AssetManager] 启动缩略图队列失败: {}", e);
+            self.running.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// 处理所有待生成的图片缩略图，直到一轮完整遍历都没有可处理的资产
+fn run_queue(app: &AppHandle) {
+    let state = app.state::<AssetManagerState>();
+    let mut after = 0i64;
+    let mut done = 0u32;
+    let mut handled_in_pass = false;
+
+    'queue: while state.thumb_worker.is_enabled() {
+        // 插队的资产：只处理仍为 pending 的
+        if let Some(asset_id) = state.thumb_worker.pop_priority() {
+            match state.db.read().and_then(|conn| db::get_pending_thumbnail(&conn, asset_id)) {
+                Ok(Some((file_path, ext))) => {
+                    handled_in_pass |= generate_one(app, &state, asset_id, &file_path, &ext, &mut done);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("[AssetManager] 读取资产失败 (asset {}): {}", asset_id, e),
+            }
+            continue;
+        }
+
+        let batch = match state.db.read()
+            .and_then(|conn| db::next_pending_thumbnails(&conn, after, WORKER_BATCH))
+        {
+            Ok(batch) => batch,
+            Err(e) => {
+                log::error!("[AssetManager] 读取缩略图队列失败: {}", e);
+                return;
+            }
+        };
+
+        if batch.is_empty() {
+            // 到达末尾：本轮处理过资产时从头再查一遍（期间可能有新扫描的文件）
+            if !handled_in_pass {
+                break;
+            }
+            after = 0;
+            handled_in_pass = false;
+            continue;
+        }
+
+        for (asset_id, file_path, ext) in batch {
+            if !state.thumb_worker.is_enabled() {
+                return;
+            }
+            after = asset_id;
+            handled_in_pass |= generate_one(app, &state, asset_id, &file_path, &ext, &mut done);
+            // 有插队的资产时中断本批，下次从 after 之后继续
+            if state.thumb_worker.has_priority() {
+                continue 'queue;
+            }
+        }
+    }
+}
+
+/// 生成一个资产的缩略图并写回数据库；不是可直接生成缩略图的格式（视频等）时跳过并返回 false
+fn generate_one(
+    app: &AppHandle,
+    state: &AssetManagerState,
+    asset_id: i64,
+    file_path: &str,
+    ext: &str,
+    done: &mut u32,
+) -> bool {
+    if !thumbnail::can_generate_thumbnail(ext) {
+        return false;
+    }
+
+    let use_placeholders = state.placeholder_thumbnails.load(Ordering::Relaxed);
+    let thumb = scan_thumbnail(file_path, ext, &state.thumb_dir, use_placeholders);
+    let success = thumb.succeeded();
+    let updated = state.db.lock().map_err(ArtHubError::db_lock).and_then(|conn| {
+        db::update_asset_thumbnail(
+            &conn, asset_id, &thumb.thumb_path, thumb.width, thumb.height, thumb.status(ext),
+        )
+    });
+    if let Err(e) = updated {
+        log::warn!("[AssetManager] 写入缩略图失败 (asset {}): {}", asset_id, e);
+    }
+
+    *done += 1;
+    let _ = app.emit_all("asset-thumbnail-worker-progress", ThumbWorkerProgress {
+        asset_id,
+        success,
+        done: *done,
+        idle: false,
+    });
+    true
+}
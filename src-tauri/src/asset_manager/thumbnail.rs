@@ -1,34 +1,14 @@
 use image::{GenericImageView, ImageFormat, RgbaImage};
+use serde::Serialize;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, SystemTime};
 
-/// 可生成缩略图的图片格式（image crate 能解码的）
-const DECODABLE_EXTENSIONS: &[&str] = &[
-    "png", "jpg", "jpeg", "gif", "bmp", "webp", "tiff", "tif", "ico", "tga", "hdr", "exr",
-];
-
-/// PSD 格式
-const PSD_EXTENSIONS: &[&str] = &["psd"];
-
-/// 视频格式（需要 FFmpeg）
-const VIDEO_EXTENSIONS: &[&str] = &[
-    "mp4", "avi", "mov", "mkv", "wmv", "flv", "webm", "m4v", "mpg", "mpeg",
-];
-
-/// 音频格式
-const AUDIO_EXTENSIONS: &[&str] = &[
-    "mp3", "wav", "ogg", "flac", "aac", "wma", "m4a", "opus",
-];
-
-/// 3D 模型格式
-const MODEL_3D_EXTENSIONS: &[&str] = &[
-    "fbx", "obj", "gltf", "glb", "blend", "3ds", "dae", "stl",
-];
-
-/// Spine 动画格式
-const SPINE_EXTENSIONS: &[&str] = &["spine", "skel", "atlas"];
+use crate::asset_manager::thumb_provider::{self, RasterProvider, ThumbnailProvider, VideoProvider};
+use crate::asset_manager::{formats, placeholder};
 
 /// 生成稳定的路径哈希作为缩略图文件名
 fn path_hash(path: &str) -> String {
@@ -37,65 +17,242 @@ fn path_hash(path: &str) -> String {
     format!("{:016x}", hasher.finish())
 }
 
+/// 缩略图输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbFormat {
+    Jpeg,
+    Webp,
+}
+
+impl ThumbFormat {
+    /// 缩略图文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbFormat::Jpeg => "jpg",
+            ThumbFormat::Webp => "webp",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(ThumbFormat::Jpeg),
+            "webp" => Some(ThumbFormat::Webp),
+            _ => None,
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            ThumbFormat::Jpeg => ImageFormat::Jpeg,
+            ThumbFormat::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+/// 源文件在这段时间内修改过时，生成缩略图前先确认已经写完
+const RECENT_WRITE_WINDOW: Duration = Duration::from_secs(10);
+
+/// 两次检查文件大小/修改时间的间隔
+const STABILITY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 最多检查次数（约 5 秒），仍在变化时放弃，本次记为缩略图失败
+const STABILITY_MAX_CHECKS: u32 = 10;
+
+/// 刚写入的文件解码失败时，等待这么久再重试一次
+const DECODE_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// 所有可能出现的缩略图扩展名（清理时逐一删除）
+const THUMB_EXTENSIONS: &[&str] = &["jpg", "png", "webp"];
+
+/// 按需缩放的缩略图缓存子目录：resized/<缩略图文件名>/<宽度>.<扩展名>
+const RESIZED_DIR: &str = "resized";
+
+/// 按需缩放允许请求的最大宽度
+pub const MAX_RESIZE_WIDTH: u32 = 1024;
+
+/// 当前缩略图格式（0 = JPEG，1 = WebP），默认 JPEG 以保持兼容
+static THUMB_FORMAT: AtomicU8 = AtomicU8::new(0);
+
+/// 获取当前缩略图格式
+pub fn thumb_format() -> ThumbFormat {
+    match THUMB_FORMAT.load(Ordering::Relaxed) {
+        1 => ThumbFormat::Webp,
+        _ => ThumbFormat::Jpeg,
+    }
+}
+
+/// 切换缩略图格式（设置由调用方持久化到 settings 表）。
+/// 已有缩略图不会立即转换，下次扫描或重新生成时按新格式输出并删除旧格式文件。
+pub fn set_thumb_format(format: ThumbFormat) {
+    THUMB_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// 缩略图按哈希前两位分子目录存放（ab/abcdef....jpg），避免单目录文件过多
+fn hashed_thumb_path(thumb_dir: &Path, hash: &str, ext: &str) -> PathBuf {
+    thumb_dir.join(&hash[..2]).join(format!("{}.{}", hash, ext))
+}
+
+/// 删除某个源文件除 keep 以外所有格式的缩略图（切换格式后清理旧文件）
+fn remove_thumb_variants(thumb_dir: &Path, file_path: &str, keep: Option<&str>) {
+    let hash = path_hash(file_path);
+    for ext in THUMB_EXTENSIONS.iter().filter(|e| Some(**e) != keep) {
+        let _ = fs::remove_file(hashed_thumb_path(thumb_dir, &hash, ext));
+    }
+    let _ = fs::remove_dir_all(thumb_dir.join(RESIZED_DIR).join(&hash));
+}
+
+/// 实际输出的缩略图扩展名：JPEG 不支持透明，带透明像素的图片改存 PNG；WebP 本身支持 alpha
+fn output_extension(format: ThumbFormat, transparent: bool) -> &'static str {
+    match (format, transparent) {
+        (ThumbFormat::Jpeg, true) => "png",
+        _ => format.extension(),
+    }
+}
+
+/// 图像是否包含实际透明的像素（有 alpha 通道但全部不透明的不算）
+fn has_transparency(img: &image::DynamicImage) -> bool {
+    img.color().has_alpha() && img.to_rgba8().pixels().any(|p| p[3] < 255)
+}
+
+/// 按扩展名保存缩略图。
+/// 编码器都不接受 16 位或浮点像素，统一转换为 8 位 RGB（透明图为 RGBA）。
+/// 注意 image crate 只提供无损 WebP 编码。
+fn save_thumbnail(img: &image::DynamicImage, path: &Path, transparent: bool) -> Result<(), String> {
+    let format = match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => ImageFormat::Png,
+        Some("webp") => ImageFormat::WebP,
+        _ => ImageFormat::Jpeg,
+    };
+    let pixels = if transparent {
+        image::DynamicImage::ImageRgba8(img.to_rgba8())
+    } else {
+        image::DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+    pixels.save_with_format(path, format)
+        .map_err(|e| format!("保存缩略图失败: {}", e))
+}
+
+/// 查找当前格式设置下已存在的缩略图（不透明图和透明图扩展名可能不同）
+fn find_existing_thumb(thumb_dir: &Path, file_path: &str, format: ThumbFormat) -> Option<PathBuf> {
+    let hash = path_hash(file_path);
+    [output_extension(format, false), output_extension(format, true)]
+        .iter()
+        .map(|ext| hashed_thumb_path(thumb_dir, &hash, ext))
+        .find(|p| p.exists())
+}
+
+/// 源文件在缩略图生成之后又被修改过（缩略图可能是按写了一半的文件生成的）
+fn is_thumb_stale(thumb_path: &Path, input_path: &str) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(Path::new(input_path)), modified(thumb_path)) {
+        (Some(source), Some(thumb)) => source > thumb,
+        _ => false,
+    }
+}
+
+/// 文件大小和修改时间
+fn file_signature(path: &Path) -> Result<(u64, SystemTime), String> {
+    let meta = fs::metadata(path).map_err(|e| format!("读取文件信息失败 {}: {}", path.display(), e))?;
+    let modified = meta.modified().map_err(|e| format!("读取修改时间失败 {}: {}", path.display(), e))?;
+    Ok((meta.len(), modified))
+}
+
+/// 刚修改过的文件（DCC 工具可能还在写入）间隔 STABILITY_INTERVAL 反复检查，
+/// 直到大小和修改时间不再变化；返回文件是否是刚写入的。
+/// 一直在变化时返回错误，不为写了一半的文件生成缩略图
+fn wait_until_stable(path: &Path) -> Result<bool, String> {
+    let mut signature = file_signature(path)?;
+    let recent = SystemTime::now()
+        .duration_since(signature.1)
+        .map(|age| age < RECENT_WRITE_WINDOW)
+        .unwrap_or(true); // 修改时间在未来（时钟偏差）时也检查一下
+    if !recent {
+        return Ok(false);
+    }
+
+    for _ in 0..STABILITY_MAX_CHECKS {
+        std::thread::sleep(STABILITY_INTERVAL);
+        let next = file_signature(path)?;
+        if next == signature {
+            return Ok(true);
+        }
+        signature = next;
+    }
+    Err(format!("文件仍在写入，稍后重试: {}", path.display()))
+}
+
+/// 确保缩略图所在的子目录存在
+fn ensure_thumb_parent(thumb_path: &Path) -> Result<(), String> {
+    if let Some(parent) = thumb_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建缩略图目录失败: {}", e))?;
+    }
+    Ok(())
+}
+
 /// 缩略图生成结果
+#[derive(Debug, Clone, Serialize)]
 pub struct ThumbResult {
     pub thumb_path: String,
     pub width: u32,
     pub height: u32,
 }
 
+/// 缩略图文件是否完好：只读文件头，文件缺失、截断头部或格式无法识别时返回 false
+pub fn is_thumbnail_readable(thumb_path: &str) -> bool {
+    image::image_dimensions(thumb_path).is_ok()
+}
+
 /// 检查是否可以为该扩展名生成缩略图
 pub fn can_generate_thumbnail(ext: &str) -> bool {
-    let e = ext.to_lowercase();
-    let e = e.as_str();
-    DECODABLE_EXTENSIONS.contains(&e) || PSD_EXTENSIONS.contains(&e)
+    formats::can_thumbnail(ext)
 }
 
 /// 检查文件类型分类
 pub fn get_file_category(ext: &str) -> &'static str {
-    let e = ext.to_lowercase();
-    let e = e.as_str();
-    if DECODABLE_EXTENSIONS.contains(&e) || PSD_EXTENSIONS.contains(&e) { "image" }
-    else if VIDEO_EXTENSIONS.contains(&e) { "video" }
-    else if AUDIO_EXTENSIONS.contains(&e) { "audio" }
-    else if MODEL_3D_EXTENSIONS.contains(&e) { "3d" }
-    else if SPINE_EXTENSIONS.contains(&e) { "spine" }
-    else { "other" }
+    formats::get_file_category(ext)
 }
 
 /// 检查是否是视频格式（需要FFmpeg生成缩略图）
 pub fn is_video(ext: &str) -> bool {
-    VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+    get_file_category(ext) == "video"
 }
 
 /// 检查是否是音频格式
 pub fn is_audio(ext: &str) -> bool {
-    AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+    get_file_category(ext) == "audio"
 }
 
 /// 检查是否是3D模型格式
 pub fn is_3d_model(ext: &str) -> bool {
-    MODEL_3D_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+    get_file_category(ext) == "3d"
 }
 
 /// 为指定图片生成缩略图
-/// - small: 宽度 300px，保持比例，JPEG quality 85
+/// - small: 宽度 300px，保持比例，按当前设置输出 JPEG 或 WebP
 /// 返回缩略图路径和原始图片尺寸
 pub fn generate_thumbnail(
     input_path: &str,
     thumb_dir: &Path,
     max_width: u32,
 ) -> Result<ThumbResult, String> {
-    let hash = path_hash(input_path);
-    let thumb_filename = format!("{}.jpg", hash);
-    let thumb_path = thumb_dir.join(&thumb_filename);
+    let format = thumb_format();
+
+    // 根据文件扩展名选择解码方式
+    let ext = std::path::Path::new(input_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
 
     // 如果缩略图已存在且源文件没变，直接返回
-    if thumb_path.exists() {
+    if let Some(thumb_path) = find_existing_thumb(thumb_dir, input_path, format)
+        .filter(|p| !is_thumb_stale(p, input_path))
+    {
         // 快速检查：获取原图尺寸（从已有缩略图推断不可靠，还是重新读取）
         // 但为了速度，如果缩略图存在就直接用
-        // 原图尺寸通过 get_image_dimensions 单独获取
-        match get_image_dimensions(input_path) {
+        // 原图尺寸通过 get_source_dimensions 单独获取
+        match get_source_dimensions(input_path, &ext) {
             Some((w, h)) => {
                 return Ok(ThumbResult {
                     thumb_path: thumb_path.to_string_lossy().to_string(),
@@ -110,35 +267,50 @@ pub fn generate_thumbnail(
         }
     }
 
-    // 根据文件扩展名选择解码方式
-    let ext = std::path::Path::new(input_path)
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_lowercase();
-
-    let img = if PSD_EXTENSIONS.contains(&ext.as_str()) {
-        generate_psd_image(input_path)?
-    } else {
-        image::open(input_path)
-            .map_err(|e| format!("无法打开图片 {}: {}", input_path, e))?
+    // 扫描时逐个调用：超时或超大的文件只记为失败，不拖住整个扫描
+    let source = Path::new(input_path);
+    let recently_written = wait_until_stable(source)?;
+    let img = match thumb_provider::render_with_timeout(provider_for(&ext), source, max_width) {
+        // 刚写入的文件解码失败，可能是写入方在检查间隙之后又追加了数据，等一会再试一次
+        Err(e) if recently_written => {
+            log::info!("[AssetManager] 刚写入的文件解码失败，稍后重试: {} ({})", input_path, e);
+            std::thread::sleep(DECODE_RETRY_DELAY);
+            wait_until_stable(source)?;
+            thumb_provider::render_with_timeout(provider_for(&ext), source, max_width)?
+        }
+        result => result?,
     };
+    save_rendered_thumbnail(img, input_path, thumb_dir, max_width, format)
+}
 
+/// 把渲染出的原图缩小到 max_width 以内并保存为缩略图，返回原图尺寸
+fn save_rendered_thumbnail(
+    img: image::DynamicImage,
+    input_path: &str,
+    thumb_dir: &Path,
+    max_width: u32,
+    format: ThumbFormat,
+) -> Result<ThumbResult, String> {
     let (orig_w, orig_h) = img.dimensions();
 
-    // 如果原图已经很小，直接复制
-    if orig_w <= max_width {
-        // 保存为 JPEG（即使原图很小，统一格式方便前端处理）
-        img.save_with_format(&thumb_path, ImageFormat::Jpeg)
-            .map_err(|e| format!("保存缩略图失败: {}", e))?;
+    // 如果原图已经很小，直接使用（仍然重新编码，统一格式方便前端处理）
+    let thumb = if orig_w <= max_width {
+        img
     } else {
         // 按比例缩放
         let ratio = max_width as f64 / orig_w as f64;
         let new_h = (orig_h as f64 * ratio) as u32;
-        let thumb = img.resize_exact(max_width, new_h.max(1), image::imageops::FilterType::Lanczos3);
-        thumb.save_with_format(&thumb_path, ImageFormat::Jpeg)
-            .map_err(|e| format!("保存缩略图失败: {}", e))?;
-    }
+        img.resize_exact(max_width, new_h.max(1), image::imageops::FilterType::Lanczos3)
+    };
+
+    let transparent = has_transparency(&thumb);
+    let ext = output_extension(format, transparent);
+    let thumb_path = hashed_thumb_path(thumb_dir, &path_hash(input_path), ext);
+    ensure_thumb_parent(&thumb_path)?;
+    save_thumbnail(&thumb, &thumb_path, transparent)?;
+
+    // 格式切换后，旧格式的缩略图不再需要
+    remove_thumb_variants(thumb_dir, input_path, Some(ext));
 
     Ok(ThumbResult {
         thumb_path: thumb_path.to_string_lossy().to_string(),
@@ -147,30 +319,204 @@ pub fn generate_thumbnail(
     })
 }
 
-/// 仅读取图片尺寸（不完全解码，更快）
-pub fn get_image_dimensions(path: &str) -> Option<(u32, u32)> {
-    image::image_dimensions(path).ok()
+/// 能处理该扩展名的提供者；没有匹配时按普通图片解码（按文件头识别格式）
+fn provider_for(ext: &str) -> std::sync::Arc<dyn ThumbnailProvider> {
+    thumb_provider::find_provider(ext).unwrap_or_else(|| std::sync::Arc::new(RasterProvider))
+}
+
+/// 完整解码源文件为图像（由 thumb_provider 中注册的提供者按扩展名处理）
+pub fn decode_image(input_path: &str, ext: &str) -> Result<image::DynamicImage, String> {
+    provider_for(ext).render(Path::new(input_path), u32::MAX)
+}
+
+/// 解码源文件并缩小到 max_width 以内，编码为 data URI（浏览器无法直接显示的 PSD/EXR 等格式用于预览）。
+/// 带透明像素的图片输出 PNG，其余输出 JPEG。
+pub fn preview_data_uri(input_path: &str, ext: &str, max_width: u32) -> Result<String, String> {
+    use base64::Engine;
+
+    let img = decode_image(input_path, ext)?;
+    let (w, h) = img.dimensions();
+    let max_width = max_width.max(1);
+    let img = if w > max_width {
+        let new_h = ((h as f64 * max_width as f64 / w as f64) as u32).max(1);
+        img.resize_exact(max_width, new_h, image::imageops::FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    let transparent = has_transparency(&img);
+    let (pixels, format, mime) = if transparent {
+        (image::DynamicImage::ImageRgba8(img.to_rgba8()), ImageFormat::Png, "image/png")
+    } else {
+        (image::DynamicImage::ImageRgb8(img.to_rgb8()), ImageFormat::Jpeg, "image/jpeg")
+    };
+    let mut bytes = Vec::new();
+    pixels.write_to(&mut std::io::Cursor::new(&mut bytes), format)
+        .map_err(|e| format!("编码预览图失败: {}", e))?;
+
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// 按格式读取源文件尺寸（PSD / glTF 等由各自的提供者解析）
+fn get_source_dimensions(path: &str, ext: &str) -> Option<(u32, u32)> {
+    provider_for(ext).dimensions(Path::new(path))
 }
 
-/// 清理文件夹对应的所有缩略图
-pub fn cleanup_thumbnails(thumb_dir: &Path, file_paths: &[String]) {
-    for path in file_paths {
-        let hash = path_hash(path);
-        let thumb_file = thumb_dir.join(format!("{}.jpg", hash));
-        let _ = fs::remove_file(thumb_file);
+/// 按数据库中记录的 thumb_path 删除缩略图文件。
+/// 只删除缩略图目录内的文件，并跳过多个资产共用的占位缩略图。
+pub fn cleanup_thumbnails(thumb_dir: &Path, thumb_paths: &[String]) {
+    for path in thumb_paths {
+        let path = Path::new(path);
+        if path.as_os_str().is_empty()
+            || !path.starts_with(thumb_dir)
+            || placeholder::is_placeholder_path(thumb_dir, path)
+        {
+            continue;
+        }
+        let _ = fs::remove_file(path);
+        if let Some(stem) = path.file_stem() {
+            let _ = fs::remove_dir_all(thumb_dir.join(RESIZED_DIR).join(stem));
+        }
     }
 }
 
-/// 获取缩略图路径（不生成）
+/// 缩略图缓存目录占用的空间和文件数（包括缩放变体和占位图）
+pub fn cache_usage(thumb_dir: &Path) -> (u64, u64) {
+    walkdir::WalkDir::new(thumb_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .fold((0, 0), |(size, count), e| {
+            (size + e.metadata().map(|m| m.len()).unwrap_or(0), count + 1)
+        })
+}
+
+/// 把已缓存的缩略图缩放到指定宽度，结果缓存在 resized 子目录中。
+/// - 宽度被限制在 MAX_RESIZE_WIDTH 以内；不小于缩略图本身宽度时直接返回原缩略图
+/// - 缩略图比缓存的变体新（已重新生成）时重新缩放
+pub fn resized_thumbnail(thumb_dir: &Path, thumb_path: &Path, width: u32) -> Result<PathBuf, String> {
+    let width = width.clamp(1, MAX_RESIZE_WIDTH);
+    let (stem, ext) = match (thumb_path.file_stem(), thumb_path.extension().and_then(|e| e.to_str())) {
+        (Some(stem), Some(ext)) => (stem, ext),
+        _ => return Ok(thumb_path.to_path_buf()),
+    };
+    let variant = thumb_dir.join(RESIZED_DIR).join(stem).join(format!("{}.{}", width, ext));
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if let (Some(cached), Some(source)) = (modified(&variant), modified(thumb_path)) {
+        if cached >= source {
+            return Ok(variant);
+        }
+    }
+
+    let (w, h) = image::image_dimensions(thumb_path)
+        .map_err(|e| format!("无法读取缩略图尺寸: {}", e))?;
+    if width >= w {
+        return Ok(thumb_path.to_path_buf());
+    }
+    let img = image::open(thumb_path)
+        .map_err(|e| format!("无法解码缩略图: {}", e))?;
+
+    let new_h = ((h as f64 * width as f64 / w as f64) as u32).max(1);
+    let resized = img.resize_exact(width, new_h, image::imageops::FilterType::Triangle);
+    let transparent = ext == "png" || (ext == "webp" && has_transparency(&resized));
+    ensure_thumb_parent(&variant)?;
+    save_thumbnail(&resized, &variant, transparent)?;
+    Ok(variant)
+}
+
+/// 获取缩略图路径（不生成），扩展名取决于当前缩略图格式（透明图片实际可能为 .png）
 pub fn get_thumb_path(thumb_dir: &Path, file_path: &str) -> PathBuf {
-    let hash = path_hash(file_path);
-    thumb_dir.join(format!("{}.jpg", hash))
+    hashed_thumb_path(thumb_dir, &path_hash(file_path), thumb_format().extension())
+}
+
+/// 一次性迁移：把旧版平铺在 thumb_dir 根目录的缩略图移动到分片子目录
+/// 返回 (旧路径, 新路径) 列表，供调用方同步更新数据库中的 thumb_path
+pub fn migrate_flat_thumbnails(thumb_dir: &Path) -> Vec<(String, String)> {
+    let mut moved = Vec::new();
+    let entries = match fs::read_dir(thumb_dir) {
+        Ok(entries) => entries,
+        Err(_) => return moved,
+    };
+
+    for entry in entries.flatten() {
+        let old_path = entry.path();
+        if !old_path.is_file() {
+            continue;
+        }
+        let hash = match old_path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".jpg")) {
+            Some(h) if h.len() == 16 && h.chars().all(|c| c.is_ascii_hexdigit()) => h.to_string(),
+            _ => continue,
+        };
+
+        let new_path = hashed_thumb_path(thumb_dir, &hash, "jpg");
+        if ensure_thumb_parent(&new_path).is_err() {
+            continue;
+        }
+        if fs::rename(&old_path, &new_path).is_ok() {
+            moved.push((
+                old_path.to_string_lossy().to_string(),
+                new_path.to_string_lossy().to_string(),
+            ));
+        }
+    }
+
+    moved
+}
+
+/// 删除已缓存的缩略图并重新生成
+/// - 图片/PSD 走 generate_thumbnail
+/// - 视频走 FFmpeg（需提供 ffmpeg 路径），尺寸通过 ffprobe 获取
+/// - 音频用 FFmpeg 生成波形图
+pub fn regenerate_thumbnail(
+    input_path: &str,
+    thumb_dir: &Path,
+    max_width: u32,
+    ffmpeg_path: Option<&Path>,
+    ffprobe_path: Option<&Path>,
+) -> Result<ThumbResult, String> {
+    if !Path::new(input_path).exists() {
+        return Err(format!("源文件不存在: {}", input_path));
+    }
+
+    remove_thumb_variants(thumb_dir, input_path, None);
+
+    let ext = Path::new(input_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+
+    if can_generate_thumbnail(&ext) {
+        generate_thumbnail(input_path, thumb_dir, max_width)
+    } else if is_video(&ext) {
+        let ffmpeg = ffmpeg_path.ok_or_else(|| "FFmpeg 未安装，无法生成视频缩略图".to_string())?;
+        let mut result = generate_video_thumbnail(ffmpeg, ffprobe_path, input_path, thumb_dir, max_width)?;
+        if let Some((w, h, _)) = ffprobe_path.and_then(|p| get_media_info(p, input_path)) {
+            result.width = w;
+            result.height = h;
+        }
+        Ok(result)
+    } else if is_audio(&ext) {
+        let ffmpeg = ffmpeg_path.ok_or_else(|| "FFmpeg 未安装，无法生成音频波形图".to_string())?;
+        generate_audio_waveform(ffmpeg, input_path, thumb_dir, max_width)
+    } else {
+        Err(format!("不支持为该格式生成缩略图: {}", ext))
+    }
 }
 
 /// 从PSD文件生成合成图像
-fn generate_psd_image(input_path: &str) -> Result<image::DynamicImage, String> {
+pub(crate) fn generate_psd_image(input_path: &str) -> Result<image::DynamicImage, String> {
     let psd_bytes = fs::read(input_path)
         .map_err(|e| format!("读取PSD文件失败: {}", e))?;
+    // 解析图层前先按文件头检查尺寸，超大的文件不做合成
+    if let Some((w, h)) = psd_header_dimensions(&psd_bytes) {
+        thumb_provider::check_pixel_limit(w, h)?;
+    }
     let psd = psd::Psd::from_bytes(&psd_bytes)
         .map_err(|e| format!("解析PSD文件失败: {:?}", e))?;
 
@@ -184,25 +530,142 @@ fn generate_psd_image(input_path: &str) -> Result<image::DynamicImage, String> {
     Ok(image::DynamicImage::ImageRgba8(img_buf))
 }
 
-/// 获取PSD文件尺寸（不完全解码）
+/// PSD 文件头：签名 "8BPS"，偏移 14 为高度、18 为宽度（大端 u32）
+const PSD_HEADER_LEN: usize = 26;
+
+fn psd_header_dimensions(header: &[u8]) -> Option<(u32, u32)> {
+    if header.len() < PSD_HEADER_LEN || &header[..4] != b"8BPS" {
+        return None;
+    }
+    let be = |at: usize| u32::from_be_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]);
+    Some((be(18), be(14)))
+}
+
+/// 获取PSD文件尺寸（只读文件头）
 pub fn get_psd_dimensions(input_path: &str) -> Option<(u32, u32)> {
-    let bytes = fs::read(input_path).ok()?;
-    let psd = psd::Psd::from_bytes(&bytes).ok()?;
-    Some((psd.width(), psd.height()))
+    use std::io::Read;
+
+    let mut header = [0u8; PSD_HEADER_LEN];
+    fs::File::open(input_path).ok()?.read_exact(&mut header).ok()?;
+    psd_header_dimensions(&header)
 }
 
-/// 为视频文件生成缩略图（需要FFmpeg路径）
+/// 从 glTF / GLB 中提取预览图像
+/// 优先使用第一个材质的 baseColor 纹理，否则取第一张图片。
+/// 图片可以存放在 GLB 的 BIN 块、data URI 或相对路径的外部文件中。
+pub(crate) fn generate_gltf_image(input_path: &str) -> Result<image::DynamicImage, String> {
+    let bytes = fs::read(input_path)
+        .map_err(|e| format!("读取glTF文件失败: {}", e))?;
+
+    let (json_bytes, bin_chunk) = if bytes.starts_with(b"glTF") {
+        parse_glb_chunks(&bytes)?
+    } else {
+        (bytes.as_slice(), None)
+    };
+
+    let doc: serde_json::Value = serde_json::from_slice(json_bytes)
+        .map_err(|e| format!("解析glTF JSON失败: {}", e))?;
+
+    let images = doc["images"].as_array()
+        .filter(|a| !a.is_empty())
+        .ok_or_else(|| "glTF中没有内嵌图片".to_string())?;
+
+    // 第一个带 baseColorTexture 的材质 -> texture -> image
+    let image_index = doc["materials"].as_array()
+        .and_then(|materials| {
+            materials.iter().find_map(|m| {
+                m["pbrMetallicRoughness"]["baseColorTexture"]["index"].as_u64()
+            })
+        })
+        .and_then(|tex| doc["textures"][tex as usize]["source"].as_u64())
+        .map(|i| i as usize)
+        .filter(|i| *i < images.len())
+        .unwrap_or(0);
+
+    let image_def = &images[image_index];
+    let base_dir = Path::new(input_path).parent().unwrap_or(Path::new(""));
+
+    let data: Vec<u8> = if let Some(view_index) = image_def["bufferView"].as_u64() {
+        let view = &doc["bufferViews"][view_index as usize];
+        let buffer_index = view["buffer"].as_u64().unwrap_or(0) as usize;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64()
+            .ok_or_else(|| "glTF bufferView 缺少 byteLength".to_string())? as usize;
+
+        let buffer = &doc["buffers"][buffer_index];
+        let buffer_data: Vec<u8> = match buffer["uri"].as_str() {
+            Some(uri) => load_gltf_uri(uri, base_dir)?,
+            None => bin_chunk.ok_or_else(|| "GLB 缺少 BIN 数据块".to_string())?.to_vec(),
+        };
+
+        buffer_data.get(offset..offset + length)
+            .ok_or_else(|| "glTF bufferView 超出缓冲区范围".to_string())?
+            .to_vec()
+    } else if let Some(uri) = image_def["uri"].as_str() {
+        load_gltf_uri(uri, base_dir)?
+    } else {
+        return Err("glTF图片既没有 uri 也没有 bufferView".to_string());
+    };
+
+    image::load_from_memory(&data)
+        .map_err(|e| format!("解码glTF内嵌图片失败: {}", e))
+}
+
+/// 拆分 GLB 文件的 JSON 块和 BIN 块
+fn parse_glb_chunks(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let read_u32 = |pos: usize| -> Option<u32> {
+        bytes.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    const CHUNK_JSON: u32 = 0x4E4F534A;
+    const CHUNK_BIN: u32 = 0x004E4942;
+
+    let mut pos = 12; // magic + version + length
+    let mut json = None;
+    let mut bin = None;
+
+    while let (Some(len), Some(kind)) = (read_u32(pos), read_u32(pos + 4)) {
+        let start = pos + 8;
+        let end = start + len as usize;
+        let chunk = bytes.get(start..end).ok_or_else(|| "GLB 数据块长度无效".to_string())?;
+        match kind {
+            CHUNK_JSON if json.is_none() => json = Some(chunk),
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {}
+        }
+        pos = end;
+    }
+
+    let json = json.ok_or_else(|| "GLB 缺少 JSON 数据块".to_string())?;
+    Ok((json, bin))
+}
+
+/// 读取 glTF 中的 uri（data URI 或相对路径）
+fn load_gltf_uri(uri: &str, base_dir: &Path) -> Result<Vec<u8>, String> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    if let Some(rest) = uri.strip_prefix("data:") {
+        let (_, payload) = rest.split_once(";base64,")
+            .ok_or_else(|| "不支持的 data URI 编码".to_string())?;
+        return general_purpose::STANDARD.decode(payload)
+            .map_err(|e| format!("解码 data URI 失败: {}", e));
+    }
+
+    fs::read(base_dir.join(uri))
+        .map_err(|e| format!("读取glTF外部资源失败 {}: {}", uri, e))
+}
+
+/// 为视频文件生成缩略图（需要FFmpeg路径），按当前缩略图格式保存
 pub fn generate_video_thumbnail(
     ffmpeg_path: &Path,
+    ffprobe_path: Option<&Path>,
     video_path: &str,
     thumb_dir: &Path,
     max_width: u32,
 ) -> Result<ThumbResult, String> {
-    let hash = path_hash(video_path);
-    let thumb_filename = format!("{}.jpg", hash);
-    let thumb_path = thumb_dir.join(&thumb_filename);
+    let format = thumb_format();
 
-    if thumb_path.exists() {
+    if let Some(thumb_path) = find_existing_thumb(thumb_dir, video_path, format) {
         // 视频缩略图已存在，直接返回（无法快速获取视频尺寸）
         return Ok(ThumbResult {
             thumb_path: thumb_path.to_string_lossy().to_string(),
@@ -211,29 +674,43 @@ pub fn generate_video_thumbnail(
         });
     }
 
-    // 用 FFmpeg 提取第1秒的帧
-    let status = std::process::Command::new(ffmpeg_path)
-        .args(&[
-            "-y", "-i", video_path,
-            "-ss", "1",
-            "-vframes", "1",
-            "-vf", &format!("scale={}:-1", max_width),
-            &thumb_path.to_string_lossy(),
-        ])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status()
+    let frame = VideoProvider::new(ffmpeg_path.to_path_buf(), ffprobe_path.map(Path::to_path_buf))
+        .render(Path::new(video_path), max_width)?;
+    save_rendered_thumbnail(frame, video_path, thumb_dir, max_width, format)
+}
+
+/// 为音频文件生成波形图作为缩略图（FFmpeg showwavespic 滤镜）。音频没有画面尺寸，宽高记为 0
+pub fn generate_audio_waveform(
+    ffmpeg_path: &Path,
+    audio_path: &str,
+    thumb_dir: &Path,
+    max_width: u32,
+) -> Result<ThumbResult, String> {
+    let format = thumb_format();
+    let filter = format!(
+        "aformat=channel_layouts=mono,showwavespic=s={}x{}:colors=0x10b981",
+        max_width,
+        (max_width / 3).max(1)
+    );
+    let output = std::process::Command::new(ffmpeg_path)
+        .args(["-v", "error", "-i", audio_path, "-filter_complex", &filter])
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "pipe:1"])
+        .stdin(std::process::Stdio::null())
+        .output()
         .map_err(|e| format!("执行FFmpeg失败: {}", e))?;
 
-    if !status.success() {
-        return Err("FFmpeg缩略图生成失败".to_string());
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!(
+            "FFmpeg波形图生成失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
     }
-
-    Ok(ThumbResult {
-        thumb_path: thumb_path.to_string_lossy().to_string(),
-        width: 0,
-        height: 0,
-    })
+    let wave = image::load_from_memory(&output.stdout)
+        .map_err(|e| format!("解码波形图失败: {}", e))?;
+    let mut result = save_rendered_thumbnail(wave, audio_path, thumb_dir, max_width, format)?;
+    result.width = 0;
+    result.height = 0;
+    Ok(result)
 }
 
 /// 通过 ffprobe 获取视频/音频尺寸和时长
@@ -273,3 +750,79 @@ pub fn get_media_info(ffprobe_path: &Path, media_path: &str) -> Option<(u32, u32
 
     Some((width, height, duration))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("arthub_thumbnail_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 把修改时间改到 RECENT_WRITE_WINDOW 之前，跳过“文件仍在写入”的等待
+    fn make_settled(path: &Path) {
+        let old = SystemTime::now() - RECENT_WRITE_WINDOW - Duration::from_secs(60);
+        fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+    }
+
+    fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        let crc = kind.iter().chain(data).fold(0xFFFF_FFFFu32, |mut crc, &byte| {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+            crc
+        }) ^ 0xFFFF_FFFF;
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    #[test]
+    fn oversized_header_is_rejected_before_decoding() {
+        let dir = temp_dir("oversized");
+        // 文件头声明 100000×100000（100 亿像素），实际只有几十字节
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&100_000u32.to_be_bytes());
+        ihdr.extend_from_slice(&100_000u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut png, b"IHDR", &ihdr);
+        png_chunk(&mut png, b"IDAT", &[]);
+        png_chunk(&mut png, b"IEND", &[]);
+        let source = dir.join("bomb.png");
+        fs::write(&source, &png).unwrap();
+        make_settled(&source);
+
+        assert_eq!(thumb_provider::exceeds_pixel_budget(&source, "png"), Some((100_000, 100_000)));
+        // 按文件头拒绝：错误来自尺寸检查，而不是解码器读取像素数据时失败
+        let err = generate_thumbnail(&source.to_string_lossy(), &dir.join("thumbs"), 300).unwrap_err();
+        assert!(err.contains("图像尺寸过大"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn transparent_png_keeps_alpha() {
+        let dir = temp_dir("alpha");
+        // 左半透明、右半不透明，宽度超过 300 需要缩放
+        let img = RgbaImage::from_fn(600, 200, |x, _| {
+            if x < 300 { image::Rgba([255, 0, 0, 0]) } else { image::Rgba([0, 0, 255, 255]) }
+        });
+        let source = dir.join("sprite.png");
+        img.save(&source).unwrap();
+        make_settled(&source);
+
+        let result = generate_thumbnail(&source.to_string_lossy(), &dir.join("thumbs"), 300).unwrap();
+        assert_eq!((result.width, result.height), (600, 200));
+        let thumb = image::open(&result.thumb_path).unwrap();
+        assert!(thumb.color().has_alpha(), "缩略图丢失了 alpha 通道: {}", result.thumb_path);
+        assert_eq!(thumb.dimensions(), (300, 100));
+        assert_eq!(thumb.get_pixel(10, 50)[3], 0);
+        assert_eq!(thumb.get_pixel(290, 50)[3], 255);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
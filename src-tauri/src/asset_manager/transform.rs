@@ -0,0 +1,71 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::fs_util::write_atomic;
+
+/// 可以写回的格式（EXR/DDS/HDR 等只读格式不支持原地变换）
+const WRITABLE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "tga", "tif", "tiff", "webp"];
+
+/// 重新编码 JPEG 时使用的质量
+const JPEG_QUALITY: u8 = 92;
+
+/// 图像变换操作
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageOp {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipH,
+    FlipV,
+}
+
+impl ImageOp {
+    fn apply(self, img: DynamicImage) -> DynamicImage {
+        match self {
+            ImageOp::Rotate90 => img.rotate90(),
+            ImageOp::Rotate180 => img.rotate180(),
+            ImageOp::Rotate270 => img.rotate270(),
+            ImageOp::FlipH => img.fliph(),
+            ImageOp::FlipV => img.flipv(),
+        }
+    }
+}
+
+/// 是否支持原地变换并写回
+pub fn can_transform(ext: &str) -> bool {
+    WRITABLE_EXTENSIONS.contains(&ext)
+}
+
+/// 对图片文件做旋转/翻转并原子写回原路径，返回变换后的尺寸。
+/// JPEG 会以 JPEG_QUALITY 重新编码（非无损），其余格式按原格式无损保存。
+pub fn transform_image_file(path: &Path, ext: &str, op: ImageOp) -> Result<(u32, u32), String> {
+    if !can_transform(ext) {
+        return Err(format!("该格式不支持写回: {}", ext));
+    }
+    let format = ImageFormat::from_extension(ext)
+        .ok_or_else(|| format!("无法识别的图片格式: {}", ext))?;
+
+    let img = image::ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| format!("无法打开图片 {}: {}", path.display(), e))?
+        .decode()
+        .map_err(|e| format!("无法解码图片 {}: {}", path.display(), e))?;
+    let img = op.apply(img);
+
+    let mut bytes = Vec::new();
+    match format {
+        ImageFormat::Jpeg => {
+            // JPEG 不支持 alpha 和 16 位像素
+            let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+            rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))
+        }
+        _ => img.write_to(&mut std::io::Cursor::new(&mut bytes), format),
+    }
+    .map_err(|e| format!("编码图片失败: {}", e))?;
+
+    write_atomic(path, &bytes).map_err(|e| format!("写入图片失败: {}", e))?;
+    Ok(img.dimensions())
+}
@@ -0,0 +1,54 @@
+// 诊断信息：用户反馈问题时一键复制运行环境，只读，不包含团队身份以外的个人信息
+use serde::Serialize;
+use std::path::Path;
+
+use crate::asset_manager::{db, ffmpeg, team, thumbnail, AssetManagerState};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub os: String, // 如 "Windows 10.0.22631"、"macOS 14.2"
+    pub arch: String,
+    pub app_version: String,
+    pub identity: team::Identity,
+    pub db_path: String,
+    pub db_size: u64,
+    pub wal_size: u64,
+    pub thumb_cache_size: u64,
+    pub thumb_cache_count: u64,
+    pub ffmpeg: ffmpeg::FfmpegStatus,
+    pub folder_count: i64,
+    pub asset_count: i64,
+    pub autostart_enabled: bool,
+}
+
+/// 收集诊断信息。会遍历缩略图目录并启动 FFmpeg 检测版本，应在后台线程调用
+pub fn collect(
+    state: &AssetManagerState,
+    app_version: String,
+    autostart_enabled: bool,
+) -> Result<Diagnostics, String> {
+    let db_path = state.db.path();
+    let file_size = |path: &Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let wal_path = format!("{}-wal", db_path.display());
+    let (thumb_cache_size, thumb_cache_count) = thumbnail::cache_usage(&state.thumb_dir);
+    let stats = {
+        let conn = state.db.read()?;
+        db::get_stats(&conn)?
+    };
+
+    Ok(Diagnostics {
+        os: whoami::distro(),
+        arch: std::env::consts::ARCH.to_string(),
+        app_version,
+        identity: team::local_identity().clone(),
+        db_path: db_path.to_string_lossy().to_string(),
+        db_size: file_size(db_path),
+        wal_size: file_size(Path::new(&wal_path)),
+        thumb_cache_size,
+        thumb_cache_count,
+        ffmpeg: ffmpeg::check_ffmpeg(),
+        folder_count: stats.total_folders,
+        asset_count: stats.total_assets,
+        autostart_enabled,
+    })
+}
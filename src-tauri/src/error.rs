@@ -0,0 +1,144 @@
+// 命令统一错误类型：前端通过 code 区分错误种类，message 用于展示
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt::Display;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArtHubError {
+    /// 文件、资产、文件夹等不存在
+    #[error("{0}")]
+    NotFound(String),
+    /// 操作系统拒绝访问
+    #[error("{0}")]
+    PermissionDenied(String),
+    /// 同名对象已存在（如重命名标签时重名）
+    #[error("{0}")]
+    AlreadyExists(String),
+    /// 位于只读文件夹中，禁止修改
+    #[error("{0}")]
+    ReadOnly(String),
+    /// 要添加的文件夹与已管理的文件夹互相嵌套（确认后可传 allow_nested 强制添加）
+    #[error("{0}")]
+    NestedFolder(String),
+    /// 共享版本库超出配额且配置为拒绝写入
+    #[error("{0}")]
+    QuotaExceeded(String),
+    /// 数据库被占用或锁定
+    #[error("{0}")]
+    DbLocked(String),
+    /// 其他数据库错误
+    #[error("{0}")]
+    Database(String),
+    /// 其他文件读写错误
+    #[error("{0}")]
+    Io(String),
+    /// 参数不合法
+    #[error("{0}")]
+    InvalidInput(String),
+    /// 格式或平台不支持
+    #[error("{0}")]
+    Unsupported(String),
+    /// 未分类的错误（尚未迁移的 String 错误都会落到这里）
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ArtHubError {
+    /// 机器可读的错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            ArtHubError::NotFound(_) => "not_found",
+            ArtHubError::PermissionDenied(_) => "permission_denied",
+            ArtHubError::AlreadyExists(_) => "already_exists",
+            ArtHubError::ReadOnly(_) => "read_only",
+            ArtHubError::NestedFolder(_) => "nested_folder",
+            ArtHubError::QuotaExceeded(_) => "quota_exceeded",
+            ArtHubError::DbLocked(_) => "db_locked",
+            ArtHubError::Database(_) => "database",
+            ArtHubError::Io(_) => "io",
+            ArtHubError::InvalidInput(_) => "invalid_input",
+            ArtHubError::Unsupported(_) => "unsupported",
+            ArtHubError::Internal(_) => "internal",
+        }
+    }
+
+    /// 获取数据库互斥锁失败（用于 state.db.lock().map_err(ArtHubError::db_lock)）
+    pub fn db_lock(e: impl Display) -> Self {
+        ArtHubError::DbLocked(format!("锁定数据库失败: {}", e))
+    }
+
+    /// 按 SQLite 错误种类归类：查无记录、忙/锁定、唯一约束、只读、无权限，其余归为 database
+    pub fn sqlite(message: String, e: rusqlite::Error) -> Self {
+        use rusqlite::ffi;
+        use rusqlite::ErrorCode;
+        match &e {
+            rusqlite::Error::QueryReturnedNoRows => ArtHubError::NotFound(message),
+            rusqlite::Error::SqliteFailure(err, _) => match err.code {
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => ArtHubError::DbLocked(message),
+                ErrorCode::ConstraintViolation
+                    if err.extended_code == ffi::SQLITE_CONSTRAINT_UNIQUE
+                        || err.extended_code == ffi::SQLITE_CONSTRAINT_PRIMARYKEY =>
+                {
+                    ArtHubError::AlreadyExists(message)
+                }
+                ErrorCode::ReadOnly => ArtHubError::ReadOnly(message),
+                ErrorCode::PermissionDenied => ArtHubError::PermissionDenied(message),
+                _ => ArtHubError::Database(message),
+            },
+            _ => ArtHubError::Database(message),
+        }
+    }
+
+    /// 带上下文的 SQLite 错误转换：.map_err(ArtHubError::db("查询资产失败"))
+    pub fn db(context: &'static str) -> impl Fn(rusqlite::Error) -> Self {
+        move |e| ArtHubError::sqlite(format!("{}: {}", context, e), e)
+    }
+}
+
+/// 序列化为 { "code": "...", "message": "..." }
+impl Serialize for ArtHubError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ArtHubError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+impl From<String> for ArtHubError {
+    fn from(message: String) -> Self {
+        ArtHubError::Internal(message)
+    }
+}
+
+impl From<&str> for ArtHubError {
+    fn from(message: &str) -> Self {
+        ArtHubError::Internal(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for ArtHubError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => ArtHubError::NotFound(e.to_string()),
+            std::io::ErrorKind::PermissionDenied => ArtHubError::PermissionDenied(e.to_string()),
+            _ => ArtHubError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ArtHubError {
+    fn from(e: rusqlite::Error) -> Self {
+        let message = match &e {
+            rusqlite::Error::QueryReturnedNoRows => "记录不存在".to_string(),
+            _ => e.to_string(),
+        };
+        ArtHubError::sqlite(message, e)
+    }
+}
+
+/// 兼容仍返回 Result<_, String> 的调用方（团队同步等），可直接用 ? 转换
+impl From<ArtHubError> for String {
+    fn from(e: ArtHubError) -> Self {
+        e.to_string()
+    }
+}
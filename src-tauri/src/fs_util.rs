@@ -0,0 +1,42 @@
+// 文件写入工具
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rayon::prelude::*;
+
+/// 原子写入：先写到同目录下的临时文件并 fsync，再 rename 覆盖目标。
+/// 同一卷内的 rename 是原子的，读者要么看到旧内容，要么看到完整的新内容，
+/// 写到一半崩溃也只会留下临时文件。父目录需已存在。
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "无效的文件路径"))?;
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+    let tmp = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        nanos
+    ));
+
+    let result = (|| {
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(contents.as_ref())?;
+        f.sync_all()?;
+        drop(f);
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// 批量检查路径是否存在，结果顺序与输入一致。
+/// 网络共享上每次 stat 都有往返延迟，因此用 rayon 并行检查。
+pub fn paths_exist(paths: &[String]) -> Vec<bool> {
+    paths.par_iter().map(|p| Path::new(p).exists()).collect()
+}
@@ -0,0 +1,244 @@
+// AI 工具窗口的 JSON 注入脚本：open_ai_tab / open_ai_window 共用同一份脚本
+//
+// 脚本模板使用占位符替换而不是 format!，避免在 JS 中成对转义花括号
+
+/// 注入脚本模板（纯 JS，不能出现 TypeScript 语法）
+const SCRIPT_TEMPLATE: &str = r#"
+(function() {
+    const jsonBase64 = "__ARTHUB_JSON_BASE64__";
+    const customSelector = __ARTHUB_SELECTOR__;
+    const autoFill = __ARTHUB_AUTO_FILL__;
+
+    // 清除旧的JSON数据（确保使用新的）
+    try {
+        localStorage.removeItem('arthub_injected_json');
+        delete window.arthubInjectedJSON;
+        delete window.arthubInjectedJSONString;
+    } catch(e) {
+        console.warn('[ArtHub] Failed to clear old JSON:', e);
+    }
+
+    let jsonString, jsonData;
+    try {
+        jsonString = atob(jsonBase64);
+        jsonData = JSON.parse(jsonString);
+        console.log('[ArtHub] JSON decoded and parsed successfully');
+    } catch(e) {
+        console.error('[ArtHub] Failed to decode/parse JSON:', e);
+        jsonString = jsonBase64;
+        jsonData = null;
+    }
+
+    // 自动查找并填充输入框；自定义选择器优先
+    function autoFillInput() {
+        if (!autoFill) return true;
+        const selectors = [
+            ...(customSelector ? [customSelector] : []),
+            'textarea',
+            'input[type="text"]',
+            'input[type="search"]',
+            '[contenteditable="true"]',
+            '[role="textbox"]',
+            '.monaco-editor textarea',
+            '.CodeMirror textarea',
+            'pre[contenteditable]'
+        ];
+
+        for (const selector of selectors) {
+            let elements;
+            try {
+                elements = document.querySelectorAll(selector);
+            } catch(e) {
+                console.warn('[ArtHub] Invalid selector:', selector, e);
+                continue;
+            }
+            for (const el of elements) {
+                const style = window.getComputedStyle(el);
+                if (style.display === 'none' || style.visibility === 'hidden') continue;
+                try {
+                    if (el.tagName === 'TEXTAREA' || el.tagName === 'INPUT') {
+                        el.value = jsonString;
+                        el.dispatchEvent(new Event('input', { bubbles: true }));
+                        el.dispatchEvent(new Event('change', { bubbles: true }));
+                        console.log('[ArtHub] JSON filled into input/textarea');
+                        return true;
+                    } else if (el.isContentEditable || el.tagName === 'PRE') {
+                        el.textContent = jsonString;
+                        el.dispatchEvent(new Event('input', { bubbles: true }));
+                        console.log('[ArtHub] JSON filled into contenteditable');
+                        return true;
+                    }
+                } catch(e) {
+                    console.warn('[ArtHub] Fill failed for element:', e);
+                }
+            }
+        }
+        return false;
+    }
+
+    function injectJSON() {
+        try {
+            // 注入到localStorage和window对象
+            try {
+                if (jsonString) {
+                    localStorage.setItem('arthub_injected_json', jsonString);
+                    window.arthubInjectedJSONString = jsonString;
+                    if (jsonData) {
+                        window.arthubInjectedJSON = jsonData;
+                    }
+                    console.log('[ArtHub] JSON injected to storage');
+                }
+            } catch(e) {
+                console.warn('[ArtHub] Storage injection failed:', e);
+            }
+
+            // 通知页面脚本
+            try {
+                const event = new CustomEvent('arthub-json-ready', {
+                    detail: jsonData || jsonString,
+                    bubbles: true,
+                    cancelable: true
+                });
+                window.dispatchEvent(event);
+                document.dispatchEvent(event);
+            } catch(e) {
+                console.warn('[ArtHub] Event dispatch failed:', e);
+            }
+
+            // 立即尝试填充，失败则延迟重试
+            if (!autoFillInput()) {
+                setTimeout(autoFillInput, 500);
+                setTimeout(autoFillInput, 1500);
+                setTimeout(autoFillInput, 3000);
+                setTimeout(autoFillInput, 5000);
+            }
+
+            console.log('%c[ArtHub] JSON已自动注入！', 'color: #00ff00; font-weight: bold;');
+        } catch(e) {
+            console.error('[ArtHub] JSON injection error:', e);
+        }
+    }
+
+    // 立即尝试注入
+    injectJSON();
+
+    // 监听页面加载事件
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', injectJSON);
+    }
+    window.addEventListener('load', injectJSON);
+
+    // 延迟注入，确保页面完全加载
+    setTimeout(injectJSON, 2000);
+    setTimeout(injectJSON, 5000);
+})();
+"#;
+
+/// 刷新已存在的标签页前清除上一次注入的数据
+pub const CLEAR_SCRIPT: &str = r#"
+try {
+    localStorage.removeItem('arthub_injected_json');
+    delete window.arthubInjectedJSON;
+    delete window.arthubInjectedJSONString;
+    console.log('[ArtHub] Old JSON cleared');
+} catch(e) {
+    console.warn('[ArtHub] Failed to clear old JSON:', e);
+}
+"#;
+
+/// 生成注入脚本：写入 localStorage / window 并自动填充输入框
+/// `json_base64` 为 base64 编码的 JSON；`selector` 为优先使用的输入框选择器
+pub fn build_injection_script(json_base64: &str, selector: Option<&str>) -> String {
+    render(json_base64, selector, true)
+}
+
+/// 只写入 localStorage / window，不自动填充输入框（注入方式为 storage 时使用）
+pub fn build_storage_script(json_base64: &str) -> String {
+    render(json_base64, None, false)
+}
+
+fn render(json_base64: &str, selector: Option<&str>, auto_fill: bool) -> String {
+    // 选择器以 JSON 字面量嵌入，避免其中的引号破坏脚本
+    let selector_js = serde_json::to_string(&selector).unwrap_or_else(|_| "null".to_string());
+    SCRIPT_TEMPLATE
+        .replace("__ARTHUB_JSON_BASE64__", json_base64)
+        .replace("__ARTHUB_SELECTOR__", &selector_js)
+        .replace("__ARTHUB_AUTO_FILL__", if auto_fill { "true" } else { "false" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_substituted() {
+        for script in [build_injection_script("eyJhIjoxfQ==", Some("textarea")), build_storage_script("eyJhIjoxfQ==")] {
+            assert!(!script.contains("__ARTHUB_"), "未替换的占位符");
+            assert!(script.contains(r#"const jsonBase64 = "eyJhIjoxfQ==";"#));
+        }
+        assert!(build_injection_script("", None).contains("const customSelector = null;"));
+        assert!(build_storage_script("").contains("const autoFill = false;"));
+    }
+
+    #[test]
+    fn selector_quotes_and_newlines_are_escaped() {
+        let script = build_injection_script("", Some("input[name=\"a'b\"]\n.x"));
+        let line = script.lines()
+            .find(|l| l.trim_start().starts_with("const customSelector"))
+            .unwrap();
+        // 引号和换行都被转义，选择器仍在同一行内且是合法的字符串字面量
+        assert_eq!(line.trim(), r#"const customSelector = "input[name=\"a'b\"]\n.x";"#);
+    }
+
+    #[test]
+    fn script_has_no_typescript_casts() {
+        let script = build_injection_script("", Some("textarea"));
+        let words: Vec<&str> = script
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+            .collect();
+        assert!(!words.contains(&"as"), "脚本中出现了 TypeScript 的 as 类型断言");
+        assert!(!script.contains("<HTMLElement>") && !script.contains("!."), "脚本中出现了 TypeScript 语法");
+    }
+
+    /// 检查 {} () [] 是否配对；跳过字符串字面量（含转义）和 // 行注释
+    fn assert_brackets_balanced(script: &str) {
+        let mut stack = Vec::new();
+        let mut chars = script.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' | '\'' | '`' => {
+                    while let Some(d) = chars.next() {
+                        if d == '\\' {
+                            chars.next();
+                        } else if d == c {
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => {
+                    for d in chars.by_ref() {
+                        if d == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '{' | '(' | '[' => stack.push(c),
+                '}' | ')' | ']' => {
+                    let open = match c { '}' => '{', ')' => '(', _ => '[' };
+                    assert_eq!(stack.pop(), Some(open), "多余或错配的 {}", c);
+                }
+                _ => {}
+            }
+        }
+        assert!(stack.is_empty(), "未闭合的括号: {:?}", stack);
+    }
+
+    #[test]
+    fn script_brackets_are_balanced() {
+        // 选择器中的括号和引号位于字符串字面量内，不参与配对
+        assert_brackets_balanced(&build_injection_script("eyJhIjoxfQ==", Some("input[name=\"a(b'\"]")));
+        assert_brackets_balanced(&build_injection_script("", None));
+        assert_brackets_balanced(&build_storage_script("eyJhIjoxfQ=="));
+    }
+}
@@ -0,0 +1,142 @@
+// 日志收集：把应用自身的日志写到控制台，同时保存在内存环形缓冲区中，
+// 并以 "log-line" 事件推送给控制台窗口（发布版没有 stdout 可看）
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::Manager;
+
+/// 环形缓冲区保留的最大行数
+pub const LOG_CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp: u64, // 毫秒
+    pub level: String,
+    pub message: String,
+}
+
+impl LogLine {
+    fn format(&self) -> String {
+        format!("{} [{}] {}", self.timestamp, self.level, self.message)
+    }
+}
+
+pub struct LogBuffer {
+    lines: Mutex<VecDeque<LogLine>>,
+}
+
+impl LogBuffer {
+    fn new() -> Self {
+        LogBuffer { lines: Mutex::new(VecDeque::with_capacity(LOG_CAPACITY)) }
+    }
+
+    fn push(&self, line: LogLine) {
+        if let Ok(mut lines) = self.lines.lock() {
+            if lines.len() >= LOG_CAPACITY {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    /// 最近的 n 行（按时间顺序）
+    pub fn recent(&self, n: usize) -> Vec<LogLine> {
+        match self.lines.lock() {
+            Ok(lines) => lines.iter().skip(lines.len().saturating_sub(n)).cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// 全部日志，每行一条
+    pub fn export_text(&self) -> String {
+        let mut out = String::new();
+        if let Ok(lines) = self.lines.lock() {
+            for line in lines.iter() {
+                out.push_str(&line.format());
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+struct AppLogger {
+    buffer: Arc<LogBuffer>,
+    app: OnceLock<tauri::AppHandle>,
+}
+
+thread_local! {
+    // 推送事件时若 tauri 内部又写日志，避免无限递归
+    static IN_LOGGER: Cell<bool> = const { Cell::new(false) };
+}
+
+impl log::Log for AppLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) || IN_LOGGER.with(|f| f.replace(true)) {
+            return;
+        }
+
+        let line = LogLine {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+            level: record.level().to_string(),
+            message: record.args().to_string(),
+        };
+
+        if record.level() <= log::Level::Warn {
+            eprintln!("{}", line.message);
+        } else {
+            println!("{}", line.message);
+        }
+        if let Some(app) = self.app.get() {
+            let _ = app.emit_all("log-line", line.clone());
+        }
+        self.buffer.push(line);
+
+        IN_LOGGER.with(|f| f.set(false));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: OnceLock<&'static AppLogger> = OnceLock::new();
+
+/// 安装全局 logger（只生效一次），返回共享的日志缓冲区
+pub fn init() -> Arc<LogBuffer> {
+    let logger = LOGGER.get_or_init(|| {
+        let logger: &'static AppLogger = Box::leak(Box::new(AppLogger {
+            buffer: Arc::new(LogBuffer::new()),
+            app: OnceLock::new(),
+        }));
+        if log::set_logger(logger).is_ok() {
+            log::set_max_level(if cfg!(debug_assertions) {
+                log::LevelFilter::Debug
+            } else {
+                log::LevelFilter::Info
+            });
+        }
+        logger
+    });
+    logger.buffer.clone()
+}
+
+/// 应用启动后调用，之后的日志会推送 "log-line" 事件
+pub fn attach_app(app: tauri::AppHandle) {
+    if let Some(logger) = LOGGER.get() {
+        let _ = logger.app.set(app);
+    }
+}
+
+/// 运行时调整日志级别："error" | "warn" | "info" | "debug" | "trace"
+pub fn set_level(level: &str) -> Result<(), String> {
+    let filter: log::LevelFilter = level.parse()
+        .map_err(|_| format!("无效的日志级别: {}", level))?;
+    log::set_max_level(filter);
+    Ok(())
+}
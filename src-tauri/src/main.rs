@@ -6,6 +6,14 @@ use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 
 mod asset_manager;
+mod app_icon;
+mod path_guard;
+mod fs_util;
+mod paste;
+mod log_sink;
+mod error;
+mod injection;
+mod diagnostics;
 
 #[cfg(target_os = "windows")]
 use winapi::um::winuser::{
@@ -52,6 +60,8 @@ struct AppState {
     ai_tabs: Mutex<Vec<String>>, // 存储AI标签页窗口标签
     main_window_visible: Mutex<bool>, // 主窗口是否真的可见（在前台，非最小化）
     authenticated: Mutex<bool>, // 用户是否已通过认证（Rust 端强制）
+    logs: std::sync::Arc<log_sink::LogBuffer>, // 最近的应用日志（控制台窗口读取）
+    export_roots: Mutex<Vec<std::path::PathBuf>>, // 本次运行中用户通过对话框选择的导出目录
 }
 
 // ---- 认证模块（Rust 端强制，无法被前端绕过） ----
@@ -119,9 +129,9 @@ async fn verify_user(app: tauri::AppHandle, username: String, user_id: String) -
         let state = app.state::<AppState>();
         let mut auth = state.authenticated.lock().unwrap();
         *auth = true;
-        println!("[Auth] 用户 '{}' 认证成功", username);
+        log::info!("[Auth] 用户 '{}' 认证成功", username);
     } else {
-        println!("[Auth] 用户 '{}' 认证失败", username);
+        log::info!("[Auth] 用户 '{}' 认证失败", username);
     }
 
     Ok(valid)
@@ -141,7 +151,7 @@ fn auth_logout(app: tauri::AppHandle) -> Result<(), String> {
     let state = app.state::<AppState>();
     let mut auth = state.authenticated.lock().unwrap();
     *auth = false;
-    println!("[Auth] 用户已登出");
+    log::info!("[Auth] 用户已登出");
     Ok(())
 }
 
@@ -162,7 +172,7 @@ fn create_icon_window(app: &tauri::AppHandle) -> Result<tauri::Window, Box<dyn s
             if let Some((screen_x, screen_y, screen_width, screen_height)) = get_screen_bounds_for_position(100, 100) {
                 let x = screen_x + screen_width - ICON_SIZE - 20; // 屏幕右边缘内侧 20px
                 let y = screen_y + (screen_height / 2) - (ICON_SIZE / 2); // 垂直居中
-                println!("Using default position (physical): x={}, y={}", x, y);
+                log::info!("Using default position (physical): x={}, y={}", x, y);
                 (x, y)
             } else {
                 // 如果获取屏幕信息失败，使用固定默认值
@@ -177,7 +187,7 @@ fn create_icon_window(app: &tauri::AppHandle) -> Result<tauri::Window, Box<dyn s
         (position.x, position.y)
     };
     
-    println!("Creating icon window at physical position: x={}, y={}", init_x, init_y);
+    log::info!("Creating icon window at physical position: x={}, y={}", init_x, init_y);
     
     // 在开发模式下使用开发服务器，生产模式下使用应用资源
     let icon_url = if cfg!(debug_assertions) {
@@ -246,13 +256,13 @@ fn create_icon_window(app: &tauri::AppHandle) -> Result<tauri::Window, Box<dyn s
             match icon_window.ns_window() {
                 Ok(ns_window_ptr) => {
                     if ns_window_ptr.is_null() {
-                        eprintln!("Warning: ns_window() returned null pointer");
+                        log::warn!("Warning: ns_window() returned null pointer");
                         return;
                     }
                     unsafe {
                         let ns_window: id = ns_window_ptr as *mut c_void as id;
                         if ns_window.is_null() {
-                            eprintln!("Warning: NSWindow id is null");
+                            log::warn!("Warning: NSWindow id is null");
                             return;
                         }
                         let clear_color: id = msg_send![class!(NSColor), clearColor];
@@ -271,18 +281,18 @@ fn create_icon_window(app: &tauri::AppHandle) -> Result<tauri::Window, Box<dyn s
                     }
                 }
                 Err(e) => {
-                    eprintln!("Warning: Failed to get NSWindow: {:?}", e);
+                    log::warn!("Warning: Failed to get NSWindow: {:?}", e);
                 }
             }
         }));
         if let Err(e) = result {
-            eprintln!("Warning: macOS icon window styling panicked: {:?}", e);
+            log::warn!("Warning: macOS icon window styling panicked: {:?}", e);
         }
     }
     
     // 获取窗口的缩放因子（DPI 缩放）
     let scale_factor = icon_window.scale_factor().unwrap_or(1.0);
-    println!("Icon window scale factor: {}", scale_factor);
+    log::info!("Icon window scale factor: {}", scale_factor);
     
     // 计算实际需要的逻辑大小（考虑 DPI 缩放）
     // 如果缩放因子是 1.5，那么逻辑大小应该是 64 / 1.5 = 42.67，但我们用物理大小
@@ -291,26 +301,26 @@ fn create_icon_window(app: &tauri::AppHandle) -> Result<tauri::Window, Box<dyn s
     
     // 显式设置窗口大小，确保窗口大小精确为 64x64（物理像素）
     if let Err(e) = icon_window.set_size(physical_size) {
-        eprintln!("Warning: Failed to set icon window size: {:?}", e);
+        log::warn!("Warning: Failed to set icon window size: {:?}", e);
     }
     
     // 验证窗口大小
     if let Ok(actual_size) = icon_window.inner_size() {
-        println!("Icon window actual size: {} x {} (logical)", actual_size.width, actual_size.height);
+        log::info!("Icon window actual size: {} x {} (logical)", actual_size.width, actual_size.height);
         let actual_physical = icon_window.outer_size().unwrap_or(actual_size);
-        println!("Icon window actual size: {} x {} (physical)", actual_physical.width, actual_physical.height);
+        log::info!("Icon window actual size: {} x {} (physical)", actual_physical.width, actual_physical.height);
     }
     
     // 使用物理坐标设置正确的位置（避免 DPI 缩放问题）
     if let Err(e) = icon_window.set_position(PhysicalPosition::new(init_x, init_y)) {
-        eprintln!("Warning: Failed to set icon position: {:?}", e);
+        log::warn!("Warning: Failed to set icon position: {:?}", e);
     }
     
     // 显式显示窗口
     let _ = icon_window.show();
     let _ = icon_window.set_focus();
     
-    println!("Icon window created and shown successfully at ({}, {})", init_x, init_y);
+    log::info!("Icon window created and shown successfully at ({}, {})", init_x, init_y);
     
     Ok(icon_window)
 }
@@ -422,17 +432,27 @@ fn save_icon_position(app: &tauri::AppHandle, x: i32, y: i32) {
     position.x = x;
     position.y = y;
     
-    // 保存到文件（使用 Tauri 的 app_data_dir）
-    if let Some(app_data_dir) = app.path_resolver().app_data_dir() {
-        let config_path = app_data_dir.join("icon_position.json");
-        if let Ok(json) = serde_json::to_string(&*position) {
-            let _ = std::fs::write(config_path, json);
+    // 保存到资源管理器数据库的 settings 表
+    if let Some(am) = app.try_state::<asset_manager::AssetManagerState>() {
+        if let (Ok(conn), Ok(value)) = (am.db.lock(), serde_json::to_value(&*position)) {
+            let _ = asset_manager::db::set_setting(&conn, asset_manager::db::SETTING_ICON_POSITION, &value);
         }
     }
 }
 
 // 加载图标位置
 fn load_icon_position(app: &tauri::AppHandle) -> IconPosition {
+    if let Some(am) = app.try_state::<asset_manager::AssetManagerState>() {
+        if let Ok(conn) = am.db.lock() {
+            if let Ok(Some(value)) = asset_manager::db::get_setting(&conn, asset_manager::db::SETTING_ICON_POSITION) {
+                if let Ok(position) = serde_json::from_value::<IconPosition>(value) {
+                    return position;
+                }
+            }
+        }
+    }
+
+    // 兼容旧版：位置保存在 icon_position.json
     if let Some(app_data_dir) = app.path_resolver().app_data_dir() {
         let config_path = app_data_dir.join("icon_position.json");
         if let Ok(content) = std::fs::read_to_string(config_path) {
@@ -500,35 +520,35 @@ fn icon_mouse_move(app: tauri::AppHandle, x: f64, y: f64) {
 // Tauri 命令：图标鼠标释放
 #[tauri::command]
 fn icon_mouse_up(app: tauri::AppHandle, x: f64, y: f64) {
-    println!("icon_mouse_up called: x={}, y={}", x, y);
+    log::info!("icon_mouse_up called: x={}, y={}", x, y);
     let state = app.state::<AppState>();
     let mut is_dragging = state.is_dragging.lock().unwrap();
     *is_dragging = false;
-    println!("is_dragging set to false");
+    log::info!("is_dragging set to false");
     
     if let Some(icon_window) = app.get_window("icon") {
         if let Ok(current_pos) = icon_window.outer_position() {
-            println!("Current window position before snap: ({}, {})", current_pos.x, current_pos.y);
+            log::info!("Current window position before snap: ({}, {})", current_pos.x, current_pos.y);
             
             // 边缘吸附（使用当前窗口位置）
             let snapped = snap_to_edge(current_pos.x, current_pos.y, ICON_SIZE);
-            println!("After snap: ({}, {})", snapped.0, snapped.1);
+            log::info!("After snap: ({}, {})", snapped.0, snapped.1);
             
             // 确保在可见区域内
             let constrained = constrain_to_visible_area(snapped.0, snapped.1, ICON_SIZE);
-            println!("After constrain: ({}, {})", constrained.0, constrained.1);
+            log::info!("After constrain: ({}, {})", constrained.0, constrained.1);
             
             if let Err(e) = icon_window.set_position(PhysicalPosition::new(constrained.0, constrained.1)) {
-                eprintln!("Failed to set icon position: {:?}", e);
+                log::error!("Failed to set icon position: {:?}", e);
             } else {
-                println!("Icon position set to: x={}, y={}", constrained.0, constrained.1);
+                log::info!("Icon position set to: x={}, y={}", constrained.0, constrained.1);
                 save_icon_position(&app, constrained.0, constrained.1);
             }
         } else {
-            println!("Failed to get current window position in icon_mouse_up");
+            log::info!("Failed to get current window position in icon_mouse_up");
         }
     } else {
-        println!("Icon window not found in icon_mouse_up");
+        log::info!("Icon window not found in icon_mouse_up");
     }
 }
 
@@ -581,7 +601,7 @@ fn find_main_window_hwnd() -> Option<HWND> {
 // Tauri 命令：双击图标（全局唯一：双击呼出/隐藏主界面）
 #[tauri::command]
 fn icon_click(app: tauri::AppHandle) {
-    println!("Icon double-clicked!");
+    log::info!("Icon double-clicked!");
     if let Some(main_window) = app.get_window("main") {
         let state = app.state::<AppState>();
         let mut window_visible = state.main_window_visible.lock().unwrap();
@@ -615,19 +635,19 @@ fn icon_click(app: tauri::AppHandle) {
             }
         }
         
-        println!("Main window state - is_visible: {}, is_minimized: {}, tracked: {}", 
+        log::info!("Main window state - is_visible: {}, is_minimized: {}, tracked: {}", 
                  is_visible_now, is_minimized, *window_visible);
         
         // 双击切换逻辑：如果窗口可见且不在最小化状态，则隐藏；否则显示
         if is_visible_now && !is_minimized {
             // 窗口当前可见且在前台，隐藏它
-            println!("Main window is visible, hiding...");
+            log::info!("Main window is visible, hiding...");
             let _ = main_window.hide();
             *window_visible = false;
-            println!("Main window hidden");
+            log::info!("Main window hidden");
         } else {
             // 窗口不可见或最小化，显示/恢复并前置
-            println!("Main window needs to be shown/restored...");
+            log::info!("Main window needs to be shown/restored...");
             
             #[cfg(target_os = "windows")]
             {
@@ -646,7 +666,7 @@ fn icon_click(app: tauri::AppHandle) {
             // 先显示窗口（这会恢复最小化的窗口，也会显示被隐藏的窗口）
             let show_result = main_window.show();
             if show_result.is_err() {
-                println!("Warning: First show() call failed, retrying...");
+                log::warn!("Warning: First show() call failed, retrying...");
                 std::thread::sleep(std::time::Duration::from_millis(100));
                 let _ = main_window.show();
             }
@@ -655,7 +675,7 @@ fn icon_click(app: tauri::AppHandle) {
             // 聚焦窗口以确保在前台
             let focus_result = main_window.set_focus();
             if focus_result.is_err() {
-                println!("Warning: First set_focus() call failed, retrying...");
+                log::warn!("Warning: First set_focus() call failed, retrying...");
                 std::thread::sleep(std::time::Duration::from_millis(100));
                 let _ = main_window.set_focus();
             }
@@ -667,10 +687,10 @@ fn icon_click(app: tauri::AppHandle) {
             for attempt in 1..=5 {
                 let is_visible_after = main_window.is_visible().unwrap_or(false);
                 if is_visible_after {
-                    println!("Window is now visible after {} attempt(s)", attempt);
+                    log::info!("Window is now visible after {} attempt(s)", attempt);
                     break;
                 } else {
-                    println!("Window still not visible, retrying (attempt {})...", attempt);
+                    log::info!("Window still not visible, retrying (attempt {})...", attempt);
                     let _ = main_window.show();
                     std::thread::sleep(std::time::Duration::from_millis(100));
                     let _ = main_window.set_focus();
@@ -694,31 +714,31 @@ fn icon_click(app: tauri::AppHandle) {
             let final_visible = main_window.is_visible().unwrap_or(false);
             if final_visible {
                 *window_visible = true;
-                println!("Window successfully shown and focused");
+                log::info!("Window successfully shown and focused");
             } else {
-                println!("ERROR: Failed to show window after all attempts");
+                log::info!("ERROR: Failed to show window after all attempts");
                 *window_visible = false;
             }
         }
     } else {
-        println!("ERROR: Main window not found!");
+        log::info!("ERROR: Main window not found!");
     }
 }
 
 // 窗口打开动画（未使用，保留以备将来需要）
 #[allow(dead_code)]
 fn animate_window_open(_app: &tauri::AppHandle, main_window: tauri::Window) {
-    println!("Opening main window...");
+    log::info!("Opening main window...");
     
     // 先检查当前状态
     let is_visible = main_window.is_visible().unwrap_or(false);
-    println!("Main window state before show - visible: {}", is_visible);
+    log::info!("Main window state before show - visible: {}", is_visible);
     
     // 显示窗口（这会自动恢复最小化的窗口）
     match main_window.show() {
-        Ok(_) => println!("Main window show() called successfully"),
+        Ok(_) => log::info!("Main window show() called successfully"),
         Err(e) => {
-            println!("Error showing window: {:?}", e);
+            log::info!("Error showing window: {:?}", e);
             return;
         }
     }
@@ -728,17 +748,17 @@ fn animate_window_open(_app: &tauri::AppHandle, main_window: tauri::Window) {
     
     // 聚焦窗口（确保窗口在前台）
     match main_window.set_focus() {
-        Ok(_) => println!("Main window focused successfully"),
-        Err(e) => println!("Error focusing window: {:?}", e),
+        Ok(_) => log::info!("Main window focused successfully"),
+        Err(e) => log::info!("Error focusing window: {:?}", e),
     }
     
     // 再次检查窗口可见性
     let final_visible = main_window.is_visible().unwrap_or(false);
-    println!("Main window state after show - visible: {}", final_visible);
+    log::info!("Main window state after show - visible: {}", final_visible);
     
     // 如果窗口仍然不可见，尝试再次显示
     if !final_visible {
-        println!("Window still not visible, trying show() again...");
+        log::info!("Window still not visible, trying show() again...");
         let _ = main_window.show();
         std::thread::sleep(std::time::Duration::from_millis(50));
         let _ = main_window.set_focus();
@@ -748,18 +768,52 @@ fn animate_window_open(_app: &tauri::AppHandle, main_window: tauri::Window) {
 // 窗口关闭动画
 #[allow(dead_code)]
 fn animate_window_close(_app: &tauri::AppHandle, main_window: tauri::Window) {
-    println!("Closing main window...");
+    log::info!("Closing main window...");
     let _ = main_window.hide();
-    println!("Main window hidden");
+    log::info!("Main window hidden");
 }
 
 // Tauri 命令：退出应用
 #[tauri::command]
 fn app_exit(app: tauri::AppHandle) {
-    println!("App exit requested");
+    log::info!("App exit requested");
     app.exit(0);
 }
 
+// Tauri 命令：收集诊断信息（系统、版本、数据库/缓存大小、FFmpeg、自启动），供用户附在问题反馈中
+#[tauri::command]
+async fn app_get_diagnostics(app: tauri::AppHandle) -> Result<diagnostics::Diagnostics, String> {
+    let autostart_enabled = is_autostart_enabled(app.clone()).unwrap_or(false);
+    let app_version = app.package_info().version.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<asset_manager::AssetManagerState>();
+        diagnostics::collect(&state, app_version, autostart_enabled)
+    }).await.map_err(|e| format!("收集诊断信息失败: {}", e))?
+}
+
+// Tauri 命令：获取最近的日志（默认 500 行）
+#[tauri::command]
+fn get_recent_logs(state: tauri::State<AppState>, lines: Option<usize>) -> Vec<log_sink::LogLine> {
+    state.logs.recent(lines.unwrap_or(500))
+}
+
+// Tauri 命令：把缓冲区中的全部日志导出为文本文件
+#[tauri::command]
+fn export_logs(state: tauri::State<AppState>, path: String) -> Result<(), String> {
+    let target = path_guard::ensure_not_system(&path)?;
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    fs_util::write_atomic(&target, state.logs.export_text())
+        .map_err(|e| format!("导出日志失败: {}", e))
+}
+
+// Tauri 命令：调整日志级别（error / warn / info / debug / trace）
+#[tauri::command]
+fn set_log_level(level: String) -> Result<(), String> {
+    log_sink::set_level(&level)
+}
+
 // Tauri 命令：打开控制台窗口
 #[tauri::command]
 async fn open_console_window(app: tauri::AppHandle) -> Result<String, String> {
@@ -793,7 +847,7 @@ async fn open_console_window(app: tauri::AppHandle) -> Result<String, String> {
         WindowUrl::App("console.html".into())
     };
     
-    println!("Creating console window with URL: {:?}", console_url);
+    log::info!("Creating console window with URL: {:?}", console_url);
     
     // 创建控制台窗口
     match tauri::WindowBuilder::new(
@@ -811,20 +865,83 @@ async fn open_console_window(app: tauri::AppHandle) -> Result<String, String> {
     .skip_taskbar(false)
     .build() {
         Ok(window) => {
-            println!("Console window created successfully");
+            log::info!("Console window created successfully");
             // 确保窗口显示
             let _ = window.show();
             let _ = window.set_focus();
             Ok("console".to_string())
         },
         Err(e) => {
-            eprintln!("Failed to create console window: {:?}", e);
-            eprintln!("Console URL was: {:?}", console_url);
+            log::error!("Failed to create console window: {:?}", e);
+            log::error!("Console URL was: {:?}", console_url);
             Err(format!("Failed to create console window: {:?}", e))
         }
     }
 }
 
+/// AI 标签页的窗口标签（包含平台信息，避免跨平台冲突）
+fn ai_tab_label(config_id: &str) -> String {
+    format!("ai_tab_{}_{}", config_id, std::env::consts::OS)
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct AiTabInfo {
+    label: String,
+    config_id: String,
+    title: String,
+}
+
+// Tauri 命令：列出当前打开的 AI 标签页（顺便清理已不存在的窗口）
+#[tauri::command]
+fn list_ai_tabs(app: tauri::AppHandle) -> Vec<AiTabInfo> {
+    let state = app.state::<AppState>();
+    let mut tabs = state.ai_tabs.lock().unwrap();
+    tabs.retain(|label| app.get_window(label).is_some());
+    let suffix = format!("_{}", std::env::consts::OS);
+    tabs.iter()
+        .filter_map(|label| {
+            let window = app.get_window(label)?;
+            let config_id = label.strip_prefix("ai_tab_")
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .unwrap_or(label)
+                .to_string();
+            Some(AiTabInfo {
+                label: label.clone(),
+                config_id,
+                title: window.title().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+// Tauri 命令：关闭指定配置的 AI 标签页，返回窗口是否存在
+#[tauri::command]
+fn close_ai_tab(app: tauri::AppHandle, config_id: String) -> Result<bool, String> {
+    let label = ai_tab_label(&config_id);
+    app.state::<AppState>().ai_tabs.lock().unwrap().retain(|l| l != &label);
+    match app.get_window(&label) {
+        Some(window) => {
+            window.close().map_err(|e| format!("关闭 AI 标签页失败: {}", e))?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// Tauri 命令：关闭全部 AI 标签页，返回关闭的数量
+#[tauri::command]
+fn close_all_ai_tabs(app: tauri::AppHandle) -> Result<u32, String> {
+    let labels: Vec<String> = std::mem::take(&mut *app.state::<AppState>().ai_tabs.lock().unwrap());
+    let mut closed = 0;
+    for label in labels {
+        if let Some(window) = app.get_window(&label) {
+            window.close().map_err(|e| format!("关闭 AI 标签页失败: {}", e))?;
+            closed += 1;
+        }
+    }
+    Ok(closed)
+}
+
 // Tauri 命令：打开AI标签页窗口
 #[tauri::command]
 async fn open_ai_tab(
@@ -838,30 +955,45 @@ async fn open_ai_tab(
     use tauri::WindowUrl;
     
     let platform = std::env::consts::OS;
-    println!("[{}] Opening AI tab: {} - {}", platform, title, url);
+    log::info!("[{}] Opening AI tab: {} - {}", platform, title, url);
     
-    // 使用前端传递的JSON内容（前端已经读取了文件）
-    let json_content_final = json_content;
+    // 读取保存的 AI 工具配置：地址缺省、输入框选择器和注入方式都以数据库中的配置为准
+    let tool = app.try_state::<asset_manager::AssetManagerState>().and_then(|am| {
+        am.db.read().ok().and_then(|conn| asset_manager::db::get_ai_tool(&conn, &config_id).ok().flatten())
+    });
+    let url = match &tool {
+        Some(t) if url.is_empty() => t.url.clone(),
+        _ => url,
+    };
+    let title = match &tool {
+        Some(t) if title.is_empty() => t.name.clone(),
+        _ => title,
+    };
+    let inject_mode = tool.as_ref().map_or("auto", |t| t.inject_mode.as_str()).to_string();
+    let input_selector = tool.as_ref().and_then(|t| t.input_selector.clone());
+    let auto_fill = inject_mode == "auto";
+    
+    // 使用前端传递的JSON内容（前端已经读取了文件）；注入方式为 none 时不注入
+    let json_content_final = if inject_mode == "none" { None } else { json_content };
     
     if json_file_path.is_some() {
-        println!("[{}] JSON file path provided: {:?}", platform, json_file_path);
+        log::info!("[{}] JSON file path provided: {:?}", platform, json_file_path);
     }
     
     if json_content_final.is_some() {
         let json_len = json_content_final.as_ref().unwrap().len();
         let json_preview = json_content_final.as_ref().unwrap().chars().take(100).collect::<String>();
-        println!("[{}] JSON content length: {}, preview: {}...", platform, json_len, json_preview);
+        log::info!("[{}] JSON content length: {}, preview: {}...", platform, json_len, json_preview);
     } else {
-        println!("[{}] WARNING: No JSON content provided!", platform);
+        log::warn!("[{}] WARNING: No JSON content provided!", platform);
     }
     
     // 生成唯一的窗口标签（包含平台信息，避免跨平台冲突）
-    let platform = std::env::consts::OS;
-    let window_label = format!("ai_tab_{}_{}", config_id, platform);
+    let window_label = ai_tab_label(&config_id);
     
     // 检查窗口是否已存在
     if let Some(existing_window) = app.get_window(&window_label) {
-        println!("[{}] Window {} already exists, reusing and injecting new JSON", platform, window_label);
+        log::info!("[{}] Window {} already exists, reusing and injecting new JSON", platform, window_label);
         // 窗口已存在，聚焦并刷新，同时重新注入JSON
         let _ = existing_window.set_focus();
         
@@ -870,19 +1002,10 @@ async fn open_ai_tab(
             let json_clone = json.clone();
             let window_clone = existing_window.clone();
             let url_clone = url.clone();
+            let input_selector = input_selector.clone();
             
             // 先清除旧的 JSON 数据
-            let clear_script = r#"
-                try {
-                    localStorage.removeItem('arthub_injected_json');
-                    delete window.arthubInjectedJSON;
-                    delete window.arthubInjectedJSONString;
-                    console.log('[ArtHub] Old JSON cleared');
-                } catch(e) {
-                    console.warn('[ArtHub] Failed to clear old JSON:', e);
-                }
-            "#;
-            let _ = existing_window.eval(clear_script);
+            let _ = existing_window.eval(injection::CLEAR_SCRIPT);
             
             // 刷新页面
             let _ = existing_window.eval(&format!("window.location.href = '{}';", url_clone));
@@ -897,118 +1020,11 @@ async fn open_ai_tab(
                 let json_base64 = general_purpose::STANDARD.encode(&json_clone);
                 
                 // 创建注入脚本 - 清除旧数据并注入新JSON
-                let injection_script = format!(
-                    r#"
-                    (function() {{
-                        // 清除旧的JSON数据
-                        try {{
-                            localStorage.removeItem('arthub_injected_json');
-                            delete window.arthubInjectedJSON;
-                            delete window.arthubInjectedJSONString;
-                        }} catch(e) {{
-                            console.warn('[ArtHub] Failed to clear old JSON:', e);
-                        }}
-                        
-                        function injectJSON() {{
-                            try {{
-                                const jsonBase64 = "{}";
-                                let jsonString, jsonData;
-                                
-                                try {{
-                                    jsonString = atob(jsonBase64);
-                                    jsonData = JSON.parse(jsonString);
-                                    console.log('[ArtHub] New JSON decoded and parsed successfully');
-                                }} catch(e) {{
-                                    console.error('[ArtHub] Failed to decode/parse JSON:', e);
-                                    jsonString = jsonBase64;
-                                    jsonData = null;
-                                }}
-                                
-                                // 注入新的JSON到localStorage和window对象
-                                try {{
-                                    if (jsonString) {{
-                                        localStorage.setItem('arthub_injected_json', jsonString);
-                                        window.arthubInjectedJSONString = jsonString;
-                                        if (jsonData) {{
-                                            window.arthubInjectedJSON = jsonData;
-                                        }}
-                                        console.log('[ArtHub] New JSON injected to storage');
-                                    }}
-                                }} catch(e) {{
-                                    console.warn('[ArtHub] Storage injection failed:', e);
-                                }}
-                                
-                                // 自动查找并填充输入框
-                                function autoFillInput() {{
-                                    const selectors = [
-                                        'textarea',
-                                        'input[type="text"]',
-                                        'input[type="search"]',
-                                        '[contenteditable="true"]',
-                                        '[role="textbox"]',
-                                        '.monaco-editor textarea',
-                                        '.CodeMirror textarea',
-                                        'pre[contenteditable]'
-                                    ]}};
-                                    
-                                    for (const selector of selectors) {{
-                                        const elements = document.querySelectorAll(selector);
-                                        for (const el of elements) {{
-                                            const style = window.getComputedStyle(el);
-                                            if (style.display !== 'none' && style.visibility !== 'hidden') {{
-                                                try {{
-                                                    if (el.tagName === 'TEXTAREA' || el.tagName === 'INPUT') {{
-                                                        (el as HTMLInputElement).value = jsonString;
-                                                        el.dispatchEvent(new Event('input', {{ bubbles: true }}));
-                                                        el.dispatchEvent(new Event('change', {{ bubbles: true }}));
-                                                        console.log('[ArtHub] New JSON filled into input/textarea');
-                                                        return true;
-                                                    }} else if (el.isContentEditable || el.tagName === 'PRE') {{
-                                                        el.textContent = jsonString;
-                                                        el.dispatchEvent(new Event('input', {{ bubbles: true }}));
-                                                        console.log('[ArtHub] New JSON filled into contenteditable');
-                                                        return true;
-                                                    }}
-                                                }} catch(e) {{
-                                                    console.warn('[ArtHub] Fill failed for element:', e);
-                                                }}
-                                            }}
-                                        }}
-                                    }}
-                                    return false;
-                                }}
-                                
-                                // 立即尝试填充
-                                if (!autoFillInput()) {{
-                                    // 如果失败，延迟重试
-                                    setTimeout(() => autoFillInput(), 500);
-                                    setTimeout(() => autoFillInput(), 1500);
-                                    setTimeout(() => autoFillInput(), 3000);
-                                    setTimeout(() => autoFillInput(), 5000);
-                                }}
-                                
-                                console.log('%c[ArtHub] 新JSON已自动注入！', 'color: #00ff00; font-weight: bold;');
-                            }} catch(e) {{
-                                console.error('[ArtHub] JSON injection error:', e);
-                            }}
-                        }}
-                        
-                        // 立即尝试注入
-                        injectJSON();
-                        
-                        // 监听页面加载事件
-                        if (document.readyState === 'loading') {{
-                            document.addEventListener('DOMContentLoaded', injectJSON);
-                        }}
-                        window.addEventListener('load', injectJSON);
-                        
-                        // 延迟注入，确保页面完全加载
-                        setTimeout(injectJSON, 2000);
-                        setTimeout(injectJSON, 5000);
-                    }})();
-                    "#,
-                    json_base64
-                );
+                let injection_script = if auto_fill {
+                    injection::build_injection_script(&json_base64, input_selector.as_deref())
+                } else {
+                    injection::build_storage_script(&json_base64)
+                };
                 
                 // 重试机制：尝试多次注入
                 let mut retry_count = 0;
@@ -1019,13 +1035,13 @@ async fn open_ai_tab(
                     
                     match window_clone.eval(&injection_script) {
                         Ok(_) => {
-                            println!("[ArtHub] JSON re-injection successful (attempt {})", retry_count + 1);
+                            log::info!("[ArtHub] JSON re-injection successful (attempt {})", retry_count + 1);
                             if retry_count >= 2 {
                                 break;
                             }
                         }
                         Err(e) => {
-                            println!("[ArtHub] Re-injection attempt {} failed: {:?}", retry_count + 1, e);
+                            log::info!("[ArtHub] Re-injection attempt {} failed: {:?}", retry_count + 1, e);
                         }
                     }
                     
@@ -1049,9 +1065,23 @@ async fn open_ai_tab(
     .build()
     .map_err(|e| format!("Failed to create window: {:?}", e))?;
     
-    // 记录标签页
+    // 记录标签页，窗口销毁时移除
     if let Ok(mut tabs) = app.state::<AppState>().ai_tabs.lock() {
-        tabs.push(window_label.clone());
+        if !tabs.contains(&window_label) {
+            tabs.push(window_label.clone());
+        }
+    }
+    {
+        let app_handle = app.clone();
+        let label = window_label.clone();
+        window.on_window_event(move |event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Ok(mut tabs) = app_handle.state::<AppState>().ai_tabs.lock() {
+                    tabs.retain(|l| l != &label);
+                }
+                log::info!("[ArtHub] AI tab closed: {}", label);
+            }
+        });
     }
     
     // 如果有JSON内容，自动注入到页面
@@ -1069,118 +1099,11 @@ async fn open_ai_tab(
             let json_base64 = general_purpose::STANDARD.encode(&json_clone);
             
             // 创建注入脚本 - 自动查找输入框并填充JSON
-            let injection_script = format!(
-                r#"
-                (function() {{
-                    function injectJSON() {{
-                        try {{
-                            const jsonBase64 = "{}";
-                            let jsonString, jsonData;
-                            
-                            try {{
-                                jsonString = atob(jsonBase64);
-                                jsonData = JSON.parse(jsonString);
-                                console.log('[ArtHub] JSON decoded and parsed successfully');
-                            }} catch(e) {{
-                                console.error('[ArtHub] Failed to decode/parse JSON:', e);
-                                jsonString = jsonBase64;
-                                jsonData = null;
-                            }}
-                            
-                            // 清除旧的JSON数据（确保使用新的）
-                            try {{
-                                localStorage.removeItem('arthub_injected_json');
-                                delete window.arthubInjectedJSON;
-                                delete window.arthubInjectedJSONString;
-                            }} catch(e) {{
-                                console.warn('[ArtHub] Failed to clear old JSON:', e);
-                            }}
-                            
-                            // 注入新的JSON到localStorage和window对象
-                            try {{
-                                if (jsonString) {{
-                                    localStorage.setItem('arthub_injected_json', jsonString);
-                                    window.arthubInjectedJSONString = jsonString;
-                                    if (jsonData) {{
-                                        window.arthubInjectedJSON = jsonData;
-                                    }}
-                                    console.log('[ArtHub] JSON injected to storage');
-                                }}
-                            }} catch(e) {{
-                                console.warn('[ArtHub] Storage injection failed:', e);
-                            }}
-                            
-                            // 自动查找并填充输入框
-                            function autoFillInput() {{
-                                const selectors = [
-                                    'textarea',
-                                    'input[type="text"]',
-                                    'input[type="search"]',
-                                    '[contenteditable="true"]',
-                                    '[role="textbox"]',
-                                    '.monaco-editor textarea',
-                                    '.CodeMirror textarea',
-                                    'pre[contenteditable]'
-                                ];
-                                
-                                for (const selector of selectors) {{
-                                    const elements = document.querySelectorAll(selector);
-                                    for (const el of elements) {{
-                                        const style = window.getComputedStyle(el);
-                                        if (style.display !== 'none' && style.visibility !== 'hidden') {{
-                                            try {{
-                                                if (el.tagName === 'TEXTAREA' || el.tagName === 'INPUT') {{
-                                                    (el as HTMLInputElement).value = jsonString;
-                                                    el.dispatchEvent(new Event('input', {{ bubbles: true }}));
-                                                    el.dispatchEvent(new Event('change', {{ bubbles: true }}));
-                                                    console.log('[ArtHub] JSON filled into input/textarea');
-                                                    return true;
-                                                }} else if (el.isContentEditable || el.tagName === 'PRE') {{
-                                                    el.textContent = jsonString;
-                                                    el.dispatchEvent(new Event('input', {{ bubbles: true }}));
-                                                    console.log('[ArtHub] JSON filled into contenteditable');
-                                                    return true;
-                                                }}
-                                            }} catch(e) {{
-                                                console.warn('[ArtHub] Fill failed for element:', e);
-                                            }}
-                                        }}
-                                    }}
-                                }}
-                                return false;
-                            }}
-                            
-                            // 立即尝试填充
-                            if (!autoFillInput()) {{
-                                // 如果失败，延迟重试
-                                setTimeout(() => autoFillInput(), 500);
-                                setTimeout(() => autoFillInput(), 1500);
-                                setTimeout(() => autoFillInput(), 3000);
-                                setTimeout(() => autoFillInput(), 5000);
-                            }}
-                            
-                            console.log('%c[ArtHub] JSON已自动注入！', 'color: #00ff00; font-weight: bold;');
-                        }} catch(e) {{
-                            console.error('[ArtHub] JSON injection error:', e);
-                        }}
-                    }}
-                    
-                    // 立即尝试注入
-                    injectJSON();
-                    
-                    // 监听页面加载事件
-                    if (document.readyState === 'loading') {{
-                        document.addEventListener('DOMContentLoaded', injectJSON);
-                    }}
-                    window.addEventListener('load', injectJSON);
-                    
-                    // 延迟注入，确保页面完全加载
-                    setTimeout(injectJSON, 2000);
-                    setTimeout(injectJSON, 5000);
-                }})();
-                "#,
-                json_base64
-            );
+            let injection_script = if auto_fill {
+                injection::build_injection_script(&json_base64, input_selector.as_deref())
+            } else {
+                injection::build_storage_script(&json_base64)
+            };
             
             // 重试机制：尝试多次注入
             let mut retry_count = 0;
@@ -1193,16 +1116,16 @@ async fn open_ai_tab(
                 // 尝试执行注入脚本
                 match window_clone.eval(&injection_script) {
                     Ok(_) => {
-                        println!("[ArtHub] JSON injection script executed successfully (attempt {})", retry_count + 1);
+                        log::info!("[ArtHub] JSON injection script executed successfully (attempt {})", retry_count + 1);
                         if retry_count >= 2 {
                             break;
                         }
                     }
                     Err(e) => {
-                        println!("[ArtHub] Injection attempt {} failed: {:?}", retry_count + 1, e);
+                        log::info!("[ArtHub] Injection attempt {} failed: {:?}", retry_count + 1, e);
                         // 如果是范围错误，说明需要配置远程域访问
                         if e.to_string().contains("Scope not defined") {
-                            eprintln!("[ArtHub] Warning: Remote domain access not configured. Please configure tauri.conf.json");
+                            log::warn!("[ArtHub] Warning: Remote domain access not configured. Please configure tauri.conf.json");
                             break;
                         }
                     }
@@ -1216,77 +1139,96 @@ async fn open_ai_tab(
     Ok(window_label)
 }
 
-// Tauri 命令：模拟 Ctrl+V 粘贴操作
+// Tauri 命令：模拟粘贴操作（Windows/Linux 为 Ctrl+V，macOS 为 Command+V）
+// window_label 为目标 AI 标签页；未指定时要求前台窗口是任意一个 AI 标签页
+// 前台窗口不是目标窗口时返回错误，不会把内容粘贴到别的程序里
 #[tauri::command]
-async fn simulate_paste(delay_ms: u64) -> Result<(), String> {
-    println!("[ArtHub] simulate_paste called with delay: {}ms", delay_ms);
+async fn simulate_paste(app: tauri::AppHandle, delay_ms: u64, window_label: Option<String>) -> Result<(), String> {
+    log::info!("[ArtHub] simulate_paste called with delay: {}ms, target: {:?}", delay_ms, window_label);
+    
+    let tabs = app.state::<AppState>().ai_tabs.lock().unwrap().clone();
+    let targets: Vec<tauri::Window> = match &window_label {
+        Some(label) => {
+            if !tabs.contains(label) {
+                return Err(format!("目标窗口不是 AI 标签页: {}", label));
+            }
+            let window = app.get_window(label)
+                .ok_or_else(|| format!("AI 标签页不存在: {}", label))?;
+            let _ = window.set_focus();
+            vec![window]
+        }
+        None => tabs.iter().filter_map(|label| app.get_window(label)).collect(),
+    };
+    if targets.is_empty() {
+        return Err("没有打开的 AI 标签页".to_string());
+    }
     
     // 等待指定的延迟时间，让浏览器窗口加载
     tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
     
-    println!("[ArtHub] Delay completed, attempting to send Ctrl+V...");
-    
-    #[cfg(target_os = "windows")]
-    {
-        use winapi::um::winuser::{VK_CONTROL, keybd_event};
-        
-        // 定义虚拟键码
-        const VK_V: u16 = 0x56;
-        
-        // 尝试多次发送，以确保成功
-        for attempt in 0..3 {
-            if attempt > 0 {
-                println!("[ArtHub] Retry attempt {}", attempt + 1);
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            }
-            
-            unsafe {
-                // 方法1：使用 keybd_event（更兼容但已弃用）
-                // 按下 Ctrl
-                keybd_event(VK_CONTROL as u8, 0, 0, 0);
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                
-                // 按下 V
-                keybd_event(VK_V as u8, 0, 0, 0);
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                
-                // 释放 V
-                keybd_event(VK_V as u8, 0, 2, 0); // KEYEVENTF_KEYUP = 2
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                
-                // 释放 Ctrl
-                keybd_event(VK_CONTROL as u8, 0, 2, 0);
-                
-                println!("[ArtHub] Sent Ctrl+V using keybd_event (attempt {})", attempt + 1);
-            }
-        }
-        
-        println!("[ArtHub] Simulated Ctrl+V paste operation completed");
-        return Ok(());
+    if !targets.iter().any(paste::is_foreground) {
+        return Err("前台窗口不是目标 AI 标签页，已取消粘贴".to_string());
     }
     
-    #[cfg(not(target_os = "windows"))]
-    {
-        return Err("Keyboard simulation is only supported on Windows".to_string());
+    paste::send_paste_shortcut()?;
+    log::info!("[ArtHub] Sent paste shortcut");
+    Ok(())
+}
+
+/// ComfyUI 请求默认超时（秒），远程实例冷启动较慢时可由前端调大
+const COMFYUI_DEFAULT_TIMEOUT_SECS: u64 = 5;
+
+/// 拼接 ComfyUI 接口地址：comfy_url + 可选的反向代理路径前缀 + 接口路径
+fn comfyui_endpoint(comfy_url: &str, base_path: Option<&str>, path: &str) -> String {
+    let mut url = comfy_url.trim_end_matches('/').to_string();
+    if let Some(prefix) = base_path.map(|p| p.trim_matches('/')).filter(|p| !p.is_empty()) {
+        url.push('/');
+        url.push_str(prefix);
     }
+    url.push_str(path);
+    url
+}
+
+/// 创建访问 ComfyUI 的 HTTP 客户端，auth_header 作为 Authorization 头附加到所有请求
+fn comfyui_client(auth_header: Option<&str>, timeout_secs: Option<u64>) -> Result<reqwest::Client, String> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(auth) = auth_header.map(str::trim).filter(|a| !a.is_empty()) {
+        let value = reqwest::header::HeaderValue::from_str(auth)
+            .map_err(|e| format!("Invalid auth header: {}", e))?;
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout_secs.unwrap_or(COMFYUI_DEFAULT_TIMEOUT_SECS).max(1)))
+        .default_headers(headers)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// 401/403 说明地址正确但认证失败，直接报错而不是静默退回剪贴板方案
+fn comfyui_auth_error(status: reqwest::StatusCode) -> Option<String> {
+    matches!(status, reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN)
+        .then(|| format!("ComfyUI 认证失败 ({})，请检查访问令牌或用户名密码", status))
 }
 
 // Tauri 命令：将工作流发送到 ComfyUI 服务器（绕过 CORS）
+// - auth_header: 可选的 Authorization 头（如 "Bearer xxx" 或 "Basic xxx"）
+// - base_path: 反向代理下的路径前缀（如 "/comfy"）
+// - timeout_secs: 每个请求的超时时间，默认 5 秒
 #[tauri::command]
 async fn send_workflow_to_comfyui(
     comfy_url: String,
     workflow_json: String,
+    auth_header: Option<String>,
+    base_path: Option<String>,
+    timeout_secs: Option<u64>,
 ) -> Result<String, String> {
-    println!("[ArtHub] Sending workflow to ComfyUI: {}", comfy_url);
+    log::info!("[ArtHub] Sending workflow to ComfyUI: {}", comfy_url);
     
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = comfyui_client(auth_header.as_deref(), timeout_secs)?;
     
     // 方案1（最佳）：尝试 ArtHub 扩展 API
-    let extension_url = format!("{}/arthub/load_workflow", comfy_url);
-    println!("[ArtHub] Trying ArtHub extension API: {}", extension_url);
+    let extension_url = comfyui_endpoint(&comfy_url, base_path.as_deref(), "/arthub/load_workflow");
+    log::info!("[ArtHub] Trying ArtHub extension API: {}", extension_url);
     
     match client.post(&extension_url)
         .header("Content-Type", "application/json")
@@ -1296,12 +1238,14 @@ async fn send_workflow_to_comfyui(
     {
         Ok(response) => {
             if response.status().is_success() {
-                println!("[ArtHub] Workflow sent to ArtHub extension successfully!");
+                log::info!("[ArtHub] Workflow sent to ArtHub extension successfully!");
                 return Ok("extension".to_string());
+            } else if let Some(err) = comfyui_auth_error(response.status()) {
+                return Err(err);
             } else {
                 // 静默处理 404，这是正常的（扩展未安装时）
                 if response.status() != 404 {
-                    println!("[ArtHub] ArtHub extension API returned status: {}", response.status());
+                    log::info!("[ArtHub] ArtHub extension API returned status: {}", response.status());
                 }
             }
         }
@@ -1310,14 +1254,14 @@ async fn send_workflow_to_comfyui(
             // 这些错误是正常的（ComfyUI 未运行或扩展未安装时）
             let error_str = e.to_string();
             if !error_str.contains("timeout") && !error_str.contains("connection") && !error_str.contains("Failed to resolve") {
-                println!("[ArtHub] ArtHub extension error: {:?}", e);
+                log::info!("[ArtHub] ArtHub extension error: {:?}", e);
             }
         }
     }
     
     // 方案2：通过 userdata API 保存工作流
-    let userdata_url = format!("{}/api/userdata/workflows/arthub_current.json", comfy_url);
-    println!("[ArtHub] Trying userdata API: {}", userdata_url);
+    let userdata_url = comfyui_endpoint(&comfy_url, base_path.as_deref(), "/api/userdata/workflows/arthub_current.json");
+    log::info!("[ArtHub] Trying userdata API: {}", userdata_url);
     
     match client.post(&userdata_url)
         .header("Content-Type", "application/json")
@@ -1327,12 +1271,14 @@ async fn send_workflow_to_comfyui(
     {
         Ok(response) => {
             if response.status().is_success() {
-                println!("[ArtHub] Workflow saved via userdata API");
+                log::info!("[ArtHub] Workflow saved via userdata API");
                 return Ok("userdata".to_string());
+            } else if let Some(err) = comfyui_auth_error(response.status()) {
+                return Err(err);
             } else {
                 // 静默处理 404，这是正常的（API 不可用时）
                 if response.status() != 404 {
-                    println!("[ArtHub] userdata API failed with status: {}", response.status());
+                    log::info!("[ArtHub] userdata API failed with status: {}", response.status());
                 }
             }
         }
@@ -1340,16 +1286,113 @@ async fn send_workflow_to_comfyui(
             // 静默处理连接错误，避免在控制台产生噪音
             let error_str = e.to_string();
             if !error_str.contains("timeout") && !error_str.contains("connection") && !error_str.contains("Failed to resolve") {
-                println!("[ArtHub] userdata API request failed: {:?}", e);
+                log::info!("[ArtHub] userdata API request failed: {:?}", e);
             }
         }
     }
     
     // 如果所有 API 方案都失败，返回剪贴板方案标识
-    println!("[ArtHub] All API methods failed, falling back to clipboard");
+    log::info!("[ArtHub] All API methods failed, falling back to clipboard");
     Ok("clipboard".to_string())
 }
 
+/// 上传到 ComfyUI 的图片大小上限
+const COMFYUI_UPLOAD_MAX_BYTES: u64 = 256 * 1024 * 1024;
+/// 上传图片的默认超时（秒），大图走远程实例时需要更久
+const COMFYUI_UPLOAD_TIMEOUT_SECS: u64 = 120;
+
+#[derive(Debug, serde::Deserialize)]
+struct ComfyUploadResponse {
+    name: String,
+    #[serde(default)]
+    subfolder: String,
+}
+
+// Tauri 命令：上传输入图片到 ComfyUI（/upload/image），返回 LoadImage 节点可用的服务器端文件名
+// 有子目录时返回 "subfolder/name"
+#[tauri::command]
+async fn comfyui_upload_image(
+    comfy_url: String,
+    image_path: String,
+    subfolder: Option<String>,
+    auth_header: Option<String>,
+    base_path: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<String, String> {
+    let path = std::path::PathBuf::from(&image_path);
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("无法读取图片 {}: {}", image_path, e))?
+        .len();
+    if size > COMFYUI_UPLOAD_MAX_BYTES {
+        return Err(format!(
+            "图片过大（{} MB），上传上限为 {} MB",
+            size / 1024 / 1024,
+            COMFYUI_UPLOAD_MAX_BYTES / 1024 / 1024
+        ));
+    }
+    let file_name = path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("无效的图片路径: {}", image_path))?;
+    let bytes = tokio::task::spawn_blocking(move || std::fs::read(&path))
+        .await
+        .map_err(|e| format!("读取线程失败: {}", e))?
+        .map_err(|e| format!("无法读取图片 {}: {}", image_path, e))?;
+
+    let part = reqwest::multipart::Part::bytes(bytes)
+        .file_name(file_name.clone())
+        .mime_str(mime_for_image(&file_name))
+        .map_err(|e| format!("构造上传请求失败: {}", e))?;
+    let mut form = reqwest::multipart::Form::new()
+        .part("image", part)
+        .text("overwrite", "true");
+    if let Some(sub) = subfolder.as_deref().filter(|s| !s.is_empty()) {
+        form = form.text("subfolder", sub.to_string());
+    }
+
+    let client = comfyui_client(auth_header.as_deref(), Some(timeout_secs.unwrap_or(COMFYUI_UPLOAD_TIMEOUT_SECS)))?;
+    let url = comfyui_endpoint(&comfy_url, base_path.as_deref(), "/upload/image");
+    log::info!("[ArtHub] Uploading image to ComfyUI: {} -> {}", file_name, url);
+
+    let response = client.post(&url)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("无法连接 ComfyUI: {}", e))?;
+    let status = response.status();
+    if let Some(err) = comfyui_auth_error(status) {
+        return Err(err);
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("ComfyUI 上传失败 ({}): {}", status, body.trim()));
+    }
+
+    let uploaded: ComfyUploadResponse = response.json()
+        .await
+        .map_err(|e| format!("无法解析 ComfyUI 上传响应: {}", e))?;
+    if uploaded.subfolder.is_empty() {
+        Ok(uploaded.name)
+    } else {
+        Ok(format!("{}/{}", uploaded.subfolder, uploaded.name))
+    }
+}
+
+/// 按扩展名推断上传图片的 MIME 类型
+fn mime_for_image(file_name: &str) -> &'static str {
+    match std::path::Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("bmp") => "image/bmp",
+        _ => "application/octet-stream",
+    }
+}
+
 // Tauri 命令：打开开发者工具
 #[tauri::command]
 fn open_devtools(window: tauri::Window) -> Result<(), String> {
@@ -1401,7 +1444,7 @@ unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: isize) -> BOOL {
             .to_string_lossy()
             .to_string();
         
-        println!("[ArtHub] Checking explorer window: title='{}', class='{}'", window_title, class_str);
+        log::info!("[ArtHub] Checking explorer window: title='{}', class='{}'", window_title, class_str);
         
         // 提取路径的最后一部分（文件夹名）
         let target_folder_name = find_data.target_path
@@ -1445,7 +1488,7 @@ unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: isize) -> BOOL {
         }
         
         if matched {
-            println!("[ArtHub] Found existing explorer window for path: {} (title: {}, class: {})", 
+            log::info!("[ArtHub] Found existing explorer window for path: {} (title: {}, class: {})", 
                      find_data.target_path, window_title, class_str);
             find_data.found_hwnd = Some(hwnd);
             return 0; // 停止枚举
@@ -1456,9 +1499,10 @@ unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: isize) -> BOOL {
 }
 
 // Tauri 命令：获取应用图标
+// size 为期望的像素尺寸，返回值中的 size 为实际提取到的尺寸
 #[tauri::command]
 #[cfg(target_os = "windows")]
-fn get_app_icon(path: String) -> Result<String, String> {
+fn get_app_icon(path: String, size: u32) -> Result<app_icon::AppIcon, String> {
     use std::path::Path;
     
     let app_path = Path::new(&path);
@@ -1475,33 +1519,133 @@ fn get_app_icon(path: String) -> Result<String, String> {
         return Err("不支持的文件类型".to_string());
     }
     
-    // 对于 .lnk 文件，需要先解析快捷方式获取目标路径
-    let target_path = if is_lnk {
-        // 尝试从快捷方式读取目标路径
-        // 注意：Windows 的 .lnk 文件解析比较复杂，这里简化处理
-        // 实际应用中可能需要使用专门的库如 shortcut-rs
-        path.clone()
-    } else {
-        path.clone()
-    };
+    app_icon::extract_icon(&path, size).map_err(|e| {
+        log::error!("提取图标失败: {}", e);
+        e
+    })
+}
+
+#[tauri::command]
+#[cfg(not(target_os = "windows"))]
+fn get_app_icon(path: String, size: u32) -> Result<app_icon::AppIcon, String> {
+    // macOS 为 .app 包（目录），Linux 为 .desktop 文件
+    if !std::path::Path::new(&path).exists() {
+        return Err("文件不存在".to_string());
+    }
     
-    // 使用 windows-icons 提取图标
-    match windows_icons::get_icon_base64_by_path(&target_path) {
-        Ok(base64_icon) => {
-            // 返回 data URI 格式
-            Ok(format!("data:image/png;base64,{}", base64_icon))
+    app_icon::extract_icon(&path, size).map_err(|e| {
+        log::error!("提取图标失败: {}", e);
+        e
+    })
+}
+
+// 已登记的数据存储目录（设置 storage.roots）
+fn storage_roots(conn: &rusqlite::Connection) -> Vec<std::path::PathBuf> {
+    asset_manager::db::get_setting(conn, asset_manager::db::SETTING_STORAGE_ROOTS)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn add_storage_root(app: &tauri::AppHandle, dir: std::path::PathBuf) -> Result<(), String> {
+    let am = app.state::<asset_manager::AssetManagerState>();
+    let conn = am.db.lock().map_err(|e| format!("数据库被占用: {}", e))?;
+    let mut roots = storage_roots(&conn);
+    if !roots.contains(&dir) {
+        roots.push(dir);
+        asset_manager::db::set_setting(&conn, asset_manager::db::SETTING_STORAGE_ROOTS, &serde_json::json!(roots))?;
+    }
+    Ok(())
+}
+
+// 允许前端写入的根目录：应用数据目录 + 资源管理器中登记的非只读文件夹
+// + 通过 select_writable_directory 选择的存储目录和本次运行的导出目录
+fn writable_roots(app: &tauri::AppHandle) -> Vec<std::path::PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(app_data_dir) = app.path_resolver().app_data_dir() {
+        roots.push(app_data_dir);
+    }
+    if let Some(am) = app.try_state::<asset_manager::AssetManagerState>() {
+        if let Ok(conn) = am.db.read() {
+            if let Ok(folders) = asset_manager::db::get_folders(&conn, None) {
+                roots.extend(
+                    folders.into_iter()
+                        .filter(|f| !f.is_readonly)
+                        .map(|f| std::path::PathBuf::from(f.path)),
+                );
+            }
+            roots.extend(storage_roots(&conn));
         }
-        Err(e) => {
-            eprintln!("提取图标失败: {:?}", e);
-            Err(format!("提取图标失败: {}", e))
+    }
+    if let Ok(export_roots) = app.state::<AppState>().export_roots.lock() {
+        roots.extend(export_roots.iter().cloned());
+    }
+    roots
+}
+
+// writable_roots 只排除了只读文件夹本身，可写文件夹内嵌套的只读文件夹仍需单独检查
+fn ensure_not_readonly(app: &tauri::AppHandle, path: &std::path::Path) -> Result<(), String> {
+    if let Some(am) = app.try_state::<asset_manager::AssetManagerState>() {
+        let conn = am.db.read()?;
+        let path = path.to_string_lossy();
+        if asset_manager::db::is_path_readonly(&conn, &path)? {
+            return Err(error::ArtHubError::ReadOnly(format!("该路径位于只读文件夹中，禁止修改: {}", path)).into());
         }
     }
+    Ok(())
 }
 
+// Tauri 命令：由后端弹出目录选择对话框，用户选择的目录加入可写入的根目录。
+// kind = "storage"：数据存储目录，持久保存；其他（如 "export"）：导出目录，仅本次运行有效
 #[tauri::command]
-#[cfg(not(target_os = "windows"))]
-fn get_app_icon(_path: String) -> Result<String, String> {
-    Err("图标提取功能仅在 Windows 上支持".to_string())
+async fn select_writable_directory(
+    app: tauri::AppHandle,
+    kind: String,
+    title: Option<String>,
+) -> Result<Option<String>, String> {
+    require_auth(&app.state::<AppState>())?;
+    let mut dialog = tauri::api::dialog::blocking::FileDialogBuilder::new();
+    if let Some(title) = title.as_deref() {
+        dialog = dialog.set_title(title);
+    }
+    let Some(dir) = dialog.pick_folder() else {
+        return Ok(None);
+    };
+    let dir = path_guard::ensure_not_system(&dir.to_string_lossy())?;
+
+    if kind == "storage" {
+        add_storage_root(&app, dir.clone())?;
+    } else {
+        let state = app.state::<AppState>();
+        let mut export_roots = state.export_roots.lock().map_err(|e| format!("锁定导出目录失败: {}", e))?;
+        if !export_roots.contains(&dir) {
+            export_roots.push(dir.clone());
+        }
+    }
+    log::info!("[ArtHub] 已允许写入目录 ({}): {}", kind, dir.display());
+    Ok(Some(dir.to_string_lossy().to_string()))
+}
+
+// Tauri 命令：升级兼容——旧版本在前端保存的存储目录没有登记到后端。
+// 仅在尚未登记任何存储目录、且目录中已有 arthub_data.json（确实用作过存储目录）时接受一次，
+// 之后只能通过 select_writable_directory 添加
+#[tauri::command]
+fn adopt_storage_root(app: tauri::AppHandle, path: String) -> Result<bool, String> {
+    require_auth(&app.state::<AppState>())?;
+    let dir = path_guard::ensure_not_system(&path)?;
+    if !dir.join("arthub_data.json").is_file() {
+        return Ok(false);
+    }
+    {
+        let am = app.state::<asset_manager::AssetManagerState>();
+        let conn = am.db.lock().map_err(|e| format!("数据库被占用: {}", e))?;
+        if !storage_roots(&conn).is_empty() {
+            return Ok(false);
+        }
+    }
+    add_storage_root(&app, dir)?;
+    Ok(true)
 }
 
 // Tauri 命令：写入文件（绕过文件系统作用域限制）
@@ -1509,9 +1653,8 @@ fn get_app_icon(_path: String) -> Result<String, String> {
 fn write_binary_file_with_path(app: tauri::AppHandle, file_path: String, content: Vec<u8>) -> Result<(), String> {
     require_auth(&app.state::<AppState>())?;
     use std::fs;
-    use std::path::Path;
     
-    let path = Path::new(&file_path);
+    let path = &path_guard::ensure_allowed(&file_path, &writable_roots(&app))?;
     
     // 确保父目录存在
     if let Some(parent) = path.parent() {
@@ -1520,8 +1663,8 @@ fn write_binary_file_with_path(app: tauri::AppHandle, file_path: String, content
         }
     }
     
-    // 写入二进制文件
-    match fs::write(path, content) {
+    // 写入二进制文件（临时文件 + rename，避免崩溃时留下截断的文件）
+    match fs_util::write_atomic(path, content) {
         Ok(_) => Ok(()),
         Err(e) => Err(format!("写入文件失败: {}", e)),
     }
@@ -1531,9 +1674,8 @@ fn write_binary_file_with_path(app: tauri::AppHandle, file_path: String, content
 fn write_file_with_path(app: tauri::AppHandle, file_path: String, content: String) -> Result<(), String> {
     require_auth(&app.state::<AppState>())?;
     use std::fs;
-    use std::path::Path;
     
-    let path = Path::new(&file_path);
+    let path = &path_guard::ensure_allowed(&file_path, &writable_roots(&app))?;
     
     // 确保父目录存在
     if let Some(parent) = path.parent() {
@@ -1542,23 +1684,41 @@ fn write_file_with_path(app: tauri::AppHandle, file_path: String, content: Strin
         }
     }
     
-    // 写入文件
-    if let Err(e) = fs::write(path, content) {
+    // 写入文件（临时文件 + rename，避免崩溃时留下截断的文件）
+    if let Err(e) = fs_util::write_atomic(path, content) {
         return Err(format!("写入文件失败: {}", e));
     }
     
     Ok(())
 }
 
+// Tauri 命令：不受根目录限制的写入（仅用于用户在保存对话框中明确选择的位置）
+// 仍然拒绝 '..' 与系统目录
+#[tauri::command]
+fn write_file_unrestricted(app: tauri::AppHandle, file_path: String, content: Vec<u8>) -> Result<(), String> {
+    require_auth(&app.state::<AppState>())?;
+    use std::fs;
+    
+    let path = path_guard::ensure_not_system(&file_path)?;
+    log::info!("[ArtHub] Unrestricted write: {}", path.display());
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    fs_util::write_atomic(&path, content).map_err(|e| format!("写入文件失败: {}", e))
+}
+
 // Tauri 命令：重命名文件或目录（绕过文件系统作用域限制）
 #[tauri::command]
 fn rename_file_with_path(app: tauri::AppHandle, old_path: String, new_path: String) -> Result<(), String> {
     require_auth(&app.state::<AppState>())?;
     use std::fs;
-    use std::path::Path;
     
-    let old_path_obj = Path::new(&old_path);
-    let new_path_obj = Path::new(&new_path);
+    let roots = writable_roots(&app);
+    let old_path_obj = &path_guard::ensure_allowed(&old_path, &roots)?;
+    let new_path_obj = &path_guard::ensure_allowed(&new_path, &roots)?;
+    ensure_not_readonly(&app, old_path_obj)?;
+    ensure_not_readonly(&app, new_path_obj)?;
     
     // 确保新路径的父目录存在
     if let Some(parent) = new_path_obj.parent() {
@@ -1579,10 +1739,12 @@ fn rename_file_with_path(app: tauri::AppHandle, old_path: String, new_path: Stri
 fn rename_directory_with_path(app: tauri::AppHandle, old_path: String, new_path: String) -> Result<(), String> {
     require_auth(&app.state::<AppState>())?;
     use std::fs;
-    use std::path::Path;
     
-    let old = Path::new(&old_path);
-    let new_path_obj = Path::new(&new_path);
+    let roots = writable_roots(&app);
+    let old = &path_guard::ensure_allowed(&old_path, &roots)?;
+    let new_path_obj = &path_guard::ensure_allowed(&new_path, &roots)?;
+    ensure_not_readonly(&app, old)?;
+    ensure_not_readonly(&app, new_path_obj)?;
     
     // 确保新路径的父目录存在
     if let Some(parent) = new_path_obj.parent() {
@@ -1598,20 +1760,80 @@ fn rename_directory_with_path(app: tauri::AppHandle, old_path: String, new_path:
     }
 }
 
-#[tauri::command]
-fn read_file_with_path(app: tauri::AppHandle, file_path: String) -> Result<String, String> {
-    require_auth(&app.state::<AppState>())?;
-    use std::fs;
-    use std::path::Path;
+// 文本读取默认上限，超出部分截断
+const DEFAULT_READ_MAX_BYTES: u64 = 4 * 1024 * 1024;
+// strict 模式（读取 JSON 数据文件）的默认上限：只用于防止误读超大文件，正常数据文件远小于此
+const STRICT_READ_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+// 文本文件读取结果
+#[derive(Debug, Clone, Serialize)]
+struct TextFileContent {
+    content: String,
+    truncated: bool,
+}
+
+// 最多读取 max_bytes 字节并按 UTF-8 解码
+// 截断处落在多字节字符中间时丢弃不完整的尾部；真正的非法 UTF-8 返回错误
+fn read_text_limited(file_path: &str, max_bytes: u64) -> Result<TextFileContent, String> {
+    use std::io::Read;
     
-    let path = Path::new(&file_path);
+    let file = std::fs::File::open(file_path)
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    let mut buf = Vec::new();
+    // 多读 1 字节用于判断是否还有剩余内容
+    file.take(max_bytes.saturating_add(1))
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("读取文件失败: {}", e))?;
     
-    match fs::read_to_string(path) {
-        Ok(content) => Ok(content),
-        Err(e) => Err(format!("读取文件失败: {}", e)),
+    let truncated = buf.len() as u64 > max_bytes;
+    if truncated {
+        buf.truncate(max_bytes as usize);
+    }
+    
+    match String::from_utf8(buf) {
+        Ok(content) => Ok(TextFileContent { content, truncated }),
+        Err(e) => {
+            let utf8_err = e.utf8_error();
+            // error_len 为 None 表示只是结尾的字符不完整（被截断）
+            if truncated && utf8_err.error_len().is_none() {
+                let mut bytes = e.into_bytes();
+                bytes.truncate(utf8_err.valid_up_to());
+                let content = String::from_utf8(bytes).unwrap_or_default();
+                Ok(TextFileContent { content, truncated })
+            } else {
+                Err(format!("文件不是有效的 UTF-8 文本（第 {} 字节）: {}", utf8_err.valid_up_to(), file_path))
+            }
+        }
     }
 }
 
+// Tauri 命令：读取文本文件，max_bytes 默认 4MB，超出部分截断并标记 truncated。
+// strict 为 true 时（JSON 数据文件，截断后再保存会丢数据）超出上限直接返回错误，默认上限 256MB
+#[tauri::command]
+fn read_file_with_path(
+    app: tauri::AppHandle,
+    file_path: String,
+    max_bytes: Option<u64>,
+    strict: Option<bool>,
+) -> Result<TextFileContent, String> {
+    require_auth(&app.state::<AppState>())?;
+    let strict = strict.unwrap_or(false);
+    let default_max = if strict { STRICT_READ_MAX_BYTES } else { DEFAULT_READ_MAX_BYTES };
+    let max_bytes = max_bytes.unwrap_or(default_max);
+    let result = read_text_limited(&file_path, max_bytes)?;
+    if strict && result.truncated {
+        return Err(format!("文件超过 {} 字节的读取上限，拒绝截断读取: {}", max_bytes, file_path));
+    }
+    Ok(result)
+}
+
+// Tauri 命令：读取文本文件开头的 bytes 字节用于预览
+#[tauri::command]
+fn read_file_head(app: tauri::AppHandle, path: String, bytes: u64) -> Result<TextFileContent, String> {
+    require_auth(&app.state::<AppState>())?;
+    read_text_limited(&path, bytes)
+}
+
 // Tauri 命令：读取二进制文件（用于创建 Blob URL）
 #[tauri::command]
 fn read_binary_file_with_path(app: tauri::AppHandle, file_path: String) -> Result<Vec<u8>, String> {
@@ -1636,6 +1858,16 @@ fn file_exists_with_path(app: tauri::AppHandle, file_path: String) -> Result<boo
     Ok(Path::new(&file_path).exists())
 }
 
+// Tauri 命令：批量检查文件是否存在，返回值与 paths 一一对应
+#[tauri::command]
+async fn files_exist_with_path(app: tauri::AppHandle, paths: Vec<String>) -> Result<Vec<bool>, String> {
+    require_auth(&app.state::<AppState>())?;
+    
+    tokio::task::spawn_blocking(move || fs_util::paths_exist(&paths))
+        .await
+        .map_err(|e| format!("检查文件失败: {}", e))
+}
+
 // Tauri 命令：创建目录（绕过文件系统作用域限制）
 #[tauri::command]
 fn create_dir_with_path(app: tauri::AppHandle, dir_path: String, recursive: bool) -> Result<(), String> {
@@ -1657,7 +1889,7 @@ fn create_dir_with_path(app: tauri::AppHandle, dir_path: String, recursive: bool
 // Tauri 命令：启动应用（Windows 上使用 cmd start）
 #[tauri::command]
 fn launch_app(app_path: String) -> Result<(), String> {
-    println!("[ArtHub] Launching app: {}", app_path);
+    log::info!("[ArtHub] Launching app: {}", app_path);
     
     #[cfg(target_os = "windows")]
     {
@@ -1681,12 +1913,12 @@ fn launch_app(app_path: String) -> Result<(), String> {
         
         match result {
             Ok(_child) => {
-                println!("[ArtHub] Successfully launched app: {}", app_path);
+                log::info!("[ArtHub] Successfully launched app: {}", app_path);
                 Ok(())
             }
             Err(e) => {
                 let error_msg = format!("Failed to launch app: {}", e);
-                println!("[ArtHub] Error: {}", error_msg);
+                log::info!("[ArtHub] Error: {}", error_msg);
                 Err(error_msg)
             }
         }
@@ -1703,12 +1935,12 @@ fn launch_app(app_path: String) -> Result<(), String> {
         
         match result {
             Ok(_child) => {
-                println!("[ArtHub] Successfully launched app: {}", app_path);
+                log::info!("[ArtHub] Successfully launched app: {}", app_path);
                 Ok(())
             }
             Err(e) => {
                 let error_msg = format!("Failed to launch app: {}", e);
-                println!("[ArtHub] Error: {}", error_msg);
+                log::info!("[ArtHub] Error: {}", error_msg);
                 Err(error_msg)
             }
         }
@@ -1725,12 +1957,12 @@ fn launch_app(app_path: String) -> Result<(), String> {
         
         match result {
             Ok(_child) => {
-                println!("[ArtHub] Successfully launched app: {}", app_path);
+                log::info!("[ArtHub] Successfully launched app: {}", app_path);
                 Ok(())
             }
             Err(e) => {
                 let error_msg = format!("Failed to launch app: {}", e);
-                println!("[ArtHub] Error: {}", error_msg);
+                log::info!("[ArtHub] Error: {}", error_msg);
                 Err(error_msg)
             }
         }
@@ -1742,10 +1974,59 @@ fn launch_app(app_path: String) -> Result<(), String> {
     }
 }
 
+// Tauri 命令：在文件管理器中显示并选中文件（与 open_folder 不同，定位到具体文件）
+#[tauri::command]
+fn reveal_file(path: String) -> Result<(), String> {
+    log::info!("[ArtHub] Revealing file: {}", path);
+
+    if !std::path::Path::new(&path).exists() {
+        return Err(format!("File not found: {}", path));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::process::Command;
+        use std::os::windows::process::CommandExt;
+
+        // explorer 需要 /select,"<path>" 这种原样格式，不能让标准库再加一层引号
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path))
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to spawn explorer: {}", e))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::process::Command;
+
+        Command::new("open")
+            .args(&["-R", &path])
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to reveal file: {}", e))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // 没有统一的"选中文件"方式，退化为打开所在目录
+        let parent = std::path::Path::new(&path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or(path);
+        open_folder(parent)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        Err("Unsupported platform".to_string())
+    }
+}
+
 // Tauri 命令：打开文件夹（使用系统命令，最可靠的方法）
 #[tauri::command]
 fn open_folder(path: String) -> Result<(), String> {
-    println!("[ArtHub] Opening folder: {}", path);
+    log::info!("[ArtHub] Opening folder: {}", path);
     
     #[cfg(target_os = "windows")]
     {
@@ -1762,7 +2043,7 @@ fn open_folder(path: String) -> Result<(), String> {
             EnumWindows(Some(enum_windows_proc), lparam);
             
             if let Some(hwnd) = find_data.found_hwnd {
-                println!("[ArtHub] Bringing existing window to front");
+                log::info!("[ArtHub] Bringing existing window to front");
                 // 恢复窗口（如果最小化）
                 ShowWindow(hwnd, SW_RESTORE);
                 // 前置窗口
@@ -1772,19 +2053,19 @@ fn open_folder(path: String) -> Result<(), String> {
         }
         
         // 如果没有找到已打开的窗口，打开新窗口
-        println!("[ArtHub] No existing window found, opening new explorer window");
+        log::info!("[ArtHub] No existing window found, opening new explorer window");
         let result = Command::new("explorer")
             .arg(&path)
             .spawn();
         
         match result {
             Ok(_child) => {
-                println!("[ArtHub] Successfully spawned explorer for: {}", path);
+                log::info!("[ArtHub] Successfully spawned explorer for: {}", path);
                 Ok(())
             }
             Err(e) => {
                 let error_msg = format!("Failed to spawn explorer: {}", e);
-                println!("[ArtHub] Error: {}", error_msg);
+                log::info!("[ArtHub] Error: {}", error_msg);
                 Err(error_msg)
             }
         }
@@ -1801,12 +2082,12 @@ fn open_folder(path: String) -> Result<(), String> {
         
         match output {
             Ok(_) => {
-                println!("[ArtHub] Successfully opened folder: {}", path);
+                log::info!("[ArtHub] Successfully opened folder: {}", path);
                 Ok(())
             }
             Err(e) => {
                 let error_msg = format!("Failed to open folder: {}", e);
-                println!("[ArtHub] Error: {}", error_msg);
+                log::info!("[ArtHub] Error: {}", error_msg);
                 Err(error_msg)
             }
         }
@@ -1823,12 +2104,12 @@ fn open_folder(path: String) -> Result<(), String> {
         
         match output {
             Ok(_) => {
-                println!("[ArtHub] Successfully opened folder: {}", path);
+                log::info!("[ArtHub] Successfully opened folder: {}", path);
                 Ok(())
             }
             Err(e) => {
                 let error_msg = format!("Failed to open folder: {}", e);
-                println!("[ArtHub] Error: {}", error_msg);
+                log::info!("[ArtHub] Error: {}", error_msg);
                 Err(error_msg)
             }
         }
@@ -1845,8 +2126,8 @@ fn open_folder(path: String) -> Result<(), String> {
 async fn open_ai_window(app: tauri::AppHandle, url: String, json_content: String) -> Result<(), String> {
     use tauri::WindowUrl;
     
-    println!("Opening AI window: {}", url);
-    println!("JSON content length: {}", json_content.len());
+    log::info!("Opening AI window: {}", url);
+    log::info!("JSON content length: {}", json_content.len());
     
     // 注意：JSON内容应该已经在前端复制到剪贴板了
     // 这里我们只负责打开窗口，并尝试注入（作为辅助）
@@ -1883,241 +2164,7 @@ async fn open_ai_window(app: tauri::AppHandle, url: String, json_content: String
         let json_base64 = general_purpose::STANDARD.encode(&json_content_clone);
         
         // 创建注入脚本，使用事件监听器确保在页面加载后执行
-        let injection_script = format!(
-            r#"
-            (function() {{
-                // 定义注入函数
-                function injectJSON() {{
-                    try {{
-                        // 使用base64解码JSON
-                        const jsonBase64 = "{}";
-                        let jsonString, jsonData;
-                        
-                        try {{
-                            // 解码base64
-                            jsonString = atob(jsonBase64);
-                            // 解析JSON
-                            jsonData = JSON.parse(jsonString);
-                            console.log('[ArtHub] JSON decoded and parsed successfully');
-                        }} catch(e) {{
-                            console.error('[ArtHub] Failed to decode/parse JSON:', e);
-                            jsonString = jsonBase64;
-                            jsonData = null;
-                        }}
-                        
-                        // 方法1: 通过localStorage注入
-                        try {{
-                            if (jsonString) {{
-                                localStorage.setItem('arthub_injected_json', jsonString);
-                                console.log('[ArtHub] JSON injected via localStorage');
-                            }}
-                        }} catch(e) {{
-                            console.warn('[ArtHub] localStorage injection failed:', e);
-                        }}
-                        
-                        // 方法2: 通过window对象注入
-                        try {{
-                            if (jsonData) {{
-                                window.arthubInjectedJSON = jsonData;
-                            }}
-                            if (jsonString) {{
-                                window.arthubInjectedJSONString = jsonString;
-                            }}
-                            console.log('[ArtHub] JSON injected via window object');
-                        }} catch(e) {{
-                            console.warn('[ArtHub] window injection failed:', e);
-                        }}
-                        
-                        // 方法3: 触发自定义事件
-                        try {{
-                            const event = new CustomEvent('arthub-json-ready', {{
-                                detail: jsonData || jsonString,
-                                bubbles: true,
-                                cancelable: true
-                            }});
-                            window.dispatchEvent(event);
-                            document.dispatchEvent(event);
-                            console.log('[ArtHub] JSON ready event dispatched');
-                        }} catch(e) {{
-                            console.warn('[ArtHub] Event dispatch failed:', e);
-                        }}
-                        
-                        // 方法4: 尝试复制到剪贴板并自动粘贴
-                        setTimeout(async function() {{
-                            try {{
-                                if (jsonString && navigator.clipboard && navigator.clipboard.writeText) {{
-                                    await navigator.clipboard.writeText(jsonString);
-                                    console.log('[ArtHub] JSON copied to clipboard');
-                                    
-                                    // 尝试自动粘贴
-                                    setTimeout(function() {{
-                                        try {{
-                                            // 方法4a: 尝试使用 execCommand (已废弃但可能仍有效)
-                                            const activeElement = document.activeElement;
-                                            if (activeElement && (activeElement.tagName === 'INPUT' || activeElement.tagName === 'TEXTAREA' || activeElement.isContentEditable)) {{
-                                                try {{
-                                                    document.execCommand('paste');
-                                                    console.log('[ArtHub] Attempted paste via execCommand');
-                                                }} catch(e) {{
-                                                    console.warn('[ArtHub] execCommand paste failed:', e);
-                                                }}
-                                            }}
-                                            
-                                            // 方法4b: 尝试模拟键盘事件 Ctrl+V
-                                            try {{
-                                                const pasteEvent = new KeyboardEvent('keydown', {{
-                                                    key: 'v',
-                                                    code: 'KeyV',
-                                                    ctrlKey: true,
-                                                    bubbles: true,
-                                                    cancelable: true
-                                                }});
-                                                document.dispatchEvent(pasteEvent);
-                                                
-                                                const pasteEvent2 = new KeyboardEvent('keyup', {{
-                                                    key: 'v',
-                                                    code: 'KeyV',
-                                                    ctrlKey: true,
-                                                    bubbles: true,
-                                                    cancelable: true
-                                                }});
-                                                document.dispatchEvent(pasteEvent2);
-                                                
-                                                console.log('[ArtHub] Attempted paste via keyboard event');
-                                            }} catch(e) {{
-                                                console.warn('[ArtHub] Keyboard event paste failed:', e);
-                                            }}
-                                            
-                                            // 方法4c: 尝试找到所有输入框并直接设置值（多次尝试）
-                                            function trySetInputValue() {{
-                                                try {{
-                                                    // 查找所有可能的输入元素
-                                                    const selectors = [
-                                                        'input[type="text"]',
-                                                        'input[type="search"]',
-                                                        'textarea',
-                                                        '[contenteditable="true"]',
-                                                        '[contenteditable]',
-                                                        '.monaco-editor textarea', // VS Code编辑器
-                                                        '.CodeMirror', // CodeMirror编辑器
-                                                        '[role="textbox"]'
-                                                    ];
-                                                    
-                                                    let targetInput = null;
-                                                    
-                                                    // 优先使用当前焦点元素
-                                                    if (document.activeElement) {{
-                                                        const active = document.activeElement;
-                                                        if (active.tagName === 'INPUT' || active.tagName === 'TEXTAREA' || active.isContentEditable) {{
-                                                            targetInput = active;
-                                                        }}
-                                                    }}
-                                                    
-                                                    // 如果焦点元素不可用，查找所有输入框
-                                                    if (!targetInput) {{
-                                                        for (const selector of selectors) {{
-                                                            const elements = document.querySelectorAll(selector);
-                                                            if (elements.length > 0) {{
-                                                                // 优先选择可见且可交互的元素
-                                                                for (const el of elements) {{
-                                                                    const style = window.getComputedStyle(el);
-                                                                    if (style.display !== 'none' && style.visibility !== 'hidden') {{
-                                                                        targetInput = el;
-                                                                        break;
-                                                                    }}
-                                                                }}
-                                                                if (targetInput) break;
-                                                                // 如果没有找到可见的，使用第一个
-                                                                if (!targetInput && elements[0]) {{
-                                                                    targetInput = elements[0];
-                                                                }}
-                                                            }}
-                                                            if (targetInput) break;
-                                                        }}
-                                                    }}
-                                                    
-                                                    if (targetInput) {{
-                                                        // 聚焦元素
-                                                        try {{
-                                                            targetInput.focus();
-                                                        }} catch(e) {{
-                                                            console.warn('[ArtHub] Focus failed:', e);
-                                                        }}
-                                                        
-                                                        // 设置值
-                                                        if (targetInput.tagName === 'INPUT' || targetInput.tagName === 'TEXTAREA') {{
-                                                            targetInput.value = jsonString;
-                                                            // 触发各种事件以确保应用响应
-                                                            targetInput.dispatchEvent(new Event('input', {{ bubbles: true, cancelable: true }}));
-                                                            targetInput.dispatchEvent(new Event('change', {{ bubbles: true, cancelable: true }}));
-                                                            targetInput.dispatchEvent(new KeyboardEvent('keydown', {{ bubbles: true }}));
-                                                            targetInput.dispatchEvent(new KeyboardEvent('keyup', {{ bubbles: true }}));
-                                                            console.log('[ArtHub] JSON set directly to input/textarea field');
-                                                        }} else if (targetInput.isContentEditable) {{
-                                                            targetInput.textContent = jsonString;
-                                                            targetInput.dispatchEvent(new Event('input', {{ bubbles: true, cancelable: true }}));
-                                                            console.log('[ArtHub] JSON set directly to contenteditable element');
-                                                        }}
-                                                        
-                                                        return true;
-                                                    }}
-                                                }} catch(e) {{
-                                                    console.warn('[ArtHub] Direct input set failed:', e);
-                                                }}
-                                                return false;
-                                            }}
-                                            
-                                            // 立即尝试
-                                            if (!trySetInputValue()) {{
-                                                // 如果失败，延迟后重试
-                                                setTimeout(trySetInputValue, 1000);
-                                                setTimeout(trySetInputValue, 2000);
-                                                setTimeout(trySetInputValue, 3000);
-                                            }}
-                                        }} catch(e) {{
-                                            console.warn('[ArtHub] Auto-paste failed:', e);
-                                        }}
-                                    }}, 500);
-                                }}
-                            }} catch(e) {{
-                                console.warn('[ArtHub] Clipboard copy failed:', e);
-                            }}
-                        }}, 1000);
-                        
-                        // 方法5: 在控制台输出提示
-                        console.log('%c[ArtHub] JSON数据已注入！', 'color: #00ff00; font-weight: bold; font-size: 14px;');
-                        console.log('%c访问方式:', 'color: #00aaff; font-weight: bold;');
-                        console.log('  - window.arthubInjectedJSON (对象)');
-                        console.log('  - window.arthubInjectedJSONString (字符串)');
-                        console.log('  - localStorage.getItem("arthub_injected_json")');
-                        console.log('  - 监听 "arthub-json-ready" 事件');
-                        console.log('%c自动粘贴已尝试，如果失败请手动按 Ctrl+V', 'color: #ffaa00; font-weight: bold;');
-                    }} catch(e) {{
-                        console.error('[ArtHub] JSON injection error:', e);
-                    }}
-                }}
-                
-                // 立即尝试注入
-                injectJSON();
-                
-                // 如果页面已经加载完成，再次注入
-                if (document.readyState === 'complete' || document.readyState === 'interactive') {{
-                    setTimeout(injectJSON, 100);
-                }}
-                
-                // 监听页面加载事件
-                if (document.readyState === 'loading') {{
-                    document.addEventListener('DOMContentLoaded', injectJSON);
-                }}
-                window.addEventListener('load', injectJSON);
-                
-                // 延迟注入，确保页面完全加载
-                setTimeout(injectJSON, 2000);
-                setTimeout(injectJSON, 5000);
-            }})();
-            "#,
-            json_base64
-        );
+        let injection_script = injection::build_injection_script(&json_base64, None);
         
         // 等待窗口显示
         tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
@@ -2133,14 +2180,14 @@ async fn open_ai_window(app: tauri::AppHandle, url: String, json_content: String
             // 尝试执行注入脚本
             match window_clone.eval(&injection_script) {
                 Ok(_) => {
-                    println!("[ArtHub] JSON injection script executed successfully (attempt {})", retry_count + 1);
+                    log::info!("[ArtHub] JSON injection script executed successfully (attempt {})", retry_count + 1);
                     // 不立即退出，继续尝试确保注入成功
                     if retry_count >= 3 {
                         break;
                     }
                 }
                 Err(e) => {
-                    println!("[ArtHub] Injection attempt {} failed: {:?}", retry_count + 1, e);
+                    log::info!("[ArtHub] Injection attempt {} failed: {:?}", retry_count + 1, e);
                 }
             }
             
@@ -2148,7 +2195,7 @@ async fn open_ai_window(app: tauri::AppHandle, url: String, json_content: String
         }
         
         if retry_count >= max_retries {
-            eprintln!("[ArtHub] Warning: Reached max retries, but injection may still work via event listeners");
+            log::warn!("[ArtHub] Warning: Reached max retries, but injection may still work via event listeners");
         }
     });
     
@@ -2186,10 +2233,13 @@ fn check_single_instance() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn main() {
+    // 最先安装 logger，之后的日志才会进入缓冲区
+    let logs = log_sink::init();
+    
     // 单实例检查
     if let Err(e) = check_single_instance() {
-        eprintln!("单实例检查失败: {}", e);
-        eprintln!("应用程序已经在运行中，退出当前实例");
+        log::error!("单实例检查失败: {}", e);
+        log::error!("应用程序已经在运行中，退出当前实例");
         std::process::exit(1);
     }
     
@@ -2202,9 +2252,12 @@ fn main() {
             ai_tabs: Mutex::new(Vec::new()),
             main_window_visible: Mutex::new(true),
             authenticated: Mutex::new(false), // 启动时未认证
+            logs,
+            export_roots: Mutex::new(Vec::new()),
         })
         .setup(|app| {
-            println!("=== Tauri setup started ===");
+            log_sink::attach_app(app.handle());
+            log::info!("=== Tauri setup started ===");
 
             // ---- 初始化资源管理器 ----
             {
@@ -2218,14 +2271,25 @@ fn main() {
                 let am_state = asset_manager::AssetManagerState::new(db_path, thumb_dir)
                     .expect("Failed to init asset manager database");
                 app.manage(am_state);
-                println!("Asset manager initialized");
+                // 继续处理上次退出时没生成完的缩略图
+                app.state::<asset_manager::AssetManagerState>().thumb_worker.kick(&app.handle());
+                // 可选的维护任务：修复损坏的缩略图缓存
+                asset_manager::run_startup_thumbnail_check(&app.handle());
+
+                // 检测 FFmpeg 需要启动子进程，放到后台线程；找到后注册视频缩略图提供者
+                std::thread::spawn(move || {
+                    if let Some(video) = asset_manager::thumb_provider::VideoProvider::detect(&app_data) {
+                        asset_manager::thumb_provider::register_provider(std::sync::Arc::new(video));
+                    }
+                });
+                log::info!("Asset manager initialized");
             }
 
             // 检查主窗口
             if let Some(main_window) = app.get_window("main") {
-                println!("Main window found, label: {}", main_window.label());
+                log::info!("Main window found, label: {}", main_window.label());
                 let _ = main_window.set_title("ArtHub - 游戏美术工作台");
-                println!("Main window title set");
+                log::info!("Main window title set");
                 
                 // 监听窗口关闭事件，阻止默认关闭行为，改为隐藏窗口
                 let app_handle = app.handle().clone();
@@ -2233,7 +2297,7 @@ fn main() {
                 main_window.on_window_event(move |event| {
                     match event {
                         tauri::WindowEvent::CloseRequested { api, .. } => {
-                            println!("Main window close requested - preventing close and hiding instead");
+                            log::info!("Main window close requested - preventing close and hiding instead");
                             // 阻止默认关闭行为
                             api.prevent_close();
                             // 隐藏窗口而不是关闭
@@ -2242,19 +2306,26 @@ fn main() {
                             let state = app_handle.state::<AppState>();
                             let mut window_visible = state.main_window_visible.lock().unwrap();
                             *window_visible = false;
-                            println!("Main window hidden (not closed), can be shown again by double-clicking icon");
+                            log::info!("Main window hidden (not closed), can be shown again by double-clicking icon");
+                        }
+                        tauri::WindowEvent::FileDrop(tauri::FileDropEvent::Dropped(paths)) => {
+                            // 转发给前端，由前端决定导入到哪个文件夹后调用 asset_import_dropped
+                            let paths: Vec<String> = paths.iter()
+                                .map(|p| p.to_string_lossy().to_string())
+                                .collect();
+                            let _ = main_window_clone.emit("asset-files-dropped", paths);
                         }
                         _ => {}
                     }
                 });
             } else {
-                println!("ERROR: Main window not found in setup!");
+                log::info!("ERROR: Main window not found in setup!");
             }
             
             // 加载图标位置
             let app_handle = app.handle();
             let position = load_icon_position(&app_handle);
-            println!("Loaded icon position: x={}, y={}", position.x, position.y);
+            log::info!("Loaded icon position: x={}, y={}", position.x, position.y);
             {
                 let state = app.state::<AppState>();
                 let mut pos = state.icon_position.lock().unwrap();
@@ -2264,37 +2335,41 @@ fn main() {
             // 创建悬浮图标窗口
             match create_icon_window(&app_handle) {
                 Ok(_icon_window) => {
-                    println!("Icon window created successfully");
+                    log::info!("Icon window created successfully");
                     // 注意：不在这里保存位置，避免 DPI 缩放导致位置飘移
                     // 位置只在用户拖拽后保存
                 }
                 Err(e) => {
-                    println!("ERROR: Failed to create icon window: {:?}", e);
+                    log::info!("ERROR: Failed to create icon window: {:?}", e);
                     return Err(e);
                 }
             }
             
             // 启动时显示主窗口
             if let Some(main_window) = app.get_window("main") {
-                println!("Showing main window on startup...");
+                log::info!("Showing main window on startup...");
                 let _ = main_window.show();
                 let _ = main_window.center();
                 let _ = main_window.set_focus();
                 // 确认窗口可见
                 let is_visible = main_window.is_visible().unwrap_or(false);
-                println!("Main window visible after show: {}", is_visible);
+                log::info!("Main window visible after show: {}", is_visible);
                 if !is_visible {
                     // 重试一次
                     std::thread::sleep(std::time::Duration::from_millis(100));
                     let _ = main_window.show();
                     let _ = main_window.set_focus();
-                    println!("Main window show retry done");
+                    log::info!("Main window show retry done");
                 }
             }
 
-            println!("=== Tauri setup completed ===");
+            log::info!("=== Tauri setup completed ===");
             Ok(())
         })
+        // 缩略图协议：<img src="thumb://<asset_id>">
+        .register_uri_scheme_protocol("thumb", |app, request| {
+            asset_manager::protocol::handle_thumb_request(app, request)
+        })
         .invoke_handler(tauri::generate_handler![
             icon_mouse_down,
             icon_mouse_move,
@@ -2306,16 +2381,30 @@ fn main() {
             open_ai_window,
             open_ai_tab,
             simulate_paste,
+            get_recent_logs,
+            app_get_diagnostics,
+            export_logs,
+            set_log_level,
+            list_ai_tabs,
+            close_ai_tab,
+            close_all_ai_tabs,
             send_workflow_to_comfyui,
+            comfyui_upload_image,
             open_devtools,
             open_folder,
+            reveal_file,
             get_app_icon,
             write_file_with_path,
+            write_file_unrestricted,
+            select_writable_directory,
+            adopt_storage_root,
             write_binary_file_with_path,
             rename_directory_with_path,
             read_file_with_path,
+            read_file_head,
             read_binary_file_with_path,
             file_exists_with_path,
+            files_exist_with_path,
             create_dir_with_path,
             rename_file_with_path,
             enable_autostart,
@@ -2327,13 +2416,44 @@ fn main() {
             auth_logout,
             asset_manager::asset_get_folders,
             asset_manager::asset_add_folder,
+            asset_manager::asset_get_subfolders,
+            asset_manager::asset_get_subtree_stats,
+            asset_manager::asset_set_folder_readonly,
+            asset_manager::asset_set_folder_sidecars,
             asset_manager::asset_remove_folder,
             asset_manager::asset_scan_folder,
+            asset_manager::asset_import_dropped,
+            asset_manager::asset_verify_folder,
+            asset_manager::asset_purge_missing,
+            asset_manager::asset_generate_contact_sheet,
+            asset_manager::asset_get_scan_queue,
             asset_manager::asset_query,
             asset_manager::asset_get_stats,
+            asset_manager::asset_regenerate_thumbnail,
+            asset_manager::asset_validate_thumbnails,
+            asset_manager::asset_set_validate_thumbnails_on_startup,
+            asset_manager::asset_get_validate_thumbnails_on_startup,
+            asset_manager::asset_transform_image,
+            asset_manager::asset_batch_regenerate_thumbnails,
+            asset_manager::asset_get_db_recovery,
+            asset_manager::asset_backup_db,
+            asset_manager::asset_vacuum_db,
+            asset_manager::asset_checkpoint_wal,
+            asset_manager::asset_get_setting,
+            asset_manager::asset_set_setting,
+            asset_manager::asset_set_placeholder_thumbnails,
+            asset_manager::asset_get_placeholder_thumbnails,
+            asset_manager::asset_set_thumbnail_format,
+            asset_manager::asset_get_thumbnail_format,
+            asset_manager::asset_set_max_decode_pixels,
+            asset_manager::asset_get_max_decode_pixels,
+            asset_manager::asset_get_supported_extensions,
+            asset_manager::asset_add_custom_extension,
+            asset_manager::asset_remove_custom_extension,
             asset_manager::asset_get_tags,
             asset_manager::asset_create_tag,
             asset_manager::asset_update_tag,
+            asset_manager::asset_set_tag_space,
             asset_manager::asset_delete_tag,
             asset_manager::asset_add_tag,
             asset_manager::asset_remove_tag,
@@ -2341,20 +2461,47 @@ fn main() {
             asset_manager::asset_set_rating,
             asset_manager::asset_set_note,
             asset_manager::asset_get_detail,
+            asset_manager::asset_get_details_bulk,
+            asset_manager::asset_mark_viewed,
+            asset_manager::asset_open_with,
+            asset_manager::asset_copy_to_clipboard,
+            asset_manager::asset_get_preview_data_uri,
+            asset_manager::ai_tools_list,
+            asset_manager::ai_tool_save,
+            asset_manager::ai_tool_delete,
             asset_manager::asset_get_smart_folders,
             asset_manager::asset_create_smart_folder,
             asset_manager::asset_update_smart_folder,
             asset_manager::asset_delete_smart_folder,
+            asset_manager::asset_save_search,
+            asset_manager::asset_get_saved_searches,
+            asset_manager::asset_delete_saved_search,
+            asset_manager::asset_run_saved_search,
+            asset_manager::asset_add_search_history,
+            asset_manager::asset_get_search_history,
+            asset_manager::asset_clear_search_history,
             asset_manager::team_check_lock,
             asset_manager::team_acquire_lock,
             asset_manager::team_release_lock,
+            asset_manager::team_start_lock_session,
+            asset_manager::team_end_lock_session,
             asset_manager::team_refresh_heartbeat,
             asset_manager::team_get_all_locks,
             asset_manager::team_get_history,
             asset_manager::team_create_version,
             asset_manager::team_restore_version,
+            asset_manager::team_relink_history,
+            asset_manager::team_get_store_usage,
+            asset_manager::team_get_store_config,
+            asset_manager::team_set_store_config,
+            asset_manager::team_version_diff,
             asset_manager::team_log_action,
             asset_manager::team_read_actions,
+            asset_manager::team_export_actions_csv,
+            asset_manager::team_init_shared_root,
+            asset_manager::team_check_shared_root,
+            asset_manager::team_update_presence,
+            asset_manager::team_get_active_users,
             asset_manager::team_load_permissions,
             asset_manager::team_set_permission,
             asset_manager::team_get_user_role,
@@ -2363,15 +2510,38 @@ fn main() {
             asset_manager::asset_get_favorite_ids,
             asset_manager::asset_batch_favorite,
             asset_manager::asset_batch_set_rating,
+            asset_manager::asset_batch_apply_metadata,
+            asset_manager::asset_get_undo_stack,
+            asset_manager::asset_undo_last,
             asset_manager::asset_batch_delete,
             asset_manager::asset_batch_export,
+            asset_manager::asset_begin_batch,
+            asset_manager::asset_cancel_batch,
+            asset_manager::asset_convert_format,
+            asset_manager::asset_export_resized,
+            asset_manager::asset_export_sidecars,
+            asset_manager::asset_optimize_jpeg,
             asset_manager::asset_get_os_username,
+            asset_manager::team_get_identity,
             asset_manager::ffmpeg_check,
             asset_manager::ffmpeg_download,
-            asset_manager::ffmpeg_extract_thumbnail
+            asset_manager::ffmpeg_set_custom_path,
+            asset_manager::asset_set_thumbnail_worker_enabled,
+            asset_manager::asset_get_thumbnail_worker_enabled,
+            asset_manager::asset_prioritize_thumbnails,
+            asset_manager::ffmpeg_extract_thumbnail,
+            asset_manager::asset_generate_missing_media_thumbnails
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // 停止团队锁的自动心跳并释放仍持有的锁
+                if let Some(am) = app.try_state::<asset_manager::AssetManagerState>() {
+                    am.lock_sessions.end_all();
+                }
+            }
+        });
 }
 
 // Tauri 命令：启用自启动
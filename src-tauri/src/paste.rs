@@ -0,0 +1,119 @@
+// 模拟粘贴快捷键（Windows: Ctrl+V，macOS: Command+V，Linux: Ctrl+V）
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+
+/// 指定窗口当前是否在前台
+#[cfg(target_os = "windows")]
+pub fn is_foreground(window: &tauri::Window) -> bool {
+    use winapi::um::winuser::GetForegroundWindow;
+
+    let foreground = unsafe { GetForegroundWindow() };
+    match window.hwnd() {
+        Ok(hwnd) => !foreground.is_null() && foreground as isize == hwnd.0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_foreground(window: &tauri::Window) -> bool {
+    window.is_focused().unwrap_or(false)
+}
+
+/// 按键发送器。真实实现基于 enigo；调用方只依赖这个接口，便于替换
+pub trait KeySender {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String>;
+}
+
+/// 基于 enigo 的跨平台实现（Windows: SendInput，macOS: CGEvent，Linux: xdo）
+pub struct EnigoSender(Enigo);
+
+impl EnigoSender {
+    pub fn new() -> Result<Self, String> {
+        Enigo::new(&Settings::default())
+            .map(EnigoSender)
+            .map_err(|e| format!("初始化键盘模拟失败: {}", e))
+    }
+}
+
+impl KeySender for EnigoSender {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+        Keyboard::key(&mut self.0, key, direction)
+            .map_err(|e| format!("发送按键失败: {}", e))
+    }
+}
+
+/// 粘贴快捷键的修饰键
+#[cfg(target_os = "macos")]
+const PASTE_MODIFIER: Key = Key::Meta;
+#[cfg(not(target_os = "macos"))]
+const PASTE_MODIFIER: Key = Key::Control;
+
+/// 发送粘贴快捷键：修饰键按下 → V 按下 → V 抬起 → 修饰键抬起
+/// 中途失败也会尝试抬起修饰键，避免修饰键卡在按下状态
+pub fn send_paste<S: KeySender>(sender: &mut S) -> Result<(), String> {
+    sender.key(PASTE_MODIFIER, Direction::Press)?;
+    let result = sender.key(Key::Unicode('v'), Direction::Press)
+        .and_then(|_| sender.key(Key::Unicode('v'), Direction::Release));
+    let release = sender.key(PASTE_MODIFIER, Direction::Release);
+    result.and(release)
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+}
+
+/// 向当前前台窗口发送粘贴快捷键。
+/// macOS 上向其他进程投递键盘事件需要“辅助功能”权限，未授权时事件会被系统静默丢弃，因此先检查。
+pub fn send_paste_shortcut() -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    if !unsafe { AXIsProcessTrusted() } {
+        return Err("自动粘贴需要辅助功能权限：请在“系统设置 → 隐私与安全性 → 辅助功能”中允许 ArtHub".to_string());
+    }
+
+    send_paste(&mut EnigoSender::new()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 记录按键顺序的模拟发送器，fail_at 指定第几次调用返回错误
+    #[derive(Default)]
+    struct MockSender {
+        events: Vec<(Key, Direction)>,
+        fail_at: Option<usize>,
+    }
+
+    impl KeySender for MockSender {
+        fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+            let index = self.events.len();
+            self.events.push((key, direction));
+            if self.fail_at == Some(index) {
+                return Err("模拟失败".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sends_modifier_and_v_in_order() {
+        let mut sender = MockSender::default();
+        send_paste(&mut sender).unwrap();
+        assert_eq!(sender.events, vec![
+            (PASTE_MODIFIER, Direction::Press),
+            (Key::Unicode('v'), Direction::Press),
+            (Key::Unicode('v'), Direction::Release),
+            (PASTE_MODIFIER, Direction::Release),
+        ]);
+    }
+
+    #[test]
+    fn releases_modifier_when_v_fails() {
+        for fail_at in [1, 2] {
+            let mut sender = MockSender { fail_at: Some(fail_at), ..Default::default() };
+            assert!(send_paste(&mut sender).is_err());
+            assert_eq!(sender.events.last(), Some(&(PASTE_MODIFIER, Direction::Release)));
+        }
+    }
+}
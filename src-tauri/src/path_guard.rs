@@ -0,0 +1,185 @@
+// 写文件路径校验：防止前端传入任意绝对路径覆盖系统文件
+use std::path::{Component, Path, PathBuf};
+
+/// 任何情况下都不允许写入的系统目录
+#[cfg(target_os = "windows")]
+const SYSTEM_DIRS: &[&str] = &[
+    "C:\\Windows",
+    "C:\\Program Files",
+    "C:\\Program Files (x86)",
+    "C:\\ProgramData",
+];
+
+#[cfg(target_os = "macos")]
+const SYSTEM_DIRS: &[&str] = &["/System", "/Library", "/bin", "/sbin", "/usr", "/etc", "/private/etc"];
+
+#[cfg(all(unix, not(target_os = "macos")))]
+const SYSTEM_DIRS: &[&str] = &["/bin", "/sbin", "/usr", "/etc", "/lib", "/lib64", "/boot", "/dev", "/proc", "/sys"];
+
+/// 规范化待写入的路径：
+/// - 拒绝包含 `..` 的路径（规范化前后都检查）
+/// - 目标可能尚不存在，因此只规范化最近的已存在祖先，再拼回剩余部分
+pub fn normalize(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if !path.is_absolute() {
+        return Err(format!("必须使用绝对路径: {}", path.display()));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("路径中不允许包含 '..': {}", path.display()));
+    }
+
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+
+    let mut resolved = dunce_canonicalize(existing)?;
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
+
+    if resolved.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("路径中不允许包含 '..': {}", resolved.display()));
+    }
+    Ok(resolved)
+}
+
+/// canonicalize 在 Windows 上会返回 `\\?\` 前缀的路径，去掉后才能和普通路径比较
+fn dunce_canonicalize(path: &Path) -> Result<PathBuf, String> {
+    let canonical = path.canonicalize()
+        .map_err(|e| format!("无法解析路径 {}: {}", path.display(), e))?;
+    #[cfg(target_os = "windows")]
+    {
+        let s = canonical.to_string_lossy();
+        if let Some(stripped) = s.strip_prefix(r"\\?\") {
+            if !stripped.starts_with("UNC\\") {
+                return Ok(PathBuf::from(stripped));
+            }
+            return Ok(PathBuf::from(format!(r"\\{}", &stripped[4..])));
+        }
+    }
+    Ok(canonical)
+}
+
+/// 是否位于系统目录内（Windows 下忽略大小写）
+fn is_system_path(path: &Path) -> bool {
+    SYSTEM_DIRS.iter().any(|dir| {
+        if cfg!(target_os = "windows") {
+            PathBuf::from(path.to_string_lossy().to_lowercase())
+                .starts_with(dir.to_lowercase())
+        } else {
+            path.starts_with(dir)
+        }
+    })
+}
+
+/// 校验路径只位于允许的根目录之内，返回规范化后的路径
+pub fn ensure_allowed(path: &str, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let resolved = normalize(path)?;
+    if is_system_path(&resolved) {
+        return Err(format!("不允许写入系统目录: {}", resolved.display()));
+    }
+
+    let allowed = roots.iter()
+        .filter_map(|root| normalize(&root.to_string_lossy()).ok())
+        .any(|root| resolved.starts_with(&root));
+    if !allowed {
+        return Err(format!("路径不在允许写入的目录内: {}", resolved.display()));
+    }
+    Ok(resolved)
+}
+
+/// 不限根目录的校验（仅供显式的不受限写入命令使用），仍拒绝 `..` 与系统目录
+pub fn ensure_not_system(path: &str) -> Result<PathBuf, String> {
+    let resolved = normalize(path)?;
+    if is_system_path(&resolved) {
+        return Err(format!("不允许写入系统目录: {}", resolved.display()));
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 每个测试使用独立的临时根目录
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("arthub_path_guard_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("inside")).unwrap();
+        dunce_canonicalize(&root).unwrap()
+    }
+
+    fn s(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn rejects_relative_paths() {
+        assert!(normalize("relative/file.txt").is_err());
+        assert!(normalize("./file.txt").is_err());
+    }
+
+    #[test]
+    fn rejects_parent_dir_segments() {
+        let root = temp_root("parent");
+        let escape = root.join("inside").join("..").join("..").join("evil.txt");
+        assert!(normalize(&s(&escape)).is_err());
+        assert!(ensure_allowed(&s(&escape), std::slice::from_ref(&root)).is_err());
+
+        // 即使 `..` 最终仍落在根目录内也拒绝
+        let stays_inside = root.join("inside").join("..").join("file.txt");
+        assert!(ensure_allowed(&s(&stays_inside), std::slice::from_ref(&root)).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn allows_new_files_inside_root() {
+        let root = temp_root("inside");
+        let target = root.join("inside").join("new_dir").join("file.txt");
+        let resolved = ensure_allowed(&s(&target), std::slice::from_ref(&root)).unwrap();
+        assert!(resolved.starts_with(&root));
+        assert!(resolved.ends_with("new_dir/file.txt") || resolved.ends_with("new_dir\\file.txt"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn rejects_paths_outside_roots() {
+        let root = temp_root("outside");
+        let sibling = temp_root("outside_sibling");
+        assert!(ensure_allowed(&s(&sibling.join("file.txt")), &[root.join("inside")]).is_err());
+        // 前缀相同但不是子目录（inside_other 不在 inside 之内）
+        let lookalike = root.join("inside_other").join("file.txt");
+        assert!(ensure_allowed(&s(&lookalike), &[root.join("inside")]).is_err());
+        assert!(ensure_allowed(&s(&root.join("inside").join("a.txt")), &[]).is_err());
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&sibling);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlink_escaping_root() {
+        let root = temp_root("symlink");
+        let outside = temp_root("symlink_target");
+        std::os::unix::fs::symlink(&outside, root.join("inside").join("link")).unwrap();
+        let through_link = root.join("inside").join("link").join("file.txt");
+        assert!(ensure_allowed(&s(&through_link), &[root.join("inside")]).is_err());
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&outside);
+    }
+
+    #[test]
+    fn rejects_system_dirs() {
+        let system = PathBuf::from(SYSTEM_DIRS[0]).join("arthub_test.txt");
+        assert!(ensure_not_system(&s(&system)).is_err());
+        assert!(ensure_allowed(&s(&system), &[PathBuf::from(SYSTEM_DIRS[0])]).is_err());
+    }
+}